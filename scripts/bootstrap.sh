@@ -208,6 +208,7 @@ INIT_CMD=(
     "-d" "$DATASET"
     init
     "--usb-device" "$DEVICE"
+    "--label" "$BESKAR_LABEL"
     "${SAFE_FLAG[@]}"
 )
 
@@ -0,0 +1,157 @@
+// ============================================================================
+// src/cmd/auditlog.rs – Review the append-only audit trail
+// ============================================================================
+
+use crate::ui::{Pace, Timing, UX};
+use crate::util::audit::{local_to_utc, parse_line, AuditEntry, AUDIT_LOG_PATH};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::fs;
+
+#[derive(Debug, Clone, Default)]
+pub struct AuditLogOptions {
+    pub since: Option<String>,
+    pub event: Option<String>,
+    pub tail: Option<usize>,
+    /// Show (and read `since`) in UTC rather than local time (`audit.utc`).
+    pub utc: bool,
+}
+
+/// Show audit records from `/var/log/beskar.log`, optionally narrowed to
+/// those at or after `since`, whose event starts with `event`, and to the
+/// last `tail` matches.
+pub fn run_audit_log(ui: &UX, timing: &Timing, opts: &AuditLogOptions) -> Result<()> {
+    ui.banner();
+    ui.phase("Audit // Chronicle Review");
+
+    let since = opts
+        .since
+        .as_deref()
+        .map(|raw| parse_since(raw, opts.utc))
+        .transpose()?;
+    let text = fs::read_to_string(AUDIT_LOG_PATH)
+        .with_context(|| format!("read audit log {}", AUDIT_LOG_PATH))?;
+    let entries = select_entries(&text, since, opts.event.as_deref(), opts.tail);
+
+    if entries.is_empty() {
+        ui.note("No audit records match the requested filters.");
+        timing.pace(Pace::Info);
+        return Ok(());
+    }
+
+    let stamps: Vec<String> = entries
+        .iter()
+        .map(|e| {
+            if opts.utc {
+                e.timestamp.format("%Y-%m-%d %H:%M:%SZ").to_string()
+            } else {
+                e.timestamp
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            }
+        })
+        .collect();
+    let rows: Vec<(&str, String)> = entries
+        .iter()
+        .zip(stamps.iter())
+        .map(|(e, ts)| (ts.as_str(), format!("{}: {}", e.event, e.detail)))
+        .collect();
+    ui.data_panel("Audit log", &rows);
+    ui.info(&format!("{} record(s) shown.", entries.len()));
+    timing.pace(Pace::Info);
+    Ok(())
+}
+
+/// Filter parsed records in file order; `tail` keeps the newest N matches.
+fn select_entries(
+    text: &str,
+    since: Option<DateTime<Utc>>,
+    event: Option<&str>,
+    tail: Option<usize>,
+) -> Vec<AuditEntry> {
+    let mut entries: Vec<AuditEntry> = text
+        .lines()
+        .filter_map(parse_line)
+        .filter(|e| since.is_none_or(|s| e.timestamp >= s))
+        .filter(|e| event.is_none_or(|prefix| e.event.starts_with(prefix)))
+        .collect();
+    if let Some(n) = tail {
+        let skip = entries.len().saturating_sub(n);
+        entries.drain(..skip);
+    }
+    entries
+}
+
+/// Accept `YYYY-MM-DD`, `YYYY-MM-DD HH:MM[:SS]` (read in UTC when `utc`,
+/// else local time), or RFC 3339 with its own offset.
+fn parse_since(raw: &str, utc: bool) -> Result<DateTime<Utc>> {
+    let raw = raw.trim();
+    let zoned = |naive: NaiveDateTime| {
+        if utc {
+            Utc.from_utc_datetime(&naive)
+        } else {
+            local_to_utc(naive)
+        }
+    };
+    for fmt in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(ts) = NaiveDateTime::parse_from_str(raw, fmt) {
+            return Ok(zoned(ts));
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        return Ok(zoned(date.and_hms_opt(0, 0, 0).expect("midnight is valid")));
+    }
+    if let Ok(ts) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+    Err(anyhow!(
+        "--since '{}' is not a date (YYYY-MM-DD[ HH:MM[:SS]] or RFC 3339)",
+        raw
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_since, select_entries};
+
+    #[test]
+    fn audit_records_filter_by_since_event_and_tail() {
+        let log = "\
+[2026-03-01 08:00:00] INIT_START: forging
+[2026-03-02 07:59:59] UNLOCK_OK: Unlocked rpool (descendants_unlocked=0)
+garbage line
+[2026-03-02 08:00:01] LOCKOUT_WAIT: 4s
+[2026-03-03 09:15:00] UNLOCK_ATTEMPT_FAIL: Attempt 1 failed for rpool: bad key
+";
+        let since = parse_since("2026-03-02", false).unwrap();
+        let all = select_entries(log, Some(since), None, None);
+        assert_eq!(all.len(), 3);
+
+        let unlocks = select_entries(log, Some(since), Some("UNLOCK_"), None);
+        assert_eq!(unlocks.len(), 2);
+        assert_eq!(unlocks[1].detail, "Attempt 1 failed for rpool: bad key");
+
+        let last = select_entries(log, None, None, Some(1));
+        assert_eq!(last[0].event, "UNLOCK_ATTEMPT_FAIL");
+
+        assert!(parse_since("2026-03-02 08:00", false).is_ok());
+        assert!(parse_since("yesterday", false).is_err());
+    }
+
+    #[test]
+    fn utc_records_read_alongside_local_ones() {
+        let log = "\
+[2026-03-02T07:00:00Z] UNLOCK_OK: Unlocked rpool (descendants_unlocked=0)
+[2026-03-02T09:30:00Z] LOCK_KEYRING_REVOKE: rpool
+";
+        let since = parse_since("2026-03-02 08:00", true).unwrap();
+        let entries = select_entries(log, Some(since), None, None);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].event, "LOCK_KEYRING_REVOKE");
+        assert_eq!(
+            parse_since("2026-03-02T10:00:00+02:00", false).unwrap(),
+            parse_since("2026-03-02 08:00", true).unwrap()
+        );
+    }
+}
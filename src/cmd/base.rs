@@ -2,11 +2,271 @@
 // src/cmd/base.rs – Allowlisted external command runner (for system utilities)
 // ============================================================================
 
+use crate::util::audit::audit_log;
+use crate::zfs::KEY_LEN;
 use anyhow::{anyhow, Context, Result};
-use std::io::{Read, Write};
-use std::process::{Child, ChildStderr, ChildStdout, Command, Stdio};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStderr, ChildStdout, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{OnceLock, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+// Security measure: restrict to known binaries
+const ALLOWED_BINARIES: &[&str] = &[
+    // zfs binary locations (must stay in sync with zfs::Zfs::discover)
+    "/sbin/zfs",
+    "/usr/sbin/zfs",
+    "/usr/local/sbin/zfs",
+    "/bin/zfs",
+    // systemctl is typically in /bin or /usr/bin
+    "/bin/systemctl",
+    "/usr/bin/systemctl",
+    // zpool helper binaries in common locations
+    "/sbin/zpool",
+    "/usr/sbin/zpool",
+    "/usr/local/sbin/zpool",
+    // dracut (optional bootstrap step)
+    "/usr/bin/dracut",
+    "/usr/sbin/dracut",
+    // initramfs listers for doctor's booted-image check
+    "/usr/bin/lsinitrd",
+    "/usr/sbin/lsinitrd",
+    "/usr/bin/lsinitramfs",
+    "/usr/sbin/lsinitramfs",
+    // block device provisioning utilities for init workflow
+    "/sbin/parted",
+    "/usr/sbin/parted",
+    "/usr/bin/parted",
+    "/sbin/mkfs.ext4",
+    "/usr/sbin/mkfs.ext4",
+    "/usr/bin/mkfs.ext4",
+    "/sbin/blkid",
+    "/usr/sbin/blkid",
+    "/usr/bin/blkid",
+    "/bin/mount",
+    "/usr/bin/mount",
+    "/bin/umount",
+    "/usr/bin/umount",
+    "/bin/lsblk",
+    "/usr/bin/lsblk",
+    "/sbin/udevadm",
+    "/usr/sbin/udevadm",
+    "/usr/bin/udevadm",
+    "/bin/systemd-ask-password",
+    "/usr/bin/systemd-ask-password",
+    // plymouth splash agent for the passphrase fallback
+    "/bin/plymouth",
+    "/usr/bin/plymouth",
+    "/bin/systemd-analyze",
+    "/usr/bin/systemd-analyze",
+    // libfido2 tools for the opt-in hmac-secret key source
+    "/usr/bin/fido2-token",
+    "/usr/bin/fido2-cred",
+    "/usr/bin/fido2-assert",
+];
+
+/// Shells `simulate`'s initramfs drill may hand its own rendered loader to.
+/// They stay out of `ALLOWED_BINARIES`; only `Cmd::new_drill_shell` reaches them.
+const DRILL_SHELLS: &[&str] = &["/bin/bash", "/usr/bin/bash"];
+
+/// Binaries that may live outside `ALLOWED_BINARIES` when an operator names
+/// them explicitly in config (e.g. Nix store paths).
+const CONFIGURABLE_BINARIES: &[&str] = &["zfs", "zpool"];
+
+/// Grace period between SIGTERM and SIGKILL when a timed-out command's
+/// process group is torn down.
+const GROUP_KILL_GRACE: Duration = Duration::from_millis(500);
+
+/// Upper bound on a secret reply (`run_secret`). The whole buffer is reserved
+/// before the first read so it never reallocates and strands a copy.
+const SECRET_OUTPUT_MAX: usize = 4096;
+
+/// Child-exit polling starts at 1ms and doubles up to 50ms: fast zfs queries
+/// are reaped within a few ms, long dracut runs wake ~20 times a second.
+const WAIT_POLL_MIN: Duration = Duration::from_millis(1);
+const WAIT_POLL_MAX: Duration = Duration::from_millis(50);
+
+fn next_poll_delay(delay: Duration) -> Duration {
+    (delay * 2).min(WAIT_POLL_MAX)
+}
+
+/// Operator-vetted additions from `policy.extra_allowed_binaries`, merged in
+/// once per process by `extend_allowlist`.
+static EXTRA_BINARIES: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Subcommands whose trailing arguments may carry key material or key paths.
+const SECRET_SUBCOMMANDS: &[&str] = &["change-key", "load-key"];
+const REDACTED: &str = "<redacted>";
+
+/// Mirrors `audit.log_commands`; off by default so the log stays small.
+static LOG_COMMANDS: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable per-invocation `CMD_EXEC` audit entries.
+pub fn set_command_audit(enabled: bool) {
+    LOG_COMMANDS.store(enabled, Ordering::Relaxed);
+}
+
+/// Mask everything after a `change-key`/`load-key` style subcommand and any
+/// argument holding a hex-encoded raw key. Stdin is never logged.
+fn redact_args(args: &[&str]) -> Vec<String> {
+    let mut secret_tail = false;
+    args.iter()
+        .map(|arg| {
+            if secret_tail || contains_hex_key(arg) {
+                return REDACTED.to_string();
+            }
+            if SECRET_SUBCOMMANDS.contains(arg) {
+                secret_tail = true;
+            }
+            arg.to_string()
+        })
+        .collect()
+}
+
+fn contains_hex_key(arg: &str) -> bool {
+    let mut run = 0;
+    for c in arg.chars() {
+        run = if c.is_ascii_hexdigit() { run + 1 } else { 0 };
+        if run >= KEY_LEN * 2 {
+            return true;
+        }
+    }
+    false
+}
+
+/// True when `path` is a static allowlisted location or a vetted config extra.
+pub fn is_allowlisted(path: &str) -> bool {
+    is_statically_allowlisted(path) || is_extra_allowlisted(path)
+}
+
+/// Canonical forms of the static allowlist entries present on this host,
+/// resolved once so usr-merge aliases (`/bin` -> `/usr/bin`) compare equal.
+fn canonical_allowlist() -> &'static [(&'static str, PathBuf)] {
+    static CANONICAL: OnceLock<Vec<(&'static str, PathBuf)>> = OnceLock::new();
+    CANONICAL.get_or_init(|| canonicalize_all(ALLOWED_BINARIES.iter().copied()))
+}
+
+/// Each entry that exists here, paired with the file it resolves to.
+fn canonicalize_all<'a>(paths: impl IntoIterator<Item = &'a str>) -> Vec<(&'a str, PathBuf)> {
+    paths
+        .into_iter()
+        .filter_map(|p| fs::canonicalize(p).ok().map(|resolved| (p, resolved)))
+        .collect()
+}
+
+fn is_statically_allowlisted(path: &str) -> bool {
+    static_allowlist_entry(path).is_some()
+}
+
+fn static_allowlist_entry(path: &str) -> Option<String> {
+    allowlisted_in(path, ALLOWED_BINARIES, canonical_allowlist())
+}
+
+/// The allowlist entry to exec for `path`: `path` itself on a literal match,
+/// otherwise the entry `path` is a usr-merge alias of (`/bin/x` for
+/// `/usr/bin/x`, `/sbin/x` for `/usr/sbin/x`, either way round) when both
+/// resolve to the same file. Any other symlink, including one elsewhere that
+/// points at an entry, is refused, and the caller's path is never what runs.
+fn allowlisted_in(path: &str, literal: &[&str], canonical: &[(&str, PathBuf)]) -> Option<String> {
+    if literal.contains(&path) {
+        return Some(path.to_string());
+    }
+    let resolved = fs::canonicalize(path).ok()?;
+    canonical
+        .iter()
+        .find(|(entry, target)| {
+            *target == resolved && usr_merge_pair(Path::new(path), Path::new(entry))
+        })
+        .map(|(entry, _)| entry.to_string())
+}
+
+/// True when `a` and `b` are the same file name under `bin`/`usr/bin` or
+/// `sbin`/`usr/sbin` of one prefix (`/` outside tests).
+fn usr_merge_pair(a: &Path, b: &Path) -> bool {
+    let merged = |short: &Path, long: &Path| {
+        let (Some(dir), Some(name)) = (short.parent(), short.file_name()) else {
+            return false;
+        };
+        let (Some(tail), Some(prefix)) = (dir.file_name(), dir.parent()) else {
+            return false;
+        };
+        (tail == "bin" || tail == "sbin") && long == prefix.join("usr").join(tail).join(name)
+    };
+    merged(a, b) || merged(b, a)
+}
+
+fn is_extra_allowlisted(path: &str) -> bool {
+    extra_allowlist_entry(path).is_some()
+}
+
+fn extra_allowlist_entry(path: &str) -> Option<String> {
+    EXTRA_BINARIES.read().ok().and_then(|extras| {
+        let literal: Vec<&str> = extras.iter().map(String::as_str).collect();
+        allowlisted_in(path, &literal, &canonicalize_all(literal.iter().copied()))
+    })
+}
+
+/// Extra allowlisted binaries whose file name is `name` (e.g. `mkfs.ext4`).
+pub fn extra_binaries_named(name: &str) -> Vec<String> {
+    EXTRA_BINARIES
+        .read()
+        .map(|extras| {
+            extras
+                .iter()
+                .filter(|p| Path::new(p).file_name().and_then(|n| n.to_str()) == Some(name))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Merge `policy.extra_allowed_binaries` into this process's allowlist. Every
+/// entry must be an absolute path to a root-owned file without group/world
+/// write access. Entries that fail are skipped, audited as
+/// `CMD_EXTRA_ALLOWLIST_SKIP` and returned, so one stale path never stops a
+/// command (boot-time `auto-unlock` included) that does not need it.
+pub fn extend_allowlist(paths: &[String]) -> Vec<anyhow::Error> {
+    let mut rejected = Vec::new();
+    let mut extras = EXTRA_BINARIES
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    for path in paths {
+        if let Err(err) = vet_extra_binary(path) {
+            audit_log("CMD_EXTRA_ALLOWLIST_SKIP", &format!("{:#}", err));
+            rejected.push(err);
+            continue;
+        }
+        if !ALLOWED_BINARIES.contains(&path.as_str()) && !extras.contains(path) {
+            extras.push(path.clone());
+        }
+    }
+    rejected
+}
+
+fn vet_extra_binary(path: &str) -> Result<()> {
+    if !Path::new(path).is_absolute() {
+        return Err(anyhow!(
+            "policy.extra_allowed_binaries entry {} must be an absolute path",
+            path
+        ));
+    }
+    let meta =
+        fs::metadata(path).with_context(|| format!("stat extra allowlisted binary {}", path))?;
+    if !meta.is_file() || meta.uid() != 0 || meta.mode() & 0o022 != 0 {
+        return Err(anyhow!(
+            "Extra allowlisted binary {} must be a root-owned file without group/world write access",
+            path
+        ));
+    }
+    Ok(())
+}
 
 /// Safe wrapper for external process execution.
 /// Used for invoking allowlisted system tools like `zfs`, `systemctl`, etc.
@@ -14,76 +274,404 @@ use std::time::{Duration, Instant};
 pub struct Cmd {
     pub path: String,
     pub timeout: Duration,
+    foreground: bool,
+    cwd: Option<PathBuf>,
+    umask: Option<libc::mode_t>,
+    env: Vec<(String, String)>,
 }
 
 #[derive(Debug)]
 pub struct OutputData {
     pub stdout: String,
     pub stderr: String,
+    /// Exit code, or -1 when the child was killed by a signal.
+    pub status: i32,
+    pub termination: Termination,
+}
+
+/// Output of `Cmd::run_secret`: stdout stays in one zeroizing buffer.
+pub struct SecretOutput {
+    pub stdout: Zeroizing<Vec<u8>>,
+    pub stderr: String,
+    /// Exit code, or -1 when the child was killed by a signal.
     pub status: i32,
+    pub termination: Termination,
+}
+
+/// How a child ended. `status` alone flattens a signal death (OOM kill,
+/// segfault) into -1 with empty stderr; this keeps the signal number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Exited(i32),
+    Signaled(i32),
+}
+
+impl Termination {
+    fn from_exit(status: Option<ExitStatus>) -> Self {
+        match status {
+            Some(s) => match (s.code(), s.signal()) {
+                (Some(code), _) => Termination::Exited(code),
+                (None, Some(signal)) => Termination::Signaled(signal),
+                (None, None) => Termination::Exited(-1),
+            },
+            None => Termination::Exited(-1),
+        }
+    }
+
+    fn status(self) -> i32 {
+        match self {
+            Termination::Exited(code) => code,
+            Termination::Signaled(_) => -1,
+        }
+    }
+}
+
+impl std::fmt::Display for Termination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Termination::Exited(code) => write!(f, "exit status {}", code),
+            Termination::Signaled(signal) => match signal_name(signal) {
+                Some(name) => write!(f, "terminated by signal {} ({})", signal, name),
+                None => write!(f, "terminated by signal {}", signal),
+            },
+        }
+    }
+}
+
+fn signal_name(signal: i32) -> Option<&'static str> {
+    Some(match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGTERM => "SIGTERM",
+        _ => return None,
+    })
 }
 
 impl Cmd {
-    /// Create a new allowlisted command runner.
+    /// Create a new allowlisted command runner. The runner execs the matching
+    /// allowlist entry, so an alias resolved here cannot be retargeted later.
     pub fn new_allowlisted<S: Into<String>>(path: S, timeout: Duration) -> Result<Self> {
         let path_str = path.into();
-        // Security measure: restrict to known binaries
-        let allowed = [
-            // zfs binary locations (must stay in sync with zfs::Zfs::discover)
-            "/sbin/zfs",
-            "/usr/sbin/zfs",
-            "/usr/local/sbin/zfs",
-            "/bin/zfs",
-            // systemctl is typically in /bin or /usr/bin
-            "/bin/systemctl",
-            "/usr/bin/systemctl",
-            // zpool helper binaries in common locations
-            "/sbin/zpool",
-            "/usr/sbin/zpool",
-            "/usr/local/sbin/zpool",
-            // dracut (optional bootstrap step)
-            "/usr/bin/dracut",
-            "/usr/sbin/dracut",
-            // block device provisioning utilities for init workflow
-            "/sbin/parted",
-            "/usr/sbin/parted",
-            "/usr/bin/parted",
-            "/sbin/mkfs.ext4",
-            "/usr/sbin/mkfs.ext4",
-            "/usr/bin/mkfs.ext4",
-            "/sbin/blkid",
-            "/usr/sbin/blkid",
-            "/usr/bin/blkid",
-            "/bin/mount",
-            "/usr/bin/mount",
-            "/bin/umount",
-            "/usr/bin/umount",
-            "/bin/lsblk",
-            "/usr/bin/lsblk",
-            "/sbin/udevadm",
-            "/usr/sbin/udevadm",
-            "/usr/bin/udevadm",
-            "/bin/systemd-ask-password",
-            "/usr/bin/systemd-ask-password",
-            "/bin/systemd-analyze",
-            "/usr/bin/systemd-analyze",
-        ];
-        if !allowed.contains(&path_str.as_str()) {
-            return Err(anyhow!("Command '{}' not in allowlist", path_str));
+        let entry = match static_allowlist_entry(&path_str) {
+            Some(entry) => entry,
+            None => {
+                let entry = extra_allowlist_entry(&path_str)
+                    .ok_or_else(|| anyhow!("Command '{}' not in allowlist", path_str))?;
+                audit_log("CMD_EXTRA_ALLOWLIST", &entry);
+                entry
+            }
+        };
+
+        Ok(Self {
+            path: entry,
+            timeout,
+            foreground: false,
+            cwd: None,
+            umask: None,
+            env: Vec::new(),
+        })
+    }
+
+    /// Create a runner for a binary path taken from operator config
+    /// (`policy.zfs_path`). The path must canonicalize to a root-owned,
+    /// non-group/world-writable file named `zfs` or `zpool`, allowlisted or
+    /// not; PATH lookups never reach this constructor. The runner execs the
+    /// allowlist entry when there is one, else the canonical target.
+    pub fn new_configured<S: Into<String>>(path: S, timeout: Duration) -> Result<Self> {
+        let path_str = path.into();
+        let canonical = fs::canonicalize(&path_str)
+            .with_context(|| format!("canonicalize configured binary {}", path_str))?;
+        let name = canonical
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if !CONFIGURABLE_BINARIES.contains(&name) {
+            return Err(anyhow!(
+                "Configured binary {} resolves to {}, which is not a zfs/zpool binary",
+                path_str,
+                canonical.display()
+            ));
         }
+        let meta = fs::metadata(&canonical)
+            .with_context(|| format!("stat configured binary {}", canonical.display()))?;
+        if !meta.is_file() || meta.uid() != 0 || meta.mode() & 0o022 != 0 {
+            return Err(anyhow!(
+                "Configured binary {} must be a root-owned file without group/world write access",
+                canonical.display()
+            ));
+        }
+
+        let path = static_allowlist_entry(&path_str)
+            .or_else(|| extra_allowlist_entry(&path_str))
+            .unwrap_or_else(|| canonical.to_string_lossy().into_owned());
+        Ok(Self {
+            path,
+            timeout,
+            foreground: false,
+            cwd: None,
+            umask: None,
+            env: Vec::new(),
+        })
+    }
 
+    /// Runner for the first bash in `DRILL_SHELLS`, for the initramfs drill's
+    /// loader script only.
+    pub fn new_drill_shell(timeout: Duration) -> Result<Self> {
+        let path = DRILL_SHELLS
+            .iter()
+            .find(|p| Path::new(p).exists())
+            .ok_or_else(|| anyhow!("bash not found; the dracut loader requires it"))?;
         Ok(Self {
-            path: path_str,
+            path: path.to_string(),
             timeout,
+            foreground: false,
+            cwd: None,
+            umask: None,
+            env: Vec::new(),
         })
     }
 
+    /// Keep the child in beskar's own process group so it can prompt on the
+    /// controlling terminal (askpass, FIDO2 PIN). A timeout then only reaches
+    /// the direct child.
+    pub fn in_foreground(mut self) -> Self {
+        self.foreground = true;
+        self
+    }
+
+    /// Start the child in `dir` instead of beskar's cwd, so it never pins a
+    /// mount (e.g. an operator shell sitting on the token) it didn't need.
+    pub fn with_cwd(mut self, dir: impl AsRef<Path>) -> Self {
+        self.cwd = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Apply `mask` as the child's umask before exec.
+    pub fn with_umask(mut self, mask: libc::mode_t) -> Self {
+        self.umask = Some(mask);
+        self
+    }
+
+    /// Set `key=value` in the child's environment (e.g. a shimmed `PATH`).
+    pub fn with_env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
     /// Run command with arguments, returning `OutputData`
     pub fn run(&self, args: &[&str], input: Option<&[u8]>) -> Result<OutputData> {
+        self.run_with_timeout(args, input, self.timeout)
+    }
+
+    /// Like `run`, but with a limit for this invocation only, so one handle
+    /// can serve quick probes and slow operations alike.
+    pub fn run_with_timeout(
+        &self,
+        args: &[&str],
+        input: Option<&[u8]>,
+        timeout: Duration,
+    ) -> Result<OutputData> {
+        let start = Instant::now();
+        let result = self.spawn(args, input).and_then(|mut child| {
+            let stdout = child.stdout.take();
+            let stderr = child.stderr.take();
+            self.wait_with_timeout(child, stdout, stderr, timeout)
+        });
+        self.audit_invocation(args, result.as_ref().map(|out| out.termination), start);
+        result
+    }
+
+    /// Like `run`, but for tools whose stdout is a secret (askpass, plymouth,
+    /// the FIDO2 hmac-secret): stdout is read straight into a zeroizing
+    /// buffer of `SECRET_OUTPUT_MAX` bytes, with no lossy UTF-8 copy.
+    pub fn run_secret(&self, args: &[&str], input: Option<&[u8]>) -> Result<SecretOutput> {
+        let start = Instant::now();
+        let result = self.spawn(args, input).and_then(|mut child| {
+            let stdout_handle = Self::spawn_secret_reader(child.stdout.take());
+            let stderr_handle = Self::spawn_output_reader(child.stderr.take());
+            let exit_status = self.await_exit(&mut child, self.timeout);
+            let stdout = stdout_handle
+                .join()
+                .map_err(|_| anyhow!("stdout reader thread panicked"))??;
+            let stderr = stderr_handle
+                .join()
+                .map_err(|_| anyhow!("stderr reader thread panicked"))??;
+            let Some(exit_status) = exit_status? else {
+                return Err(anyhow!("Command timed out after {:?}", self.timeout));
+            };
+            let termination = Termination::from_exit(Some(exit_status));
+            Ok(SecretOutput {
+                stdout,
+                stderr,
+                status: termination.status(),
+                termination,
+            })
+        });
+        self.audit_invocation(args, result.as_ref().map(|out| out.termination), start);
+        result
+    }
+
+    /// Like `run`, but any outcome other than exit status 0 becomes an error
+    /// naming the binary, redacted args, termination, and trimmed stderr (or
+    /// stdout when stderr is empty).
+    pub fn run_checked(&self, args: &[&str], input: Option<&[u8]>) -> Result<OutputData> {
+        let out = self.run(args, input)?;
+        self.ensure_success(args, out)
+    }
+
+    /// The check behind `run_checked`, for output obtained another way
+    /// (`run_with_timeout`, `run_streaming`).
+    pub fn ensure_success(&self, args: &[&str], out: OutputData) -> Result<OutputData> {
+        if out.termination != Termination::Exited(0) {
+            let stderr = out.stderr.trim();
+            let detail = if stderr.is_empty() {
+                out.stdout.trim()
+            } else {
+                stderr
+            };
+            return Err(anyhow!(
+                "{} {} failed ({}): {}",
+                self.path,
+                redact_args(args).join(" "),
+                out.termination,
+                detail
+            ));
+        }
+        Ok(out)
+    }
+
+    /// Like `run`, but hands each stdout/stderr line to `on_line` as it arrives
+    /// so long-running tools (dracut, update-initramfs) can report progress.
+    /// The aggregate `OutputData` is still returned for error handling.
+    pub fn run_streaming<F>(&self, args: &[&str], on_line: F) -> Result<OutputData>
+    where
+        F: FnMut(&str),
+    {
+        let start = Instant::now();
+        let result = self.stream_output(args, on_line);
+        self.audit_invocation(args, result.as_ref().map(|out| out.termination), start);
+        result
+    }
+
+    fn stream_output<F>(&self, args: &[&str], mut on_line: F) -> Result<OutputData>
+    where
+        F: FnMut(&str),
+    {
+        let mut child = self.spawn(args, None)?;
+        let (tx, rx) = mpsc::channel();
+        let stdout_handle = Self::spawn_line_reader(child.stdout.take(), tx.clone());
+        let stderr_handle = Self::spawn_line_reader(child.stderr.take(), tx);
+        let start = Instant::now();
+        let mut timed_out = false;
+        let mut streaming = true;
+        let mut delay = WAIT_POLL_MIN;
+
+        let exit_status = loop {
+            if let Some(status) = child.try_wait()? {
+                break Some(status);
+            }
+            if start.elapsed() > self.timeout {
+                timed_out = true;
+                self.terminate(&mut child);
+                break None;
+            }
+            if streaming {
+                match rx.recv_timeout(Duration::from_millis(50)) {
+                    Ok(line) => on_line(&line),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // Both pipes closed while the child runs on; recv would
+                    // return at once from here, so poll with backoff instead.
+                    Err(RecvTimeoutError::Disconnected) => streaming = false,
+                }
+            } else {
+                thread::sleep(delay);
+                delay = next_poll_delay(delay);
+            }
+        };
+
+        let stdout = stdout_handle
+            .join()
+            .map_err(|_| anyhow!("stdout reader thread panicked"))??;
+        let stderr = stderr_handle
+            .join()
+            .map_err(|_| anyhow!("stderr reader thread panicked"))??;
+        for line in rx.try_iter() {
+            on_line(&line);
+        }
+
+        if timed_out {
+            return Err(anyhow!("Command timed out after {:?}", self.timeout));
+        }
+
+        let termination = Termination::from_exit(exit_status);
+        Ok(OutputData {
+            stdout,
+            stderr,
+            status: termination.status(),
+            termination,
+        })
+    }
+
+    /// Emit a `CMD_EXEC` entry when `audit.log_commands` is on.
+    fn audit_invocation(
+        &self,
+        args: &[&str],
+        result: Result<Termination, &anyhow::Error>,
+        start: Instant,
+    ) {
+        if !LOG_COMMANDS.load(Ordering::Relaxed) {
+            return;
+        }
+        let outcome = match result {
+            Ok(termination) => termination.to_string(),
+            Err(err) => format!("error={}", err),
+        };
+        audit_log(
+            "CMD_EXEC",
+            &format!(
+                "{} [{}] {} in {}ms",
+                self.path,
+                redact_args(args).join(" "),
+                outcome,
+                start.elapsed().as_millis()
+            ),
+        );
+    }
+
+    fn spawn(&self, args: &[&str], input: Option<&[u8]>) -> Result<Child> {
         let mut command = Command::new(&self.path);
         command.args(args);
+        // Callers parse tool output (e.g. ZfsError classification); pin the locale.
+        command.env("LC_ALL", "C");
+        for (key, value) in &self.env {
+            command.env(key, value);
+        }
         command.stdout(Stdio::piped());
         command.stderr(Stdio::piped());
+        if !self.foreground {
+            // Own process group, so a timeout can also reach grandchildren
+            // (dracut's cpio/compressors) that would otherwise hold /boot.
+            command.process_group(0);
+        }
+        if let Some(dir) = &self.cwd {
+            command.current_dir(dir);
+        }
+        if let Some(mask) = self.umask {
+            // SAFETY: umask(2) is async-signal-safe and touches no shared state.
+            unsafe {
+                command.pre_exec(move || {
+                    libc::umask(mask);
+                    Ok(())
+                });
+            }
+        }
 
         if input.is_some() {
             command.stdin(Stdio::piped());
@@ -100,10 +688,32 @@ impl Cmd {
             }
         }
 
-        let stdout = child.stdout.take();
-        let stderr = child.stderr.take();
+        Ok(child)
+    }
 
-        self.wait_with_timeout(child, stdout, stderr)
+    /// Stop a timed-out child: SIGTERM its process group, give it
+    /// `GROUP_KILL_GRACE` to exit, then SIGKILL whatever remains.
+    fn terminate(&self, child: &mut Child) {
+        if self.foreground {
+            let _ = child.kill();
+            let _ = child.wait();
+            return;
+        }
+
+        let group = -(child.id() as libc::pid_t);
+        // SAFETY: kill(2) with a negative pid only signals the group we created
+        // in `spawn`; it has no memory-safety preconditions.
+        unsafe { libc::kill(group, libc::SIGTERM) };
+        let deadline = Instant::now() + GROUP_KILL_GRACE;
+        while Instant::now() < deadline {
+            if matches!(child.try_wait(), Ok(Some(_))) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        // The leader may be gone while grandchildren linger; sweep the group.
+        unsafe { libc::kill(group, libc::SIGKILL) };
+        let _ = child.wait();
     }
 
     fn wait_with_timeout(
@@ -111,31 +721,11 @@ impl Cmd {
         mut child: Child,
         stdout_pipe: Option<ChildStdout>,
         stderr_pipe: Option<ChildStderr>,
+        timeout: Duration,
     ) -> Result<OutputData> {
-        let timeout = self.timeout;
-        let start = Instant::now();
         let stdout_handle = Self::spawn_output_reader(stdout_pipe);
         let stderr_handle = Self::spawn_output_reader(stderr_pipe);
-        let mut exit_status = None;
-        let mut timed_out = false;
-
-        loop {
-            match child.try_wait()? {
-                Some(status) => {
-                    exit_status = Some(status);
-                    break;
-                }
-                None => {
-                    if start.elapsed() > timeout {
-                        timed_out = true;
-                        let _ = child.kill();
-                        let _ = child.wait();
-                        break;
-                    }
-                    thread::sleep(Duration::from_millis(50));
-                }
-            }
-        }
+        let exit_status = self.await_exit(&mut child, timeout);
 
         let stdout = stdout_handle
             .join()
@@ -144,16 +734,91 @@ impl Cmd {
             .join()
             .map_err(|_| anyhow!("stderr reader thread panicked"))??;
 
-        if timed_out {
+        let Some(exit_status) = exit_status? else {
             return Err(anyhow!("Command timed out after {:?}", timeout));
-        }
-
-        let status = exit_status.map(|s| s.code().unwrap_or(-1)).unwrap_or(-1);
+        };
 
+        let termination = Termination::from_exit(Some(exit_status));
         Ok(OutputData {
             stdout,
             stderr,
-            status,
+            status: termination.status(),
+            termination,
+        })
+    }
+
+    /// Poll `child` with backoff until it exits (`Some`) or `timeout` passes,
+    /// in which case its process group is torn down and `None` returned.
+    fn await_exit(&self, child: &mut Child, timeout: Duration) -> Result<Option<ExitStatus>> {
+        let start = Instant::now();
+        let mut delay = WAIT_POLL_MIN;
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(Some(status));
+            }
+            if start.elapsed() > timeout {
+                self.terminate(child);
+                return Ok(None);
+            }
+            thread::sleep(delay);
+            delay = next_poll_delay(delay);
+        }
+    }
+
+    fn spawn_line_reader<R>(
+        pipe: Option<R>,
+        lines: Sender<String>,
+    ) -> thread::JoinHandle<Result<String>>
+    where
+        R: Read + Send + 'static,
+    {
+        thread::spawn(move || -> Result<String> {
+            let mut collected = String::new();
+            if let Some(reader) = pipe {
+                let mut reader = BufReader::new(reader);
+                let mut buf = Vec::new();
+                loop {
+                    buf.clear();
+                    let read = reader
+                        .read_until(b'\n', &mut buf)
+                        .context("read child process pipe")?;
+                    if read == 0 {
+                        break;
+                    }
+                    let line = String::from_utf8_lossy(&buf);
+                    collected.push_str(&line);
+                    let _ = lines.send(line.trim_end().to_string());
+                }
+            }
+            Ok(collected)
+        })
+    }
+
+    /// Read a secret reply into one pre-sized zeroizing buffer; output beyond
+    /// `SECRET_OUTPUT_MAX` is refused rather than grown into.
+    fn spawn_secret_reader<R>(pipe: Option<R>) -> thread::JoinHandle<Result<Zeroizing<Vec<u8>>>>
+    where
+        R: Read + Send + 'static,
+    {
+        thread::spawn(move || -> Result<Zeroizing<Vec<u8>>> {
+            let mut buf = Zeroizing::new(vec![0u8; SECRET_OUTPUT_MAX]);
+            let mut filled = 0;
+            if let Some(mut reader) = pipe {
+                loop {
+                    if filled == buf.len() {
+                        return Err(anyhow!("secret output exceeds {} bytes", SECRET_OUTPUT_MAX));
+                    }
+                    match reader.read(&mut buf[filled..]) {
+                        Ok(0) => break,
+                        Ok(read) => filled += read,
+                        Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                        Err(err) => return Err(err).context("read child process pipe"),
+                    }
+                }
+            }
+            // Shrinking keeps the allocation; the tail is wiped with it on drop.
+            buf.truncate(filled);
+            Ok(buf)
         })
     }
 
@@ -178,8 +843,32 @@ impl Cmd {
 #[cfg(test)]
 mod tests {
     use super::Cmd;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
     use std::time::Duration;
 
+    /// Test-only allowlist: a POSIX shell drives the runner's process-group,
+    /// signal and umask handling without a shell in `ALLOWED_BINARIES`.
+    const TEST_BINARIES: &[&str] = &["/bin/sh"];
+
+    /// A runner for a `TEST_BINARIES` entry. POSIX guarantees `/bin/sh`, so a
+    /// host without it fails loudly instead of passing these tests vacuously.
+    fn test_cmd(path: &str, timeout: Duration) -> Cmd {
+        assert!(TEST_BINARIES.contains(&path), "{path} is not a test binary");
+        assert!(
+            std::path::Path::new(path).exists(),
+            "{path} is missing; the runner tests need it"
+        );
+        Cmd {
+            path: path.to_string(),
+            timeout,
+            foreground: false,
+            cwd: None,
+            umask: None,
+            env: Vec::new(),
+        }
+    }
+
     #[test]
     fn zfs_discover_paths_are_allowlisted() {
         let zfs_paths = [
@@ -196,4 +885,176 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn streaming_run_forwards_lines_and_keeps_output() {
+        if !std::path::Path::new("/usr/bin/lsblk").exists() {
+            return;
+        }
+        let cmd = Cmd::new_allowlisted("/usr/bin/lsblk", Duration::from_secs(5)).unwrap();
+        let mut seen = Vec::new();
+        let out = cmd
+            .run_streaming(&["--version"], |line| seen.push(line.to_string()))
+            .unwrap();
+        assert_eq!(out.status, 0);
+        assert_eq!(seen.join("\n"), out.stdout.trim_end());
+    }
+
+    #[test]
+    fn command_audit_redacts_key_material() {
+        let key = "ab".repeat(32);
+        assert_eq!(
+            super::redact_args(&["change-key", "-o", "keylocation=file:///k", "rpool"]),
+            vec!["change-key", "<redacted>", "<redacted>", "<redacted>"]
+        );
+        assert_eq!(
+            super::redact_args(&["get", "-H", &format!("x{}", key), "rpool"]),
+            vec!["get", "-H", "<redacted>", "rpool"]
+        );
+        assert_eq!(
+            super::redact_args(&["get", &"ab".repeat(31)]),
+            vec!["get", &"ab".repeat(31)]
+        );
+    }
+
+    #[test]
+    fn timeout_reaps_the_whole_process_group() {
+        // The handle's own limit is generous; the per-call override must win.
+        let cmd = test_cmd("/bin/sh", Duration::from_secs(60));
+        let started = std::time::Instant::now();
+        // The backgrounded sleep inherits stdout; if it survived the timeout,
+        // the pipe reader would block until it exited.
+        let result =
+            cmd.run_with_timeout(&["-c", "sleep 30 & wait"], None, Duration::from_millis(200));
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn wait_poll_doubles_to_the_cap() {
+        let mut delay = super::WAIT_POLL_MIN;
+        let mut schedule = Vec::new();
+        for _ in 0..8 {
+            schedule.push(delay.as_millis());
+            delay = super::next_poll_delay(delay);
+        }
+        assert_eq!(schedule, vec![1, 2, 4, 8, 16, 32, 50, 50]);
+    }
+
+    /// Wall-clock bound, so it only runs on request (`cargo test -- --ignored`);
+    /// the old fixed 50ms sleep could never get under it.
+    #[test]
+    #[ignore = "timing-sensitive"]
+    fn wait_poll_reaps_a_fast_child_quickly() {
+        let cmd = test_cmd("/bin/sh", Duration::from_secs(5));
+        let fastest = (0..5)
+            .map(|_| {
+                let started = std::time::Instant::now();
+                cmd.run(&["-c", ":"], None).unwrap();
+                started.elapsed()
+            })
+            .min()
+            .unwrap();
+        assert!(fastest < Duration::from_millis(40), "took {:?}", fastest);
+    }
+
+    #[test]
+    fn secret_output_stays_in_one_presized_buffer() {
+        let cmd = test_cmd("/bin/sh", Duration::from_secs(5));
+        let out = cmd
+            .run_secret(&["-c", "printf 'hunter2\\n'"], None)
+            .unwrap();
+        assert_eq!(&out.stdout[..], b"hunter2\n");
+        assert_eq!(out.stdout.capacity(), super::SECRET_OUTPUT_MAX);
+
+        let flood = format!("head -c {} /dev/zero", super::SECRET_OUTPUT_MAX + 1);
+        assert!(cmd.run_secret(&["-c", &flood], None).is_err());
+    }
+
+    #[test]
+    fn cwd_and_umask_apply_to_the_child_only() {
+        let cmd = test_cmd("/bin/sh", Duration::from_secs(5));
+        let cmd = cmd.with_cwd("/").with_umask(0o077);
+        let out = cmd.run(&["-c", "pwd; umask"], None).unwrap();
+        assert_eq!(out.stdout, "/\n0077\n");
+    }
+
+    #[test]
+    fn signal_deaths_are_reported_distinctly() {
+        let cmd = test_cmd("/bin/sh", Duration::from_secs(5));
+        let out = cmd.run(&["-c", "kill -SEGV $$"], None).unwrap();
+        assert_eq!(out.termination, super::Termination::Signaled(libc::SIGSEGV));
+        assert_eq!(out.status, -1);
+        assert_eq!(
+            out.termination.to_string(),
+            "terminated by signal 11 (SIGSEGV)"
+        );
+
+        let out = cmd.run(&["-c", "exit 3"], None).unwrap();
+        assert_eq!(out.termination, super::Termination::Exited(3));
+    }
+
+    #[test]
+    fn usr_merge_aliases_pass_while_foreign_symlinks_do_not() {
+        use std::os::unix::fs::symlink;
+
+        let farm = tempfile::tempdir().unwrap();
+        let root = farm.path();
+        fs::create_dir_all(root.join("usr/bin")).unwrap();
+        fs::create_dir_all(root.join("elsewhere")).unwrap();
+        fs::create_dir_all(root.join("tmp/x")).unwrap();
+        fs::write(root.join("usr/bin/systemctl"), "#!/bin/sh\n").unwrap();
+        fs::write(root.join("elsewhere/systemctl"), "#!/bin/sh\n").unwrap();
+        symlink(root.join("usr/bin"), root.join("bin")).unwrap();
+        symlink(root.join("elsewhere/systemctl"), root.join("usr/bin/evil")).unwrap();
+        symlink(root.join("usr/bin/systemctl"), root.join("usr/bin/rm")).unwrap();
+        symlink(root.join("usr/bin/systemctl"), root.join("tmp/x/systemctl")).unwrap();
+
+        let entry = root
+            .join("usr/bin/systemctl")
+            .to_string_lossy()
+            .into_owned();
+        let literal = [entry.as_str()];
+        let canonical = super::canonicalize_all(literal);
+        let check =
+            |p: &str| super::allowlisted_in(&root.join(p).to_string_lossy(), &literal, &canonical);
+
+        assert_eq!(check("usr/bin/systemctl").as_deref(), Some(entry.as_str()));
+        assert_eq!(check("bin/systemctl").as_deref(), Some(entry.as_str()));
+        assert_eq!(check("usr/bin/evil"), None);
+        assert_eq!(check("bin/rm"), None);
+        assert_eq!(check("tmp/x/systemctl"), None);
+        assert_eq!(check("elsewhere/systemctl"), None);
+        assert_eq!(check("bin/missing"), None);
+    }
+
+    #[test]
+    fn extra_allowlist_rejects_relative_and_unowned_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let loose = dir.path().join("mkfs.ext4");
+        fs::write(&loose, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&loose, fs::Permissions::from_mode(0o775)).unwrap();
+        let loose = loose.to_string_lossy().into_owned();
+        let stale = dir.path().join("gone").to_string_lossy().into_owned();
+
+        let rejected =
+            super::extend_allowlist(&["mkfs.ext4".to_string(), loose.clone(), stale.clone()]);
+        assert_eq!(rejected.len(), 3);
+        assert!(!super::is_allowlisted(&loose));
+        assert!(!super::is_allowlisted(&stale));
+    }
+
+    #[test]
+    fn configured_paths_only_admit_vetted_zfs_binaries() {
+        let timeout = Duration::from_secs(1);
+        assert!(Cmd::new_allowlisted("/bin/sh", timeout).is_err());
+        assert!(Cmd::new_configured("/bin/sh", timeout).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let fake = dir.path().join("zfs");
+        fs::write(&fake, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&fake, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(Cmd::new_configured(fake.to_string_lossy(), timeout).is_err());
+        assert!(Cmd::new_allowlisted(fake.to_string_lossy(), timeout).is_err());
+    }
 }
@@ -0,0 +1,193 @@
+// ============================================================================
+// src/cmd/benchmark.rs – unlock path latency, phase by phase
+// ============================================================================
+
+use crate::config::ConfigFile;
+use crate::ui::{Pace, Timing, UX};
+use crate::util::audit::audit_log;
+use crate::util::keyfile::{read_key_material, read_key_material_strict};
+use crate::zfs::{not_encrypted, KeyTreeTimings, Zfs};
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Wall-clock samples per phase, one entry per iteration.
+#[derive(Debug, Default)]
+pub struct BenchSamples {
+    pub key_read: Vec<Duration>,
+    pub checksum: Vec<Duration>,
+    pub root_load: Vec<Duration>,
+    pub descendant_load: Vec<Duration>,
+    pub total: Vec<Duration>,
+}
+
+pub fn run_benchmark(
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    dataset: &str,
+    iterations: usize,
+) -> Result<()> {
+    ui.banner();
+    let zfs = Zfs::from_config(cfg)?;
+    let root = match zfs.encryption_root(dataset)? {
+        Some(root) => root,
+        None => return Err(not_encrypted(dataset)),
+    };
+    // Every iteration ends in unload-key; only a sealed root can take that
+    // without disturbing mounted filesystems.
+    if zfs.is_unlocked(&root)? {
+        return Err(anyhow!(
+            "{} is unlocked; seal it first (`lock`) or benchmark the holoforge scaffold with --simulate",
+            root
+        ));
+    }
+    timing.pace(Pace::Info);
+
+    let samples = measure(ui, &zfs, cfg, &root, iterations)?;
+    report(ui, &root, &samples);
+    Ok(())
+}
+
+/// Time `iterations` full key read → checksum → `load_key_tree` passes
+/// against a sealed `root`, unloading the tree after each one. Only the USB
+/// key file is used; fallback prompts are never consulted.
+pub fn measure(
+    ui: &UX,
+    zfs: &Zfs,
+    cfg: &ConfigFile,
+    root: &str,
+    iterations: usize,
+) -> Result<BenchSamples> {
+    if iterations == 0 {
+        return Err(anyhow!("--iterations must be at least 1"));
+    }
+    let key_path = Path::new(&cfg.usb.key_hex_path);
+    let mut samples = BenchSamples::default();
+    ui.info(&format!(
+        "Timing {} unlock pass(es) of {} from {}.",
+        iterations,
+        root,
+        key_path.display()
+    ));
+
+    for iteration in 1..=iterations {
+        let started = Instant::now();
+        let material = if cfg.usb.strict_keyfile {
+            read_key_material_strict(key_path)
+        } else {
+            read_key_material(key_path)
+        }
+        .with_context(|| format!("read key file {}", key_path.display()))?;
+        samples.key_read.push(started.elapsed());
+
+        let checksum_started = Instant::now();
+        if let Some(expected) = &cfg.usb.expected_sha256 {
+            if !cfg.usb.checksum_algo.matches(&material.raw, expected) {
+                return Err(anyhow!(
+                    "USB key checksum mismatch on iteration {}",
+                    iteration
+                ));
+            }
+        }
+        samples.checksum.push(checksum_started.elapsed());
+
+        let mut tree = KeyTreeTimings::default();
+        zfs.load_key_tree(root, &material.raw, &mut tree)
+            .and_then(|outcome| outcome.strict(root))
+            .with_context(|| format!("load key tree {} (iteration {})", root, iteration))?;
+        samples.root_load.push(tree.root);
+        samples.descendant_load.push(tree.descendants);
+        samples.total.push(started.elapsed());
+
+        let report = zfs.unload_key_tree(root)?;
+        if let Some((dataset, why)) = report.busy.iter().chain(&report.failed).next() {
+            return Err(anyhow!(
+                "could not reseal {} after iteration {}: {}",
+                dataset,
+                iteration,
+                why
+            ));
+        }
+        ui.trace(&format!(
+            "iteration {}: {} ms",
+            iteration,
+            started.elapsed().as_millis()
+        ));
+    }
+    Ok(samples)
+}
+
+pub fn report(ui: &UX, root: &str, samples: &BenchSamples) {
+    let phases: [(&str, &[Duration]); 5] = [
+        ("Key read", &samples.key_read),
+        ("Checksum", &samples.checksum),
+        ("Root load-key", &samples.root_load),
+        ("Descendant load-key", &samples.descendant_load),
+        ("Total", &samples.total),
+    ];
+    let mut rows = Vec::new();
+    let mut audit = Vec::new();
+    for (label, values) in phases {
+        let (min, median, max) = spread(values);
+        rows.push((
+            label,
+            format!(
+                "min {} / median {} / max {}",
+                fmt_ms(min),
+                fmt_ms(median),
+                fmt_ms(max)
+            ),
+        ));
+        audit.push(format!(
+            "{}_median_us={}",
+            label.to_lowercase().replace([' ', '-'], "_"),
+            median.as_micros()
+        ));
+    }
+    ui.data_panel(
+        &format!(
+            "Unlock benchmark // {} ({} runs)",
+            root,
+            samples.total.len()
+        ),
+        &rows,
+    );
+    audit_log(
+        "BENCHMARK",
+        &format!(
+            "root={} iterations={} {}",
+            root,
+            samples.total.len(),
+            audit.join(" ")
+        ),
+    );
+}
+
+/// Min, median (lower middle for even counts), and max of `values`.
+fn spread(values: &[Duration]) -> (Duration, Duration, Duration) {
+    let mut sorted = values.to_vec();
+    sorted.sort();
+    match (sorted.first(), sorted.last()) {
+        (Some(&min), Some(&max)) => (min, sorted[(sorted.len() - 1) / 2], max),
+        _ => Default::default(),
+    }
+}
+
+fn fmt_ms(d: Duration) -> String {
+    format!("{:.2} ms", d.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::spread;
+    use std::time::Duration;
+
+    #[test]
+    fn spread_reports_min_median_max() {
+        let ms = Duration::from_millis;
+        assert_eq!(spread(&[ms(9), ms(1), ms(5)]), (ms(1), ms(5), ms(9)));
+        assert_eq!(spread(&[ms(4), ms(2), ms(8), ms(6)]), (ms(2), ms(4), ms(8)));
+        assert_eq!(spread(&[]), Default::default());
+    }
+}
@@ -0,0 +1,100 @@
+// ============================================================================
+// src/cmd/breakglass.rs – Seal a shared break-glass passphrase onto the token
+// ============================================================================
+
+use crate::cmd::recover::verify_fingerprint;
+use crate::config::{persist_config, ConfigFile};
+use crate::ui::{require_terminal, Pace, Timing, UX};
+use crate::util::atomic::atomic_write_bytes;
+use crate::util::audit::audit_log;
+use crate::util::breakglass;
+use crate::util::keyfile::read_key_material;
+use crate::util::token::remount_writable;
+use anyhow::{anyhow, Context, Result};
+use dialoguer::Password;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+const BREAK_GLASS_FILE: &str = "beskar.breakglass";
+pub(crate) const BREAK_GLASS_ITERS: u32 = 250_000;
+/// A passphrase shared across an on-call rotation outlives any one holder;
+/// keep it out of casual guessing range.
+pub(crate) const MIN_PASSPHRASE_LEN: usize = 12;
+
+/// Wrap the current token key under a break-glass passphrase, write the blob
+/// next to the key, and point `[usb] break_glass_path` at it.
+pub fn run_set_break_glass(
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    path: Option<&Path>,
+) -> Result<()> {
+    ui.banner();
+    ui.phase("Break-Glass // Sealing");
+
+    let key_path = Path::new(&cfg.usb.key_hex_path);
+    let key = read_key_material(key_path)?;
+    verify_fingerprint(cfg, &key.raw)?;
+
+    let target = match (path, cfg.usb.break_glass_path.as_deref()) {
+        (Some(path), _) => path.to_path_buf(),
+        (None, Some(configured)) => PathBuf::from(configured),
+        (None, None) => key_path
+            .parent()
+            .ok_or_else(|| anyhow!("key path {} has no parent", key_path.display()))?
+            .join(BREAK_GLASS_FILE),
+    };
+
+    require_terminal("the break-glass passphrase prompt")?;
+    ui.warn("Anyone holding the token and this passphrase can unseal the vault; keep it to the on-call roster.");
+    timing.pace(Pace::Prompt);
+    let passphrase = Zeroizing::new(
+        Password::new()
+            .with_prompt("Break-glass passphrase")
+            .allow_empty_password(false)
+            .interact()
+            .context("break-glass passphrase prompt failed")?,
+    );
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(anyhow!(
+            "Break-glass passphrase must be at least {} characters.",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+    let confirm = Zeroizing::new(
+        Password::new()
+            .with_prompt("Confirm break-glass passphrase")
+            .allow_empty_password(false)
+            .interact()
+            .context("break-glass passphrase confirmation failed")?,
+    );
+    if passphrase != confirm {
+        return Err(anyhow!("Break-glass passphrases did not match."));
+    }
+
+    let blob = breakglass::seal(passphrase.as_bytes(), &key.raw, BREAK_GLASS_ITERS)?;
+    write_seal(ui, &target, &blob)?;
+
+    let target_str = target.to_string_lossy().into_owned();
+    if cfg.usb.break_glass_path.as_deref() != Some(target_str.as_str()) {
+        let mut updated = cfg.clone();
+        updated.usb.break_glass_path = Some(target_str.clone());
+        persist_config(&updated)?;
+    }
+    audit_log("BREAK_GLASS_SET", &format!("path={}", target_str));
+
+    ui.success(&format!("Break-glass seal etched at {}.", target.display()));
+    ui.note("Unlock offers it before the fallback passphrase when the primary key is absent or rejected.");
+    ui.note("Rerun set-break-glass after rotating the key; the old seal wraps the old key.");
+    ui.success("This is the Way.");
+    timing.pace(Pace::Critical);
+    Ok(())
+}
+
+/// Write a seal, lifting a read-only token mount (the default location sits
+/// on `run-beskar.mount`, which is `ro`) for just the write.
+pub(crate) fn write_seal(ui: &UX, target: &Path, blob: &str) -> Result<()> {
+    let _writable = remount_writable(ui, target)?;
+    atomic_write_bytes(target, blob.as_bytes(), 0o400, true)
+        .with_context(|| format!("write break-glass file {}", target.display()))
+}
@@ -0,0 +1,191 @@
+// ============================================================================
+// src/cmd/changepin.rs – Rotate a passphrase-wrapped key source in place
+// ============================================================================
+
+use crate::cmd::breakglass::{write_seal, BREAK_GLASS_ITERS, MIN_PASSPHRASE_LEN};
+use crate::cmd::init::{apply_passphrase_plan, seal_fallback_passphrase};
+use crate::cmd::unlock::recover_raw_key_from_passphrase;
+use crate::config::{persist_config, ConfigFile};
+use crate::ui::{require_terminal, Pace, Timing, UX};
+use crate::util::audit::audit_log;
+use crate::util::breakglass;
+use crate::util::keyfile::read_key_material;
+use crate::util::rawtoken;
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use dialoguer::Password;
+use std::fs;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Which passphrase-protected copy of the key `change-pin` rewraps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum PinSource {
+    /// The `[fallback]` passphrase sealed by `init`.
+    #[default]
+    Fallback,
+    /// The `set-break-glass` seal at `usb.break_glass_path`.
+    BreakGlass,
+}
+
+impl PinSource {
+    fn label(self) -> &'static str {
+        match self {
+            PinSource::Fallback => "fallback",
+            PinSource::BreakGlass => "break-glass",
+        }
+    }
+}
+
+/// Prove the old passphrase recovers the token key, then wrap that key under
+/// a new passphrase. The token, ZFS, and the key itself are left untouched.
+pub fn run_change_pin(ui: &UX, timing: &Timing, cfg: &ConfigFile, source: PinSource) -> Result<()> {
+    ui.banner();
+    ui.phase(&format!("Change PIN // {} seal", source.label()));
+    require_terminal("the change-pin prompts")?;
+
+    let result = match source {
+        PinSource::Fallback => change_fallback(ui, timing, cfg),
+        PinSource::BreakGlass => change_break_glass(ui, timing, cfg),
+    };
+    if let Err(err) = &result {
+        audit_log(
+            "CHANGE_PIN_FAIL",
+            &format!("source={} reason={}", source.label(), err),
+        );
+        return result;
+    }
+    audit_log("CHANGE_PIN", &format!("source={}", source.label()));
+    ui.success("This is the Way.");
+    timing.pace(Pace::Critical);
+    Ok(())
+}
+
+fn change_fallback(ui: &UX, timing: &Timing, cfg: &ConfigFile) -> Result<()> {
+    if !cfg.fallback.enabled || cfg.fallback.passphrase_xor.is_none() {
+        return Err(anyhow!(
+            "No fallback passphrase is sealed in the config; init sets one."
+        ));
+    }
+    timing.pace(Pace::Prompt);
+    let old = prompt_secret("Current fallback passphrase")?;
+    let raw = recover_raw_key_from_passphrase(&cfg.fallback, old.as_bytes())?;
+    prove_key(cfg, &raw).context("current fallback passphrase incorrect")?;
+    ui.success("Current passphrase recovers the token key.");
+
+    let new = prompt_new_secret("New fallback passphrase", 1)?;
+    let mut updated = cfg.clone();
+    apply_passphrase_plan(
+        &seal_fallback_passphrase(new.as_bytes(), &raw, cfg.fallback.passphrase_iters.max(1)),
+        &mut updated,
+    );
+    persist_config(&updated)?;
+    ui.success(&format!(
+        "Fallback passphrase resealed under a fresh salt in {}.",
+        cfg.path.display()
+    ));
+    ui.note("The old passphrase no longer opens the vault; the token key is unchanged.");
+    Ok(())
+}
+
+fn change_break_glass(ui: &UX, timing: &Timing, cfg: &ConfigFile) -> Result<()> {
+    let path = cfg.usb.break_glass_path.as_deref().ok_or_else(|| {
+        anyhow!("No break-glass seal configured (usb.break_glass_path); run set-break-glass.")
+    })?;
+    let path = Path::new(path);
+    let blob = fs::read_to_string(path)
+        .with_context(|| format!("read break-glass file {}", path.display()))?;
+
+    timing.pace(Pace::Prompt);
+    let old = prompt_secret("Current break-glass passphrase")?;
+    let raw = breakglass::open(&blob, old.as_bytes())?;
+    prove_key(cfg, &raw).context("break-glass seal wraps a stale key; rerun set-break-glass")?;
+    ui.success("Current passphrase opens the break-glass seal.");
+
+    let new = prompt_new_secret("New break-glass passphrase", MIN_PASSPHRASE_LEN)?;
+    let resealed = breakglass::seal(new.as_bytes(), &raw, BREAK_GLASS_ITERS)?;
+    write_seal(ui, path, &resealed)?;
+    ui.success(&format!(
+        "Break-glass seal rewrapped at {}.",
+        path.display()
+    ));
+    ui.note("Hand the new passphrase to the on-call roster; the old one no longer opens the seal.");
+    Ok(())
+}
+
+/// A wrong fallback passphrase still "decrypts" to 32 bytes, so the result is
+/// held to the recorded fingerprint, or to the token key when none is recorded.
+fn prove_key(cfg: &ConfigFile, raw: &[u8]) -> Result<()> {
+    if let Some(expected) = &cfg.usb.expected_sha256 {
+        if cfg.usb.checksum_algo.matches(raw, expected) {
+            return Ok(());
+        }
+        return Err(anyhow!("recovered key does not match usb.expected_sha256"));
+    }
+    let token = match cfg.usb.raw_device.as_deref() {
+        Some(device) => rawtoken::read_key(Path::new(device), cfg.usb.raw_offset),
+        None => read_key_material(Path::new(&cfg.usb.key_hex_path)).map(|m| m.raw),
+    }
+    .context("no usb.expected_sha256 recorded and the token key is unreadable")?;
+    if token[..] != *raw {
+        return Err(anyhow!("recovered key does not match the token key"));
+    }
+    Ok(())
+}
+
+fn prompt_secret(prompt: &str) -> Result<Zeroizing<String>> {
+    Ok(Zeroizing::new(
+        Password::new()
+            .with_prompt(prompt)
+            .allow_empty_password(false)
+            .interact()
+            .with_context(|| format!("{} prompt failed", prompt))?,
+    ))
+}
+
+fn prompt_new_secret(prompt: &str, min_len: usize) -> Result<Zeroizing<String>> {
+    let new = prompt_secret(prompt)?;
+    if new.chars().count() < min_len {
+        return Err(anyhow!(
+            "{} must be at least {} characters.",
+            prompt,
+            min_len
+        ));
+    }
+    let confirm = prompt_secret(&format!("Confirm {}", prompt.to_lowercase()))?;
+    if new != confirm {
+        return Err(anyhow!("Passphrases did not match."));
+    }
+    Ok(new)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::init::{seal_fallback_passphrase, PassphrasePlan};
+    use crate::cmd::unlock::recover_raw_key_from_passphrase;
+    use crate::config::Fallback;
+
+    #[test]
+    fn resealed_fallback_recovers_the_same_key_only_under_the_new_pin() {
+        let raw = [5u8; 32];
+        let PassphrasePlan::Configured {
+            salt_hex,
+            xor_hex,
+            iters,
+        } = seal_fallback_passphrase(b"new pin", &raw, 10)
+        else {
+            panic!("sealing always configures the fallback");
+        };
+        let fallback = Fallback {
+            enabled: true,
+            passphrase_salt: Some(salt_hex),
+            passphrase_xor: Some(xor_hex),
+            passphrase_iters: iters,
+            ..Fallback::default()
+        };
+        let recovered = recover_raw_key_from_passphrase(&fallback, b"new pin").unwrap();
+        assert_eq!(&recovered[..], &raw);
+        let stale = recover_raw_key_from_passphrase(&fallback, b"old pin").unwrap();
+        assert_ne!(&stale[..], &raw);
+    }
+}
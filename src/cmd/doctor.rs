@@ -7,25 +7,26 @@ use crate::cmd::init::{
     detect_initramfs_flavor, install_initramfs_tools_scripts, rebuild_initramfs, InitramfsFlavor,
     INITRAMFS_HOOK_PATH, INITRAMFS_LOCAL_TOP_PATH,
 };
-use crate::cmd::repair::{self, USB_MOUNT_UNIT};
+use crate::cmd::repair::{self, UNLOCK_UNIT, USB_MOUNT_UNIT};
 use crate::cmd::Cmd;
-use crate::config::ConfigFile;
+use crate::config::{persist_config, ConfigFile, CONFIG_VERSION};
 use crate::dracut::{self, ModuleContext, ModulePaths, DEFAULT_MOUNTPOINT};
 use crate::ui::{Pace, Timing, UX};
-use crate::util::atomic::atomic_write_toml;
 use crate::util::audit::audit_log;
 use crate::util::binary::determine_binary_path;
-use crate::util::keyfile::{ensure_raw_key_file, KeyEncoding};
-use crate::zfs::Zfs;
+use crate::util::keyfile::{ensure_raw_key_file, read_key_material, KeyEncoding};
+use crate::util::token::find_labeled_tokens;
+use crate::util::wear;
+use crate::zfs::{not_encrypted, Zfs, KEY_LEN};
+use crate::zpool::{pool_of, PoolState, Zpool};
 use anyhow::{anyhow, Result};
-use sha2::{Digest, Sha256};
 use std::fs;
-use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-const CONFIG_PATH: &str = "/etc/zfs-beskar.toml";
-const UNLOCK_UNIT_NAME: &str = "beskar-unlock.service";
+const ZFS_MOUNT_UNIT: &str = "zfs-mount.service";
+/// Directory initramfs-tools installs hooks and scripts under.
+const INITRAMFS_TOOLS_ROOT: &str = "/etc/initramfs-tools/";
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Status {
@@ -52,6 +53,206 @@ struct ReportEntry {
     detail: String,
 }
 
+/// Check categories that `doctor --fix-only` can allow to mutate state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepairCategory {
+    Units,
+    Dracut,
+    Keylocation,
+    Checksum,
+    Binary,
+}
+
+impl RepairCategory {
+    const ALL: [RepairCategory; 5] = [
+        RepairCategory::Units,
+        RepairCategory::Dracut,
+        RepairCategory::Keylocation,
+        RepairCategory::Checksum,
+        RepairCategory::Binary,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            RepairCategory::Units => "units",
+            RepairCategory::Dracut => "dracut",
+            RepairCategory::Keylocation => "keylocation",
+            RepairCategory::Checksum => "checksum",
+            RepairCategory::Binary => "binary",
+        }
+    }
+
+    /// Parse a comma-separated `--fix-only` list such as `units,dracut`.
+    pub fn parse_list(raw: &str) -> Result<Vec<Self>> {
+        let mut categories = Vec::new();
+        for item in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let category = Self::ALL
+                .iter()
+                .copied()
+                .find(|c| c.name().eq_ignore_ascii_case(item))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Unknown --fix-only category '{}' (expected {})",
+                        item,
+                        Self::ALL.map(Self::name).join(", ")
+                    )
+                })?;
+            if !categories.contains(&category) {
+                categories.push(category);
+            }
+        }
+        if categories.is_empty() {
+            return Err(anyhow!("--fix-only needs at least one category"));
+        }
+        Ok(categories)
+    }
+}
+
+/// Doctor run options. `fix_only = None` keeps the full-repair sweep.
+#[derive(Clone, Debug, Default)]
+pub struct DoctorOptions {
+    pub fix_only: Option<Vec<RepairCategory>>,
+    /// Follow the report with remediation notes for every warning or failure.
+    pub explain: bool,
+}
+
+impl DoctorOptions {
+    fn allows(&self, category: RepairCategory) -> bool {
+        self.fix_only
+            .as_ref()
+            .map(|list| list.contains(&category))
+            .unwrap_or(true)
+    }
+
+    /// Repairs outside the named categories only run in a full sweep.
+    fn full_repair(&self) -> bool {
+        self.fix_only.is_none()
+    }
+}
+
+fn read_only(detail: String, category: Option<RepairCategory>) -> String {
+    match category {
+        Some(category) => format!(
+            "{} (read-only; allow with --fix-only={})",
+            detail,
+            category.name()
+        ),
+        None => format!("{} (read-only; rerun doctor without --fix-only)", detail),
+    }
+}
+
+/// Remediation notes for `doctor --explain`, keyed by `ReportEntry::name`.
+/// They live in the binary so a rescue shell without network still has them.
+const REMEDIATION: &[(&str, &str)] = &[
+    (
+        "zfs",
+        "Install the ZFS userland (`apt install zfsutils-linux`, `dnf install zfs`) and load the module with `modprobe zfs`. Set `[policy] zfs_path` if the binary lives outside the searched paths.",
+    ),
+    (
+        "zpool",
+        "`zpool` ships with the same package as `zfs`; install zfsutils-linux (or your distribution's zfs package) and confirm with `zpool version`.",
+    ),
+    (
+        "systemctl",
+        "Boot integration needs systemd. On hosts without it, skip `install-units` and unlock with `zfs_beskar_key unlock` from your own init scripts.",
+    ),
+    (
+        "Initramfs tooling",
+        "Install dracut (`apt install dracut` / `dnf install dracut`) or keep initramfs-tools with `update-initramfs` on PATH, then rerun `doctor` so the boot loader module can be installed.",
+    ),
+    (
+        "Config file",
+        "Run `sudo zfs_beskar_key init` to forge a token and write /etc/zfs-beskar.toml. If init left a backup, restore the newest with `cp /etc/zfs-beskar.toml.bak-<stamp> /etc/zfs-beskar.toml && chmod 600 /etc/zfs-beskar.toml`.",
+    ),
+    (
+        "Config schema",
+        "The config predates this build's schema and is upgraded in memory on every load. Rerun `sudo zfs_beskar_key doctor` without --fix-only to rewrite it (a `.bak-<stamp>` copy is kept beside it); comments in the old file are not carried over.",
+    ),
+    (
+        "Dataset roster",
+        "List the datasets to unlock under `[policy] datasets = [\"pool/dataset\"]` in /etc/zfs-beskar.toml, then run `sudo zfs_beskar_key install-units` so the boot unit covers them.",
+    ),
+    (
+        "Encryption root",
+        "Check the dataset with `zfs get -H encryption,encryptionroot <dataset>`. A dataset with encryptionroot '-' is not encrypted and cannot be guarded. Otherwise point `[policy] datasets` at the encryption root shown, or rerun doctor without --fix-only to realign it.",
+    ),
+    (
+        "Shared token key",
+        "Every root in `[policy] datasets` is opened with the one token key. Rekey the odd root onto it with `zfs change-key -o keylocation=file://<key path> -o keyformat=raw <root>` while it is unlocked, or give that root its own config and token and select it with `--config`.",
+    ),
+    (
+        "Keylocation",
+        "Make `[usb] key_hex_path` an absolute path such as /run/beskar/beskar.key, then align ZFS with `zfs set keylocation=file:///run/beskar/beskar.key <encryption root>` for every root, or rerun `doctor --fix-only=keylocation`.",
+    ),
+    (
+        "Binary path",
+        "Install the binary (`install -m 0755 zfs_beskar_key /usr/local/bin/`) and set `[policy] binary_path` to that path, then run `sudo zfs_beskar_key install-units` so the unit's ExecStart follows.",
+    ),
+    (
+        "Token label",
+        "Insert the token and look for it with `lsblk -o NAME,LABEL`. A token carrying a different label can be relabelled with `e2label /dev/sdX1 BESKARKEY`, or set `[usb] label` to match it.",
+    ),
+    (
+        "USB key file",
+        "Mount the token (`systemctl start run-beskar.mount`) and check `ls -l` on `[usb] key_hex_path`. A missing key means the token must be re-forged with `zfs_beskar_key recover` or `init --force`; legacy hex contents are converted by `doctor --fix-only=checksum`.",
+    ),
+    (
+        "USB checksum",
+        "Compare `sha256sum <key file>` with `[usb] expected_sha256`. If the token is the right one, rerun `doctor --fix-only=checksum` to record its fingerprint; if not, the token was swapped or corrupted, so restore it with `zfs_beskar_key recover`.",
+    ),
+    (
+        "Runtime directory",
+        "Create the token mountpoint with `mkdir -p /run/beskar`, or rerun doctor without --fix-only. The directory must be absolute because the mount unit and the boot loaders both use it.",
+    ),
+    (
+        "Token wear",
+        "Flash media wears with every rewrite. Forge a spare with `zfs_beskar_key recover` onto a fresh stick, keep it offline, and retire the worn token once the spare unlocks cleanly.",
+    ),
+    (
+        "Initramfs module",
+        "Reinstall the boot loader module with `doctor --fix-only=dracut`, then rebuild the image (`dracut -f` or `update-initramfs -u`). Confirm with `lsinitrd | grep beskar` (dracut) or `lsinitramfs /boot/initrd.img-$(uname -r) | grep beskar`.",
+    ),
+    (
+        "Initramfs image",
+        "The booted image predates the module. Rebuild it with `sudo dracut -f` (or `sudo update-initramfs -u`), then confirm with `lsinitrd /boot/initramfs-$(uname -r).img | grep beskar` or `lsinitramfs /boot/initrd.img-$(uname -r) | grep beskar`.",
+    ),
+    (
+        "Initramfs",
+        "Rebuild the image so the repaired module is included: `dracut -f` on dracut hosts, `update-initramfs -u` on initramfs-tools hosts. Reboot with the token attached to confirm.",
+    ),
+    (
+        "Systemd units",
+        "Regenerate the units with `sudo zfs_beskar_key install-units` and inspect them with `systemctl cat beskar-unlock.service run-beskar.mount`.",
+    ),
+    (
+        "Systemd verification",
+        "Run `systemd-analyze verify /etc/systemd/system/beskar-unlock.service /etc/systemd/system/run-beskar.mount` to see the complaint in full, then `sudo zfs_beskar_key install-units` to rewrite the files.",
+    ),
+    (
+        "Systemd ordering",
+        "beskar-unlock.service must run after run-beskar.mount and before zfs-mount.service. Check with `systemd-analyze dot beskar-unlock.service` and look for drop-ins overriding After=/Before= under /etc/systemd/system/beskar-unlock.service.d/.",
+    ),
+    (
+        "Systemctl enable",
+        "Enable the units by hand with `systemctl daemon-reload && systemctl enable beskar-unlock.service run-beskar.mount`, then confirm with `systemctl is-enabled beskar-unlock.service`.",
+    ),
+    (
+        "Pool health",
+        "Inspect `zpool status -x <pool>`. Replace failed vdevs with `zpool replace`, clear transient errors with `zpool clear`, and run `zpool scrub` once the pool is back to ONLINE. A running resilver clears on its own; wait for it before rebooting.",
+    ),
+    (
+        "Dataset encryption",
+        "Check `zfs get encryption <encryption root>`. Encryption cannot be turned on in place: create an encrypted dataset and move the data with `zfs send | zfs receive`, then update `[policy] datasets`.",
+    ),
+];
+
+fn remediation(name: &str) -> Option<&'static str> {
+    REMEDIATION
+        .iter()
+        .find(|(check, _)| *check == name)
+        .map(|(_, text)| *text)
+}
+
 enum UnitVerification {
     Pass(String),
     Fixed(String),
@@ -59,9 +260,23 @@ enum UnitVerification {
     Fail(String),
 }
 
-pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
+/// Sweep the installation; `config_path` is the file this run resolved
+/// (`--config`, `$BESKAR_CONFIG`, or the stock TOML/YAML), re-read here so a
+/// parse failure is reported rather than fatal.
+pub fn run_doctor(
+    ui: &UX,
+    timing: &Timing,
+    config_path: &Path,
+    opts: &DoctorOptions,
+) -> Result<()> {
     ui.banner();
     ui.phase("Diagnostics // Armour Sweep");
+    if let Some(list) = &opts.fix_only {
+        ui.note(&format!(
+            "Surgical mode: only {} may be repaired; everything else is inspected read-only.",
+            list.iter().map(|c| c.name()).collect::<Vec<_>>().join(", ")
+        ));
+    }
 
     let mut report: Vec<ReportEntry> = Vec::new();
     let mut need_initramfs_refresh = false;
@@ -156,7 +371,6 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
     // ---------------------------------------------------------------------
     // Load configuration
     // ---------------------------------------------------------------------
-    let config_path = Path::new(CONFIG_PATH);
     if !config_path.exists() {
         log_entry(
             &mut report,
@@ -164,9 +378,12 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
             timing,
             "Config file",
             Status::Fail,
-            format!("Missing {} – run `zfs_beskar_key init` first.", CONFIG_PATH),
+            format!(
+                "Missing {} – run `zfs_beskar_key init` first.",
+                config_path.display()
+            ),
         );
-        summarize(&report, ui, timing)?;
+        summarize(&report, ui, timing, opts)?;
         return Err(anyhow!("Beskar config missing"));
     }
 
@@ -178,7 +395,7 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
                 timing,
                 "Config file",
                 Status::Pass,
-                format!("Loaded {}", CONFIG_PATH),
+                format!("Loaded {}", config_path.display()),
             );
             cfg
         }
@@ -191,11 +408,44 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
                 Status::Fail,
                 format!("Unable to parse config: {}", err),
             );
-            summarize(&report, ui, timing)?;
+            summarize(&report, ui, timing, opts)?;
             return Err(anyhow!("Invalid config"));
         }
     };
 
+    if let Some(from) = cfg.migrated_from {
+        let detail = format!("version {} -> {}", from, CONFIG_VERSION);
+        if !opts.full_repair() {
+            log_entry(
+                &mut report,
+                ui,
+                timing,
+                "Config schema",
+                Status::Warn,
+                read_only(format!("{} (file not rewritten)", detail), None),
+            );
+        } else {
+            match cfg.persist_migration() {
+                Ok(backup) => log_entry(
+                    &mut report,
+                    ui,
+                    timing,
+                    "Config schema",
+                    Status::Fixed,
+                    format!("{} (backup {})", detail, backup.display()),
+                ),
+                Err(err) => log_entry(
+                    &mut report,
+                    ui,
+                    timing,
+                    "Config schema",
+                    Status::Warn,
+                    format!("{}: unable to rewrite config: {}", detail, err),
+                ),
+            }
+        }
+    }
+
     let key_path = Path::new(&cfg.usb.key_hex_path);
     let key_runtime_dir: PathBuf = key_path
         .parent()
@@ -229,27 +479,39 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
         .unwrap_or_else(|| "rpool/ROOT".to_string());
 
     let zfs_timeout = Duration::from_secs(cfg.crypto.timeout_secs.max(1));
-    let zfs_client = cfg
-        .policy
-        .zfs_path
-        .as_ref()
-        .map(|p| Zfs::with_path(p, zfs_timeout))
-        .unwrap_or_else(|| Zfs::discover(zfs_timeout));
+    let zfs_client = Zfs::from_config(&cfg);
 
     let mut primary_encryption_root = primary_dataset.clone();
     match zfs_client.as_ref() {
         Ok(client) => match client.encryption_root(&primary_dataset) {
-            Ok(root) => {
+            Ok(None) => log_entry(
+                &mut report,
+                ui,
+                timing,
+                "Encryption root",
+                Status::Fail,
+                not_encrypted(&primary_dataset).to_string(),
+            ),
+            Ok(Some(root)) => {
                 if root != primary_dataset {
                     let detail =
                         format!("{} anchored at encryption root {}", primary_dataset, root);
-                    if cfg
+                    let misaligned = cfg
                         .policy
                         .datasets
                         .first()
                         .map(|d| d != &root)
-                        .unwrap_or(true)
-                    {
+                        .unwrap_or(true);
+                    if misaligned && !opts.full_repair() {
+                        log_entry(
+                            &mut report,
+                            ui,
+                            timing,
+                            "Encryption root",
+                            Status::Warn,
+                            read_only(format!("{} (policy not realigned)", detail), None),
+                        );
+                    } else if misaligned {
                         cfg.policy.datasets.retain(|d| d != &root);
                         cfg.policy.datasets.insert(0, root.clone());
                         match persist_config(&cfg) {
@@ -315,52 +577,36 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
         ),
     }
 
+    let (roster_roots, unresolved) = zfs_client
+        .as_ref()
+        .map(|client| roster_encryption_roots(client, &primary_encryption_root, &cfg))
+        .unwrap_or_default();
+    for (dataset, err) in unresolved {
+        log_entry(
+            &mut report,
+            ui,
+            timing,
+            "Encryption root",
+            Status::Warn,
+            format!("{}: unable to resolve encryption root: {:#}", dataset, err),
+        );
+    }
     let expected_keylocation = format!("file://{}", key_path.display());
     if key_path.is_absolute() {
         match zfs_client.as_ref() {
-            Ok(client) => match client.get_property(&primary_encryption_root, "keylocation") {
-                Ok(current) if current.eq_ignore_ascii_case(&expected_keylocation) => log_entry(
-                    &mut report,
-                    ui,
-                    timing,
-                    "Keylocation",
-                    Status::Pass,
-                    current,
-                ),
-                Ok(_) => match client.set_property(
-                    &primary_encryption_root,
-                    "keylocation",
-                    &expected_keylocation,
-                ) {
-                    Ok(_) => log_entry(
-                        &mut report,
-                        ui,
-                        timing,
-                        "Keylocation",
-                        Status::Fixed,
-                        format!("Aligned to {}", expected_keylocation),
-                    ),
-                    Err(err) => log_entry(
+            Ok(client) => {
+                for root in &roster_roots {
+                    reconcile_keylocation(
                         &mut report,
                         ui,
                         timing,
-                        "Keylocation",
-                        Status::Warn,
-                        format!(
-                            "Unable to set keylocation to {}: {}",
-                            expected_keylocation, err
-                        ),
-                    ),
-                },
-                Err(err) => log_entry(
-                    &mut report,
-                    ui,
-                    timing,
-                    "Keylocation",
-                    Status::Warn,
-                    format!("Unable to query keylocation: {}", err),
-                ),
-            },
+                        opts,
+                        client,
+                        root,
+                        &expected_keylocation,
+                    );
+                }
+            }
             Err(err) => log_entry(
                 &mut report,
                 ui,
@@ -384,6 +630,40 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
         );
     }
 
+    // Checked after any keylocation repair, so only roots still pointing
+    // elsewhere are flagged.
+    match zfs_client.as_ref() {
+        Ok(client) if roster_roots.len() > 1 => {
+            let differing: Vec<String> = roster_roots
+                .iter()
+                .filter(|root| {
+                    !client
+                        .get_property(root, "keylocation")
+                        .is_ok_and(|loc| loc.eq_ignore_ascii_case(&expected_keylocation))
+                })
+                .cloned()
+                .collect();
+            let (status, detail) = if differing.is_empty() {
+                (
+                    Status::Pass,
+                    format!(
+                        "{} encryption roots ({}) all load the token key from {}",
+                        roster_roots.len(),
+                        roster_roots.join(", "),
+                        expected_keylocation
+                    ),
+                )
+            } else {
+                (
+                    Status::Warn,
+                    shared_key_warning(&differing, &expected_keylocation),
+                )
+            };
+            log_entry(&mut report, ui, timing, "Shared token key", status, detail);
+        }
+        _ => {}
+    }
+
     let binary_path = match determine_binary_path(Some(&cfg)) {
         Ok(path) => path,
         Err(err) => {
@@ -395,7 +675,7 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
                 Status::Fail,
                 format!("Unable to resolve zfs_beskar_key binary: {}", err),
             );
-            summarize(&report, ui, timing)?;
+            summarize(&report, ui, timing, opts)?;
             return Err(anyhow!("Missing zfs_beskar_key binary"));
         }
     };
@@ -409,6 +689,17 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
             Status::Pass,
             format!("Using {}", binary_path_string),
         ),
+        _ if !opts.allows(RepairCategory::Binary) => log_entry(
+            &mut report,
+            ui,
+            timing,
+            "Binary path",
+            Status::Warn,
+            read_only(
+                format!("Config does not record {}", binary_path_string),
+                Some(RepairCategory::Binary),
+            ),
+        ),
         _ => {
             cfg.policy.binary_path = Some(binary_path_string.clone());
             match persist_config(&cfg) {
@@ -435,6 +726,34 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
         }
     }
 
+    // ---------------------------------------------------------------------
+    // Verify token label
+    // ---------------------------------------------------------------------
+    let token_labels = cfg.usb.token_labels();
+    let mut found = Vec::new();
+    let mut scan_errors = Vec::new();
+    for label in &token_labels {
+        match find_labeled_tokens(label) {
+            Ok(tokens) if tokens.is_empty() => {}
+            Ok(tokens) => found.push(format!("{} found on {}", label, tokens.join(", "))),
+            Err(err) => scan_errors.push(format!("Unable to scan for {}: {}", label, err)),
+        }
+    }
+    let (status, detail) = if !found.is_empty() {
+        (Status::Pass, found.join("; "))
+    } else if !scan_errors.is_empty() {
+        (Status::Warn, scan_errors.join("; "))
+    } else {
+        (
+            Status::Warn,
+            format!(
+                "No device carries label {}; insert the token.",
+                token_labels.join(" or ")
+            ),
+        )
+    };
+    log_entry(&mut report, ui, timing, "Token label", status, detail);
+
     // ---------------------------------------------------------------------
     // Verify key material
     // ---------------------------------------------------------------------
@@ -451,9 +770,26 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
             ),
         );
     } else {
-        match ensure_raw_key_file(key_path) {
+        let key_result = if opts.allows(RepairCategory::Checksum) {
+            ensure_raw_key_file(key_path)
+        } else {
+            read_key_material(key_path)
+        };
+        match key_result {
             Ok(material) => {
-                if material.encoding == KeyEncoding::Hex {
+                if material.encoding == KeyEncoding::Hex && !opts.allows(RepairCategory::Checksum) {
+                    log_entry(
+                        &mut report,
+                        ui,
+                        timing,
+                        "USB key file",
+                        Status::Warn,
+                        read_only(
+                            format!("{} still holds legacy hex", key_path.display()),
+                            Some(RepairCategory::Checksum),
+                        ),
+                    );
+                } else if material.encoding == KeyEncoding::Hex {
                     need_initramfs_refresh = true;
                     log_entry(
                         &mut report,
@@ -462,8 +798,9 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
                         "USB key file",
                         Status::Fixed,
                         format!(
-                            "Converted legacy hex contents at {} into 32 raw bytes.",
-                            key_path.display()
+                            "Converted legacy hex contents at {} into {} raw bytes.",
+                            key_path.display(),
+                            KEY_LEN
                         ),
                     );
                 } else {
@@ -473,22 +810,34 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
                         timing,
                         "USB key file",
                         Status::Pass,
-                        format!("{} present (32-byte raw key).", key_path.display()),
+                        format!("{} present ({}-byte raw key).", key_path.display(), KEY_LEN),
                     );
                 }
 
-                let actual_sha = hex::encode(Sha256::digest(&*material.raw));
+                let algo = cfg.usb.checksum_algo;
+                let actual_sha = algo.digest_hex(&material.raw);
                 match cfg.usb.expected_sha256.as_ref() {
-                    Some(expected) if expected.eq_ignore_ascii_case(&actual_sha) => {
+                    Some(expected) if algo.matches(&material.raw, expected) => {
                         log_entry(
                             &mut report,
                             ui,
                             timing,
                             "USB checksum",
                             Status::Pass,
-                            "SHA-256 matches recorded expectation.".to_string(),
+                            format!("{} matches recorded expectation.", algo.label()),
                         );
                     }
+                    _ if !opts.allows(RepairCategory::Checksum) => log_entry(
+                        &mut report,
+                        ui,
+                        timing,
+                        "USB checksum",
+                        Status::Warn,
+                        read_only(
+                            format!("Token {} does not match config", algo.label()),
+                            Some(RepairCategory::Checksum),
+                        ),
+                    ),
                     _ => {
                         cfg.usb.expected_sha256 = Some(actual_sha.clone());
                         if let Err(err) = persist_config(&cfg) {
@@ -528,7 +877,16 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
     // Ensure runtime mount directory exists
     let run_dir = key_runtime_dir.as_path();
     if run_dir.is_absolute() {
-        if !run_dir.exists() {
+        if !run_dir.exists() && !opts.full_repair() {
+            log_entry(
+                &mut report,
+                ui,
+                timing,
+                "Runtime directory",
+                Status::Warn,
+                read_only(format!("{} missing", run_dir.display()), None),
+            );
+        } else if !run_dir.exists() {
             if let Err(err) = fs::create_dir_all(run_dir) {
                 log_entry(
                     &mut report,
@@ -572,6 +930,57 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
         );
     }
 
+    // Token write endurance (ledger lives beside the key on the token)
+    match wear::load(run_dir) {
+        Ok(Some(record)) if record.verify_failures > 0 => log_entry(
+            &mut report,
+            ui,
+            timing,
+            "Token wear",
+            Status::Warn,
+            format!(
+                "{} read-back failure(s) across {} rewrites — replace this token.",
+                record.verify_failures, record.writes
+            ),
+        ),
+        Ok(Some(record)) if record.is_worn() => log_entry(
+            &mut report,
+            ui,
+            timing,
+            "Token wear",
+            Status::Warn,
+            format!(
+                "{} rewrites recorded (threshold {}); keep a spare token ready.",
+                record.writes,
+                wear::WEAR_WARN_WRITES
+            ),
+        ),
+        Ok(Some(record)) => log_entry(
+            &mut report,
+            ui,
+            timing,
+            "Token wear",
+            Status::Pass,
+            format!("{} rewrites recorded.", record.writes),
+        ),
+        Ok(None) => log_entry(
+            &mut report,
+            ui,
+            timing,
+            "Token wear",
+            Status::Pass,
+            "No wear ledger on token yet.".to_string(),
+        ),
+        Err(err) => log_entry(
+            &mut report,
+            ui,
+            timing,
+            "Token wear",
+            Status::Warn,
+            format!("Unable to read wear ledger: {}", err),
+        ),
+    }
+
     // ---------------------------------------------------------------------
     // Initramfs integration
     // ---------------------------------------------------------------------
@@ -596,6 +1005,12 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
                 mountpoint: &mountpoint_owned,
                 key_path: &key_path_owned,
                 key_sha256: key_sha,
+                checksum_algo: cfg.usb.checksum_algo,
+                token_labels: &token_labels,
+                require_serial: cfg.usb.require_serial.as_deref(),
+                require_vidpid: cfg.usb.require_vidpid.as_deref(),
+                raw_device: cfg.usb.raw_device.as_deref(),
+                raw_offset: cfg.usb.raw_offset,
             };
 
             let module_exists = module_paths.root.exists();
@@ -630,12 +1045,25 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
                 true
             };
 
-            if needs_reinstall {
+            if needs_reinstall && !opts.allows(RepairCategory::Dracut) {
+                log_entry(
+                    &mut report,
+                    ui,
+                    timing,
+                    "Initramfs module",
+                    Status::Warn,
+                    read_only(
+                        format!("{} missing or stale", module_dir.display()),
+                        Some(RepairCategory::Dracut),
+                    ),
+                );
+            } else if needs_reinstall {
                 match dracut_install::install_for_dataset(
                     ui,
                     &cfg,
                     Some(&primary_encryption_root),
                     Some(InitramfsFlavor::Dracut(module_dir.clone())),
+                    false,
                 ) {
                     Ok(_) => {
                         let detail = if module_exists {
@@ -672,7 +1100,11 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
             let ubuntu_style = hook_exists
                 && local_top_exists
                 && fs::read_to_string(INITRAMFS_LOCAL_TOP_PATH)
-                    .map(|content| content.contains("zfs load-key -a"))
+                    .map(|content| {
+                        content.contains("zfs load-key -a")
+                            && content
+                                .contains(&format!("TOKEN_LABELS=\"{}\"", token_labels.join(" ")))
+                    })
                     .unwrap_or(false);
 
             if hook_exists && local_top_exists && ubuntu_style {
@@ -684,8 +1116,25 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
                     Status::Pass,
                     "initramfs-tools scripts present".to_string(),
                 );
+            } else if !opts.allows(RepairCategory::Dracut) {
+                log_entry(
+                    &mut report,
+                    ui,
+                    timing,
+                    "Initramfs module",
+                    Status::Warn,
+                    read_only(
+                        "initramfs-tools scripts missing or outdated".to_string(),
+                        Some(RepairCategory::Dracut),
+                    ),
+                );
             } else {
-                match install_initramfs_tools_scripts(key_runtime_dir.as_path(), key_path, ui) {
+                match install_initramfs_tools_scripts(
+                    key_runtime_dir.as_path(),
+                    key_path,
+                    &cfg.usb,
+                    ui,
+                ) {
                     Ok(_) => {
                         need_initramfs_refresh = true;
                         log_entry(
@@ -723,7 +1172,8 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
     // ---------------------------------------------------------------------
     // Systemd units
     // ---------------------------------------------------------------------
-    if repair::units_exist() {
+    let repair_units = opts.allows(RepairCategory::Units);
+    if repair::units_exist(&cfg) {
         match repair::unit_exec_matches(&binary_path) {
             Ok(true) => log_entry(
                 &mut report,
@@ -731,7 +1181,21 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
                 timing,
                 "Systemd units",
                 Status::Pass,
-                format!("{} & beskar-unlock.service present.", USB_MOUNT_UNIT),
+                format!("{} present.", repair::token_units(&cfg).join(" & ")),
+            ),
+            Ok(false) if !repair_units => log_entry(
+                &mut report,
+                ui,
+                timing,
+                "Systemd units",
+                Status::Warn,
+                read_only(
+                    format!(
+                        "beskar-unlock ExecStart differs from {}",
+                        binary_path_string
+                    ),
+                    Some(RepairCategory::Units),
+                ),
             ),
             Ok(false) => match repair::install_units(ui, &cfg, &binary_path) {
                 Ok(_) => log_entry(
@@ -760,6 +1224,18 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
                 format!("Unable to verify unit ExecStart: {}", err),
             ),
         }
+    } else if !repair_units {
+        log_entry(
+            &mut report,
+            ui,
+            timing,
+            "Systemd units",
+            Status::Warn,
+            read_only(
+                "Beskar unit files missing".to_string(),
+                Some(RepairCategory::Units),
+            ),
+        );
     } else {
         match repair::install_units(ui, &cfg, &binary_path) {
             Ok(_) => {
@@ -785,7 +1261,7 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
         }
     }
 
-    match verify_systemd_units(ui, &cfg, &binary_path) {
+    match verify_systemd_units(ui, &cfg, &binary_path, repair_units) {
         UnitVerification::Pass(detail) => log_entry(
             &mut report,
             ui,
@@ -820,7 +1296,45 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
         ),
     }
 
-    match ensure_units_enabled(ui) {
+    if repair::units_exist(&cfg) {
+        // A raw token has no mount unit; the unlock then follows pool import.
+        let after = if cfg.usb.raw_device.is_some() {
+            "zfs-import.target"
+        } else {
+            USB_MOUNT_UNIT
+        };
+        match verify_unit_ordering(after) {
+            Ok(gaps) if gaps.is_empty() => log_entry(
+                &mut report,
+                ui,
+                timing,
+                "Systemd ordering",
+                Status::Pass,
+                format!(
+                    "{} runs after {} and before {}.",
+                    UNLOCK_UNIT, after, ZFS_MOUNT_UNIT
+                ),
+            ),
+            Ok(gaps) => log_entry(
+                &mut report,
+                ui,
+                timing,
+                "Systemd ordering",
+                Status::Warn,
+                gaps.join("; "),
+            ),
+            Err(err) => log_entry(
+                &mut report,
+                ui,
+                timing,
+                "Systemd ordering",
+                Status::Warn,
+                format!("Unable to inspect unit ordering: {}", err),
+            ),
+        }
+    }
+
+    match ensure_units_enabled(ui, &cfg, repair_units) {
         Ok(msg) => {
             if let Some(detail) = msg {
                 log_entry(
@@ -852,6 +1366,37 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
         ),
     }
 
+    // ---------------------------------------------------------------------
+    // Pool health via zpool
+    // ---------------------------------------------------------------------
+    match Zpool::discover(zfs_timeout)
+        .and_then(|zpool| zpool.pool_health(pool_of(&primary_encryption_root)))
+    {
+        Ok(health) => {
+            let status = match &health.state {
+                PoolState::Online if health.is_healthy() => Status::Pass,
+                state if state.is_unusable() => Status::Fail,
+                _ => Status::Warn,
+            };
+            log_entry(
+                &mut report,
+                ui,
+                timing,
+                "Pool health",
+                status,
+                format!("Pool health: {}", health.summary()),
+            );
+        }
+        Err(err) => log_entry(
+            &mut report,
+            ui,
+            timing,
+            "Pool health",
+            Status::Warn,
+            format!("Unable to query pool health: {}", err),
+        ),
+    }
+
     // ---------------------------------------------------------------------
     // Dataset sanity via zfs
     // ---------------------------------------------------------------------
@@ -885,7 +1430,19 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
     // ---------------------------------------------------------------------
     // Rebuild initramfs if required
     // ---------------------------------------------------------------------
-    if need_initramfs_refresh {
+    if need_initramfs_refresh && !opts.allows(RepairCategory::Dracut) {
+        log_entry(
+            &mut report,
+            ui,
+            timing,
+            "Initramfs",
+            Status::Warn,
+            read_only(
+                "Repairs above changed initramfs inputs; image not rebuilt".to_string(),
+                Some(RepairCategory::Dracut),
+            ),
+        );
+    } else if need_initramfs_refresh {
         match initramfs_flavor
             .as_ref()
             .map(|flavor| rebuild_initramfs(ui, flavor))
@@ -925,7 +1482,32 @@ pub fn run_doctor(ui: &UX, timing: &Timing) -> Result<()> {
         }
     }
 
-    summarize(&report, ui, timing)?;
+    // ---------------------------------------------------------------------
+    // Booted image contents
+    // ---------------------------------------------------------------------
+    if let Some(flavor) = &initramfs_flavor {
+        let (status, detail) = match inspect_initramfs_image(flavor) {
+            Ok((image, missing)) if missing.is_empty() => (
+                Status::Pass,
+                format!("{} carries the Beskar loader", image.display()),
+            ),
+            Ok((image, missing)) => (
+                Status::Warn,
+                format!(
+                    "{} lacks {}; the module is installed but the image was not rebuilt",
+                    image.display(),
+                    missing.join(", ")
+                ),
+            ),
+            Err(err) => (
+                Status::Warn,
+                format!("Unable to inspect the initramfs image: {}", err),
+            ),
+        };
+        log_entry(&mut report, ui, timing, "Initramfs image", status, detail);
+    }
+
+    summarize(&report, ui, timing, opts)?;
     audit_log("DOCTOR", "Environment diagnostics completed");
     Ok(())
 }
@@ -956,7 +1538,7 @@ fn log_entry(
     });
 }
 
-fn summarize(report: &[ReportEntry], ui: &UX, timing: &Timing) -> Result<()> {
+fn summarize(report: &[ReportEntry], ui: &UX, timing: &Timing, opts: &DoctorOptions) -> Result<()> {
     let mut passes = 0;
     let mut fixed = 0;
     let mut warns = 0;
@@ -1000,6 +1582,10 @@ fn summarize(report: &[ReportEntry], ui: &UX, timing: &Timing) -> Result<()> {
         ));
     }
 
+    if opts.explain {
+        explain(report, ui);
+    }
+
     if fails > 0 {
         Err(anyhow!("Diagnostics uncovered blocking issues"))
     } else {
@@ -1008,8 +1594,32 @@ fn summarize(report: &[ReportEntry], ui: &UX, timing: &Timing) -> Result<()> {
     }
 }
 
-fn verify_systemd_units(ui: &UX, cfg: &ConfigFile, binary_path: &Path) -> UnitVerification {
-    match run_unit_verification(&[USB_MOUNT_UNIT, UNLOCK_UNIT_NAME]) {
+/// One remediation note per check that warned or failed, in report order.
+fn explain(report: &[ReportEntry], ui: &UX) {
+    let mut seen: Vec<&str> = Vec::new();
+    for entry in report {
+        if !matches!(entry.status, Status::Warn | Status::Fail) || seen.contains(&entry.name) {
+            continue;
+        }
+        if seen.is_empty() {
+            ui.phase("Field Manual // Remediation");
+        }
+        seen.push(entry.name);
+        ui.info(&format!("{}:", entry.name));
+        ui.note(
+            remediation(entry.name)
+                .unwrap_or("No field notes for this check; follow the detail printed above."),
+        );
+    }
+}
+
+fn verify_systemd_units(
+    ui: &UX,
+    cfg: &ConfigFile,
+    binary_path: &Path,
+    repair_units: bool,
+) -> UnitVerification {
+    match run_unit_verification(repair::token_units(cfg)) {
         Ok(_) => {
             UnitVerification::Pass("systemd-analyze verify clean for Beskar units.".to_string())
         }
@@ -1020,8 +1630,11 @@ fn verify_systemd_units(ui: &UX, cfg: &ConfigFile, binary_path: &Path) -> UnitVe
                     "systemd-analyze missing; skipping verification.".to_string(),
                 );
             }
+            if !repair_units {
+                return UnitVerification::Warn(read_only(err_msg, Some(RepairCategory::Units)));
+            }
             match repair::install_units(ui, cfg, binary_path) {
-                Ok(_) => match run_unit_verification(&[USB_MOUNT_UNIT, UNLOCK_UNIT_NAME]) {
+                Ok(_) => match run_unit_verification(repair::token_units(cfg)) {
                     Ok(_) => UnitVerification::Fixed(format!(
                         "Reinstalled units after verification error: {}",
                         err_msg
@@ -1055,6 +1668,71 @@ fn run_unit_verification(units: &[&str]) -> Result<()> {
     Ok(())
 }
 
+/// Ask systemd for the effective `After=` graph around the unlock unit and
+/// report every edge that should exist but does not. `verify` only proves the
+/// unit files parse; generator-based mounts can still leave the unlock racing
+/// `zfs-mount.service`.
+fn verify_unit_ordering(after: &str) -> Result<Vec<String>> {
+    let analyzer = systemd_analyze(Duration::from_secs(10))?;
+    let output = analyzer.run(&["dot", "--order", UNLOCK_UNIT], None)?;
+    if output.status != 0 {
+        return Err(anyhow!(
+            "systemd-analyze dot failed: {}",
+            output.stderr.trim()
+        ));
+    }
+    let edges = parse_order_edges(&output.stdout);
+    Ok(ordering_gaps(&edges, UNLOCK_UNIT, after, ZFS_MOUNT_UNIT))
+}
+
+/// Parse `"a"->"b"` lines from `systemd-analyze dot --order`; each edge means
+/// `a` is ordered after `b`.
+fn parse_order_edges(dot: &str) -> Vec<(String, String)> {
+    dot.lines()
+        .filter_map(|line| {
+            let (from, rest) = line.trim().split_once("->")?;
+            let to = rest.split_whitespace().next()?;
+            Some((
+                from.trim().trim_matches('"').to_string(),
+                to.trim_end_matches(';').trim_matches('"').to_string(),
+            ))
+        })
+        .collect()
+}
+
+fn ordering_gaps(edges: &[(String, String)], unit: &str, after: &str, before: &str) -> Vec<String> {
+    let is_after = |a: &str, b: &str| edges.iter().any(|(x, y)| x == a && y == b);
+    let mut gaps = Vec::new();
+    if !is_after(unit, after) {
+        gaps.push(format!("{} is not ordered after {}", unit, after));
+    }
+    if !is_after(before, unit) {
+        gaps.push(format!("{} is not ordered before {}", unit, before));
+    }
+    if !gaps.is_empty() {
+        let mut afters: Vec<&str> = edges
+            .iter()
+            .filter(|(x, _)| x == unit)
+            .map(|(_, y)| y.as_str())
+            .collect();
+        let mut befores: Vec<&str> = edges
+            .iter()
+            .filter(|(_, y)| y == unit)
+            .map(|(x, _)| x.as_str())
+            .collect();
+        afters.sort_unstable();
+        afters.dedup();
+        befores.sort_unstable();
+        befores.dedup();
+        gaps.push(format!(
+            "actual: after [{}], before [{}]",
+            afters.join(", "),
+            befores.join(", ")
+        ));
+    }
+    gaps
+}
+
 fn systemd_analyze(timeout: Duration) -> Result<Cmd> {
     for candidate in ["/bin/systemd-analyze", "/usr/bin/systemd-analyze"] {
         if Path::new(candidate).exists() {
@@ -1064,6 +1742,62 @@ fn systemd_analyze(timeout: Duration) -> Result<Cmd> {
     Err(anyhow!("systemd-analyze not found"))
 }
 
+/// List the initramfs the running kernel boots (`lsinitrd` or
+/// `lsinitramfs`) and return it with the loader files it lacks.
+fn inspect_initramfs_image(flavor: &InitramfsFlavor) -> Result<(PathBuf, Vec<&'static str>)> {
+    let release = fs::read_to_string("/proc/sys/kernel/osrelease")
+        .map_err(|err| anyhow!("read kernel release: {}", err))?;
+    let release = release.trim();
+    let image = [
+        format!("/boot/initramfs-{}.img", release),
+        format!("/boot/initrd.img-{}", release),
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .find(|path| path.exists())
+    .ok_or_else(|| anyhow!("no initramfs image for kernel {} under /boot", release))?;
+
+    // The image carries the local-top script at the path `init` installs it
+    // under, relative to the initramfs-tools root.
+    let local_top = [INITRAMFS_LOCAL_TOP_PATH
+        .strip_prefix(INITRAMFS_TOOLS_ROOT)
+        .unwrap_or(INITRAMFS_LOCAL_TOP_PATH)];
+    let (tool, candidates, wanted): (&str, &[&str], &[&'static str]) = match flavor {
+        InitramfsFlavor::Dracut(_) => (
+            "lsinitrd",
+            &["/usr/bin/lsinitrd", "/usr/sbin/lsinitrd"],
+            &[dracut::SCRIPT_NAME, dracut::SERVICE_NAME],
+        ),
+        InitramfsFlavor::InitramfsTools => (
+            "lsinitramfs",
+            &["/usr/bin/lsinitramfs", "/usr/sbin/lsinitramfs"],
+            &local_top,
+        ),
+    };
+    let lister = find_binary(candidates).ok_or_else(|| anyhow!("{} not found", tool))?;
+    let image_str = image.to_string_lossy();
+    let output =
+        Cmd::new_allowlisted(lister, Duration::from_secs(60))?.run(&[image_str.as_ref()], None)?;
+    if output.status != 0 {
+        return Err(anyhow!(
+            "listing {} failed: {}",
+            image.display(),
+            output.stderr.trim()
+        ));
+    }
+    let missing = missing_from_listing(&output.stdout, wanted);
+    Ok((image, missing))
+}
+
+/// Entries of `wanted` no line of an image listing ends with.
+fn missing_from_listing<'a>(listing: &str, wanted: &[&'a str]) -> Vec<&'a str> {
+    wanted
+        .iter()
+        .copied()
+        .filter(|name| !listing.lines().any(|line| line.trim_end().ends_with(name)))
+        .collect()
+}
+
 fn find_binary(candidates: &[&str]) -> Option<String> {
     candidates
         .iter()
@@ -1072,27 +1806,191 @@ fn find_binary(candidates: &[&str]) -> Option<String> {
         .map(|p| p.to_string())
 }
 
-fn persist_config(cfg: &ConfigFile) -> Result<()> {
-    let path = cfg.path.as_path();
-    atomic_write_toml(path, cfg, true)?;
-    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
-    Ok(())
+/// Encryption roots behind every roster dataset, primary first and each only
+/// once. `keylocation` lives on the root, so that is where drift shows up
+/// after `usb.key_hex_path` changes. Datasets whose root cannot be resolved
+/// come back separately with the error.
+fn roster_encryption_roots(
+    client: &Zfs,
+    primary_root: &str,
+    cfg: &ConfigFile,
+) -> (Vec<String>, Vec<(String, anyhow::Error)>) {
+    let mut roots = vec![primary_root.to_string()];
+    let mut unresolved = Vec::new();
+    for dataset in &cfg.policy.datasets {
+        let root = match client.encryption_root(dataset) {
+            Ok(Some(root)) => root,
+            // Unencrypted roster entries carry no keylocation to drift.
+            Ok(None) => continue,
+            Err(err) => {
+                unresolved.push((dataset.clone(), err));
+                continue;
+            }
+        };
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+    (roots, unresolved)
+}
+
+/// One token key and one `usb.expected_sha256` serve every roster root;
+/// unlock fails on the first root that was keyed with something else.
+/// `roots` are the ones whose keylocation is not the token key.
+fn shared_key_warning(roots: &[String], expected: &str) -> String {
+    format!(
+        "{} roster encryption root(s) ({}) have a keylocation other than {} yet share the single token key and usb.expected_sha256; any root keyed separately will refuse it at unlock",
+        roots.len(),
+        roots.join(", "),
+        expected
+    )
+}
+
+/// Compare one root's live `keylocation` with the configured key path and,
+/// when the keylocation category is allowed, realign it.
+fn reconcile_keylocation(
+    report: &mut Vec<ReportEntry>,
+    ui: &UX,
+    timing: &Timing,
+    opts: &DoctorOptions,
+    client: &Zfs,
+    root: &str,
+    expected: &str,
+) {
+    let (status, detail) = match client.get_property(root, "keylocation") {
+        Ok(current) if current.eq_ignore_ascii_case(expected) => {
+            (Status::Pass, format!("{}: {}", root, current))
+        }
+        Ok(current) if !opts.allows(RepairCategory::Keylocation) => (
+            Status::Warn,
+            read_only(
+                format!("{}: {} (expected {})", root, current, expected),
+                Some(RepairCategory::Keylocation),
+            ),
+        ),
+        Ok(current) => match client.set_property(root, "keylocation", expected) {
+            Ok(_) => {
+                audit_log(
+                    "DOCTOR_KEYLOCATION",
+                    &format!("{} keylocation {} -> {}", root, current, expected),
+                );
+                (
+                    Status::Fixed,
+                    format!("{}: aligned {} to {}", root, current, expected),
+                )
+            }
+            Err(err) => (
+                Status::Warn,
+                format!(
+                    "{}: unable to set keylocation to {}: {}",
+                    root, expected, err
+                ),
+            ),
+        },
+        Err(err) => (
+            Status::Warn,
+            format!("{}: unable to query keylocation: {}", root, err),
+        ),
+    };
+    log_entry(report, ui, timing, "Keylocation", status, detail);
 }
 
-fn ensure_units_enabled(ui: &UX) -> Result<Option<String>> {
+fn ensure_units_enabled(ui: &UX, cfg: &ConfigFile, repair_units: bool) -> Result<Option<String>> {
     let systemctl_path = find_binary(&["/bin/systemctl", "/usr/bin/systemctl"])
         .ok_or_else(|| anyhow!("systemctl not found on PATH"))?;
     let cmd = Cmd::new_allowlisted(systemctl_path.clone(), Duration::from_secs(5))?;
-    let usb = cmd.run(&["is-enabled", USB_MOUNT_UNIT], None)?;
-    let unlock = cmd.run(&["is-enabled", "beskar-unlock.service"], None)?;
+    let units = repair::token_units(cfg);
+    let mut all_enabled = true;
+    for unit in units {
+        all_enabled &= cmd.run(&["is-enabled", unit], None)?.status == 0;
+    }
 
-    if usb.status == 0 && unlock.status == 0 {
+    if all_enabled {
         return Ok(None);
     }
+    if !repair_units {
+        return Err(anyhow!(read_only(
+            "Beskar units not enabled".to_string(),
+            Some(RepairCategory::Units)
+        )));
+    }
 
-    repair::ensure_units_enabled(ui)?;
+    repair::ensure_units_enabled(ui, cfg)?;
     Ok(Some(format!(
-        "Enabled {} & beskar-unlock.service via systemctl.",
-        USB_MOUNT_UNIT
+        "Enabled {} via systemctl.",
+        units.join(" & ")
     )))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        missing_from_listing, ordering_gaps, parse_order_edges, remediation, RepairCategory,
+        REMEDIATION,
+    };
+
+    #[test]
+    fn fix_only_lists_parse_and_reject_unknowns() {
+        assert_eq!(
+            RepairCategory::parse_list("units, Dracut,units").unwrap(),
+            vec![RepairCategory::Units, RepairCategory::Dracut]
+        );
+        assert!(RepairCategory::parse_list("units,initramfs").is_err());
+        assert!(RepairCategory::parse_list(" , ").is_err());
+    }
+
+    #[test]
+    fn unit_ordering_reads_systemd_dot_edges() {
+        let dot = r#"digraph systemd {
+	"beskar-unlock.service"->"run-beskar.mount" [color="green"];
+	"zfs-mount.service"->"beskar-unlock.service" [color="green"];
+	"beskar-unlock.service"->"zfs-import.target" [color="green"];
+}"#;
+        let edges = parse_order_edges(dot);
+        assert_eq!(edges.len(), 3);
+        assert!(ordering_gaps(
+            &edges,
+            "beskar-unlock.service",
+            "run-beskar.mount",
+            "zfs-mount.service"
+        )
+        .is_empty());
+
+        let gaps = ordering_gaps(
+            &edges[..1],
+            "beskar-unlock.service",
+            "run-beskar.mount",
+            "zfs-mount.service",
+        );
+        assert_eq!(gaps.len(), 2);
+        assert!(gaps[1].contains("after [run-beskar.mount], before []"));
+    }
+
+    #[test]
+    fn image_listing_reports_only_absent_loader_files() {
+        let listing = "-rwxr-xr-x   1 root root  4096 Jan  1 00:00 usr/sbin/beskar-load-key.sh\n\
+                       drwxr-xr-x   2 root root     0 Jan  1 00:00 usr/lib/systemd/system\n";
+        assert_eq!(
+            missing_from_listing(listing, &["beskar-load-key.sh", "beskar-load-key.service"]),
+            ["beskar-load-key.service"]
+        );
+        assert!(
+            missing_from_listing("scripts/local-top/beskar\n", &["scripts/local-top/beskar"])
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn remediation_notes_are_keyed_once_per_check() {
+        for (i, (name, text)) in REMEDIATION.iter().enumerate() {
+            assert!(!text.is_empty(), "{} has an empty note", name);
+            assert!(
+                REMEDIATION[i + 1..].iter().all(|(other, _)| other != name),
+                "{} is listed twice",
+                name
+            );
+        }
+        assert!(remediation("Keylocation").is_some());
+        assert!(remediation("Unknown check").is_none());
+    }
+}
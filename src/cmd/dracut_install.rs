@@ -7,27 +7,46 @@ use crate::config::ConfigFile;
 use crate::dracut::{self, ModuleContext, ModulePaths, DEFAULT_MOUNTPOINT};
 use crate::ui::UX;
 use crate::util::keyfile::{ensure_raw_key_file, KeyEncoding};
-use crate::zfs::Zfs;
+use crate::zfs::{not_encrypted, Zfs};
 use anyhow::{anyhow, Context, Result};
+use std::fs;
 use std::path::Path;
-use std::time::Duration;
 
 pub fn run(
     ui: &UX,
     cfg: &ConfigFile,
     dataset_override: Option<&str>,
     flavor_hint: Option<InitramfsFlavor>,
+    force: bool,
 ) -> Result<()> {
     ui.banner();
-    install_for_dataset(ui, cfg, dataset_override, flavor_hint)
+    install_for_dataset(ui, cfg, dataset_override, flavor_hint, force)
 }
 
+/// Point keylocation at the token and stamp the dracut module. The module
+/// write and `dracut -f` are skipped when the installed module already
+/// matches the rendered templates, unless `force` is set.
 pub fn install_for_dataset(
     ui: &UX,
     cfg: &ConfigFile,
     dataset_override: Option<&str>,
     flavor_hint: Option<InitramfsFlavor>,
+    force: bool,
 ) -> Result<()> {
+    let flavor = match flavor_hint {
+        Some(f) => f,
+        None => detect_initramfs_flavor()?,
+    };
+    let module_dir = match &flavor {
+        InitramfsFlavor::Dracut(path) => path.clone(),
+        InitramfsFlavor::InitramfsTools => {
+            return Err(anyhow!(
+            "initramfs-tools detected; dracut module installation not applicable on this system."
+        ))
+        }
+    };
+    dracut::ensure_checksum_tool(cfg.usb.checksum_algo)?;
+
     let dataset_hint = dataset_override
         .map(|d| d.to_string())
         .or_else(|| cfg.policy.datasets.first().cloned())
@@ -38,22 +57,14 @@ pub fn install_for_dataset(
         dataset_hint
     ));
 
-    let zfs_timeout = Duration::from_secs(cfg.crypto.timeout_secs.max(1));
-    let zfs_client = cfg
-        .policy
-        .zfs_path
-        .as_ref()
-        .map(|p| Zfs::with_path(p, zfs_timeout))
-        .unwrap_or_else(|| Zfs::discover(zfs_timeout));
+    let zfs_client = Zfs::from_config(cfg);
 
     let client = zfs_client.map_err(|err| anyhow!("Unable to initialize zfs client: {}", err))?;
 
-    let mut encryption_root = client
+    let encryption_root = client
         .encryption_root(&dataset_hint)
-        .with_context(|| format!("resolve encryption root for {}", dataset_hint))?;
-    if encryption_root.trim().is_empty() {
-        encryption_root = dataset_hint.clone();
-    }
+        .with_context(|| format!("resolve encryption root for {}", dataset_hint))?
+        .ok_or_else(|| not_encrypted(&dataset_hint))?;
 
     if encryption_root != dataset_hint {
         ui.info(&format!(
@@ -66,10 +77,7 @@ pub fn install_for_dataset(
         .is_encrypted(&encryption_root)
         .with_context(|| format!("verify encryption status for {}", encryption_root))?
     {
-        return Err(anyhow!(
-            "Dataset {} reports encryption=off. Beskar dracut module not required.",
-            encryption_root
-        ));
+        return Err(not_encrypted(&encryption_root));
     }
 
     let key_path = Path::new(&cfg.usb.key_hex_path);
@@ -102,19 +110,6 @@ pub fn install_for_dataset(
         encryption_root, key_location
     ));
 
-    let flavor = match flavor_hint {
-        Some(f) => f,
-        None => detect_initramfs_flavor()?,
-    };
-
-    let module_dir = match &flavor {
-        InitramfsFlavor::Dracut(path) => path.clone(),
-        InitramfsFlavor::InitramfsTools => {
-            return Err(anyhow!(
-            "initramfs-tools detected; dracut module installation not applicable on this system."
-        ))
-        }
-    };
     let key_sha = cfg.usb.expected_sha256.as_deref();
     if key_sha.is_none() {
         ui.warn(
@@ -122,19 +117,39 @@ pub fn install_for_dataset(
         );
     }
     let module_paths = ModulePaths::new(&module_dir);
+    let token_labels = cfg.usb.token_labels();
     let ctx = ModuleContext {
         mountpoint: &mountpoint_owned,
         key_path: &key_path_owned,
         key_sha256: key_sha,
+        checksum_algo: cfg.usb.checksum_algo,
+        token_labels: &token_labels,
+        require_serial: cfg.usb.require_serial.as_deref(),
+        require_vidpid: cfg.usb.require_vidpid.as_deref(),
+        raw_device: cfg.usb.raw_device.as_deref(),
+        raw_offset: cfg.usb.raw_offset,
     };
 
+    if !force && dracut::module_is_current(&module_paths, &ctx).unwrap_or(false) {
+        ui.info(&format!(
+            "Dracut module at {} already current; initramfs rebuild skipped (use --force to rebuild).",
+            module_dir.display()
+        ));
+        return Ok(());
+    }
+
     dracut::install_module(&module_paths, &ctx)?;
     ui.success(&format!(
         "Dracut module stamped at {}.",
         module_dir.display()
     ));
 
-    rebuild_initramfs(ui, &flavor).context("invoke dracut -f for updated Beskar module")?;
+    if let Err(err) = rebuild_initramfs(ui, &flavor) {
+        // Leave the module looking stale so the next run rebuilds instead of
+        // trusting an image that never received it.
+        let _ = fs::remove_file(&module_paths.script);
+        return Err(err).context("invoke dracut -f for updated Beskar module");
+    }
     ui.success("dracut -f completed with Beskar module embedded.");
 
     Ok(())
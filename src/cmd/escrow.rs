@@ -0,0 +1,89 @@
+// ============================================================================
+// src/cmd/escrow.rs – Offline key escrow to a security-team age recipient
+// ============================================================================
+
+use crate::cmd::init::token_key_file_name;
+use crate::cmd::recover::{etch_recovered_key, verify_fingerprint};
+use crate::config::ConfigFile;
+use crate::ui::{Pace, Timing, UX};
+use crate::util::atomic::atomic_write_bytes;
+use crate::util::audit::audit_log;
+use crate::util::escrow::{open_with_identity, seal_for_recipient};
+use crate::util::keyfile::read_key_material;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Encrypt the token key to `recipient` and write the armored blob to `output`.
+pub fn run_escrow(
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    dataset: &str,
+    recipient: &str,
+    output: &Path,
+) -> Result<()> {
+    ui.banner();
+    ui.phase("Escrow // Covert Tribute");
+
+    let key_path = Path::new(&cfg.usb.key_hex_path);
+    let key = read_key_material(key_path)?;
+    verify_fingerprint(cfg, &key.raw)?;
+
+    let blob = seal_for_recipient(recipient, &key.raw)?;
+    atomic_write_bytes(output, blob.as_bytes(), 0o600, false)
+        .with_context(|| format!("write escrow blob {}", output.display()))?;
+    audit_log(
+        "ESCROW_EXPORT",
+        &format!(
+            "{} sealed to {} at {}",
+            dataset,
+            recipient.trim(),
+            output.display()
+        ),
+    );
+
+    ui.success(&format!(
+        "Key for {} sealed to the security recipient at {}.",
+        dataset,
+        output.display()
+    ));
+    ui.note("Only the recipient's private identity can open this blob; deliver it offline.");
+    timing.pace(Pace::Critical);
+    Ok(())
+}
+
+/// Decrypt an escrow blob with the security team's identity and etch the key
+/// onto a fresh USB token.
+pub fn run_unescrow(
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    dataset: &str,
+    identity: &Path,
+    input: &Path,
+) -> Result<()> {
+    ui.banner();
+    ui.phase("Escrow // Tribute Returned");
+
+    let identity_text = Zeroizing::new(
+        fs::read_to_string(identity)
+            .with_context(|| format!("read escrow identity {}", identity.display()))?,
+    );
+    let blob = fs::read(input).with_context(|| format!("read escrow blob {}", input.display()))?;
+    let raw_key = open_with_identity(&identity_text, &blob)?;
+    verify_fingerprint(cfg, &raw_key)?;
+    audit_log(
+        "ESCROW_RESTORE",
+        &format!("{} restored from {}", dataset, input.display()),
+    );
+
+    let key_filename = token_key_file_name(cfg, dataset)?;
+    etch_recovered_key(ui, &cfg.usb.label, None, &key_filename, &raw_key[..])?;
+
+    ui.success("Escrowed tribute reforged on Beskar token.");
+    ui.success("This is the Way.");
+    timing.pace(Pace::Critical);
+    Ok(())
+}
@@ -0,0 +1,80 @@
+// ============================================================================
+// src/cmd/fido2.rs – Enroll a FIDO2 authenticator as an hmac-secret key source
+// ============================================================================
+
+use crate::cmd::recover::verify_fingerprint;
+use crate::config::{persist_config, ConfigFile};
+use crate::ui::{Pace, Timing, UX};
+use crate::util::audit::audit_log;
+use crate::util::fido2;
+use crate::util::keyfile::read_key_material;
+use anyhow::Result;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default)]
+pub struct Fido2EnrollOptions {
+    pub device: Option<String>,
+    pub relying_party: Option<String>,
+    pub primary: bool,
+}
+
+/// Bind the current token key to a FIDO2 authenticator and record the wrapped
+/// key in `[fido2]`. The raw key never lands in config; only its XOR with the
+/// authenticator's hmac-secret output does.
+pub fn run_enroll_fido2(
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    opts: &Fido2EnrollOptions,
+) -> Result<()> {
+    ui.banner();
+    ui.phase("FIDO2 // Signet Binding");
+
+    let key_path = Path::new(&cfg.usb.key_hex_path);
+    let key = read_key_material(key_path)?;
+    verify_fingerprint(cfg, &key.raw)?;
+
+    let device = fido2::find_device(opts.device.as_deref().or(cfg.fido2.device.as_deref()))?;
+    let relying_party = opts
+        .relying_party
+        .clone()
+        .unwrap_or_else(|| cfg.fido2.relying_party.clone());
+    ui.info(&format!("Authenticator {} answers the call.", device));
+    ui.note("Touch the authenticator twice: once to mint the credential, once to prove it.");
+    timing.pace(Pace::Prompt);
+
+    let enrollment = fido2::enroll(&device, &relying_party, &key.raw)?;
+
+    let mut updated = cfg.clone();
+    updated.fido2.enabled = true;
+    updated.fido2.primary = opts.primary;
+    updated.fido2.relying_party = relying_party.clone();
+    updated.fido2.credential_id = Some(enrollment.credential_id);
+    updated.fido2.hmac_salt = Some(enrollment.hmac_salt);
+    updated.fido2.key_xor = Some(enrollment.key_xor);
+    if opts.device.is_some() {
+        updated.fido2.device = opts.device.clone();
+    }
+
+    persist_config(&updated)?;
+    audit_log(
+        "FIDO2_ENROLL",
+        &format!(
+            "rp={} device={} primary={}",
+            relying_party, device, opts.primary
+        ),
+    );
+
+    ui.success(&format!(
+        "FIDO2 signet bound; config updated at {}.",
+        updated.path.display()
+    ));
+    if opts.primary {
+        ui.note("Unlock now challenges the authenticator before the USB token.");
+    } else {
+        ui.note("Unlock challenges the authenticator when the USB token is absent.");
+    }
+    ui.success("This is the Way.");
+    timing.pace(Pace::Critical);
+    Ok(())
+}
@@ -0,0 +1,106 @@
+// ============================================================================
+// src/cmd/fingerprint.rs – Read-only key fingerprint for cross-checking tokens
+// ============================================================================
+
+use crate::cmd::init::group_string;
+use crate::config::{ConfigFile, RecoveryDisplay};
+use crate::ui::{Pace, Timing, UX};
+use crate::util::keyfile::read_key_material;
+use crate::util::rawtoken;
+use crate::util::recovery::encode_recovery_code;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use data_encoding::BASE32_NOPAD;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// How `show-fingerprint` renders the SHA-256 of the token key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FingerprintFormat {
+    /// Lowercase hex, as `sha256sum` prints it.
+    #[default]
+    Hex,
+    /// The 32 digest bytes on stdout and nothing else, for piping.
+    Raw,
+    /// Uppercase Base32 in groups of four, for reading out over the phone.
+    Base32,
+}
+
+/// Print the fingerprint of the configured token key, plus its recovery-code
+/// form when `[policy] recovery_display` is `stdout`, without touching ZFS,
+/// the token, or the config.
+pub fn run_show_fingerprint(
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    format: FingerprintFormat,
+) -> Result<()> {
+    let (source, key) = match cfg.usb.raw_device.as_deref() {
+        Some(device) => (
+            device.to_string(),
+            rawtoken::read_key(Path::new(device), cfg.usb.raw_offset)?,
+        ),
+        None => {
+            let key_path = Path::new(&cfg.usb.key_hex_path);
+            (
+                key_path.display().to_string(),
+                read_key_material(key_path)?.raw,
+            )
+        }
+    };
+    let digest = Sha256::digest(&key[..]);
+
+    if format == FingerprintFormat::Raw {
+        let mut stdout = std::io::stdout().lock();
+        stdout
+            .write_all(&digest)
+            .and_then(|_| stdout.flush())
+            .context("write fingerprint to stdout")?;
+        return Ok(());
+    }
+
+    ui.banner();
+    ui.phase("Fingerprint // Signet Check");
+    let fingerprint = match format {
+        FingerprintFormat::Base32 => {
+            group_string(&BASE32_NOPAD.encode(&digest), 4, '-').to_string()
+        }
+        _ => hex::encode(digest),
+    };
+    let config_match = match &cfg.usb.expected_sha256 {
+        Some(expected) if cfg.usb.checksum_algo.matches(&key, expected) => format!(
+            "matches usb.expected_sha256 ({})",
+            cfg.usb.checksum_algo.label()
+        ),
+        Some(_) => format!(
+            "MISMATCH against usb.expected_sha256 ({})",
+            cfg.usb.checksum_algo.label()
+        ),
+        None => "usb.expected_sha256 not set".to_string(),
+    };
+    ui.data_panel(
+        "Token Fingerprint",
+        &[
+            ("Source", source),
+            ("SHA-256", fingerprint),
+            ("Config", config_match),
+        ],
+    );
+    // The sigil is the key itself; keep it off the terminal unless the
+    // operator already accepts it there.
+    if cfg.policy.recovery_display == RecoveryDisplay::Stdout {
+        ui.security(&Zeroizing::new(format!(
+            "Recovery sigil form: {}. Compare it against the stored sigil, then clear the screen.",
+            *group_string(&encode_recovery_code(&key), 4, '-')
+        )));
+    } else {
+        ui.note(&format!(
+            "Recovery sigil form withheld under [policy] recovery_display = \"{}\"; compare the fingerprint instead.",
+            cfg.policy.recovery_display.label()
+        ));
+    }
+    timing.pace(Pace::Prompt);
+    Ok(())
+}
@@ -4,32 +4,49 @@
 // ============================================================================
 
 use anyhow::{anyhow, Context, Result};
-use chrono::Utc;
 use rand::rngs::OsRng;
-use rand::RngCore;
+use rand::{Rng, RngCore};
 use sha2::{Digest, Sha256};
 use std::fs::{self, File, Metadata, Permissions};
-use std::io::Write;
+use std::io::{self, IsTerminal, Read, Write};
+use std::os::fd::AsRawFd;
 use std::os::unix::fs::{FileTypeExt, PermissionsExt};
 use std::path::{Path, PathBuf};
-use std::time::Duration;
-use tempfile::{tempdir, NamedTempFile};
-use zeroize::Zeroizing;
-
-use crate::cmd::{Cmd, OutputData};
-use crate::config::{ConfigFile, CryptoCfg, Fallback, Policy, Usb};
-use crate::ui::{Pace, Timing, UX};
-use crate::util::atomic::atomic_write_toml;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::cmd::{Cmd, OutputData, Termination};
+use crate::config::{
+    backup_config, validate_token_label, Audit, ConfigFile, CryptoCfg, Fallback, Fido2, Policy,
+    RecoveryDisplay, UiCfg, Usb, CONFIG_VERSION, DEFAULT_USB_WAIT_SECS,
+};
+use crate::ui::{
+    interactive_terminal, non_interactive, require_terminal, Pace, SpinnerHandle, Timing, UX,
+};
+use crate::util::atomic::{atomic_write_bytes, atomic_write_key, atomic_write_toml};
 use crate::util::audit::audit_log;
 use crate::util::binary::determine_binary_path;
+use crate::util::checksum::ChecksumAlgo;
+use crate::util::holders::{describe_holders, mount_holders};
 use crate::util::kdf::pbkdf2_sha256;
-use crate::util::keyfile::read_key_material;
+use crate::util::keyfile::{read_key_material, TransientKeyFile};
+use crate::util::rawtoken;
 use crate::util::recovery::encode_recovery_code;
-use crate::zfs::Zfs;
+use crate::util::token::{
+    self, external_cmd, find_labeled_tokens, BLKID_BINARIES, MOUNT_BINARIES, MOUNT_TIMEOUT,
+    PROBE_TIMEOUT, UMOUNT_BINARIES,
+};
+use crate::util::wear;
+use crate::zfs::{not_encrypted, Zfs, KEY_LEN};
+use console::Term;
 use dialoguer::{theme::ColorfulTheme, Confirm, Input, Password, Select};
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
-const BESKAR_LABEL: &str = crate::dracut::BESKAR_TOKEN_LABEL;
 const DEFAULT_CONFIG_PATH: &str = "/etc/zfs-beskar.toml";
 const DEFAULT_ZFS_BIN: &str = "/sbin/zfs";
 const DEFAULT_TIMEOUT: u64 = 10;
@@ -42,11 +59,20 @@ const MKFS_BINARIES: &[&str] = &[
     "/usr/sbin/mkfs.ext4",
     "/usr/bin/mkfs.ext4",
 ];
-const BLKID_BINARIES: &[&str] = &["/sbin/blkid", "/usr/sbin/blkid", "/usr/bin/blkid"];
 const LSBLK_BINARIES: &[&str] = &["/bin/lsblk", "/usr/bin/lsblk"];
-const MOUNT_BINARIES: &[&str] = &["/bin/mount", "/usr/bin/mount"];
-const UMOUNT_BINARIES: &[&str] = &["/bin/umount", "/usr/bin/umount"];
 const UDEVADM_BINARIES: &[&str] = &["/sbin/udevadm", "/usr/sbin/udevadm", "/usr/bin/udevadm"];
+const RETRY_BUDGET_ENV: &str = "BESKAR_RETRY_BUDGET_SECS";
+const DEFAULT_RETRY_BUDGET_SECS: u64 = 15;
+/// How long "Retry scan" watches for a freshly inserted stick to enumerate.
+const DEVICE_WAIT_ENV: &str = "BESKAR_DEVICE_WAIT_SECS";
+const DEFAULT_DEVICE_WAIT_SECS: u64 = 10;
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const SETTLE_ATTEMPTS: u32 = 3;
+// Per-operation limits for `run_external`; probe/mount ones live in `util::token`.
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(10);
+const PARTED_TIMEOUT: Duration = Duration::from_secs(20);
+const MKFS_TIMEOUT: Duration = Duration::from_secs(60);
+const INITRAMFS_REBUILD_TIMEOUT: Duration = Duration::from_secs(180);
 
 #[derive(Debug, Clone)]
 pub(crate) enum InitramfsFlavor {
@@ -84,30 +110,93 @@ pub struct InitOptions {
     pub force: bool,
     pub auto_unlock: bool,
     pub confirm_each_phase: bool,
+    pub key_name_template: Option<String>,
+    pub checksum_algo: ChecksumAlgo,
+    pub skip_initramfs: bool,
+    pub label: String,
+    /// Raw record on an unformatted partition instead of an ext4 key file.
+    pub raw_token: bool,
+    /// `[policy] recovery_display`: how the recovery sigil is handed over.
+    pub recovery_display: RecoveryDisplay,
+    /// `[policy] recovery_file`, required under `RecoveryDisplay::File`.
+    pub recovery_file: Option<PathBuf>,
+    /// Directory that receives a 0600 copy of the recovery material.
+    pub artifacts_dir: Option<PathBuf>,
 }
 
+/// The operator declined a safe-mode or target-selection prompt. Typed so
+/// callers can tell a deliberate stand-down from a forge failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperatorDeclined;
+
+impl std::fmt::Display for OperatorDeclined {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "initialization aborted by operator")
+    }
+}
+
+impl std::error::Error for OperatorDeclined {}
+
 // ----------------------------------------------------------------------------
 // Public entrypoint
 // ----------------------------------------------------------------------------
 
 pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
     ui.banner();
+    validate_token_label(&opts.label)?;
+    if opts.recovery_display == RecoveryDisplay::File && opts.recovery_file.is_none() {
+        return Err(anyhow!(
+            "[policy] recovery_display = \"file\" needs recovery_file set to a root-only path."
+        ));
+    }
+    if opts.recovery_display == RecoveryDisplay::Qr
+        && (ui.quiet || ui.json_mode() || !io::stdout().is_terminal())
+    {
+        return Err(anyhow!(
+            "[policy] recovery_display = \"qr\" needs a terminal on stdout without --quiet or --json; use \"file\" instead."
+        ));
+    }
+    if let Some(dir) = &opts.artifacts_dir {
+        if !dir.is_dir() {
+            return Err(anyhow!(
+                "--artifacts-dir {} is not an existing directory; mount the volume first.",
+                dir.display()
+            ));
+        }
+    }
     begin_phase(ui, "Armorer Temper", opts.confirm_each_phase)?;
     ui.info("Token docked. Name the hunt.");
     timing.pace(Pace::Info);
 
     let binary_path = determine_binary_path(None)?;
+    if !opts.skip_initramfs {
+        match detect_initramfs_flavor()? {
+            InitramfsFlavor::InitramfsTools if opts.raw_token => {
+                return Err(anyhow!(
+                    "--raw-token boot unlock needs dracut; the initramfs-tools loader only reads a mounted key file (pass --no-initramfs to wire it yourself)."
+                ));
+            }
+            InitramfsFlavor::Dracut(_) => {
+                crate::dracut::ensure_checksum_tool(opts.checksum_algo)?;
+            }
+            InitramfsFlavor::InitramfsTools => {}
+        }
+    }
 
     let zfs = Zfs::discover(Duration::from_secs(DEFAULT_TIMEOUT))
         .context("detect zfs binary for encryption checks")?;
-    let detected_root = match zfs.dataset_with_mountpoint("/") {
-        Ok(ds) => ds,
-        Err(err) => {
-            ui.warn(&format!(
-                "Lineage obscured ({}). Defaulting to rpool/ROOT.",
-                err
-            ));
-            None
+    let detected_root = if opts.pool.is_some() {
+        None
+    } else {
+        match zfs.dataset_with_mountpoint("/") {
+            Ok(candidates) => choose_root_candidate(ui, candidates)?,
+            Err(err) => {
+                ui.warn(&format!(
+                    "Lineage obscured ({}). Defaulting to rpool/ROOT.",
+                    err
+                ));
+                None
+            }
         }
     };
 
@@ -121,8 +210,8 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
     };
 
     let enc_root = match zfs.encryption_root(&target_dataset) {
-        Ok(root) if !root.trim().is_empty() => root,
-        Ok(_) => target_dataset.clone(),
+        Ok(Some(root)) => root,
+        Ok(None) => return Err(not_encrypted(&target_dataset)),
         Err(err) => {
             ui.warn(&format!(
                 "Lineage unknown for {} ({}). Using dataset.",
@@ -140,10 +229,7 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
         .is_encrypted(&enc_root)
         .with_context(|| format!("verify encryption status of {}", enc_root))?
     {
-        return Err(anyhow!(
-            "Dataset {} is not encrypted — no key forge required.",
-            enc_root
-        ));
+        return Err(not_encrypted(&enc_root));
     }
 
     if !zfs
@@ -156,12 +242,11 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
         ));
     }
 
-    let key_basename = sanitize_key_name(&enc_root);
-
-    let key_path = opts
-        .key_path
-        .clone()
-        .unwrap_or_else(|| PathBuf::from(format!("/run/beskar/{}.keyhex", key_basename)));
+    let key_path = match opts.key_path.clone() {
+        Some(path) => path,
+        None => Path::new("/run/beskar")
+            .join(key_file_name(&enc_root, opts.key_name_template.as_deref())?),
+    };
     let key_filename = key_path
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
@@ -176,7 +261,7 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
 
     let usb_target = match opts.usb_device.clone() {
         Some(dev) => dev,
-        None => select_usb_device(ui, opts.confirm_each_phase)?,
+        None => select_usb_device(ui, opts.confirm_each_phase, &opts.label)?,
     };
 
     let (usb_disk, usb_partition) = derive_device_layout(&usb_target)?;
@@ -193,6 +278,15 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
             ("USB Partition", usb_partition.clone()),
             ("Key Mount Path", key_mount_dir.clone()),
             ("Key File", key_filename.clone()),
+            ("Token Label", opts.label.clone()),
+            (
+                "Token Layout",
+                if opts.raw_token {
+                    format!("raw record @ {}", rawtoken::DEFAULT_OFFSET)
+                } else {
+                    "ext4 key file".to_string()
+                },
+            ),
             ("Auto-Unlock", flag_label(opts.auto_unlock)),
         ],
     );
@@ -205,14 +299,26 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
     }
     timing.pace(Pace::Info);
 
-    let mut effective_force = opts.force;
+    // A raw token has no label to recognise it by, so it is always reforged.
+    let mut effective_force = opts.force || opts.raw_token;
 
-    if effective_force {
+    if opts.raw_token {
+        ui.warn(&format!(
+            "Override accepted. Purging {} to bare alloy; no filesystem will be cast.",
+            usb_disk
+        ));
+        partition_raw_token(&usb_disk, &usb_partition, &opts.label, ui)?;
+        settle_udev(ui)?;
+        audit_log(
+            "INIT_USB_WIPE",
+            &format!("disk={} partition={} layout=raw", usb_disk, usb_partition),
+        );
+    } else if effective_force {
         ui.warn(&format!(
             "Override accepted. Purging {} to bare alloy.",
             usb_disk
         ));
-        wipe_usb_token(&usb_disk, &usb_partition, ui)?;
+        wipe_usb_token(&usb_disk, &usb_partition, &opts.label, ui)?;
         settle_udev(ui)?;
         audit_log(
             "INIT_USB_WIPE",
@@ -220,7 +326,7 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
         );
     } else {
         loop {
-            match ensure_beskar_partition(&usb_partition, ui) {
+            match ensure_beskar_partition(&usb_partition, &opts.label, ui) {
                 Ok(_) => break,
                 Err(err) => {
                     if !opts.confirm_each_phase {
@@ -240,7 +346,7 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
                     match choice {
                         0 => {
                             ui.warn(&format!("Cleansing {} now.", usb_disk));
-                            wipe_usb_token(&usb_disk, &usb_partition, ui)?;
+                            wipe_usb_token(&usb_disk, &usb_partition, &opts.label, ui)?;
                             settle_udev(ui)?;
                             effective_force = true;
                             continue;
@@ -252,7 +358,7 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
                         }
                         _ => {
                             ui.warn("Safe mode ended. Forge idle.");
-                            return Err(anyhow!("initialization aborted by operator"));
+                            return Err(OperatorDeclined.into());
                         }
                     }
                 }
@@ -261,7 +367,8 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
     }
 
     begin_phase(ui, "Forge Key", opts.confirm_each_phase)?;
-    let key_material = generate_key_material()?;
+    guard_independent_descendants(&zfs, &enc_root, opts.confirm_each_phase, ui)?;
+    let key_material = generate_key_material(opts.checksum_algo)?;
     let passphrase_plan = configure_passphrase_plan(ui, &key_material.raw[..])?;
     apply_key_to_encryption_root(
         &zfs,
@@ -271,29 +378,56 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
         &key_location_uri,
         ui,
     )?;
-    write_key_to_usb(
-        &usb_partition,
-        &key_filename,
-        effective_force,
-        &key_material.raw[..],
-        ui,
-    )?;
-    timing.pace(Pace::Info);
+    let raw_device = if opts.raw_token {
+        rawtoken::write_key(
+            Path::new(&usb_partition),
+            rawtoken::DEFAULT_OFFSET,
+            &key_material.raw[..],
+        )?;
+        let partuuid = detect_partition_partuuid(&usb_partition)?;
+        ui.success(&format!(
+            "Beskar key record sealed at offset {} of {}.",
+            rawtoken::DEFAULT_OFFSET,
+            usb_partition
+        ));
+        timing.pace(Pace::Info);
+        Some(format!("/dev/disk/by-partuuid/{}", partuuid))
+    } else {
+        write_key_to_usb(
+            &usb_partition,
+            &key_filename,
+            effective_force,
+            &key_material.raw[..],
+            ui,
+        )?;
+        timing.pace(Pace::Info);
 
-    ensure_runtime_mount(
-        &usb_partition,
-        Path::new(&key_mount_dir),
-        &key_filename,
-        opts.confirm_each_phase,
-        ui,
-    )?;
-    timing.pace(Pace::Info);
+        ensure_runtime_mount(
+            &usb_partition,
+            Path::new(&key_mount_dir),
+            &key_filename,
+            opts.confirm_each_phase,
+            ui,
+        )?;
+        timing.pace(Pace::Info);
+        None
+    };
 
-    let fingerprint_short = group_string(&key_material.sha256[..32], 8, ' ');
-    ui.security(&format!("Key hash: {}", fingerprint_short));
+    let digest = &key_material.digest;
+    let fingerprint_short = group_string(&digest[..digest.len() / 2], 8, ' ').to_string();
+    ui.security(&format!(
+        "Key hash ({}): {}",
+        key_material.algo.label(),
+        fingerprint_short
+    ));
     audit_log(
         "INIT_KEY",
-        &format!("partition={} sha256={}", usb_partition, key_material.sha256),
+        &format!(
+            "partition={} {}={}",
+            usb_partition,
+            key_material.algo.label(),
+            key_material.digest
+        ),
     );
 
     begin_phase(ui, "Config Etch", opts.confirm_each_phase)?;
@@ -302,7 +436,7 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
     let (mut config, force_write) = if config_path.exists() {
         ui.note("Old creed found; aligning lines.");
 
-        let backup_path = backup_existing_config(&config_path)?;
+        let backup_path = backup_config(&config_path)?;
         ui.note(&format!("Backup etched at {}.", backup_path.display()));
 
         let cfg = match ConfigFile::load(&config_path) {
@@ -311,7 +445,7 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
                     &mut existing,
                     &enc_root,
                     &key_path,
-                    &key_material.sha256,
+                    &key_material,
                     DEFAULT_TIMEOUT,
                     &binary_path,
                 );
@@ -325,7 +459,7 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
                 default_config(
                     &enc_root,
                     &key_path,
-                    &key_material.sha256,
+                    &key_material,
                     DEFAULT_TIMEOUT,
                     &config_path,
                     &binary_path,
@@ -338,7 +472,7 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
             default_config(
                 &enc_root,
                 &key_path,
-                &key_material.sha256,
+                &key_material,
                 DEFAULT_TIMEOUT,
                 &config_path,
                 &binary_path,
@@ -347,6 +481,26 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
         )
     };
 
+    config.usb.label = opts.label.clone();
+    // A token forged under its own label joins the search list rather than
+    // replacing it; the others still hold the previous key.
+    if !config.usb.labels.is_empty() && !config.usb.labels.contains(&opts.label) {
+        config.usb.labels.push(opts.label.clone());
+    }
+    let stale: Vec<String> = config
+        .usb
+        .token_labels()
+        .into_iter()
+        .filter(|label| label != &opts.label)
+        .collect();
+    if !stale.is_empty() {
+        ui.warn(&format!(
+            "Tokens labelled {} still carry the previous key; etch the new one with `recover --label=<label>`.",
+            stale.join(", ")
+        ));
+    }
+    config.usb.raw_device = raw_device;
+    config.usb.raw_offset = rawtoken::DEFAULT_OFFSET;
     apply_passphrase_plan(&passphrase_plan, &mut config);
 
     atomic_write_toml(&config_path, &config, force_write)?;
@@ -356,58 +510,100 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
     audit_log("INIT_CFG", &format!("Created {}", config_path.display()));
     timing.pace(Pace::Info);
 
-    let initramfs_flavor =
-        detect_initramfs_flavor().context("detect initramfs tooling for auto-unlock")?;
+    let initramfs_flavor = if opts.skip_initramfs {
+        None
+    } else {
+        Some(detect_initramfs_flavor().context("detect initramfs tooling for auto-unlock")?)
+    };
 
-    begin_phase(ui, "Initramfs Fittings", opts.confirm_each_phase)?;
-    match &initramfs_flavor {
-        InitramfsFlavor::Dracut(_) => crate::cmd::dracut_install::install_for_dataset(
-            ui,
-            &config,
-            Some(&enc_root),
-            Some(initramfs_flavor.clone()),
-        )?,
-        InitramfsFlavor::InitramfsTools => {
-            install_initramfs_tools_scripts(Path::new(&key_mount_dir), &key_path, ui)?
+    if let Some(flavor) = &initramfs_flavor {
+        begin_phase(ui, "Initramfs Fittings", opts.confirm_each_phase)?;
+        match flavor {
+            InitramfsFlavor::Dracut(_) => crate::cmd::dracut_install::install_for_dataset(
+                ui,
+                &config,
+                Some(&enc_root),
+                Some(flavor.clone()),
+                false,
+            )?,
+            InitramfsFlavor::InitramfsTools => install_initramfs_tools_scripts(
+                Path::new(&key_mount_dir),
+                &key_path,
+                &config.usb,
+                ui,
+            )?,
         }
+    } else {
+        ui.note(
+            "Boot integration deferred (--no-initramfs); wire the loader into your own pipeline.",
+        );
+        audit_log("INIT_INITRAMFS", "Skipped initramfs integration by request");
     }
     timing.pace(Pace::Info);
 
     begin_phase(ui, "Contingency", opts.confirm_each_phase)?;
     let recovery_code = encode_recovery_code(&key_material.raw);
     let recovery_formatted = group_string(&recovery_code, 4, '-');
-    ui.security(&format!(
-        "Recovery sigil: {}. Guard it.",
-        recovery_formatted
-    ));
-    audit_log("INIT_RECOVERY", "Generated recovery key");
+    let recovery_row = hand_over_recovery_sigil(ui, &opts, &recovery_formatted)?;
+    audit_log(
+        "INIT_RECOVERY",
+        &format!(
+            "Generated recovery key (display={})",
+            opts.recovery_display.label()
+        ),
+    );
     timing.pace(Pace::Info);
 
-    begin_phase(ui, "Initramfs Briefing", opts.confirm_each_phase)?;
-    ui.info("Summoning smiths to rebuild initramfs.");
-    match rebuild_initramfs(ui, &initramfs_flavor) {
-        Ok(_) => ui.success("Initramfs now bears the Beskar loader."),
-        Err(e) => {
-            ui.warn(&format!("Automatic initramfs rebuild failed ({}).", e));
-            ui.note(
-                "Manual fallback: run `zfs_beskar_key install-dracut` followed by `sudo dracut -f`.",
-            );
+    if let Some(flavor) = &initramfs_flavor {
+        begin_phase(ui, "Initramfs Briefing", opts.confirm_each_phase)?;
+        ui.info("Summoning smiths to rebuild initramfs.");
+        match rebuild_initramfs(ui, flavor) {
+            Ok(_) => ui.success("Initramfs now bears the Beskar loader."),
+            Err(e) => {
+                ui.warn(&format!("Automatic initramfs rebuild failed ({}).", e));
+                ui.note(
+                    "Manual fallback: run `zfs_beskar_key install-dracut` followed by `sudo dracut -f`.",
+                );
+            }
         }
     }
 
     let usb_uuid = detect_partition_uuid(&usb_partition).unwrap_or_else(|_| "unknown".to_string());
 
     begin_phase(ui, "Forge Summary", opts.confirm_each_phase)?;
-    ui.data_panel(
-        "Artifacts",
-        &[
-            ("Key Path", key_path.to_string_lossy().into_owned()),
-            ("Config", config_path.display().to_string()),
-            ("Recovery Token", recovery_formatted.clone()),
-            ("Fingerprint", fingerprint_short),
-            ("USB UUID", usb_uuid.clone()),
-        ],
-    );
+    let mut artifacts = vec![
+        ("Key Path", key_path.to_string_lossy().into_owned()),
+        ("Config", config_path.display().to_string()),
+        ("Recovery Token", recovery_row),
+        ("Fingerprint", fingerprint_short),
+        ("USB UUID", usb_uuid.clone()),
+    ];
+    if let Some(dir) = &opts.artifacts_dir {
+        let summary = ArtifactSummary {
+            dataset: &target_dataset,
+            enc_root: &enc_root,
+            usb_uuid: &usb_uuid,
+            algo: key_material.algo,
+            digest,
+            recovery_code: &recovery_code,
+        };
+        match write_artifacts_file(dir, &summary) {
+            Ok(path) => {
+                audit_log("INIT_ARTIFACTS", &format!("path={}", path.display()));
+                artifacts.push(("Artifacts File", path.display().to_string()));
+            }
+            Err(err) => {
+                ui.error(&format!(
+                    "Artifacts file not written ({:#}); copy the recovery sigil by hand.",
+                    err
+                ));
+                audit_log("INIT_ARTIFACTS_FAIL", &format!("{:#}", err));
+            }
+        }
+    }
+    ui.data_panel("Artifacts", &artifacts);
+    // Under `stdout` the panel row is a plain String copy of the sigil; scrub it now.
+    artifacts[2].1.zeroize();
 
     ui.success("Beskar plating secured. Systems primed.");
     ui.note("Run `zfs_beskar_key doctor` then drill.");
@@ -426,6 +622,7 @@ pub fn run_init(ui: &UX, timing: &Timing, opts: InitOptions) -> Result<()> {
 
 fn begin_phase(ui: &UX, label: &str, confirm: bool) -> Result<()> {
     if confirm {
+        require_terminal("safe-mode confirmation")?;
         let theme = ColorfulTheme::default();
         let prompt = format!("Safe mode: proceed with {}?", label);
         let proceed = Confirm::with_theme(&theme)
@@ -435,7 +632,7 @@ fn begin_phase(ui: &UX, label: &str, confirm: bool) -> Result<()> {
             .context("safe mode confirmation failed")?;
         if !proceed {
             ui.warn("Safe mode abort; forge halted.");
-            return Err(anyhow!("initialization aborted by operator"));
+            return Err(OperatorDeclined.into());
         }
     }
     ui.phase(label);
@@ -443,11 +640,10 @@ fn begin_phase(ui: &UX, label: &str, confirm: bool) -> Result<()> {
 }
 
 // ----------------------------------------------------------------------------
-// Helper: Generate recovery key
+// Helper: Fallback passphrase plan
 // ----------------------------------------------------------------------------
 
-/// Generate a random 24-character recovery key (A-Z, a-z, 0-9)
-enum PassphrasePlan {
+pub(crate) enum PassphrasePlan {
     Disabled,
     Configured {
         salt_hex: String,
@@ -457,36 +653,51 @@ enum PassphrasePlan {
 }
 
 fn configure_passphrase_plan(ui: &UX, raw_key: &[u8]) -> Result<PassphrasePlan> {
-    let passphrase = Password::new()
-        .with_prompt("Armorer passphrase (blank to skip)")
-        .allow_empty_password(true)
-        .interact()
-        .context("fallback passphrase prompt failed")?;
+    if non_interactive() {
+        ui.note("Passphrase skipped (--non-interactive); USB stands alone.");
+        return Ok(PassphrasePlan::Disabled);
+    }
+    require_terminal("the fallback passphrase prompt")?;
+    let passphrase = Zeroizing::new(
+        Password::new()
+            .with_prompt("Armorer passphrase (blank to skip)")
+            .allow_empty_password(true)
+            .interact()
+            .context("fallback passphrase prompt failed")?,
+    );
 
     if passphrase.is_empty() {
         ui.note("Passphrase skipped; USB stands alone.");
         return Ok(PassphrasePlan::Disabled);
     }
 
-    let confirm = Password::new()
-        .with_prompt("Confirm Armorer passphrase")
-        .allow_empty_password(false)
-        .interact()
-        .context("fallback passphrase confirmation failed")?;
+    let confirm = Zeroizing::new(
+        Password::new()
+            .with_prompt("Confirm Armorer passphrase")
+            .allow_empty_password(false)
+            .interact()
+            .context("fallback passphrase confirmation failed")?,
+    );
 
     if passphrase != confirm {
         return Err(anyhow!("Fallback passphrases did not match."));
     }
 
+    let plan = seal_fallback_passphrase(passphrase.as_bytes(), raw_key, DEFAULT_PASSPHRASE_ITERS);
+    ui.success("Passphrase sealed. Guard it offline.");
+    Ok(plan)
+}
+
+/// XOR `raw_key` with a PBKDF2 derivation of `passphrase` under a fresh salt.
+pub(crate) fn seal_fallback_passphrase(
+    passphrase: &[u8],
+    raw_key: &[u8],
+    iters: u32,
+) -> PassphrasePlan {
     let mut salt = [0u8; 16];
     OsRng.fill_bytes(&mut salt);
     let mut derived = Zeroizing::new(vec![0u8; raw_key.len()]);
-    pbkdf2_sha256(
-        passphrase.as_bytes(),
-        &salt,
-        DEFAULT_PASSPHRASE_ITERS,
-        &mut derived,
-    );
+    pbkdf2_sha256(passphrase, &salt, iters, &mut derived);
 
     let xor_bytes: Vec<u8> = raw_key
         .iter()
@@ -494,15 +705,14 @@ fn configure_passphrase_plan(ui: &UX, raw_key: &[u8]) -> Result<PassphrasePlan>
         .map(|(a, b)| a ^ b)
         .collect();
 
-    ui.success("Passphrase sealed. Guard it offline.");
-    Ok(PassphrasePlan::Configured {
+    PassphrasePlan::Configured {
         salt_hex: hex::encode(salt),
         xor_hex: hex::encode(&xor_bytes),
-        iters: DEFAULT_PASSPHRASE_ITERS,
-    })
+        iters,
+    }
 }
 
-fn apply_passphrase_plan(plan: &PassphrasePlan, cfg: &mut ConfigFile) {
+pub(crate) fn apply_passphrase_plan(plan: &PassphrasePlan, cfg: &mut ConfigFile) {
     match plan {
         PassphrasePlan::Disabled => {
             cfg.fallback.enabled = false;
@@ -529,30 +739,95 @@ pub(crate) fn sanitize_key_name(dataset: &str) -> String {
         .collect::<String>()
 }
 
+/// Default token file name: the sanitized dataset plus a short digest of the
+/// full name, so `rpool/ROOT` and `rpool_ROOT` never share a key file.
+pub(crate) const DEFAULT_KEY_NAME_TEMPLATE: &str = "{name}-{hash}.keyhex";
+
+/// Render the token key file name for `dataset` from `template` (or the default).
+pub(crate) fn key_file_name(dataset: &str, template: Option<&str>) -> Result<String> {
+    let template = template
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .unwrap_or(DEFAULT_KEY_NAME_TEMPLATE);
+    let digest = hex::encode(Sha256::digest(dataset.as_bytes()));
+    let rendered = template
+        .replace("{name}", &sanitize_key_name(dataset))
+        .replace("{hash}", &digest[..8]);
+    if rendered.is_empty() || rendered.contains('/') || rendered == "." || rendered == ".." {
+        return Err(anyhow!(
+            "key_name_template {:?} renders an invalid file name {:?}",
+            template,
+            rendered
+        ));
+    }
+    Ok(rendered)
+}
+
+/// Key file name on the token for `dataset`. Datasets already recorded in the
+/// config keep the file name init etched; others follow the template.
+pub(crate) fn token_key_file_name(cfg: &ConfigFile, dataset: &str) -> Result<String> {
+    if cfg.policy.datasets.iter().any(|d| d == dataset) {
+        if let Some(name) = Path::new(&cfg.usb.key_hex_path).file_name() {
+            return Ok(name.to_string_lossy().into_owned());
+        }
+    }
+    key_file_name(dataset, cfg.usb.key_name_template.as_deref())
+}
+
 fn default_config(
     dataset: &str,
     key_path: &Path,
-    sha256: &str,
+    key_material: &KeyMaterial,
     timeout: u64,
     config_path: &Path,
     binary_path: &Path,
 ) -> ConfigFile {
     ConfigFile {
+        version: CONFIG_VERSION,
         policy: Policy {
             datasets: vec![dataset.to_string()],
             zfs_path: Some(DEFAULT_ZFS_BIN.to_string()),
             binary_path: Some(binary_path.to_string_lossy().into_owned()),
             allow_root: true,
+            extra_allowed_binaries: Vec::new(),
+            require_healthy_pool: false,
+            dataset_overrides: Vec::new(),
+            recovery_display: RecoveryDisplay::Stdout,
+            recovery_file: None,
         },
         crypto: CryptoCfg {
             timeout_secs: timeout,
+            keyring: None,
+            keyring_secs: 600,
+            keyring_cache: false,
+            keyring_cache_secs: 600,
+            require_all_descendants: false,
         },
         usb: Usb {
             key_hex_path: key_path.to_string_lossy().into_owned(),
-            expected_sha256: Some(sha256.to_string()),
+            expected_sha256: Some(key_material.digest.clone()),
+            checksum_algo: key_material.algo,
+            key_name_template: None,
+            label: crate::dracut::BESKAR_TOKEN_LABEL.to_string(),
+            labels: Vec::new(),
+            strict_keyfile: false,
+            wait_secs: DEFAULT_USB_WAIT_SECS,
+            auto_mount: true,
+            require_serial: None,
+            require_vidpid: None,
+            break_glass_path: None,
+            raw_device: None,
+            raw_offset: rawtoken::DEFAULT_OFFSET,
         },
         fallback: Fallback::default(),
+        fido2: Fido2::default(),
+        audit: Audit {
+            utc: true,
+            ..Audit::default()
+        },
+        ui: UiCfg::default(),
         path: config_path.to_path_buf(),
+        migrated_from: None,
     }
 }
 
@@ -560,7 +835,7 @@ fn normalize_config(
     cfg: &mut ConfigFile,
     dataset: &str,
     key_path: &Path,
-    sha256: &str,
+    key_material: &KeyMaterial,
     default_timeout: u64,
     binary_path: &Path,
 ) {
@@ -586,44 +861,14 @@ fn normalize_config(
     }
 
     cfg.usb.key_hex_path = key_path.to_string_lossy().into_owned();
-    cfg.usb.expected_sha256 = Some(sha256.to_string());
+    cfg.usb.expected_sha256 = Some(key_material.digest.clone());
+    cfg.usb.checksum_algo = key_material.algo;
 
     if cfg.fallback.askpass_path.is_none() {
         cfg.fallback.askpass_path = Some("/usr/bin/systemd-ask-password".to_string());
     }
 }
 
-fn backup_existing_config(path: &Path) -> Result<PathBuf> {
-    if !path.exists() {
-        return Err(anyhow!(
-            "No existing config to backup at {}",
-            path.display()
-        ));
-    }
-
-    let file_name = path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or_else(|| anyhow!("Config path missing filename"))?;
-    let stamp = Utc::now().format("%Y%m%d%H%M%S");
-    let backup_name = format!("{}.bak-{}", file_name, stamp);
-    let backup_path = path.with_file_name(backup_name);
-
-    fs::copy(path, &backup_path).with_context(|| {
-        format!(
-            "Failed to backup existing config to {}",
-            backup_path.display()
-        )
-    })?;
-    fs::set_permissions(&backup_path, Permissions::from_mode(0o600)).with_context(|| {
-        format!(
-            "Failed to set permissions on backup {}",
-            backup_path.display()
-        )
-    })?;
-    Ok(backup_path)
-}
-
 pub(crate) fn report_usb_target(ui: &UX, device: &str) {
     match fs::metadata(device) {
         Ok(meta) => {
@@ -665,17 +910,130 @@ fn describe_target(meta: &Metadata) -> &'static str {
     }
 }
 
-pub(crate) fn group_string(input: &str, chunk: usize, separator: char) -> String {
-    if chunk == 0 {
-        return input.to_string();
+/// `input` in `chunk`-char groups joined by `separator`. Recovery sigils pass
+/// through here, so the result is built in one pre-sized zeroizing buffer.
+pub(crate) fn group_string(input: &str, chunk: usize, separator: char) -> Zeroizing<String> {
+    let groups = input.chars().count() / chunk.max(1) + 1;
+    let mut out = Zeroizing::new(String::with_capacity(
+        input.len() + groups * separator.len_utf8(),
+    ));
+    for (idx, ch) in input.chars().enumerate() {
+        if chunk > 0 && idx > 0 && idx % chunk == 0 {
+            out.push(separator);
+        }
+        out.push(ch);
     }
-    input
-        .chars()
-        .collect::<Vec<_>>()
-        .chunks(chunk)
-        .map(|part| part.iter().collect::<String>())
-        .collect::<Vec<_>>()
-        .join(&separator.to_string())
+    out
+}
+
+/// Show `sigil` the way `[policy] recovery_display` asks and return what the
+/// Artifacts panel should list for it.
+fn hand_over_recovery_sigil(ui: &UX, opts: &InitOptions, sigil: &str) -> Result<String> {
+    match (opts.recovery_display, opts.recovery_file.as_deref()) {
+        (RecoveryDisplay::File, Some(path)) => {
+            write_recovery_file(path, sigil)?;
+            ui.security(&format!(
+                "Recovery sigil etched to {} (0600). Carry it offline, then shred the file.",
+                path.display()
+            ));
+            Ok(format!("in {}", path.display()))
+        }
+        (RecoveryDisplay::Qr, _) => {
+            show_recovery_qr(ui, sigil)?;
+            Ok("shown as QR code".to_string())
+        }
+        _ => {
+            ui.security(&Zeroizing::new(format!(
+                "Recovery sigil: {}. Guard it.",
+                sigil
+            )));
+            Ok(sigil.to_string())
+        }
+    }
+}
+
+/// What `--artifacts-dir` records about one forge.
+struct ArtifactSummary<'a> {
+    dataset: &'a str,
+    enc_root: &'a str,
+    usb_uuid: &'a str,
+    algo: ChecksumAlgo,
+    digest: &'a str,
+    recovery_code: &'a str,
+}
+
+/// Write the forge summary, recovery code included, as a timestamped 0600
+/// file under `dir`. Returns the path written.
+fn write_artifacts_file(dir: &Path, summary: &ArtifactSummary) -> Result<PathBuf> {
+    let now = chrono::Utc::now();
+    let path = dir.join(format!(
+        "beskar-artifacts-{}-{}.txt",
+        summary.enc_root.replace('/', "_"),
+        now.format("%Y%m%dT%H%M%SZ")
+    ));
+    let contents = render_artifacts(summary, &now.to_rfc3339());
+    atomic_write_bytes(&path, contents.as_bytes(), 0o600, false)
+        .with_context(|| format!("write artifacts file {}", path.display()))?;
+    Ok(path)
+}
+
+fn render_artifacts(summary: &ArtifactSummary, created: &str) -> Zeroizing<String> {
+    let mut out = Zeroizing::new(String::with_capacity(1024));
+    out.push_str(&format!(
+        "# SENSITIVE: Beskar init artifacts for {}\n\
+         # recovery_code IS the vault key. Anyone holding it can unlock the\n\
+         # encryption root and forge a replacement token with `zfs_beskar_key\n\
+         # recover`. Keep this file on an encrypted volume or offline media.\n\
+         created = {}\n\
+         dataset = {}\n\
+         encryption_root = {}\n\
+         usb_uuid = {}\n\
+         fingerprint = {}:{}\n",
+        summary.enc_root,
+        created,
+        summary.dataset,
+        summary.enc_root,
+        summary.usb_uuid,
+        summary.algo.label(),
+        summary.digest,
+    ));
+    out.push_str("recovery_code = ");
+    out.push_str(summary.recovery_code);
+    out.push('\n');
+    out
+}
+
+fn write_recovery_file(path: &Path, sigil: &str) -> Result<()> {
+    let contents = Zeroizing::new(format!("{}\n", sigil));
+    atomic_write_bytes(path, contents.as_bytes(), 0o600, true)
+        .with_context(|| format!("write recovery sigil to {}", path.display()))
+}
+
+/// Render `sigil` as a terminal QR code and, when someone is there to
+/// confirm the capture, wipe it from the screen again.
+fn show_recovery_qr(ui: &UX, sigil: &str) -> Result<()> {
+    let code = QrCode::new(sigil.as_bytes()).context("encode recovery sigil as a QR code")?;
+    // Inverted so the code reads on the usual dark terminal background.
+    let art = Zeroizing::new(
+        code.render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .quiet_zone(true)
+            .build(),
+    );
+    ui.security("Recovery sigil follows as a QR code. Photograph it for offline storage.");
+    ui.verbatim(&art);
+    if !interactive_terminal() {
+        ui.warn("No operator to confirm the capture; clear the screen once the code is stored.");
+        return Ok(());
+    }
+    let term = Term::stdout();
+    term.write_str("Press Enter once the code is captured: ")?;
+    term.read_line().context("await QR capture confirmation")?;
+    term.clear_last_lines(art.lines().count() + 1)
+        .context("clear recovery QR code")?;
+    ui.note("QR code cleared from the screen.");
+    Ok(())
 }
 
 fn flag_label(enabled: bool) -> String {
@@ -720,7 +1078,7 @@ fn existing_partition_for_disk(disk: &str) -> Result<Option<String>> {
     let out = run_external(
         LSBLK_BINARIES,
         &["-P", "-nrpo", "PATH,TYPE", disk],
-        Duration::from_secs(5),
+        PROBE_TIMEOUT,
     )?;
 
     if out.status != 0 {
@@ -756,11 +1114,11 @@ fn predict_partition_name(disk: &str) -> String {
     }
 }
 
-fn ensure_beskar_partition(partition: &str, ui: &UX) -> Result<()> {
+fn ensure_beskar_partition(partition: &str, expected_label: &str, ui: &UX) -> Result<()> {
     let out = run_external(
         BLKID_BINARIES,
         &["-s", "LABEL", "-o", "value", partition],
-        Duration::from_secs(5),
+        PROBE_TIMEOUT,
     )?;
 
     if out.status != 0 {
@@ -773,84 +1131,190 @@ fn ensure_beskar_partition(partition: &str, ui: &UX) -> Result<()> {
     }
 
     let label = out.stdout.trim();
-    if label != BESKAR_LABEL {
+    if label != expected_label {
         ui.warn(&format!(
             "Partition {} bears the stamp '{}'; expected '{}'. Invoke --force to recast it.",
-            partition, label, BESKAR_LABEL
+            partition, label, expected_label
         ));
         return Err(anyhow!("Unexpected label {} for {}", label, partition));
     }
     Ok(())
 }
 
-pub(crate) fn wipe_usb_token(disk: &str, partition: &str, ui: &UX) -> Result<()> {
+pub(crate) fn wipe_usb_token(disk: &str, partition: &str, label: &str, ui: &UX) -> Result<()> {
     dismantle_mounts(disk, ui)?;
     dismantle_mounts(partition, ui)?;
 
-    run_external(
+    run_external_checked(
         PARTED_BINARIES,
         &["-s", disk, "mklabel", "gpt"],
-        Duration::from_secs(20),
+        PARTED_TIMEOUT,
     )?;
-    run_external(
+    run_external_checked(
         PARTED_BINARIES,
         &["-s", disk, "mkpart", "BESKAR_PART", "ext4", "1MiB", "100%"],
-        Duration::from_secs(20),
+        PARTED_TIMEOUT,
     )?;
 
-    settle_udev(ui)?;
+    await_block_node(ui, partition)?;
 
-    run_external(
-        MKFS_BINARIES,
-        &["-F", "-L", BESKAR_LABEL, partition],
-        Duration::from_secs(60),
-    )?;
+    run_external_checked(MKFS_BINARIES, &["-F", "-L", label, partition], MKFS_TIMEOUT)?;
 
     ui.success(&format!(
         "{} quenched; it now carries the {} sigil.",
-        partition, BESKAR_LABEL
+        partition, label
+    ));
+    Ok(())
+}
+
+/// Fresh GPT with one unformatted partition named after the token label, so a
+/// raw token is still recognisable in `lsblk -o PARTLABEL`.
+fn partition_raw_token(disk: &str, partition: &str, label: &str, ui: &UX) -> Result<()> {
+    dismantle_mounts(disk, ui)?;
+    dismantle_mounts(partition, ui)?;
+
+    run_external_checked(
+        PARTED_BINARIES,
+        &["-s", disk, "mklabel", "gpt"],
+        PARTED_TIMEOUT,
+    )?;
+    run_external_checked(
+        PARTED_BINARIES,
+        &["-s", disk, "mkpart", label, "1MiB", "100%"],
+        PARTED_TIMEOUT,
+    )?;
+
+    await_block_node(ui, partition)?;
+    ui.success(&format!(
+        "{} quenched; left bare for a raw key record.",
+        partition
     ));
     Ok(())
 }
 
+/// Pause standing in for `udevadm settle` where udev is absent (containers,
+/// minimal rescue systems); callers that need a node poll for it as well.
+const NO_UDEV_PAUSE: Duration = Duration::from_millis(500);
+const NODE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+static NO_UDEV_NOTED: AtomicBool = AtomicBool::new(false);
+/// A settle requested this soon after a completed one, with no partition,
+/// filesystem, or mount change in between, is skipped.
+const SETTLE_COALESCE_WINDOW: Duration = Duration::from_secs(2);
+/// Tools whose runs queue udev events (see `run_external`).
+const DEVICE_CHANGING_BINARIES: &[&[&str]] = &[
+    PARTED_BINARIES,
+    MKFS_BINARIES,
+    MOUNT_BINARIES,
+    UMOUNT_BINARIES,
+];
+static DEVICES_CHANGED: AtomicBool = AtomicBool::new(true);
+static LAST_SETTLE: Mutex<Option<Instant>> = Mutex::new(None);
+
 pub(crate) fn settle_udev(ui: &UX) -> Result<()> {
-    let res = run_external(UDEVADM_BINARIES, &["settle"], Duration::from_secs(10));
-    if let Err(err) = res {
+    let last = LAST_SETTLE.lock().map(|last| *last).unwrap_or(None);
+    if !settle_needed(DEVICES_CHANGED.load(Ordering::SeqCst), last) {
+        return Ok(());
+    }
+    // Cleared before settling, so a change made meanwhile forces the next one.
+    DEVICES_CHANGED.store(false, Ordering::SeqCst);
+    let settled = settle_now(ui);
+    if settled {
+        if let Ok(mut last) = LAST_SETTLE.lock() {
+            *last = Some(Instant::now());
+        }
+    } else {
+        DEVICES_CHANGED.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+fn settle_needed(devices_changed: bool, last_settle: Option<Instant>) -> bool {
+    devices_changed || last_settle.is_none_or(|at| at.elapsed() >= SETTLE_COALESCE_WINDOW)
+}
+
+/// One `udevadm settle` with retries; false when it never succeeded.
+fn settle_now(ui: &UX) -> bool {
+    if external_cmd(UDEVADM_BINARIES).is_err() {
+        if !NO_UDEV_NOTED.swap(true, Ordering::SeqCst) {
+            ui.note("udevadm not present; pausing briefly and polling device nodes instead.");
+        }
+        std::thread::sleep(NO_UDEV_PAUSE);
+        return true;
+    }
+    let budget = retry_budget();
+    let started = Instant::now();
+    let mut last_err = None;
+    for attempt in 0..SETTLE_ATTEMPTS {
+        match run_external(UDEVADM_BINARIES, &["settle"], SETTLE_TIMEOUT) {
+            Ok(out) if out.status == 0 => return true,
+            Ok(out) => last_err = Some(anyhow!("exit status {}", out.status)),
+            Err(err) => last_err = Some(err),
+        }
+        if started.elapsed() >= budget {
+            break;
+        }
+        std::thread::sleep(jittered_backoff(100, attempt));
+    }
+    if let Some(err) = last_err {
         ui.warn(&format!(
             "udevadm settle faltered ({}). Expect a brief delay.",
             err
         ));
     }
+    false
+}
+
+/// Settle udev, then wait up to `SETTLE_TIMEOUT` for `node` to exist, so a
+/// freshly created partition is formatted only once the kernel exposed it.
+fn await_block_node(ui: &UX, node: &str) -> Result<()> {
+    settle_udev(ui)?;
+    let started = Instant::now();
+    while !Path::new(node).exists() {
+        if started.elapsed() >= SETTLE_TIMEOUT {
+            return Err(anyhow!(
+                "{} did not appear within {}s of partitioning",
+                node,
+                SETTLE_TIMEOUT.as_secs()
+            ));
+        }
+        std::thread::sleep(NODE_POLL_INTERVAL);
+    }
     Ok(())
 }
 
+/// Total time allowed for settle/unmount retries; override via `BESKAR_RETRY_BUDGET_SECS`.
+fn retry_budget() -> Duration {
+    let secs = std::env::var(RETRY_BUDGET_ENV)
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_RETRY_BUDGET_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Linear backoff plus up to 50% random jitter so retries drift away from
+/// udev workers that would otherwise collide on every pass.
+fn jittered_backoff(base_ms: u64, attempt: u32) -> Duration {
+    let linear = base_ms * (attempt as u64 + 1);
+    let jitter = OsRng.gen_range(0..=linear / 2);
+    Duration::from_millis(linear + jitter)
+}
+
 fn query_block_info(device: &str, field: &str) -> Result<String> {
-    let out = run_external(
-        LSBLK_BINARIES,
-        &["-no", field, device],
-        Duration::from_secs(5),
-    )?;
-    if out.status != 0 {
-        return Err(anyhow!(
-            "lsblk -no {} {} failed: {}",
-            field,
-            device,
-            out.stderr.trim()
-        ));
-    }
+    let out = run_external_checked(LSBLK_BINARIES, &["-no", field, device], PROBE_TIMEOUT)?;
     Ok(out.stdout.trim().to_string())
 }
 
 struct KeyMaterial {
     raw: Zeroizing<Vec<u8>>,
-    sha256: String,
+    digest: String,
+    algo: ChecksumAlgo,
 }
 
-fn generate_key_material() -> Result<KeyMaterial> {
-    let mut raw = Zeroizing::new(vec![0u8; 32]);
+fn generate_key_material(algo: ChecksumAlgo) -> Result<KeyMaterial> {
+    let mut raw = Zeroizing::new(vec![0u8; KEY_LEN]);
     OsRng.fill_bytes(&mut raw[..]);
-    let sha256 = hex::encode(Sha256::digest(&*raw));
-    Ok(KeyMaterial { raw, sha256 })
+    let digest = algo.digest_hex(&raw);
+    Ok(KeyMaterial { raw, digest, algo })
 }
 
 pub(crate) fn write_key_to_usb(
@@ -864,39 +1328,56 @@ pub(crate) fn write_key_to_usb(
     mount_partition(partition, mount_dir.path())?;
 
     let key_path = mount_dir.path().join(key_filename);
-    if key_path.exists() {
-        if force {
-            fs::remove_file(&key_path).context("remove existing key file")?;
-        } else {
-            ui.warn(&format!("Old ingot {} detected; reforging.", key_filename));
-            fs::remove_file(&key_path).ok();
-        }
+    if key_path.exists() && !force {
+        ui.warn(&format!("Old ingot {} detected; reforging.", key_filename));
     }
 
-    let mut file = File::create(&key_path)
-        .with_context(|| format!("create key file at {}", key_path.display()))?;
-    file.write_all(key_raw)?;
-    file.sync_all().ok();
-    fs::set_permissions(&key_path, Permissions::from_mode(0o400))
-        .context("set key file permissions")?;
-    drop(file);
+    // Temp file + rename: power loss mid-forge leaves the old key intact.
+    atomic_write_key(&key_path, key_raw, true)
+        .with_context(|| format!("write key file at {}", key_path.display()))?;
+    match wear::record_write(mount_dir.path()) {
+        Ok(record) if record.is_worn() => ui.warn(&format!(
+            "Token has been rewritten {} times; forge a spare before it tires.",
+            record.writes
+        )),
+        Ok(_) => {}
+        Err(err) => ui.warn(&format!("Wear ledger not updated ({}).", err)),
+    }
+    // Held until the token is released; a bad read-back must not strand the mount.
+    let read_back_err = verify_read_back(&key_path, key_raw).err();
+    if let Some(err) = &read_back_err {
+        ui.error(&format!(
+            "Read-back of {} faltered ({:#}).",
+            key_filename, err
+        ));
+        audit_log(
+            "USB_KEY_READBACK_FAIL",
+            &format!("{}: {:#}", partition, err),
+        );
+        if let Err(ledger_err) = wear::record_verify_failure(mount_dir.path()) {
+            ui.warn(&format!("Wear ledger not updated ({}).", ledger_err));
+        }
+    }
     std::thread::sleep(Duration::from_millis(150));
 
     let mount_path = mount_dir.path().to_path_buf();
     let mut unmounted = false;
     let mut last_err: Option<anyhow::Error> = None;
 
+    let budget = retry_budget();
+    let started = Instant::now();
     for attempt in 0..3 {
         match unmount_partition(mount_path.as_path()) {
             Ok(_) => {
                 unmounted = true;
                 break;
             }
-            Err(err) => {
-                last_err = Some(err);
-                std::thread::sleep(Duration::from_millis(200 * (attempt as u64 + 1)));
-            }
+            Err(err) => last_err = Some(err),
+        }
+        if started.elapsed() >= budget {
+            break;
         }
+        std::thread::sleep(jittered_backoff(200, attempt));
     }
 
     if !unmounted {
@@ -942,6 +1423,13 @@ pub(crate) fn write_key_to_usb(
         ));
     }
 
+    if let Some(err) = read_back_err {
+        return Err(err.context(format!(
+            "key on {} did not read back intact; the token may be failing",
+            partition
+        )));
+    }
+
     ui.success(&format!(
         "Beskar key sealed at {} atop {}.",
         key_filename, partition
@@ -949,6 +1437,89 @@ pub(crate) fn write_key_to_usb(
     Ok(())
 }
 
+/// Reread a freshly written key and compare it with what was meant to land.
+/// The page cache is dropped first so the bytes come from the stick itself.
+fn verify_read_back(path: &Path, expected: &[u8]) -> Result<()> {
+    let mut file = File::open(path).with_context(|| format!("reopen {}", path.display()))?;
+    // Advisory only: a kernel that keeps the pages still yields a valid compare.
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+    }
+    let mut actual = Zeroizing::new(Vec::with_capacity(expected.len()));
+    file.read_to_end(&mut actual)
+        .with_context(|| format!("read back {}", path.display()))?;
+    if actual.as_slice() != expected {
+        return Err(anyhow!(
+            "{} reads back {} bytes that differ from the {} written",
+            path.display(),
+            actual.len(),
+            expected.len()
+        ));
+    }
+    Ok(())
+}
+
+/// `change-key` only reaches datasets inheriting `enc_root`. Name every
+/// descendant keyed elsewhere so nobody believes the whole tree rotated; safe
+/// mode asks before rekeying anyway.
+fn guard_independent_descendants(zfs: &Zfs, enc_root: &str, confirm: bool, ui: &UX) -> Result<()> {
+    let independent = match zfs.independently_keyed(enc_root) {
+        Ok(list) => list,
+        Err(err) => {
+            ui.warn(&format!(
+                "Unable to survey descendants of {} for independent keys ({}).",
+                enc_root, err
+            ));
+            return Ok(());
+        }
+    };
+    if independent.is_empty() {
+        return Ok(());
+    }
+
+    ui.warn(&format!(
+        "{} descendant(s) of {} hold their own encryption root; this forge will not rekey them:",
+        independent.len(),
+        enc_root
+    ));
+    for (dataset, root) in &independent {
+        if dataset == root {
+            ui.note(&format!("  {} (own encryption root)", dataset));
+        } else {
+            ui.note(&format!("  {} (keyed by {})", dataset, root));
+        }
+    }
+    audit_log(
+        "INIT_INDEPENDENT_KEYS",
+        &format!(
+            "encryption_root={} untouched={}",
+            enc_root,
+            independent
+                .iter()
+                .map(|(dataset, _)| dataset.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+    );
+
+    if confirm {
+        require_terminal("safe-mode confirmation")?;
+        let proceed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "Safe mode: rekey {} and leave these datasets on their current keys?",
+                enc_root
+            ))
+            .default(false)
+            .interact()
+            .context("safe mode confirmation failed")?;
+        if !proceed {
+            ui.warn("Safe mode abort; forge halted.");
+            return Err(OperatorDeclined.into());
+        }
+    }
+    Ok(())
+}
+
 struct ExistingKey {
     raw: Zeroizing<Vec<u8>>,
 }
@@ -972,15 +1543,25 @@ fn apply_key_to_encryption_root(
 ) -> Result<()> {
     ui.info(&format!("Tempering new key for {}.", enc_root));
 
-    change_key_with_bytes(zfs, enc_root, &key_material.raw[..])
+    let previous_raw = existing_key.map(|key| &key.raw[..]);
+    let pre_state = rekey_encryption_root(zfs, enc_root, &key_material.raw[..], previous_raw)
         .with_context(|| format!("change-key invocation for {}", enc_root))?;
+    if pre_state == KeyPreState::Sealed {
+        ui.note("Encryption root was sealed; change-key -l loaded the previous key first.");
+    }
 
     zfs.set_property(enc_root, "keylocation", "prompt")
         .with_context(|| format!("restore keylocation=prompt on {}", enc_root))?;
     verify_keyformat_raw(zfs, enc_root)?;
 
-    match zfs.load_key_tree(enc_root, &key_material.raw[..]) {
-        Ok(unlocked) => {
+    // change-key leaves the new key resident on the root; only descendants
+    // still need loading.
+    match zfs
+        .load_descendant_keys(enc_root, &key_material.raw[..])
+        .and_then(|outcome| outcome.strict(enc_root))
+    {
+        Ok(outcome) => {
+            let unlocked = outcome.fully_unlocked;
             let descendants = unlocked.iter().filter(|ds| *ds != enc_root).count();
             if descendants > 0 {
                 ui.success(&format!(
@@ -996,8 +1577,8 @@ fn apply_key_to_encryption_root(
             audit_log(
                 "INIT_ZFS_REKEY",
                 &format!(
-                    "encryption_root={} sha256={} descendants={}",
-                    enc_root, key_material.sha256, descendants
+                    "encryption_root={} digest={} descendants={} pre_state={:?}",
+                    enc_root, key_material.digest, descendants, pre_state
                 ),
             );
             zfs.set_property(enc_root, "keylocation", key_location)
@@ -1005,31 +1586,15 @@ fn apply_key_to_encryption_root(
             Ok(())
         }
         Err(err) => {
-            let err_msg = err.to_string();
-            if err_msg.contains("Key already loaded") {
-                zfs.set_property(enc_root, "keylocation", key_location)
-                    .with_context(|| {
-                        format!("set keylocation to {} on {}", key_location, enc_root)
-                    })?;
-                ui.note("ZFS reports the key was already resident; verification deferred to the self-test.");
-                audit_log(
-                    "INIT_ZFS_REKEY_WARN",
-                    &format!(
-                        "encryption_root={} note={}",
-                        enc_root,
-                        err_msg.replace('\n', " ")
-                    ),
-                );
-                return Ok(());
-            }
-
             ui.error(&format!(
                 "New key rejected when loading {} ({}).",
-                enc_root, err_msg
+                enc_root, err
             ));
             if let Some(previous) = existing_key {
                 ui.warn("Trying previous key to regain access.");
-                if let Err(revert_err) = change_key_with_bytes(zfs, enc_root, &previous.raw[..]) {
+                if let Err(revert_err) =
+                    rekey_encryption_root(zfs, enc_root, &previous.raw[..], None)
+                {
                     ui.error(&format!(
                         "Unable to revert encryption root {} ({}). Manual recovery required.",
                         enc_root, revert_err
@@ -1042,7 +1607,10 @@ fn apply_key_to_encryption_root(
                             enc_root, check_err
                         ));
                     }
-                    if let Err(load_err) = zfs.load_key_tree(enc_root, &previous.raw[..]) {
+                    if let Err(load_err) = zfs
+                        .load_descendant_keys(enc_root, &previous.raw[..])
+                        .and_then(|outcome| outcome.strict(enc_root))
+                    {
                         ui.warn(&format!(
                             "Reverted key could not be loaded automatically ({}).",
                             load_err
@@ -1059,15 +1627,78 @@ fn apply_key_to_encryption_root(
     }
 }
 
-fn change_key_with_bytes(zfs: &Zfs, dataset: &str, key_bytes: &[u8]) -> Result<()> {
-    let mut temp = NamedTempFile::new().context("create temporary key material file")?;
-    temp.write_all(key_bytes)
-        .context("write key material to temporary file")?;
-    temp.as_file().sync_all().ok();
-    fs::set_permissions(temp.path(), Permissions::from_mode(0o600))
-        .context("set temporary key permissions")?;
-    zfs.change_key_from_file(dataset, temp.path())?;
-    Ok(())
+/// Keystatus of the encryption root observed before change-key runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyPreState {
+    Loaded,
+    Sealed,
+}
+
+/// The zfs key operations the rekey sequence relies on (mocked in tests).
+trait RekeyOps {
+    fn is_unlocked(&self, dataset: &str) -> Result<bool>;
+    fn set_property(&self, dataset: &str, property: &str, value: &str) -> Result<()>;
+    fn change_key(&self, dataset: &str, new_key: &[u8], load_with: Option<&[u8]>) -> Result<()>;
+}
+
+impl RekeyOps for Zfs {
+    fn is_unlocked(&self, dataset: &str) -> Result<bool> {
+        Zfs::is_unlocked(self, dataset)
+    }
+
+    fn set_property(&self, dataset: &str, property: &str, value: &str) -> Result<()> {
+        Zfs::set_property(self, dataset, property, value)
+    }
+
+    fn change_key(&self, dataset: &str, new_key: &[u8], load_with: Option<&[u8]>) -> Result<()> {
+        let staged = TransientKeyFile::new(new_key)?;
+        self.change_key_from_file(dataset, staged.path(), load_with)?;
+        Ok(())
+    }
+}
+
+/// Swap the wrapping key on `enc_root`. A loaded root takes a plain
+/// change-key; a sealed one needs `current_key` for `change-key -l` (via
+/// keylocation=prompt). Either way ZFS must report the root unlocked after.
+fn rekey_encryption_root(
+    ops: &impl RekeyOps,
+    enc_root: &str,
+    new_key: &[u8],
+    current_key: Option<&[u8]>,
+) -> Result<KeyPreState> {
+    let pre_state = if ops
+        .is_unlocked(enc_root)
+        .with_context(|| format!("check keystatus for {}", enc_root))?
+    {
+        KeyPreState::Loaded
+    } else {
+        KeyPreState::Sealed
+    };
+
+    match pre_state {
+        KeyPreState::Loaded => ops.change_key(enc_root, new_key, None)?,
+        KeyPreState::Sealed => {
+            let current = current_key.ok_or_else(|| {
+                anyhow!(
+                    "Encryption root {} is sealed and no previous key is on hand to load it",
+                    enc_root
+                )
+            })?;
+            ops.set_property(enc_root, "keylocation", "prompt")?;
+            ops.change_key(enc_root, new_key, Some(current))?;
+        }
+    }
+
+    if !ops
+        .is_unlocked(enc_root)
+        .with_context(|| format!("check keystatus for {}", enc_root))?
+    {
+        return Err(anyhow!(
+            "Encryption root {} reports a sealed keystatus after change-key",
+            enc_root
+        ));
+    }
+    Ok(pre_state)
 }
 
 fn verify_keyformat_raw(zfs: &Zfs, dataset: &str) -> Result<()> {
@@ -1158,7 +1789,7 @@ fn ensure_runtime_mount(
                     settle_udev(ui)?;
                     continue;
                 } else {
-                    return Err(anyhow!("initialization aborted by operator"));
+                    return Err(OperatorDeclined.into());
                 }
             }
         }
@@ -1181,115 +1812,97 @@ fn mount_partition(partition: &str, mountpoint: &Path) -> Result<()> {
     let mount_str = mountpoint
         .to_str()
         .ok_or_else(|| anyhow!("invalid mount path"))?;
-    let out = run_external(
-        MOUNT_BINARIES,
-        &[partition, mount_str],
-        Duration::from_secs(10),
-    )?;
-    if out.status != 0 {
-        return Err(anyhow!(
-            "Failed to mount {} at {}: {}",
-            partition,
-            mountpoint.display(),
-            out.stderr.trim()
-        ));
-    }
+    run_external_checked(MOUNT_BINARIES, &[partition, mount_str], MOUNT_TIMEOUT)?;
     Ok(())
 }
 
+/// `token::unmount_partition`, flagging the udev work it queues.
 fn unmount_partition(mountpoint: &Path) -> Result<()> {
-    let mount_str = mountpoint
-        .to_str()
-        .ok_or_else(|| anyhow!("invalid mount path"))?;
-    let out = run_external(UMOUNT_BINARIES, &[mount_str], Duration::from_secs(10))?;
-    if out.status != 0 {
-        return Err(anyhow!(
-            "Failed to unmount {}: {}",
-            mountpoint.display(),
-            out.stderr.trim()
-        ));
-    }
-    Ok(())
+    note_device_change(UMOUNT_BINARIES);
+    token::unmount_partition(mountpoint)
 }
 
-fn detect_partition_uuid(partition: &str) -> Result<String> {
-    let out = run_external(
+pub(crate) fn detect_partition_uuid(partition: &str) -> Result<String> {
+    let out = run_external_checked(
         BLKID_BINARIES,
         &["-s", "UUID", "-o", "value", partition],
-        Duration::from_secs(5),
+        PROBE_TIMEOUT,
     )?;
-    if out.status != 0 {
-        return Err(anyhow!("blkid failed for {}", partition));
-    }
     Ok(out.stdout.trim().to_string())
 }
 
-pub(crate) fn select_usb_device(ui: &UX, confirm_each_phase: bool) -> Result<String> {
+/// GPT partition UUID; unlike the filesystem UUID it exists without a filesystem.
+fn detect_partition_partuuid(partition: &str) -> Result<String> {
+    let out = run_external_checked(
+        BLKID_BINARIES,
+        &["-s", "PARTUUID", "-o", "value", partition],
+        PROBE_TIMEOUT,
+    )?;
+    let partuuid = out.stdout.trim();
+    if partuuid.is_empty() {
+        return Err(anyhow!("{} reports no PARTUUID", partition));
+    }
+    Ok(partuuid.to_string())
+}
+
+/// Pick the root dataset when several candidates claim `/` (boot environments).
+fn choose_root_candidate(ui: &UX, mut candidates: Vec<String>) -> Result<Option<String>> {
+    if candidates.len() <= 1 {
+        return Ok(candidates.pop());
+    }
+    ui.warn(&format!(
+        "{} datasets claim the root mount; choose the one to guard.",
+        candidates.len()
+    ));
+    require_terminal("root dataset selection; pass --dataset")?;
+    let choice = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Root dataset")
+        .items(&candidates)
+        .default(0)
+        .interact()
+        .context("select root dataset")?;
+    Ok(Some(candidates.swap_remove(choice)))
+}
+
+/// Resolve the existing Beskar token partition without prompting when exactly
+/// one device carries `label`; otherwise defer to the picker.
+pub(crate) fn select_existing_token(ui: &UX, label: &str) -> Result<String> {
+    match find_labeled_tokens(label) {
+        Ok(tokens) if tokens.len() == 1 => {
+            ui.info(&format!("Existing Beskar token found at {}.", tokens[0]));
+            return Ok(tokens[0].clone());
+        }
+        Ok(tokens) if tokens.len() > 1 => {
+            ui.warn(&format!(
+                "Multiple {} tokens attached ({}); choose one.",
+                label,
+                tokens.join(", ")
+            ));
+        }
+        Ok(_) => ui.note(&format!(
+            "No {} label detected; falling back to manual selection.",
+            label
+        )),
+        Err(err) => ui.warn(&format!("Label scan unavailable ({}).", err)),
+    }
+    select_usb_device(ui, false, label)
+}
+
+pub(crate) fn select_usb_device(
+    ui: &UX,
+    confirm_each_phase: bool,
+    token_label: &str,
+) -> Result<String> {
     begin_phase(
         ui,
         "Target Selection // Choose Beskar Ingot",
         confirm_each_phase,
     )?;
+    require_terminal("USB device selection; pass --usb-device")?;
     let theme = ColorfulTheme::default();
 
+    let (mut scanned, mut detected_beskar) = scan_removable(token_label)?;
     let (disks, beskar_index) = loop {
-        let out = run_external(
-            LSBLK_BINARIES,
-            &["-P", "-nrpo", "NAME,TYPE,RM,SIZE,MODEL,LABEL"],
-            Duration::from_secs(5),
-        )?;
-
-        let mut scanned = Vec::new();
-        let mut detected_beskar: Option<usize> = None;
-
-        for line in out.stdout.lines() {
-            let pairs = parse_lsblk_pairs(line);
-            let kind = pairs.get("TYPE").cloned().unwrap_or_default();
-            let removable = pairs.get("RM").map(String::as_str) == Some("1");
-            let name = pairs.get("NAME").cloned().unwrap_or_default();
-            let label = pairs.get("LABEL").cloned().unwrap_or_default();
-
-            if kind == "disk" && removable {
-                let size = pairs
-                    .get("SIZE")
-                    .cloned()
-                    .unwrap_or_else(|| "?".to_string());
-                let model = pairs
-                    .get("MODEL")
-                    .cloned()
-                    .unwrap_or_else(|| "Unknown".to_string());
-                if label.eq_ignore_ascii_case(BESKAR_LABEL) {
-                    detected_beskar = Some(scanned.len());
-                }
-                let desc = format!(
-                    "{}  [{}]  {}{}",
-                    name,
-                    size,
-                    model,
-                    if label.is_empty() {
-                        String::new()
-                    } else {
-                        format!("  (label: {})", label)
-                    }
-                );
-                scanned.push((format!("/dev/{}", name), desc));
-            } else if kind == "part" && removable {
-                let desc = format!(
-                    "{} (partition){}",
-                    name,
-                    if label.is_empty() {
-                        String::new()
-                    } else {
-                        format!(" label={}", label)
-                    }
-                );
-                if label.eq_ignore_ascii_case(BESKAR_LABEL) {
-                    detected_beskar = Some(scanned.len());
-                }
-                scanned.push((format!("/dev/{}", name), desc));
-            }
-        }
-
         if scanned.is_empty() {
             if confirm_each_phase {
                 ui.warn("No removable block devices answered the call.");
@@ -1306,7 +1919,7 @@ pub(crate) fn select_usb_device(ui: &UX, confirm_each_phase: bool) -> Result<Str
                     .unwrap_or(choices.len() - 1);
                 match selection {
                     0 => {
-                        settle_udev(ui)?;
+                        (scanned, detected_beskar) = await_removable(ui, token_label)?;
                         continue;
                     }
                     1 => {
@@ -1318,6 +1931,7 @@ pub(crate) fn select_usb_device(ui: &UX, confirm_each_phase: bool) -> Result<Str
                         let trimmed = manual.trim();
                         if trimmed.is_empty() {
                             ui.warn("Empty device path received — restarting the scan.");
+                            (scanned, detected_beskar) = scan_removable(token_label)?;
                             continue;
                         }
                         ui.note(&format!(
@@ -1330,7 +1944,7 @@ pub(crate) fn select_usb_device(ui: &UX, confirm_each_phase: bool) -> Result<Str
                         ui.warn(
                             "Operator withdrew from the forge during safe-mode device selection.",
                         );
-                        return Err(anyhow!("initialization aborted by operator"));
+                        return Err(OperatorDeclined.into());
                     }
                 }
             } else {
@@ -1374,6 +1988,100 @@ pub(crate) fn select_usb_device(ui: &UX, confirm_each_phase: bool) -> Result<Str
     }
 }
 
+/// `(path, description)` per removable device, plus the index of the one
+/// already carrying the token label.
+type RemovableScan = (Vec<(String, String)>, Option<usize>);
+
+/// One lsblk pass over removable disks and partitions.
+fn scan_removable(token_label: &str) -> Result<RemovableScan> {
+    let out = run_external(
+        LSBLK_BINARIES,
+        &["-P", "-nrpo", "NAME,TYPE,RM,SIZE,MODEL,LABEL"],
+        PROBE_TIMEOUT,
+    )?;
+
+    let mut scanned = Vec::new();
+    let mut detected_beskar: Option<usize> = None;
+
+    for line in out.stdout.lines() {
+        let pairs = parse_lsblk_pairs(line);
+        let kind = pairs.get("TYPE").cloned().unwrap_or_default();
+        let removable = pairs.get("RM").map(String::as_str) == Some("1");
+        let name = pairs.get("NAME").cloned().unwrap_or_default();
+        let label = pairs.get("LABEL").cloned().unwrap_or_default();
+
+        if kind == "disk" && removable {
+            let size = pairs
+                .get("SIZE")
+                .cloned()
+                .unwrap_or_else(|| "?".to_string());
+            let model = pairs
+                .get("MODEL")
+                .cloned()
+                .unwrap_or_else(|| "Unknown".to_string());
+            if label.eq_ignore_ascii_case(token_label) {
+                detected_beskar = Some(scanned.len());
+            }
+            let desc = format!(
+                "{}  [{}]  {}{}",
+                name,
+                size,
+                model,
+                if label.is_empty() {
+                    String::new()
+                } else {
+                    format!("  (label: {})", label)
+                }
+            );
+            scanned.push((format!("/dev/{}", name), desc));
+        } else if kind == "part" && removable {
+            let desc = format!(
+                "{} (partition){}",
+                name,
+                if label.is_empty() {
+                    String::new()
+                } else {
+                    format!(" label={}", label)
+                }
+            );
+            if label.eq_ignore_ascii_case(token_label) {
+                detected_beskar = Some(scanned.len());
+            }
+            scanned.push((format!("/dev/{}", name), desc));
+        }
+    }
+    Ok((scanned, detected_beskar))
+}
+
+/// Poll lsblk every `DEVICE_POLL_INTERVAL` until a removable device shows up
+/// or the `BESKAR_DEVICE_WAIT_SECS` window closes, with a countdown.
+fn await_removable(ui: &UX, token_label: &str) -> Result<RemovableScan> {
+    let window = Duration::from_secs(
+        std::env::var(DEVICE_WAIT_ENV)
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_DEVICE_WAIT_SECS),
+    );
+    settle_udev(ui)?;
+    let spinner = ui.spinner("Awaiting the USB tribute");
+    let started = Instant::now();
+    loop {
+        let found = scan_removable(token_label)?;
+        if !found.0.is_empty() {
+            return Ok(found);
+        }
+        let elapsed = started.elapsed();
+        if elapsed >= window {
+            return Ok(found);
+        }
+        spinner.trace(&format!(
+            "{}s left",
+            window.saturating_sub(elapsed).as_secs_f32().ceil() as u64
+        ));
+        std::thread::sleep(DEVICE_POLL_INTERVAL);
+    }
+}
+
 fn parse_lsblk_pairs(line: &str) -> HashMap<String, String> {
     let mut map = HashMap::new();
     let chars: Vec<char> = line.chars().collect();
@@ -1428,7 +2136,7 @@ pub(crate) fn dismantle_mounts(node: &str, ui: &UX) -> Result<()> {
     let out = run_external(
         LSBLK_BINARIES,
         &["-P", "-nrpo", "NAME,MOUNTPOINT", node],
-        Duration::from_secs(5),
+        PROBE_TIMEOUT,
     )?;
 
     for line in out.stdout.lines() {
@@ -1442,7 +2150,7 @@ pub(crate) fn dismantle_mounts(node: &str, ui: &UX) -> Result<()> {
             "Disengaging mount {} at {} — the path must be clear.",
             target, mount
         ));
-        match run_external(UMOUNT_BINARIES, &[mount.as_str()], Duration::from_secs(10)) {
+        match run_external(UMOUNT_BINARIES, &[mount.as_str()], MOUNT_TIMEOUT) {
             Ok(res) if res.status == 0 => {
                 settle_udev(ui)?;
             }
@@ -1453,7 +2161,7 @@ pub(crate) fn dismantle_mounts(node: &str, ui: &UX) -> Result<()> {
     }
 
     // also ensure the block node itself is not mounted
-    if let Err(err) = run_external(UMOUNT_BINARIES, &[node], Duration::from_secs(10)) {
+    if let Err(err) = run_external(UMOUNT_BINARIES, &[node], MOUNT_TIMEOUT) {
         ui.warn(&format!(
             "Direct unmount of {} resisted release ({}).",
             node, err
@@ -1477,27 +2185,46 @@ fn force_unmount(target: &str, ui: &UX) -> Result<()> {
         vec!["-f".to_string(), "-l".to_string(), target.to_string()],
     ];
 
-    for args in attempts {
+    let budget = retry_budget();
+    let started = Instant::now();
+
+    'strategies: for args in attempts {
         let refs: Vec<&str> = args.iter().map(String::as_str).collect();
         for backoff in 0..3 {
-            if let Ok(out) = run_external(UMOUNT_BINARIES, &refs, Duration::from_secs(10)) {
+            if started.elapsed() >= budget {
+                break 'strategies;
+            }
+            if let Ok(out) = run_external(UMOUNT_BINARIES, &refs, MOUNT_TIMEOUT) {
                 if out.status == 0 {
                     settle_udev(ui)?;
                     return Ok(());
                 }
             }
-            std::thread::sleep(Duration::from_millis(150 * (backoff as u64 + 1)));
+            std::thread::sleep(jittered_backoff(150, backoff));
         }
     }
 
-    Err(anyhow!("unable to unmount {} (resource busy)", target))
+    let holders = mount_holders(target);
+    if holders.is_empty() {
+        return Err(anyhow!(
+            "unable to unmount {} within {}s (resource busy)",
+            target,
+            budget.as_secs()
+        ));
+    }
+    Err(anyhow!(
+        "unable to unmount {} within {}s; still held by PID(s) {}. Stop those processes and retry.",
+        target,
+        budget.as_secs(),
+        describe_holders(&holders)
+    ))
 }
 
 fn device_has_mounts(node: &str) -> Result<bool> {
     let out = run_external(
         LSBLK_BINARIES,
         &["-P", "-nrpo", "NAME,MOUNTPOINT", node],
-        Duration::from_secs(5),
+        PROBE_TIMEOUT,
     )?;
 
     if out.status != 0 {
@@ -1518,6 +2245,7 @@ fn device_has_mounts(node: &str) -> Result<bool> {
 pub(crate) fn install_initramfs_tools_scripts(
     key_mount_path: &Path,
     key_path: &Path,
+    usb: &Usb,
     ui: &UX,
 ) -> Result<()> {
     let hook_path = Path::new(INITRAMFS_HOOK_PATH);
@@ -1545,6 +2273,9 @@ fi
 if command -v mountpoint >/dev/null 2>&1; then
     copy_exec "$(command -v mountpoint)"
 fi
+if command -v udevadm >/dev/null 2>&1; then
+    copy_exec "$(command -v udevadm)"
+fi
 "#
     .to_string();
 
@@ -1585,16 +2316,31 @@ case "$1" in
         ;;
 esac
 
-TOKEN_LABEL="{label}"
+export BESKAR_INITRAMFS=1
+
+TOKEN_LABELS="{labels}"
 MOUNTPOINT="{mountpoint}"
 KEY_PATH="{key_path}"
+REQUIRE_SERIAL="{require_serial}"
+REQUIRE_VIDPID="{require_vidpid}"
 MAX_WAIT=30
 SLEEP_INTERVAL=1
 
+# First label in TOKEN_LABELS (priority order) that blkid can resolve.
+present_label() {{
+    for label in $TOKEN_LABELS; do
+        if blkid -L "$label" >/dev/null 2>&1; then
+            echo "$label"
+            return 0
+        fi
+    done
+    return 1
+}}
+
 wait_for_device() {{
     local elapsed=0
     while [ "$elapsed" -lt "$MAX_WAIT" ]; do
-        if blkid -L "$TOKEN_LABEL" >/dev/null 2>&1; then
+        if present_label >/dev/null; then
             return 0
         fi
         sleep "$SLEEP_INTERVAL"
@@ -1616,16 +2362,47 @@ wait_for_key() {{
 }}
 
 if ! wait_for_device; then
-    echo "beskar: token $TOKEN_LABEL not detected within ${{MAX_WAIT}}s; deferring to native prompts." >&2
+    echo "beskar: no token labelled $TOKEN_LABELS detected within ${{MAX_WAIT}}s; deferring to native prompts." >&2
     exit 0
 fi
 
 mkdir -p "$MOUNTPOINT"
-DEVICE="$(blkid -L "$TOKEN_LABEL" 2>/dev/null || true)"
+LABEL="$(present_label || true)"
+DEVICE=""
+if [ -n "$LABEL" ]; then
+    DEVICE="$(blkid -L "$LABEL" 2>/dev/null || true)"
+fi
 if [ -z "$DEVICE" ]; then
     echo "beskar: token not detected; skipping auto-unlock" >&2
     exit 0
 fi
+echo "beskar: token label $LABEL satisfied the search ($DEVICE)." >&2
+
+# Refuse a look-alike token: the pinned USB serial / vendor:product must match.
+if [ -n "$REQUIRE_SERIAL" ] || [ -n "$REQUIRE_VIDPID" ]; then
+    SERIAL=""
+    VENDOR=""
+    MODEL=""
+    # Quoted and read line by line, so property values never word-split or glob.
+    PROPS="$(udevadm info --query=property --name="$DEVICE" 2>/dev/null || true)"
+    while IFS= read -r line; do
+        case "$line" in
+            ID_SERIAL_SHORT=*) SERIAL="${{line#ID_SERIAL_SHORT=}}" ;;
+            ID_VENDOR_ID=*) VENDOR="${{line#ID_VENDOR_ID=}}" ;;
+            ID_MODEL_ID=*) MODEL="${{line#ID_MODEL_ID=}}" ;;
+        esac
+    done <<EOF_PROPS
+$PROPS
+EOF_PROPS
+    if [ -n "$REQUIRE_SERIAL" ] && [ "$SERIAL" != "$REQUIRE_SERIAL" ]; then
+        echo "beskar: token $DEVICE serial '$SERIAL' does not match the pinned serial; refusing its key." >&2
+        exit 0
+    fi
+    if [ -n "$REQUIRE_VIDPID" ] && [ "$VENDOR:$MODEL" != "$REQUIRE_VIDPID" ]; then
+        echo "beskar: token $DEVICE id '$VENDOR:$MODEL' does not match pinned $REQUIRE_VIDPID; refusing its key." >&2
+        exit 0
+    fi
+fi
 
 if mountpoint -q "$MOUNTPOINT"; then
     echo "beskar: token already mounted at $MOUNTPOINT" >&2
@@ -1640,9 +2417,16 @@ elif ! zfs load-key -a; then
     echo "beskar: zfs load-key -a failed; fallback to native prompts." >&2
 fi
 "#,
-        label = BESKAR_LABEL,
+        labels = usb.token_labels().join(" "),
         mountpoint = mountpoint,
-        key_path = key_path_str
+        key_path = key_path_str,
+        require_serial = usb.require_serial.as_deref().unwrap_or_default(),
+        // udev reports ids in lowercase hex.
+        require_vidpid = usb
+            .require_vidpid
+            .as_deref()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
     );
 
     let mut local_top_file = File::create(local_top_path)
@@ -1675,15 +2459,12 @@ pub(crate) fn rebuild_initramfs(ui: &UX, flavor: &InitramfsFlavor) -> Result<()>
                 "Calling dracut via {} to refresh the initramfs image…",
                 dracut_path
             ));
-            let cmd = Cmd::new_allowlisted(*dracut_path, Duration::from_secs(180))?;
-            let out = cmd.run(&["-f", "--add", "zfs-beskar"], None)?;
-            if out.status != 0 {
-                return Err(anyhow!(
-                    "dracut exited with status {}: {}",
-                    out.status,
-                    out.stderr.trim()
-                ));
-            }
+            let cmd = Cmd::new_allowlisted(*dracut_path, INITRAMFS_REBUILD_TIMEOUT)?;
+            let spinner = ui.spinner("dracut forging initramfs image");
+            let args = ["-f", "--add", "zfs-beskar"];
+            let out = cmd.run_streaming(&args, |line| relay_progress(ui, &spinner, line));
+            spinner.finish();
+            cmd.ensure_success(&args, out?)?;
         }
         InitramfsFlavor::InitramfsTools => {
             let update_initramfs = "/usr/sbin/update-initramfs";
@@ -1694,29 +2475,224 @@ pub(crate) fn rebuild_initramfs(ui: &UX, flavor: &InitramfsFlavor) -> Result<()>
                 ));
             }
             ui.info("Calling update-initramfs -u to refresh the initramfs image…");
-            let cmd = Cmd::new_allowlisted(update_initramfs, Duration::from_secs(180))?;
-            let out = cmd.run(&["-u"], None)?;
-            if out.status != 0 {
-                return Err(anyhow!(
-                    "update-initramfs exited with status {}: {}",
-                    out.status,
-                    out.stderr.trim()
-                ));
-            }
+            let cmd = Cmd::new_allowlisted(update_initramfs, INITRAMFS_REBUILD_TIMEOUT)?;
+            let spinner = ui.spinner("update-initramfs forging image");
+            let out = cmd.run_streaming(&["-u"], |line| relay_progress(ui, &spinner, line));
+            spinner.finish();
+            cmd.ensure_success(&["-u"], out?)?;
         }
     }
     Ok(())
 }
 
+/// Route a line of rebuild output to the spinner, or to the trace log when no
+/// spinner is drawing (not a terminal, JSON mode) so progress is not lost.
+fn relay_progress(ui: &UX, spinner: &SpinnerHandle, line: &str) {
+    if spinner.is_live() {
+        spinner.trace(line);
+    } else if !line.trim().is_empty() {
+        ui.trace(line);
+    }
+}
+
+/// Flag pending udev work for `settle_udev` when `candidates` is a tool that
+/// repartitions, formats, or (un)mounts.
+fn note_device_change(candidates: &[&str]) {
+    if DEVICE_CHANGING_BINARIES.contains(&candidates) {
+        DEVICES_CHANGED.store(true, Ordering::SeqCst);
+    }
+}
+
 fn run_external(candidates: &[&str], args: &[&str], timeout: Duration) -> Result<OutputData> {
-    for &path in candidates {
-        if Path::new(path).exists() {
-            let cmd = Cmd::new_allowlisted(path, timeout)?;
-            return cmd.run(args, None);
+    note_device_change(candidates);
+    let cmd = external_cmd(candidates)?;
+    let out = cmd.run_with_timeout(args, None, timeout)?;
+    if let Termination::Signaled(_) = out.termination {
+        return Err(anyhow!(
+            "{} {}: {}",
+            cmd.path,
+            out.termination,
+            out.stderr.trim()
+        ));
+    }
+    Ok(out)
+}
+
+/// `run_external`, but a non-zero exit becomes a uniform error carrying the
+/// tool's stderr.
+fn run_external_checked(
+    candidates: &[&str],
+    args: &[&str],
+    timeout: Duration,
+) -> Result<OutputData> {
+    note_device_change(candidates);
+    let cmd = external_cmd(candidates)?;
+    let out = cmd.run_with_timeout(args, None, timeout)?;
+    cmd.ensure_success(args, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        generate_key_material, key_file_name, rekey_encryption_root, render_artifacts,
+        settle_needed, write_recovery_file, ArtifactSummary, KeyPreState, RekeyOps,
+        SETTLE_COALESCE_WINDOW,
+    };
+    use crate::util::checksum::ChecksumAlgo;
+    use crate::util::keyfile::read_key_material;
+    use crate::util::recovery::{decode_recovery_code, encode_recovery_code};
+    use crate::zfs::KEY_LEN;
+    use anyhow::{anyhow, Result};
+    use std::cell::{Cell, RefCell};
+    use std::time::Instant;
+
+    struct MockZfs {
+        unlocked: Cell<bool>,
+        calls: RefCell<Vec<String>>,
+    }
+
+    impl MockZfs {
+        fn new(unlocked: bool) -> Self {
+            Self {
+                unlocked: Cell::new(unlocked),
+                calls: RefCell::new(Vec::new()),
+            }
         }
     }
-    Err(anyhow!(
-        "None of the candidate binaries {:?} were found on this system",
-        candidates
-    ))
+
+    impl RekeyOps for MockZfs {
+        fn is_unlocked(&self, _dataset: &str) -> Result<bool> {
+            Ok(self.unlocked.get())
+        }
+
+        fn set_property(&self, dataset: &str, property: &str, value: &str) -> Result<()> {
+            self.calls
+                .borrow_mut()
+                .push(format!("set {}={} {}", property, value, dataset));
+            Ok(())
+        }
+
+        fn change_key(
+            &self,
+            dataset: &str,
+            _new_key: &[u8],
+            load_with: Option<&[u8]>,
+        ) -> Result<()> {
+            let flag = if load_with.is_some() { " -l" } else { "" };
+            self.calls
+                .borrow_mut()
+                .push(format!("change-key{} {}", flag, dataset));
+            if !self.unlocked.get() && load_with.is_none() {
+                return Err(anyhow!("Key change error: Key must be loaded."));
+            }
+            // Mirrors ZFS: the new wrapping key stays loaded after change-key.
+            self.unlocked.set(true);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn key_generation_and_load_paths_agree_on_length() {
+        let forged = generate_key_material(ChecksumAlgo::Sha256).unwrap();
+        assert_eq!(forged.raw.len(), KEY_LEN);
+
+        let dir = tempfile::tempdir().unwrap();
+        let raw_path = dir.path().join("raw.key");
+        std::fs::write(&raw_path, &forged.raw[..]).unwrap();
+        assert_eq!(read_key_material(&raw_path).unwrap().raw, forged.raw);
+        let hex_path = dir.path().join("legacy.keyhex");
+        std::fs::write(&hex_path, hex::encode(&forged.raw[..])).unwrap();
+        assert_eq!(read_key_material(&hex_path).unwrap().raw, forged.raw);
+
+        let recovered = decode_recovery_code(&encode_recovery_code(&forged.raw)).unwrap();
+        assert_eq!(recovered, forged.raw);
+
+        std::fs::write(&raw_path, &forged.raw[..KEY_LEN - 1]).unwrap();
+        assert!(read_key_material(&raw_path).is_err());
+        assert!(decode_recovery_code(&encode_recovery_code(&forged.raw[..KEY_LEN - 1])).is_err());
+    }
+
+    #[test]
+    fn rekey_models_loaded_and_sealed_pre_states() {
+        let loaded = MockZfs::new(true);
+        let state = rekey_encryption_root(&loaded, "rpool", &[1; 32], Some(&[2; 32])).unwrap();
+        assert_eq!(state, KeyPreState::Loaded);
+        assert_eq!(*loaded.calls.borrow(), vec!["change-key rpool"]);
+
+        let sealed = MockZfs::new(false);
+        let state = rekey_encryption_root(&sealed, "rpool", &[1; 32], Some(&[2; 32])).unwrap();
+        assert_eq!(state, KeyPreState::Sealed);
+        assert_eq!(
+            *sealed.calls.borrow(),
+            vec!["set keylocation=prompt rpool", "change-key -l rpool"]
+        );
+        assert!(sealed.unlocked.get());
+
+        let orphan = MockZfs::new(false);
+        assert!(rekey_encryption_root(&orphan, "rpool", &[1; 32], None).is_err());
+        assert!(orphan.calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn key_file_names_do_not_collide_after_sanitizing() {
+        let slash = key_file_name("rpool/ROOT", None).unwrap();
+        let underscore = key_file_name("rpool_ROOT", None).unwrap();
+        assert!(slash.starts_with("rpool_ROOT-") && slash.ends_with(".keyhex"));
+        assert_ne!(slash, underscore);
+        assert_ne!(
+            key_file_name("tank/a-b", None).unwrap(),
+            key_file_name("tank/a_b", None).unwrap()
+        );
+
+        assert_eq!(
+            key_file_name("tank/vault", Some("{name}.bin")).unwrap(),
+            "tank_vault.bin"
+        );
+        assert!(key_file_name("tank/vault", Some("../{name}")).is_err());
+    }
+
+    #[test]
+    fn back_to_back_settles_coalesce_unless_devices_changed() {
+        let just_now = Some(Instant::now());
+        assert!(settle_needed(true, None));
+        assert!(settle_needed(false, None));
+        assert!(settle_needed(true, just_now));
+        assert!(!settle_needed(false, just_now));
+        let stale = Instant::now().checked_sub(SETTLE_COALESCE_WINDOW);
+        assert!(settle_needed(false, stale));
+    }
+
+    #[test]
+    fn recovery_file_is_root_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sigil.txt");
+        write_recovery_file(&path, "ABCD-EFGH").unwrap();
+        let meta = std::fs::metadata(&path).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ABCD-EFGH\n");
+    }
+
+    #[test]
+    fn artifacts_carry_a_recovery_code_that_decodes_to_the_key() {
+        let forged = generate_key_material(ChecksumAlgo::Sha256).unwrap();
+        let code = encode_recovery_code(&forged.raw);
+        let rendered = render_artifacts(
+            &ArtifactSummary {
+                dataset: "rpool/ROOT/ubuntu",
+                enc_root: "rpool/ROOT",
+                usb_uuid: "1234-ABCD",
+                algo: ChecksumAlgo::Sha256,
+                digest: &forged.digest,
+                recovery_code: &code,
+            },
+            "2026-01-01T00:00:00+00:00",
+        );
+        assert!(rendered.starts_with("# SENSITIVE"));
+        let line = rendered
+            .lines()
+            .find_map(|l| l.strip_prefix("recovery_code = "))
+            .unwrap();
+        assert_eq!(&decode_recovery_code(line).unwrap()[..], &forged.raw[..]);
+    }
 }
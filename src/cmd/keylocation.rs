@@ -0,0 +1,145 @@
+// ============================================================================
+// src/cmd/keylocation.rs – Offline keylocation retarget (no forge, no doctor)
+// ============================================================================
+
+use crate::config::{persist_config, ConfigFile};
+use crate::ui::{Pace, Timing, UX};
+use crate::util::audit::audit_log;
+use crate::zfs::{not_encrypted, Zfs};
+use anyhow::{anyhow, Context, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// Point the encryption root's `keylocation` at `location` (`prompt` or a
+/// `file://` URI) and keep `usb.key_hex_path` in step for file URIs.
+pub fn run_set_keylocation(
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    dataset: &str,
+    location: &str,
+) -> Result<()> {
+    ui.banner();
+    ui.phase("Keylocation // Retarget");
+
+    let key_file = parse_keylocation(location)?;
+
+    let zfs = Zfs::from_config(cfg)?;
+    if !zfs
+        .is_encrypted(dataset)
+        .with_context(|| format!("verify encryption status of {}", dataset))?
+    {
+        return Err(not_encrypted(dataset));
+    }
+    let enc_root = zfs
+        .encryption_root(dataset)
+        .with_context(|| format!("resolve encryption root for {}", dataset))?
+        .ok_or_else(|| not_encrypted(dataset))?;
+    if enc_root != dataset {
+        ui.info(&format!("{} inherits {}.", dataset, enc_root));
+    }
+
+    let previous = zfs
+        .get_property(&enc_root, "keylocation")
+        .unwrap_or_else(|_| "unknown".to_string());
+    zfs.set_property(&enc_root, "keylocation", location)?;
+    audit_log(
+        "KEYLOCATION_SET",
+        &format!("{} keylocation {} -> {}", enc_root, previous, location),
+    );
+    ui.success(&format!("{} keylocation now {}.", enc_root, location));
+
+    if let Some(path) = key_file {
+        if !path.exists() {
+            ui.warn(&format!(
+                "{} is not present right now; mount the token there before the next unlock.",
+                path.display()
+            ));
+        }
+        let updated = retarget_config(cfg, &path)?;
+        ui.success(&format!(
+            "Creed at {} now expects the key at {}.",
+            updated.path.display(),
+            path.display()
+        ));
+    } else {
+        ui.note("Keylocation set to prompt; ZFS will ask for the passphrase directly.");
+    }
+
+    ui.note("Rerun `install-dracut` if the token mountpoint moved so the loader follows.");
+    timing.pace(Pace::Critical);
+    Ok(())
+}
+
+/// Record `key_file` as `usb.key_hex_path` and rewrite the config in its own
+/// format (TOML or YAML).
+fn retarget_config(cfg: &ConfigFile, key_file: &Path) -> Result<ConfigFile> {
+    let mut updated = cfg.clone();
+    updated.usb.key_hex_path = key_file.to_string_lossy().into_owned();
+    persist_config(&updated)?;
+    Ok(updated)
+}
+
+/// Accept `prompt` or an absolute, non-traversing `file://` URI; returns the
+/// key file path for the latter.
+fn parse_keylocation(location: &str) -> Result<Option<PathBuf>> {
+    if location == "prompt" {
+        return Ok(None);
+    }
+    let raw = location.strip_prefix("file://").ok_or_else(|| {
+        anyhow!(
+            "keylocation '{}' must be `prompt` or a file:// URI",
+            location
+        )
+    })?;
+    let path = PathBuf::from(raw);
+    if !path.is_absolute() || path.file_name().is_none() {
+        return Err(anyhow!(
+            "keylocation '{}' must name an absolute key file path",
+            location
+        ));
+    }
+    if Path::new(raw)
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+    {
+        return Err(anyhow!("keylocation '{}' must not contain '..'", location));
+    }
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_keylocation, retarget_config};
+    use crate::config::ConfigFile;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn keylocation_accepts_prompt_and_absolute_file_uris() {
+        assert_eq!(parse_keylocation("prompt").unwrap(), None);
+        assert_eq!(
+            parse_keylocation("file:///run/beskar/rpool.keyhex").unwrap(),
+            Some(PathBuf::from("/run/beskar/rpool.keyhex"))
+        );
+        assert!(parse_keylocation("file://run/beskar/key").is_err());
+        assert!(parse_keylocation("file:///run/beskar/../etc/shadow").is_err());
+        assert!(parse_keylocation("https://example.com/key").is_err());
+        assert!(parse_keylocation("file:///").is_err());
+    }
+
+    #[test]
+    fn retargeting_a_yaml_config_keeps_it_yaml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zfs-beskar.yaml");
+        std::fs::write(
+            &path,
+            "version: 1\npolicy:\n  datasets:\n    - rpool/ROOT\nusb:\n  key_hex_path: /run/beskar/key.hex\n",
+        )
+        .unwrap();
+        let cfg = ConfigFile::load(&path).unwrap();
+        retarget_config(&cfg, Path::new("/run/beskar/rpool.key")).unwrap();
+
+        let reloaded = ConfigFile::load(&path).unwrap();
+        assert_eq!(reloaded.usb.key_hex_path, "/run/beskar/rpool.key");
+        assert_eq!(reloaded.policy.datasets, vec!["rpool/ROOT"]);
+    }
+}
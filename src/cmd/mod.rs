@@ -1,14 +1,24 @@
 // ============================================================================
 // src/cmd/mod.rs – command subsystem root
 // ============================================================================
+pub mod auditlog; // zbk audit-log
 pub mod base; // core shell execution utilities (Cmd, OutputData)
+pub mod benchmark; // zbk benchmark
+pub mod breakglass; // zbk set-break-glass
+pub mod changepin; // zbk change-pin
 pub mod doctor;
 pub mod dracut_install; // standalone dracut installer
+pub mod escrow; // zbk escrow / unescrow
+pub mod fido2; // zbk enroll-fido2
+pub mod fingerprint; // zbk show-fingerprint
 pub mod init; // zbk init // zbk doctor
+pub mod keylocation; // zbk set-keylocation
 pub mod recover; // USB recovery from key
 pub mod repair; // shared repair helpers (units, etc.)
 pub mod simulate; // ephemeral vault simulations
+pub mod status; // zbk status
+pub mod tokens; // zbk list-tokens
 pub mod unlock; // zbk unlock
 
 // Re-export common types for convenience:
-pub use base::{Cmd, OutputData};
+pub use base::{Cmd, OutputData, Termination};
@@ -3,26 +3,136 @@
 // ============================================================================
 
 use crate::cmd::init::{
-    derive_device_layout, dismantle_mounts, sanitize_key_name, select_usb_device, settle_udev,
-    wipe_usb_token, write_key_to_usb,
+    derive_device_layout, dismantle_mounts, select_existing_token, select_usb_device, settle_udev,
+    token_key_file_name, wipe_usb_token, write_key_to_usb,
 };
-use crate::ui::{Pace, Timing, UX};
+use crate::config::{validate_token_label, ConfigFile};
+use crate::ui::{non_interactive, require_terminal, Pace, Timing, UX};
 use crate::util::recovery::decode_recovery_code;
-use anyhow::{Context, Result};
-use dialoguer::Password;
+use crate::util::secret::{read_stdin_secret, secret_bytes_line};
+use anyhow::{anyhow, Context, Result};
+use dialoguer::{Confirm, Password};
+use zeroize::Zeroizing;
 
-pub fn run_recover(ui: &UX, timing: &Timing, dataset: &str) -> Result<()> {
+/// Upper bound on what `--non-interactive` reads from stdin for the sigil.
+const MAX_STDIN_SIGIL: usize = 4096;
+
+pub fn run_recover(
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    dataset: &str,
+    in_place: bool,
+    label: &str,
+    usb_device: Option<&str>,
+) -> Result<()> {
+    validate_token_label(label)?;
     ui.banner();
     ui.phase("Recovery // Tribute Recall");
 
-    let recovery_code = Password::new()
-        .with_prompt("Enter Armorer recovery sigil")
-        .allow_empty_password(false)
-        .interact()
-        .context("read recovery key input")?;
+    let recovery_code = read_recovery_sigil()?;
     let raw_key = decode_recovery_code(&recovery_code)?;
+    confirm_recovered_key(ui, cfg, dataset, &raw_key)?;
+    let key_filename = token_key_file_name(cfg, dataset)?;
+
+    if in_place {
+        rewrite_existing_token(ui, label, usb_device, &key_filename, &raw_key[..])?;
+    } else {
+        etch_recovered_key(ui, label, usb_device, &key_filename, &raw_key[..])?;
+    }
+    if !cfg.usb.token_labels().iter().any(|l| l == label) {
+        ui.warn(&format!(
+            "Label {} is not in usb.labels; add it there, then rerun install-dracut so unlock and boot search for it.",
+            label
+        ));
+    }
+
+    ui.success("Tribute reborn on Beskar token.");
+    ui.success("This is the Way.");
+    timing.pace(Pace::Critical);
+    Ok(())
+}
+
+/// The sigil from the prompt, or under `--non-interactive` the first line of
+/// stdin so automation can pipe it in.
+fn read_recovery_sigil() -> Result<Zeroizing<String>> {
+    if non_interactive() {
+        let mut piped =
+            read_stdin_secret(MAX_STDIN_SIGIL).context("read recovery sigil from stdin")?;
+        let end = piped
+            .iter()
+            .position(|&b| b == b'\n')
+            .unwrap_or(piped.len());
+        // Only the first line is the sigil; wipe whatever followed it.
+        for b in piped[end..].iter_mut() {
+            *b = 0;
+        }
+        piped.truncate(end);
+        let mut first = secret_bytes_line(piped);
+        let line = match String::from_utf8(std::mem::take(&mut *first)) {
+            Ok(text) => Zeroizing::new(text),
+            Err(err) => {
+                drop(Zeroizing::new(err.into_bytes()));
+                return Err(anyhow!("recovery sigil on stdin is not UTF-8"));
+            }
+        };
+        if line.trim().is_empty() {
+            return Err(anyhow!(
+                "--non-interactive recover expects the recovery sigil on stdin"
+            ));
+        }
+        return Ok(line);
+    }
+    require_terminal("the recovery sigil prompt")?;
+    Ok(Zeroizing::new(
+        Password::new()
+            .with_prompt("Enter Armorer recovery sigil")
+            .allow_empty_password(false)
+            .interact()
+            .context("read recovery key input")?,
+    ))
+}
+
+/// Prove the decoded sigil is the key this dataset was forged with before any
+/// USB is wiped; a mismatched key would leave the next boot unable to unlock.
+fn confirm_recovered_key(ui: &UX, cfg: &ConfigFile, dataset: &str, raw_key: &[u8]) -> Result<()> {
+    let on_record = cfg.policy.datasets.iter().any(|d| d == dataset);
+    if on_record && cfg.usb.expected_sha256.is_some() {
+        verify_fingerprint(cfg, raw_key)?;
+        ui.success("Sigil matches the recorded fingerprint.");
+        return Ok(());
+    }
+
+    ui.warn(&format!(
+        "No fingerprint on record for {}; this sigil cannot be proven against the vault.",
+        dataset
+    ));
+    require_terminal("the unverified-key confirmation; record usb.expected_sha256 first")?;
+    let proceed = Confirm::new()
+        .with_prompt("Etch the unverified key anyway?")
+        .default(false)
+        .interact()
+        .context("recovery confirmation failed")?;
+    if !proceed {
+        return Err(anyhow!("recovery aborted: key fingerprint unverified"));
+    }
+    Ok(())
+}
 
-    let device = select_usb_device(ui, false)?;
+/// Wipe `usb_device` (or a freshly selected USB token), stamp it with `label`,
+/// and etch `raw_key` onto it as `key_filename`. Shared by the recovery-sigil
+/// and escrow restore flows.
+pub(crate) fn etch_recovered_key(
+    ui: &UX,
+    label: &str,
+    usb_device: Option<&str>,
+    key_filename: &str,
+    raw_key: &[u8],
+) -> Result<()> {
+    let device = match usb_device {
+        Some(device) => device.to_string(),
+        None => select_usb_device(ui, false, label)?,
+    };
     let (usb_disk, usb_partition) = derive_device_layout(&device)?;
 
     dismantle_mounts(&usb_disk, ui)?;
@@ -32,14 +142,43 @@ pub fn run_recover(ui: &UX, timing: &Timing, dataset: &str) -> Result<()> {
         "Wiping {} and {} before etching.",
         usb_disk, usb_partition
     ));
-    wipe_usb_token(&usb_disk, &usb_partition, ui)?;
+    wipe_usb_token(&usb_disk, &usb_partition, label, ui)?;
     settle_udev(ui)?;
 
-    let key_filename = format!("{}.keyhex", sanitize_key_name(dataset));
-    write_key_to_usb(&usb_partition, &key_filename, true, &raw_key[..], ui)?;
+    write_key_to_usb(&usb_partition, key_filename, true, raw_key, ui)
+}
 
-    ui.success("Tribute reborn on Beskar token.");
-    ui.success("This is the Way.");
-    timing.pace(Pace::Critical);
+/// Rewrite the key file on an attached `label` token (or `usb_device`)
+/// without wiping it.
+pub(crate) fn rewrite_existing_token(
+    ui: &UX,
+    label: &str,
+    usb_device: Option<&str>,
+    key_filename: &str,
+    raw_key: &[u8],
+) -> Result<()> {
+    let device = match usb_device {
+        Some(device) => device.to_string(),
+        None => select_existing_token(ui, label)?,
+    };
+    let (_, usb_partition) = derive_device_layout(&device)?;
+    dismantle_mounts(&usb_partition, ui)?;
+
+    write_key_to_usb(&usb_partition, key_filename, true, raw_key, ui)
+}
+
+/// Reject key material that does not match the configured fingerprint.
+pub(crate) fn verify_fingerprint(cfg: &ConfigFile, raw: &[u8]) -> Result<()> {
+    let algo = cfg.usb.checksum_algo;
+    if let Some(expected) = cfg.usb.expected_sha256.as_deref() {
+        if !algo.matches(raw, expected) {
+            return Err(anyhow!(
+                "Key {} fingerprint {} does not match configured expected_sha256 {}.",
+                algo.label(),
+                algo.digest_hex(raw),
+                expected
+            ));
+        }
+    }
     Ok(())
 }
@@ -5,7 +5,6 @@
 use crate::cmd::Cmd;
 use crate::config::ConfigFile;
 use crate::ui::UX;
-use crate::zfs::Zfs;
 use anyhow::{anyhow, Context, Result};
 use std::fs::{self, File};
 use std::io::Write;
@@ -15,8 +14,101 @@ use std::time::Duration;
 pub const USB_MOUNT_UNIT: &str = "run-beskar.mount";
 pub const USB_UNIT_PATH: &str = "/etc/systemd/system/run-beskar.mount";
 pub const UNLOCK_UNIT_PATH: &str = "/etc/systemd/system/beskar-unlock.service";
+pub const UNLOCK_UNIT: &str = "beskar-unlock.service";
+/// Unchanged lines shown around each hunk of `install-units --check-only`.
+const DIFF_CONTEXT: usize = 3;
+
+/// Units `install_units` manages for this config: a raw token has no
+/// filesystem, so it gets no mount unit.
+pub fn token_units(cfg: &ConfigFile) -> &'static [&'static str] {
+    if cfg.usb.raw_device.is_some() {
+        &[UNLOCK_UNIT]
+    } else {
+        &[USB_MOUNT_UNIT, UNLOCK_UNIT]
+    }
+}
 
 pub fn install_units(ui: &UX, cfg: &ConfigFile, binary_path: &Path) -> Result<()> {
+    let (mount_content, unlock_content) = rendered_units(ui, cfg, binary_path)?;
+    match &mount_content {
+        Some(content) => write_unit(USB_UNIT_PATH, content)?,
+        None => retire_mount_unit(ui)?,
+    }
+    write_unit(UNLOCK_UNIT_PATH, &unlock_content)?;
+
+    ui.info("Reloading systemd daemon and enabling sentry units…");
+    systemctl(Duration::from_secs(5))?.run_checked(&["daemon-reload"], None)?;
+    let mut args = vec!["enable"];
+    args.extend_from_slice(token_units(cfg));
+    systemctl(Duration::from_secs(5))?.run_checked(&args, None)?;
+    Ok(())
+}
+
+/// Disable and delete a mount unit left from before the token went raw; its
+/// by-uuid `Type=ext4` mount can never succeed again.
+fn retire_mount_unit(ui: &UX) -> Result<()> {
+    if !Path::new(USB_UNIT_PATH).exists() {
+        return Ok(());
+    }
+    if let Err(err) = systemctl(Duration::from_secs(5))
+        .and_then(|cmd| cmd.run(&["disable", USB_MOUNT_UNIT], None))
+    {
+        ui.warn(&format!("Unable to disable {} ({}).", USB_MOUNT_UNIT, err));
+    }
+    fs::remove_file(USB_UNIT_PATH).with_context(|| format!("remove {}", USB_UNIT_PATH))?;
+    ui.info(&format!(
+        "Retired {}: a raw token has no filesystem to mount.",
+        USB_MOUNT_UNIT
+    ));
+    Ok(())
+}
+
+/// `install-units --check-only`: compare the on-disk units with what
+/// `install_units` would write, printing a unified-style diff for each that
+/// differs. Nothing is written; drift is returned as an error.
+pub fn check_units(ui: &UX, cfg: &ConfigFile, binary_path: &Path) -> Result<()> {
+    let (mount_content, unlock_content) = rendered_units(ui, cfg, binary_path)?;
+    let mut drifted = Vec::new();
+    // A raw token expects no mount unit at all; one still on disk is drift.
+    let mount_content = mount_content.unwrap_or_default();
+    for (path, expected) in [
+        (USB_UNIT_PATH, &mount_content),
+        (UNLOCK_UNIT_PATH, &unlock_content),
+    ] {
+        let current = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(anyhow!("read {}: {}", path, err)),
+        };
+        if current.is_empty() && expected.is_empty() {
+            continue;
+        }
+        if &current == expected {
+            ui.success(&format!("{} matches the rendered unit.", path));
+            continue;
+        }
+        ui.warn(&format!("{} drifts from the rendered unit.", path));
+        print!("{}", unified_diff(&current, expected, path, "rendered"));
+        drifted.push(path);
+    }
+    if drifted.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} unit(s) drift from the rendered content: {}",
+            drifted.len(),
+            drifted.join(", ")
+        ))
+    }
+}
+
+/// Gather the inputs `install_units` needs (token UUID, binary path) and
+/// render the unit files.
+fn rendered_units(
+    ui: &UX,
+    cfg: &ConfigFile,
+    binary_path: &Path,
+) -> Result<(Option<String>, String)> {
     if !binary_path.exists() {
         return Err(anyhow!(
             "zfs_beskar_key binary not found at {}",
@@ -24,14 +116,60 @@ pub fn install_units(ui: &UX, cfg: &ConfigFile, binary_path: &Path) -> Result<()
         ));
     }
 
-    let sysd_path = "/etc/systemd/system";
-    let usb_unit = format!("{}/{}", sysd_path, USB_MOUNT_UNIT);
-    let unlock_unit = format!("{}/beskar-unlock.service", sysd_path);
-    let usb_uuid = get_usb_uuid()?;
+    let usb_uuid = match &cfg.usb.raw_device {
+        Some(device) => {
+            ui.info(&format!(
+                "Raw token: no mount unit; the unlock service reads {} directly.",
+                device
+            ));
+            None
+        }
+        None => {
+            let (label, usb_uuid) = get_usb_uuid(&cfg.usb.token_labels())?;
+            ui.info(&format!(
+                "Token mount unit pinned to {} (UUID {}).",
+                label, usb_uuid
+            ));
+            Some(usb_uuid)
+        }
+    };
     let binary = binary_path.to_string_lossy().into_owned();
 
-    let mount_content = format!(
-        r#"[Unit]
+    // The service runs `auto-unlock --all`, so it needs targets in config.
+    if cfg.policy.datasets.is_empty() {
+        return Err(anyhow!(
+            "policy.datasets is empty; the unlock unit would have nothing to open"
+        ));
+    }
+    ui.info(&format!(
+        "Systemd unlock unit covers every root behind: {}.",
+        cfg.policy.datasets.join(", ")
+    ));
+
+    // Boot must read the file this run loaded, not the stock TOML path.
+    let config = fs::canonicalize(&cfg.path).unwrap_or_else(|_| cfg.path.clone());
+    Ok(render_units(
+        usb_uuid.as_deref(),
+        &binary,
+        &config.to_string_lossy(),
+    ))
+}
+
+/// Content of the token mount unit and the unlock service, in that order.
+/// The mount unit pins one token's UUID, so the service only `Wants=` it:
+/// with a spare inserted instead, the mount fails and `auto-unlock` still
+/// runs and finds the spare by label. `RuntimeDirectory=` keeps /run/beskar
+/// available for that walk even though `ProtectSystem=strict` leaves /run
+/// read-only. Without a UUID (raw token) there is no mount unit and the
+/// service depends on none.
+pub fn render_units(
+    usb_uuid: Option<&str>,
+    binary: &str,
+    config: &str,
+) -> (Option<String>, String) {
+    let mount_content = usb_uuid.map(|uuid| {
+        format!(
+            r#"[Unit]
 Description=Mount BESKAR key USB
 DefaultDependencies=no
 Before=local-fs-pre.target
@@ -45,60 +183,23 @@ Options=ro,nosuid,nodev,noexec,x-systemd.device-timeout=5s
 [Install]
 WantedBy=local-fs-pre.target
 "#,
-        uuid = usb_uuid
-    );
-
-    let dataset = cfg
-        .policy
-        .datasets
-        .first()
-        .cloned()
-        .unwrap_or_else(|| "rpool/ROOT".to_string());
-
-    let unlock_dataset = {
-        let timeout = Duration::from_secs(cfg.crypto.timeout_secs.max(1));
-        let zfs_client = if let Some(path) = &cfg.policy.zfs_path {
-            Zfs::with_path(path, timeout)
-        } else {
-            Zfs::discover(timeout)
-        };
-        match zfs_client {
-            Ok(client) => match client.encryption_root(&dataset) {
-                Ok(root) if !root.trim().is_empty() => {
-                    if root != dataset {
-                        ui.info(&format!(
-                            "Systemd unlock unit aligned to encryption root {} (requested {}).",
-                            root, dataset
-                        ));
-                    }
-                    root
-                }
-                Ok(_) => dataset.clone(),
-                Err(err) => {
-                    ui.warn(&format!(
-                        "Unable to resolve encryption root for {} ({}). Using original dataset.",
-                        dataset, err
-                    ));
-                    dataset.clone()
-                }
-            },
-            Err(err) => {
-                ui.warn(&format!(
-                    "Unable to initialize zfs client for unlock unit ({}). Using original dataset.",
-                    err
-                ));
-                dataset.clone()
-            }
-        }
+            uuid = uuid
+        )
+    });
+    let (after_mount, wants_mount) = match usb_uuid {
+        Some(_) => (
+            format!("{} ", USB_MOUNT_UNIT),
+            format!("Wants={}\n", USB_MOUNT_UNIT),
+        ),
+        None => (String::new(), String::new()),
     };
 
     let unlock_content = format!(
         r#"[Unit]
-Description=Unlock ZFS dataset with BESKAR USB key
+Description=Unlock ZFS datasets with BESKAR USB key
 DefaultDependencies=no
-After={mount_unit} zfs-import-cache.service zfs-import.target
-Requires={mount_unit}
-Before=zfs-load-key.service zfs-mount.service local-fs.target
+After={after_mount}zfs-import-cache.service zfs-import.target
+{wants_mount}Before=zfs-load-key.service zfs-mount.service local-fs.target
 
 [Service]
 Type=oneshot
@@ -115,39 +216,36 @@ RestrictRealtime=true
 RestrictNamespaces=true
 IPAddressDeny=any
 ReadWritePaths=/dev
-ReadOnlyPaths=/run/beskar
+RuntimeDirectory=beskar
+RuntimeDirectoryPreserve=yes
 TemporaryFileSystem=/tmp:ro
 UMask=0077
-ExecStart={binary} auto-unlock --config=/etc/zfs-beskar.toml --dataset={dataset}
+ExecStart={binary} auto-unlock --config={config} --all
 
 [Install]
 WantedBy=zfs-mount.service
 "#,
-        dataset = unlock_dataset,
         binary = binary,
-        mount_unit = USB_MOUNT_UNIT
+        config = config,
+        after_mount = after_mount,
+        wants_mount = wants_mount
     );
 
-    write_unit(&usb_unit, &mount_content)?;
-    write_unit(&unlock_unit, &unlock_content)?;
-
-    ui.info("Reloading systemd daemon and enabling sentry units…");
-    systemctl(Duration::from_secs(5))?.run(&["daemon-reload"], None)?;
-    systemctl(Duration::from_secs(5))?
-        .run(&["enable", USB_MOUNT_UNIT, "beskar-unlock.service"], None)?;
-    Ok(())
+    (mount_content, unlock_content)
 }
 
-pub fn ensure_units_enabled(ui: &UX) -> Result<()> {
+pub fn ensure_units_enabled(ui: &UX, cfg: &ConfigFile) -> Result<()> {
     let enable = systemctl(Duration::from_secs(5))?;
-    enable.run(&["enable", USB_MOUNT_UNIT], None)?;
-    enable.run(&["enable", "beskar-unlock.service"], None)?;
+    for unit in token_units(cfg) {
+        enable.run_checked(&["enable", unit], None)?;
+    }
     ui.info("Systemd sentry units stand ready.");
     Ok(())
 }
 
-pub fn units_exist() -> bool {
-    Path::new(USB_UNIT_PATH).exists() && Path::new(UNLOCK_UNIT_PATH).exists()
+pub fn units_exist(cfg: &ConfigFile) -> bool {
+    Path::new(UNLOCK_UNIT_PATH).exists()
+        && (cfg.usb.raw_device.is_some() || Path::new(USB_UNIT_PATH).exists())
 }
 
 pub fn unit_exec_matches(binary_path: &Path) -> Result<bool> {
@@ -162,6 +260,77 @@ pub fn unit_exec_matches(binary_path: &Path) -> Result<bool> {
     Ok(false)
 }
 
+/// Unified-style line diff of `old` against `new`: `---`/`+++` headers, then
+/// `@@` hunks with `DIFF_CONTEXT` lines of context. Empty when equal.
+fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // Longest-common-subsequence table; unit files are a few dozen lines.
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<(char, &str)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((' ', a[i]));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            ops.push(('+', b[j]));
+            j += 1;
+        } else {
+            ops.push(('-', a[i]));
+            i += 1;
+        }
+    }
+
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != ' ')
+        .map(|(k, _)| k)
+        .collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    let mut k = 0;
+    while k < changed.len() {
+        let start = changed[k].saturating_sub(DIFF_CONTEXT);
+        let mut last = changed[k];
+        while k + 1 < changed.len() && changed[k + 1] <= last + 2 * DIFF_CONTEXT {
+            k += 1;
+            last = changed[k];
+        }
+        let end = (last + DIFF_CONTEXT + 1).min(ops.len());
+        let count =
+            |range: &[(char, &str)], skip: char| range.iter().filter(|(op, _)| *op != skip).count();
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            count(&ops[..start], '+') + 1,
+            count(&ops[start..end], '+'),
+            count(&ops[..start], '-') + 1,
+            count(&ops[start..end], '-')
+        ));
+        for (op, line) in &ops[start..end] {
+            out.push_str(&format!("{}{}\n", op, line));
+        }
+        k += 1;
+    }
+    out
+}
+
 fn write_unit(path: &str, content: &str) -> Result<()> {
     if let Some(parent) = Path::new(path).parent() {
         fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
@@ -171,24 +340,33 @@ fn write_unit(path: &str, content: &str) -> Result<()> {
     Ok(())
 }
 
-fn get_usb_uuid() -> Result<String> {
+/// Filesystem UUID of the first attached token, trying `labels` in priority
+/// order; returns the label that matched alongside it.
+fn get_usb_uuid(labels: &[String]) -> Result<(String, String)> {
     for candidate in ["/sbin/blkid", "/usr/sbin/blkid", "/usr/bin/blkid"] {
         if Path::new(candidate).exists() {
             let cmd = Cmd::new_allowlisted(candidate, Duration::from_secs(5))?;
-            let output = cmd.run(&[], None)?;
-            for line in output.stdout.lines() {
-                if line.contains("BESKARKEY") {
-                    if let Some(u) = line.split("UUID=\"").nth(1) {
-                        return Ok(u.split('"').next().unwrap_or_default().to_string());
+            let output = cmd.run_checked(&[], None)?;
+            for label in labels {
+                let needle = format!(" LABEL=\"{}\"", label);
+                for line in output.stdout.lines() {
+                    if line.contains(&needle) {
+                        if let Some(u) = line.split("UUID=\"").nth(1) {
+                            let uuid = u.split('"').next().unwrap_or_default().to_string();
+                            return Ok((label.clone(), uuid));
+                        }
                     }
                 }
             }
         }
     }
-    Err(anyhow!("could not detect BESKARKEY UUID"))
+    Err(anyhow!(
+        "could not detect a UUID for {}",
+        labels.join(" or ")
+    ))
 }
 
-fn systemctl(timeout: Duration) -> Result<Cmd> {
+pub(crate) fn systemctl(timeout: Duration) -> Result<Cmd> {
     for candidate in ["/bin/systemctl", "/usr/bin/systemctl"] {
         if Path::new(candidate).exists() {
             return Cmd::new_allowlisted(candidate, timeout);
@@ -196,3 +374,51 @@ fn systemctl(timeout: Duration) -> Result<Cmd> {
     }
     Err(anyhow!("systemctl not found"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{render_units, unified_diff};
+
+    #[test]
+    fn unit_drift_diffs_show_only_changed_hunks() {
+        let config = "/etc/zfs-beskar.yaml";
+        let (_, current) = render_units(Some("1234-ABCD"), "/usr/local/bin/zfs_beskar_key", config);
+        let (_, rendered) = render_units(Some("1234-ABCD"), "/usr/bin/zfs_beskar_key", config);
+        assert!(unified_diff(&current, &current, "a", "b").is_empty());
+
+        let diff = unified_diff(&current, &rendered, "on-disk", "rendered");
+        assert!(diff.starts_with("--- on-disk\n+++ rendered\n@@ -"));
+        assert!(diff.contains("\n-ExecStart=/usr/local/bin/zfs_beskar_key auto-unlock"));
+        assert!(diff.contains(
+            "\n+ExecStart=/usr/bin/zfs_beskar_key auto-unlock --config=/etc/zfs-beskar.yaml --all"
+        ));
+        assert!(!diff.contains("Description="));
+        assert_eq!(diff.matches("@@ -").count(), 1);
+
+        // A failed pin (spare inserted) must not stop the label search.
+        assert!(current.contains("\nWants=run-beskar.mount\n"));
+        assert!(!current.contains("Requires="));
+    }
+
+    #[test]
+    fn raw_tokens_render_no_mount_unit_and_no_dependency_on_one() {
+        let (mount, unlock) = render_units(None, "/usr/bin/zfs_beskar_key", "/etc/zfs-beskar.toml");
+        assert!(mount.is_none());
+        assert!(!unlock.contains("run-beskar.mount"));
+        assert!(unlock.contains("\nAfter=zfs-import-cache.service zfs-import.target\n"));
+        assert!(unlock.contains("auto-unlock --config=/etc/zfs-beskar.toml --all"));
+    }
+
+    #[test]
+    fn unlock_unit_can_create_the_token_mountpoint_itself() {
+        let (_, unlock) = render_units(
+            Some("1234-ABCD"),
+            "/usr/bin/zfs_beskar_key",
+            "/etc/zfs-beskar.toml",
+        );
+        assert!(unlock.contains("\nProtectSystem=strict\n"));
+        assert!(unlock.contains("\nRuntimeDirectory=beskar\n"));
+        assert!(unlock.contains("\nRuntimeDirectoryPreserve=yes\n"));
+        assert!(!unlock.contains("ReadOnlyPaths=-/run/beskar"));
+    }
+}
@@ -2,10 +2,16 @@
 // src/cmd/simulate.rs – Ephemeral ZFS vault simulation for menu demos
 // ============================================================================
 
-use crate::cmd::{unlock::UnlockOptions, Cmd, OutputData};
-use crate::config::{ConfigFile, CryptoCfg, Fallback, Policy, Usb};
+use crate::cmd::{benchmark, unlock::UnlockOptions, Cmd, OutputData};
+use crate::config::{
+    ConfigFile, CryptoCfg, Fallback, Fido2, Policy, RecoveryDisplay, Usb, CONFIG_VERSION,
+};
+use crate::dracut::{self, ModuleContext};
 use crate::ui::{Pace, Timing, UX};
-use crate::zfs::Zfs;
+use crate::util::audit::audit_log;
+use crate::util::checksum::ChecksumAlgo;
+use crate::util::token::{mount_scratch_token, remount, unmount_partition};
+use crate::zfs::{Zfs, KEY_LEN};
 use anyhow::{anyhow, Context, Result};
 use nanoid::nanoid;
 use rand::rngs::OsRng;
@@ -15,14 +21,17 @@ use std::fs::{self, File};
 use std::io::Write;
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tempfile::TempDir;
 
 pub fn run_vault_drill(ui: &UX, timing: &Timing, base_cfg: &ConfigFile) -> Result<()> {
     ui.banner();
     ui.phase("Holoforge // Prep");
 
-    let mut sim = match VaultSimulation::prepare(base_cfg) {
+    let spinner = ui.spinner("Hammering holoforge pool (zpool create)");
+    let prepared = VaultSimulation::prepare(base_cfg);
+    spinner.finish();
+    let mut sim = match prepared {
         Ok(sim) => sim,
         Err(err) => {
             emit_preflight_remediation(ui, timing, base_cfg, &err);
@@ -65,7 +74,10 @@ pub fn run_vault_drill(ui: &UX, timing: &Timing, base_cfg: &ConfigFile) -> Resul
 
     ui.phase("Holoforge // Reseal Key");
     let zfs = sim.zfs()?;
-    if let Err(err) = zfs.unload_key(&sim.dataset_name) {
+    if let Err(err) = zfs
+        .unload_key(&sim.dataset_name)
+        .with_context(|| format!("zfs unload-key {}", sim.dataset_name))
+    {
         emit_reseal_remediation(ui, timing, base_cfg, &err);
         return Err(err);
     }
@@ -99,12 +111,108 @@ pub fn run_vault_drill(ui: &UX, timing: &Timing, base_cfg: &ConfigFile) -> Resul
     Ok(())
 }
 
+/// `benchmark --simulate`: time the unlock phases against a throwaway pool so
+/// numbers can be taken without sealing a real root.
+pub fn run_benchmark_drill(
+    ui: &UX,
+    timing: &Timing,
+    base_cfg: &ConfigFile,
+    iterations: usize,
+) -> Result<()> {
+    ui.banner();
+    ui.phase("Holoforge // Benchmark Prep");
+
+    let spinner = ui.spinner("Hammering holoforge pool (zpool create)");
+    let prepared = VaultSimulation::prepare(base_cfg);
+    spinner.finish();
+    let mut sim = match prepared {
+        Ok(sim) => sim,
+        Err(err) => {
+            emit_preflight_remediation(ui, timing, base_cfg, &err);
+            return Err(err);
+        }
+    };
+    timing.pace(Pace::Info);
+
+    ui.phase("Holoforge // Benchmark");
+    let outcome = sim.ensure_locked().and_then(|_| {
+        let zfs = sim.zfs()?;
+        benchmark::measure(ui, &zfs, &sim.config, &sim.dataset_name, iterations)
+    });
+
+    ui.phase("Holoforge // Cleanup");
+    sim.teardown()?;
+    let samples = outcome?;
+    benchmark::report(ui, &sim.dataset_name, &samples);
+    ui.note("Holoforge numbers reflect a file-backed pool; rerun against the real root for boot figures.");
+    Ok(())
+}
+
+/// Render the dracut loader for a throwaway pool and run the real script with
+/// `blkid`, `mountpoint`, and `zfs load-key -a` shimmed onto the simulated
+/// token, so template regressions surface before a boot depends on them.
+pub fn run_initramfs_drill(ui: &UX, timing: &Timing, base_cfg: &ConfigFile) -> Result<()> {
+    ui.banner();
+    ui.phase("Holoforge // Initramfs Prep");
+
+    let spinner = ui.spinner("Hammering holoforge pool (zpool create)");
+    let prepared = VaultSimulation::prepare(base_cfg);
+    spinner.finish();
+    let mut sim = match prepared {
+        Ok(sim) => sim,
+        Err(err) => {
+            emit_preflight_remediation(ui, timing, base_cfg, &err);
+            return Err(err);
+        }
+    };
+    ui.note("Vault sealed; loader script rendered from the live templates.");
+    timing.pace(Pace::Info);
+
+    ui.phase("Holoforge // Loader Drill");
+    let outcome = sim
+        .ensure_locked()
+        .and_then(|_| sim.run_load_key_script(ui))
+        .and_then(|_| {
+            if sim.zfs()?.is_unlocked(&sim.dataset_name)? {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "loader exited cleanly but {} is still sealed",
+                    sim.dataset_name
+                ))
+            }
+        });
+    timing.pace(Pace::Info);
+
+    ui.phase("Holoforge // Cleanup");
+    let teardown = sim.teardown();
+
+    match outcome {
+        Ok(()) => {
+            teardown?;
+            ui.success("Loader script unsealed the holoforge vault. Templates hold.");
+            ui.success("This is the Way.");
+            timing.pace(Pace::Critical);
+            Ok(())
+        }
+        Err(err) => {
+            ui.error(&format!("Initramfs loader drill failed: {}", err));
+            ui.note("Inspect the loader output above, then rerun `zfs_beskar_key install-dracut`.");
+            timing.pace(Pace::Error);
+            if let Err(cleanup_err) = teardown {
+                ui.warn(&format!("Holoforge cleanup also failed: {}", cleanup_err));
+            }
+            Err(err)
+        }
+    }
+}
+
 // ----------------------------------------------------------------------------
 // Internal scaffolding
 // ----------------------------------------------------------------------------
 
 struct VaultSimulation {
-    _temp_dir: TempDir,
+    temp_dir: TempDir,
     pool_name: String,
     dataset_name: String,
     image_path: PathBuf,
@@ -132,7 +240,7 @@ impl VaultSimulation {
         let dataset_name = format!("{}/forge", pool_name);
 
         let raw_key_path = temp_dir.path().join("beskar.key");
-        let mut key_bytes = [0u8; 32];
+        let mut key_bytes = [0u8; KEY_LEN];
         OsRng.fill_bytes(&mut key_bytes);
 
         {
@@ -165,7 +273,7 @@ impl VaultSimulation {
             ));
         }
 
-        let zfs_cmd = Cmd::new_allowlisted(&zfs_path, Duration::from_secs(10))?;
+        let zfs_cmd = Cmd::new_configured(&zfs_path, Duration::from_secs(10))?;
         let keylocation = format!("keylocation=file://{}", raw_key_path.to_string_lossy());
         let dataset_out = zfs_cmd
             .run(
@@ -194,21 +302,49 @@ impl VaultSimulation {
 
         let config_path = temp_dir.path().join("zfs-beskar-sim.toml");
         let sim_config = ConfigFile {
+            version: CONFIG_VERSION,
             policy: Policy {
                 datasets: vec![dataset_name.clone()],
                 zfs_path: Some(zfs_path.clone()),
                 binary_path: base_cfg.policy.binary_path.clone(),
                 allow_root: true,
+                extra_allowed_binaries: base_cfg.policy.extra_allowed_binaries.clone(),
+                require_healthy_pool: false,
+                dataset_overrides: Vec::new(),
+                recovery_display: RecoveryDisplay::Stdout,
+                recovery_file: None,
             },
             crypto: CryptoCfg {
                 timeout_secs: base_cfg.crypto.timeout_secs.max(1),
+                keyring: None,
+                keyring_secs: 600,
+                keyring_cache: false,
+                keyring_cache_secs: 600,
+                require_all_descendants: false,
             },
             usb: Usb {
                 key_hex_path: raw_key_path.to_string_lossy().into_owned(),
                 expected_sha256: Some(sha256.clone()),
+                checksum_algo: ChecksumAlgo::Sha256,
+                key_name_template: None,
+                label: base_cfg.usb.label.clone(),
+                labels: Vec::new(),
+                strict_keyfile: base_cfg.usb.strict_keyfile,
+                wait_secs: base_cfg.usb.wait_secs,
+                auto_mount: true,
+                // The holoforge token is a loop device with no USB identity.
+                require_serial: None,
+                require_vidpid: None,
+                break_glass_path: None,
+                raw_device: None,
+                raw_offset: crate::util::rawtoken::DEFAULT_OFFSET,
             },
             fallback: Fallback::default(),
+            fido2: Fido2::default(),
+            audit: base_cfg.audit.clone(),
+            ui: base_cfg.ui.clone(),
             path: config_path.clone(),
+            migrated_from: None,
         };
 
         let toml = toml::to_string_pretty(&sim_config).context("serialize simulation config")?;
@@ -217,7 +353,7 @@ impl VaultSimulation {
             .context("set simulation config permissions")?;
 
         Ok(Self {
-            _temp_dir: temp_dir,
+            temp_dir,
             pool_name,
             dataset_name,
             image_path,
@@ -241,6 +377,84 @@ impl VaultSimulation {
         Ok(())
     }
 
+    /// Stage the simulated key on a tmpfs "token" remounted read-only, as
+    /// `run-beskar.mount` mounts the real one, render the loader against it,
+    /// and run it with tool shims first on PATH.
+    fn run_load_key_script(&self, ui: &UX) -> Result<()> {
+        let root = self.temp_dir.path();
+        let token_dir = root.join("token");
+        let shim_dir = root.join("shims");
+        fs::create_dir_all(&token_dir).context("create simulated token dir")?;
+        fs::create_dir_all(&shim_dir).context("create loader shim dir")?;
+
+        let _token = DrillToken::mount(&token_dir)?;
+        let key_path = token_dir.join("beskar.key");
+        fs::copy(&self.config.usb.key_hex_path, &key_path).context("stage simulated token key")?;
+        fs::set_permissions(&key_path, fs::Permissions::from_mode(0o400))
+            .context("seal simulated token key")?;
+        remount(&token_dir, "remount,ro,nosuid,nodev,noexec")
+            .context("remount simulated token read-only")?;
+
+        let key_path_str = key_path.to_string_lossy().into_owned();
+        self.zfs()?.set_property(
+            &self.dataset_name,
+            "keylocation",
+            &format!("file://{}", key_path_str),
+        )?;
+
+        let token_dir_str = token_dir.to_string_lossy().into_owned();
+        let ctx = ModuleContext {
+            mountpoint: &token_dir_str,
+            key_path: &key_path_str,
+            key_sha256: self.config.usb.expected_sha256.as_deref(),
+            checksum_algo: self.config.usb.checksum_algo,
+            token_labels: &self.config.usb.token_labels(),
+            require_serial: None,
+            require_vidpid: None,
+            raw_device: None,
+            raw_offset: 0,
+        };
+        let script_path = root.join(dracut::SCRIPT_NAME);
+        write_executable(&script_path, &dracut::expected_module(&ctx).script)?;
+
+        write_executable(
+            &shim_dir.join("blkid"),
+            &format!(
+                r#"#!/bin/sh
+if [ "$1" = "-L" ] && [ "$2" = "{label}" ]; then
+    echo /dev/beskar-drill
+    exit 0
+fi
+exit 2
+"#,
+                label = self.config.usb.label
+            ),
+        )?;
+        write_executable(&shim_dir.join("mountpoint"), "#!/bin/sh\nexit 0\n")?;
+        // Keep the loader's trigger/settle from replaying events on the host.
+        write_executable(&shim_dir.join("udevadm"), "#!/bin/sh\nexit 0\n")?;
+        write_executable(
+            &shim_dir.join("zfs"),
+            &format!(
+                r#"#!/bin/sh
+if [ "$1" = "load-key" ] && [ "$2" = "-a" ]; then
+    exec "{zfs}" load-key "{dataset}"
+fi
+exec "{zfs}" "$@"
+"#,
+                zfs = self.zfs_path,
+                dataset = self.dataset_name
+            ),
+        )?;
+
+        let search_path = format!(
+            "{}:{}",
+            shim_dir.display(),
+            std::env::var("PATH").unwrap_or_else(|_| "/usr/sbin:/usr/bin:/sbin:/bin".to_string())
+        );
+        run_loader_script(ui, &script_path, &search_path)
+    }
+
     fn teardown(&mut self) -> Result<()> {
         if self.cleaned {
             return Ok(());
@@ -268,7 +482,7 @@ impl VaultSimulation {
     }
 
     fn destroy_dataset(&self) -> Result<OutputData> {
-        let cmd = Cmd::new_allowlisted(&self.zfs_path, self.timeout)?;
+        let cmd = Cmd::new_configured(&self.zfs_path, self.timeout)?;
         cmd.run(&["destroy", "-f", &self.dataset_name], None)
             .context("destroy simulated dataset")
     }
@@ -280,6 +494,137 @@ impl VaultSimulation {
     }
 }
 
+const DRILL_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// The drill's tmpfs token, unmounted when the drill is done with it.
+struct DrillToken {
+    mountpoint: PathBuf,
+}
+
+impl DrillToken {
+    fn mount(mountpoint: &Path) -> Result<Self> {
+        mount_scratch_token(mountpoint).context("mount simulated token")?;
+        Ok(Self {
+            mountpoint: mountpoint.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for DrillToken {
+    fn drop(&mut self) {
+        if let Err(err) = unmount_partition(&self.mountpoint) {
+            audit_log(
+                "SIM_TOKEN_UMOUNT_FAIL",
+                &format!("{}: {}", self.mountpoint.display(), err),
+            );
+        }
+    }
+}
+
+/// Run the drill's own rendered loader under bash with `search_path` as PATH,
+/// relaying its `[BESKAR]` lines. The script path and outcome are audited as
+/// `SIM_LOADER_EXEC`; a loader still running after `DRILL_TIMEOUT` has its
+/// process group torn down by `Cmd`.
+fn run_loader_script(ui: &UX, script: &Path, search_path: &str) -> Result<()> {
+    let shell = Cmd::new_drill_shell(DRILL_TIMEOUT)?.with_env("PATH", search_path);
+    let script_str = script.to_string_lossy();
+    let args = [script_str.as_ref()];
+    let started = Instant::now();
+    let result = shell
+        .run_streaming(&args, |line| {
+            if line.starts_with("[BESKAR]") {
+                ui.note(line.trim_start_matches("[BESKAR]").trim());
+            }
+        })
+        .and_then(|out| shell.ensure_success(&args, out));
+    let outcome = match &result {
+        Ok(_) => "exit status 0".to_string(),
+        Err(err) => err.to_string(),
+    };
+    audit_log(
+        "SIM_LOADER_EXEC",
+        &format!(
+            "{} {} {} in {}ms",
+            shell.path,
+            script.display(),
+            outcome,
+            started.elapsed().as_millis()
+        ),
+    );
+    result.map(|_| ()).context("loader script")
+}
+
+/// Opt-in switch for tests that build a real pool; `cargo test` never creates
+/// one on a developer's host by accident.
+#[cfg(test)]
+const TEST_VAULT_ENV: &str = "BESKAR_ZFS_TESTS";
+
+/// `VaultSimulation` for `#[test]` functions: the same loopback pool and
+/// config, sealed on creation and destroyed on drop. Runs only as root with
+/// `BESKAR_ZFS_TESTS=1` and the ZFS module loaded; elsewhere `create` yields
+/// `None` and the test passes without touching ZFS.
+#[cfg(test)]
+pub(crate) struct TestVault {
+    sim: VaultSimulation,
+}
+
+#[cfg(test)]
+impl TestVault {
+    pub(crate) fn create() -> Result<Option<Self>> {
+        if let Some(reason) = test_vault_unavailable() {
+            eprintln!("skipping ZFS-backed test: {}", reason);
+            return Ok(None);
+        }
+        let mut base: ConfigFile =
+            toml::from_str(crate::config::CONFIG_TEMPLATE).context("parse config template")?;
+        base.policy.zfs_path = ["/sbin/zfs", "/usr/sbin/zfs", "/usr/bin/zfs"]
+            .into_iter()
+            .find(|p| Path::new(p).exists())
+            .map(str::to_string);
+        let sim = VaultSimulation::prepare(&base)?;
+        sim.ensure_locked()?;
+        Ok(Some(Self { sim }))
+    }
+
+    /// Config pointing at the vault's key file, with its checksum recorded.
+    pub(crate) fn config(&self) -> &ConfigFile {
+        &self.sim.config
+    }
+
+    /// The encrypted dataset (its own encryption root).
+    pub(crate) fn dataset(&self) -> &str {
+        &self.sim.dataset_name
+    }
+
+    pub(crate) fn zfs(&self) -> Result<Zfs> {
+        self.sim.zfs()
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestVault {
+    fn drop(&mut self) {
+        if let Err(err) = self.sim.teardown() {
+            eprintln!("test vault {} left behind: {:#}", self.sim.pool_name, err);
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_vault_unavailable() -> Option<String> {
+    if std::env::var(TEST_VAULT_ENV).as_deref() != Ok("1") {
+        return Some(format!("set {}=1 to run it", TEST_VAULT_ENV));
+    }
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    if unsafe { libc::geteuid() } != 0 {
+        return Some("creating a pool needs root".to_string());
+    }
+    if !Path::new("/dev/zfs").exists() {
+        return Some("ZFS module not loaded (/dev/zfs missing)".to_string());
+    }
+    resolve_zpool_path().err().map(|err| err.to_string())
+}
+
 fn emit_preflight_remediation(ui: &UX, timing: &Timing, cfg: &ConfigFile, err: &anyhow::Error) {
     ui.error(&format!(
         "Simulation prep failed: {}. Confirm zfs/zpool binaries and free space.",
@@ -325,3 +670,107 @@ fn resolve_zpool_path() -> Result<String> {
     }
     Err(anyhow!("zpool binary not found on standard paths"))
 }
+
+fn write_executable(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, content).with_context(|| format!("write {}", path.display()))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("chmod {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TestVault;
+    use crate::cmd::unlock::{run_unlock, UnlockOptions};
+    use crate::config::KeySource;
+    use crate::lock_dataset;
+    use crate::ui::{Timing, UX};
+    use crate::util::keyfile::TransientKeyFile;
+    use crate::zfs::KEY_LEN;
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::RngCore;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_vault_unlocks_with_its_token_and_reseals() -> Result<()> {
+        let Some(vault) = TestVault::create()? else {
+            return Ok(());
+        };
+        let (ui, timing) = (UX::new(false, false), Timing::new(false, false));
+        let zfs = vault.zfs()?;
+
+        run_unlock(
+            &ui,
+            &timing,
+            vault.config(),
+            vault.dataset(),
+            UnlockOptions::default(),
+        )?;
+        assert!(zfs.is_unlocked(vault.dataset())?);
+
+        let report = zfs.unload_key_tree(vault.dataset())?;
+        assert!(report.busy.is_empty() && report.failed.is_empty());
+        assert!(!zfs.is_unlocked(vault.dataset())?);
+
+        let mut tampered = vault.config().clone();
+        tampered.usb.expected_sha256 = Some("00".repeat(32));
+        tampered.fallback.enabled = false;
+        let strict = UnlockOptions {
+            strict_usb: true,
+            ..UnlockOptions::default()
+        };
+        assert!(run_unlock(&ui, &timing, &tampered, vault.dataset(), strict).is_err());
+        assert!(!zfs.is_unlocked(vault.dataset())?);
+
+        // Strict USB drops the passphrase turn, so the plan falls through to
+        // the token instead of prompting.
+        let planned = UnlockOptions {
+            sources: &[KeySource::Passphrase],
+            ..strict
+        };
+        run_unlock(&ui, &timing, vault.config(), vault.dataset(), planned)?;
+        assert!(zfs.is_unlocked(vault.dataset())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vault_locks_and_rotates_its_key() -> Result<()> {
+        let Some(vault) = TestVault::create()? else {
+            return Ok(());
+        };
+        let (ui, timing) = (UX::new(false, false), Timing::new(false, false));
+        let zfs = vault.zfs()?;
+        let opts = UnlockOptions {
+            strict_usb: true,
+            ..UnlockOptions::default()
+        };
+
+        run_unlock(&ui, &timing, vault.config(), vault.dataset(), opts)?;
+        lock_dataset(&ui, &timing, vault.config(), vault.dataset(), false)?;
+        assert!(!zfs.is_unlocked(vault.dataset())?);
+
+        // Rotate: swap the wrapping key, then point a fresh token at it.
+        run_unlock(&ui, &timing, vault.config(), vault.dataset(), opts)?;
+        let mut new_key = [0u8; KEY_LEN];
+        OsRng.fill_bytes(&mut new_key);
+        let staged = TransientKeyFile::new(&new_key)?;
+        zfs.change_key_from_file(vault.dataset(), staged.path(), None)?;
+        drop(staged);
+
+        let token = tempfile::TempDir::new()?;
+        let key_path = token.path().join("beskar.key");
+        fs::write(&key_path, new_key)?;
+        fs::set_permissions(&key_path, fs::Permissions::from_mode(0o400))?;
+        let mut rotated = vault.config().clone();
+        rotated.usb.key_hex_path = key_path.to_string_lossy().into_owned();
+        rotated.usb.expected_sha256 = Some(rotated.usb.checksum_algo.digest_hex(&new_key));
+
+        lock_dataset(&ui, &timing, &rotated, vault.dataset(), false)?;
+        assert!(run_unlock(&ui, &timing, vault.config(), vault.dataset(), opts).is_err());
+        assert!(!zfs.is_unlocked(vault.dataset())?);
+        run_unlock(&ui, &timing, &rotated, vault.dataset(), opts)?;
+        assert!(zfs.is_unlocked(vault.dataset())?);
+        Ok(())
+    }
+}
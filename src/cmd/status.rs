@@ -0,0 +1,66 @@
+// ============================================================================
+// src/cmd/status.rs – At-a-glance vault status (keys, pool health)
+// ============================================================================
+
+use crate::config::ConfigFile;
+use crate::ui::{Pace, Timing, UX};
+use crate::zfs::{not_encrypted, Zfs};
+use crate::zpool::{pool_of, Zpool};
+use anyhow::{Context, Result};
+use std::time::Duration;
+
+pub fn run_status(ui: &UX, timing: &Timing, cfg: &ConfigFile, dataset: &str) -> Result<()> {
+    ui.banner();
+    ui.phase("Status // Vault Ledger");
+
+    let zfs = Zfs::from_config(cfg)?;
+
+    let enc_root = zfs
+        .encryption_root(dataset)
+        .with_context(|| format!("resolve encryption root for {}", dataset))?
+        .ok_or_else(|| not_encrypted(dataset))?;
+    let keystatus = match zfs.is_unlocked(&enc_root) {
+        Ok(true) => "available".to_string(),
+        Ok(false) => "unavailable".to_string(),
+        Err(err) => format!("unknown ({})", err),
+    };
+    let keylocation = zfs
+        .get_property(&enc_root, "keylocation")
+        .unwrap_or_else(|err| format!("unknown ({})", err));
+
+    // An unlocked but unmounted dataset is usually canmount=noauto, which
+    // boot-time `zfs mount -a` skips.
+    let canmount = match zfs.get_property(dataset, "canmount") {
+        Ok(value) if value == "noauto" => {
+            "noauto (explicit `zfs mount` or `unlock --mount` only)".to_string()
+        }
+        Ok(value) => value,
+        Err(err) => format!("unknown ({})", err),
+    };
+    let mounted = zfs
+        .get_property(dataset, "mounted")
+        .unwrap_or_else(|err| format!("unknown ({})", err));
+
+    let pool = pool_of(dataset);
+    let timeout = Duration::from_secs(cfg.crypto.timeout_secs.max(1));
+    let health = match Zpool::discover(timeout).and_then(|zpool| zpool.pool_health(pool)) {
+        Ok(health) => health.summary(),
+        Err(err) => format!("{} unknown ({})", pool, err),
+    };
+
+    ui.data_panel(
+        "Vault Status",
+        &[
+            ("Dataset", dataset.to_string()),
+            ("Encryption Root", enc_root.clone()),
+            ("Keystatus", keystatus),
+            ("Keylocation", keylocation),
+            ("Canmount", canmount),
+            ("Mounted", mounted),
+            ("Pool Health", health),
+            ("Token Key Path", cfg.usb.key_hex_path.clone()),
+        ],
+    );
+    timing.pace(Pace::Prompt);
+    Ok(())
+}
@@ -0,0 +1,129 @@
+// ============================================================================
+// src/cmd/tokens.rs – Inventory of attached Beskar tokens
+// ============================================================================
+
+use crate::cmd::init::detect_partition_uuid;
+use crate::config::ConfigFile;
+use crate::ui::{Pace, Timing, UX};
+use crate::util::keyfile::read_key_material;
+use crate::util::token::{find_labeled_tokens, mount_partition_read_only, unmount_partition};
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use tempfile::tempdir;
+
+/// What one labeled partition holds, relative to the config on record.
+struct TokenReport {
+    device: String,
+    uuid: String,
+    checksum: String,
+    verdict: &'static str,
+}
+
+/// List every attached partition carrying the configured label, mount each
+/// read-only, and compare its key against `usb.expected_sha256`.
+pub fn run_list_tokens(ui: &UX, timing: &Timing, cfg: &ConfigFile) -> Result<()> {
+    ui.banner();
+    ui.phase("Tokens // Armory Inventory");
+
+    let label = &cfg.usb.label;
+    let key_name = Path::new(&cfg.usb.key_hex_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow!("usb.key_hex_path has no file name"))?
+        .to_string();
+
+    let devices = find_labeled_tokens(label)?;
+    if devices.is_empty() {
+        ui.warn(&format!("No partitions labelled {} are attached.", label));
+        timing.pace(Pace::Prompt);
+        return Ok(());
+    }
+
+    let mut reports = Vec::new();
+    for device in devices {
+        let report = inspect_token(ui, cfg, &device, &key_name).unwrap_or_else(|err| {
+            ui.warn(&format!("{} could not be inspected ({}).", device, err));
+            TokenReport {
+                device: device.clone(),
+                uuid: detect_partition_uuid(&device).unwrap_or_else(|_| "?".to_string()),
+                checksum: "-".to_string(),
+                verdict: "unreadable",
+            }
+        });
+        reports.push(report);
+    }
+
+    let rows: Vec<(&str, String)> = reports
+        .iter()
+        .map(|r| {
+            (
+                r.device.as_str(),
+                format!(
+                    "uuid={}  {}={}  {}",
+                    r.uuid,
+                    cfg.usb.checksum_algo.label(),
+                    r.checksum,
+                    r.verdict
+                ),
+            )
+        })
+        .collect();
+    ui.data_panel(&format!("{} Tokens // key {}", label, key_name), &rows);
+
+    let matching = reports
+        .iter()
+        .filter(|r| r.verdict == "matches config")
+        .count();
+    ui.info(&format!(
+        "{} of {} token(s) carry the key on record.",
+        matching,
+        reports.len()
+    ));
+    timing.pace(Pace::Prompt);
+    Ok(())
+}
+
+fn inspect_token(ui: &UX, cfg: &ConfigFile, device: &str, key_name: &str) -> Result<TokenReport> {
+    let uuid = detect_partition_uuid(device).unwrap_or_else(|_| "?".to_string());
+    let mount_dir = tempdir().context("create inspection mountpoint")?;
+    mount_partition_read_only(device, mount_dir.path())?;
+
+    let key_path = mount_dir.path().join(key_name);
+    let key = if key_path.exists() {
+        Some(read_key_material(&key_path))
+    } else {
+        None
+    };
+    // The read already happened; a stuck mount is worth a warning, not the
+    // report. The mountpoint is kept so nothing recurses into the token.
+    if let Err(err) = unmount_partition(mount_dir.path()) {
+        ui.warn(&format!(
+            "{} stays mounted read-only at {} ({}); unmount it by hand.",
+            device,
+            mount_dir.keep().display(),
+            err
+        ));
+    }
+
+    let algo = cfg.usb.checksum_algo;
+    let (checksum, verdict) = match key {
+        None => ("-".to_string(), "no key file"),
+        Some(Err(_)) => ("-".to_string(), "malformed key"),
+        Some(Ok(material)) => {
+            let digest = algo.digest_hex(&material.raw);
+            let verdict = match &cfg.usb.expected_sha256 {
+                Some(expected) if algo.matches(&material.raw, expected) => "matches config",
+                Some(_) => "MISMATCH",
+                None => "no checksum on record",
+            };
+            (digest[..16.min(digest.len())].to_string(), verdict)
+        }
+    };
+
+    Ok(TokenReport {
+        device: device.to_string(),
+        uuid,
+        checksum,
+        verdict,
+    })
+}
@@ -2,24 +2,97 @@
 // src/cmd/unlock.rs – Secure unlock workflow with adaptive lockout
 // ============================================================================
 
+use crate::cmd::init::settle_udev;
+use crate::cmd::recover::verify_fingerprint;
+use crate::cmd::repair::{systemctl, USB_MOUNT_UNIT};
 use crate::cmd::Cmd;
-use crate::config::{ConfigFile, Fallback};
-use crate::ui::{Pace, Timing, UX};
+use crate::config::{persist_config, ConfigFile, Fallback, KeySource, PromptBackend};
+use crate::ui::{interactive_terminal, require_terminal, Pace, Timing, UX};
 use crate::util::audit::audit_log;
+use crate::util::breakglass;
+use crate::util::fido2;
+use crate::util::holders::unescape_mount_field;
+use crate::util::initramfs;
 use crate::util::kdf::pbkdf2_sha256;
-use crate::util::keyfile::{ensure_raw_key_file, KeyEncoding};
+use crate::util::keyfile::{
+    ensure_raw_key_file, ensure_raw_key_file_strict, read_key_material_stdin, KeyEncoding,
+};
+use crate::util::keyring;
 use crate::util::lockout::Lockout;
-use crate::zfs::Zfs;
+use crate::util::rawtoken;
+use crate::util::recovery::decode_recovery_code;
+use crate::util::secret::{secret_bytes_line, secret_line};
+use crate::util::token::{mount_token_if_needed, MountedToken};
+use crate::util::usbid;
+use crate::zfs::{
+    is_zfs_error, not_encrypted, KeyTreeOutcome, KeyTreeTimings, MountState, Zfs, ZfsError, KEY_LEN,
+};
+use crate::zpool::{pool_of, Zpool};
 use anyhow::{anyhow, Context, Result};
-use dialoguer::Password;
-use sha2::{Digest, Sha256};
+use dialoguer::{Confirm, Password};
+use std::fs;
+use std::io::ErrorKind;
 use std::path::Path;
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 use zeroize::Zeroizing;
 
+/// `/run` is tmpfs; give a racing `run-beskar.mount` a few chances to land.
+const MOUNT_RETRY_ATTEMPTS: u32 = 3;
+const MOUNT_RETRY_DELAY: Duration = Duration::from_millis(750);
+/// Re-check cadence while waiting out `[usb] wait_secs` for the key file.
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How long a password agent (systemd, plymouth) may wait on the operator.
+const ASKPASS_TIMEOUT: Duration = Duration::from_secs(90);
+const PLYMOUTH_BINARIES: [&str; 2] = ["/usr/bin/plymouth", "/bin/plymouth"];
+const PLYMOUTH_PID_FILES: [&str; 2] = ["/run/plymouth/pid", "/run/plymouthd.pid"];
+/// Unlock attempts whose key material is refused before the run aborts.
+const MAX_ATTEMPTS: usize = 3;
+/// Failures that never put key material before ZFS (a flaky read, a `zfs`
+/// call that died) are retried on their own budget, outside `MAX_ATTEMPTS`.
+const MAX_UNAVAILABLE_RETRIES: usize = 5;
+const UNAVAILABLE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 #[derive(Clone, Copy, Default)]
-pub struct UnlockOptions {
+pub struct UnlockOptions<'a> {
     pub strict_usb: bool,
+    /// Key source for each attempt in turn (`[fallback] order`); empty keeps
+    /// the implicit USB-then-fallback order.
+    pub sources: &'a [KeySource],
+    /// Take the key from the `init` recovery sigil instead of any token,
+    /// authenticator, or passphrase.
+    pub recovery: bool,
+    /// Mount the unlocked tree afterwards, the `named` dataset even if
+    /// `canmount=noauto`.
+    pub mount: bool,
+    /// The operator gave this dataset as a literal `--dataset`, not through
+    /// `--pool`, a glob, `--recursive` or the configured default.
+    pub named: bool,
+    /// Let ZFS judge a token whose checksum no longer matches the config and,
+    /// if it opens the root, record the new checksum.
+    pub accept_new_checksum: bool,
+    /// Skip the `accept_new_checksum` confirmation.
+    pub yes: bool,
+    /// Take the key piped on stdin by automation instead of any other source.
+    pub stdin_key: bool,
+    /// Do not hold the stdin key to `usb.expected_sha256`.
+    pub skip_checksum: bool,
+}
+
+/// Per-phase durations for one unlock run, reported under `--verbose` or
+/// `--json` and always audited so boot-time device timeouts can be tuned.
+#[derive(Default)]
+struct UnlockTimings {
+    /// Acquiring key material from any source (includes `checksum`).
+    key_fetch: Duration,
+    checksum: Duration,
+    tree: KeyTreeTimings,
+    /// Wall time of each `load_key_tree` call, one per key tried.
+    loads: Vec<Duration>,
+    /// Attempts counted against `MAX_ATTEMPTS`; one given back (key never
+    /// judged, stale cache) is not reported.
+    attempts: usize,
+    descendants: usize,
 }
 
 // ----------------------------------------------------------------------------
@@ -40,26 +113,482 @@ pub fn run_unlock(
     ));
     timing.pace(Pace::Info);
 
-    // ------------------------------------------------------------------------
-    // Step 1: Prepare ZFS interface and verify dataset state
-    // ------------------------------------------------------------------------
-    let zfs = if let Some(path) = &cfg.policy.zfs_path {
-        Zfs::with_path(path, Duration::from_secs(cfg.crypto.timeout_secs))?
+    let mut session = UnlockSession::new(cfg)?;
+    let unlocked = unlock_dataset(&mut session, ui, timing, cfg, dataset, opts)?;
+    report_unlocked(ui, &session.zfs, dataset, opts.named, &unlocked, opts.mount);
+    Ok(())
+}
+
+/// `unlock --all` / `auto-unlock --all`: every encryption root behind
+/// `policy.datasets` (only those in `pool` under `--pool`), in config order,
+/// with one zfs client, one USB read and one lockout tracker. Each root is
+/// attempted even if an earlier one fails; the summary lists every outcome.
+pub fn run_unlock_all(
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    pool: Option<&str>,
+    opts: UnlockOptions,
+) -> Result<()> {
+    ui.banner();
+    let mut session = UnlockSession::new(cfg)?;
+    let roots = configured_roots(ui, &session.zfs, cfg, pool)?;
+    ui.info(&format!(
+        "Initiating unlock sequence for {} encryption root(s): {}.",
+        roots.len(),
+        roots.join(", ")
+    ));
+    timing.pace(Pace::Info);
+
+    let mut rows = Vec::new();
+    let mut sealed = Vec::new();
+    for root in &roots {
+        match unlock_dataset(&mut session, ui, timing, cfg, root, opts) {
+            Ok(unlocked) => {
+                report_unlocked(ui, &session.zfs, root, false, &unlocked, opts.mount);
+                rows.push((root.as_str(), "open".to_string()));
+            }
+            Err(err) => {
+                ui.error(&format!("{} stays sealed ({}).", root, err));
+                rows.push((root.as_str(), format!("sealed: {}", err)));
+                sealed.push(root.as_str());
+            }
+        }
+    }
+    ui.data_panel("Unlock summary", &rows);
+    audit_log(
+        "UNLOCK_ALL",
+        &format!(
+            "{} of {} encryption root(s) open; sealed: [{}]",
+            roots.len() - sealed.len(),
+            roots.len(),
+            sealed.join(", ")
+        ),
+    );
+    if !sealed.is_empty() {
+        return Err(anyhow!(
+            "{} of {} encryption root(s) remain sealed: {}",
+            sealed.len(),
+            roots.len(),
+            sealed.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Boot-time gate ahead of `auto-unlock`: report and audit the health of each
+/// pool behind `datasets`. A pool that is not ONLINE, is resilvering, or has
+/// scan errors aborts the unlock under `[policy] require_healthy_pool` and
+/// only warns otherwise. Health that cannot be read (no zpool in the
+/// initramfs) never blocks.
+pub fn gate_pool_health(ui: &UX, cfg: &ConfigFile, datasets: &[String]) -> Result<()> {
+    let timeout = Duration::from_secs(cfg.crypto.timeout_secs.max(1));
+    let zpool = match Zpool::discover(timeout) {
+        Ok(zpool) => zpool,
+        Err(err) => {
+            ui.warn(&format!("Pool health unavailable ({}).", err));
+            audit_log("UNLOCK_POOL_HEALTH", &format!("unknown: {}", err));
+            return Ok(());
+        }
+    };
+
+    let mut pools: Vec<&str> = Vec::new();
+    for dataset in datasets {
+        let pool = pool_of(dataset);
+        if !pools.contains(&pool) {
+            pools.push(pool);
+        }
+    }
+
+    let mut unhealthy = Vec::new();
+    for pool in pools {
+        match zpool.pool_health(pool) {
+            Ok(health) => {
+                audit_log("UNLOCK_POOL_HEALTH", &health.summary());
+                if health.is_healthy() {
+                    ui.info(&format!("Pool {} stands ONLINE.", pool));
+                } else {
+                    ui.warn(&format!("Pool health: {}.", health.summary()));
+                    unhealthy.push(health.summary());
+                }
+            }
+            Err(err) => {
+                ui.warn(&format!(
+                    "Unable to read health of pool {} ({}).",
+                    pool, err
+                ));
+                audit_log("UNLOCK_POOL_HEALTH", &format!("{} unknown: {}", pool, err));
+            }
+        }
+    }
+    if unhealthy.is_empty() {
+        return Ok(());
+    }
+    if cfg.policy.require_healthy_pool {
+        audit_log(
+            "UNLOCK_POOL_GATE",
+            &format!("held: {}", unhealthy.join("; ")),
+        );
+        return Err(anyhow!(
+            "policy.require_healthy_pool holds the unlock: {}",
+            unhealthy.join("; ")
+        ));
+    }
+    ui.note("Unlocking anyway; set [policy] require_healthy_pool = true to hold boot on an unhealthy pool.");
+    Ok(())
+}
+
+/// List the datasets this run unlocked (`unlocked`, encryption root first)
+/// with their mountpoint, canmount and mount state; under `--mount`, mount
+/// `target`'s tree first so the panel shows how each mount went. `named` is
+/// set when the operator passed `target` as a literal `--dataset`.
+fn report_unlocked(
+    ui: &UX,
+    zfs: &Zfs,
+    target: &str,
+    named: bool,
+    unlocked: &[String],
+    mount: bool,
+) {
+    let outcomes = if mount {
+        mount_tree(ui, zfs, target, named)
     } else {
-        Zfs::discover(Duration::from_secs(cfg.crypto.timeout_secs))?
+        Vec::new()
     };
+    let Some(root) = unlocked.first() else {
+        return;
+    };
+    let states = match zfs.mount_states(root) {
+        Ok(states) => states,
+        Err(err) => {
+            ui.warn(&format!(
+                "Unable to list mountpoints under {} ({}).",
+                root, err
+            ));
+            return;
+        }
+    };
+    let rows: Vec<(&str, String)> = unlocked
+        .iter()
+        .map(|name| {
+            let detail = match states.iter().find(|s| &s.name == name) {
+                Some(state) => {
+                    let status = outcomes
+                        .iter()
+                        .find(|(dataset, _)| dataset == name)
+                        .map(|(_, outcome)| outcome.clone())
+                        .unwrap_or_else(|| {
+                            if state.mounted {
+                                "mounted"
+                            } else {
+                                "not mounted"
+                            }
+                            .to_string()
+                        });
+                    format!(
+                        "{} (canmount={}, {})",
+                        state.mountpoint, state.canmount, status
+                    )
+                }
+                None => "volume; nothing to mount".to_string(),
+            };
+            (name.as_str(), detail)
+        })
+        .collect();
+    ui.data_panel(&format!("Unlocked datasets // {}", root), &rows);
+}
+
+/// `--mount`: mount every unlocked filesystem under `target`, parents first.
+/// `target` itself is mounted even with `canmount=noauto` when the operator
+/// `named` it, since `zfs mount -a` (and the mount generator) skip it; noauto
+/// descendants (zsys boot environments among them) stay unmounted, as do
+/// `legacy`/`none` mountpoints (left to fstab), `/`, and any mountpoint
+/// something else already occupies. Failures are warnings, since the keys
+/// are already loaded. Returns `(dataset, outcome)` for every filesystem that
+/// was mounted or skipped.
+fn mount_tree(ui: &UX, zfs: &Zfs, target: &str, named: bool) -> Vec<(String, String)> {
+    let mut outcomes = Vec::new();
+    let states = match zfs.mount_states(target) {
+        Ok(states) => states,
+        Err(err) => {
+            ui.warn(&format!(
+                "Unable to list mounts under {} ({}).",
+                target, err
+            ));
+            return outcomes;
+        }
+    };
+    let in_use = mountpoints_in_use();
+    for state in &states {
+        let named = named && state.name == target;
+        if let Some(reason) = mount_skip_reason(state, named, &in_use) {
+            outcomes.push((state.name.clone(), format!("skipped: {}", reason)));
+            continue;
+        }
+        if !state.wants_mount(named) {
+            continue;
+        }
+        match zfs.mount(&state.name) {
+            Ok(()) => {
+                outcomes.push((state.name.clone(), "mounted now".to_string()));
+                ui.info(&format!("Mounted {} at {}.", state.name, state.mountpoint));
+                if state.canmount == "noauto" {
+                    ui.note(&format!(
+                        "{} has canmount=noauto; mounted now, but it will not auto-mount on the next boot.",
+                        state.name
+                    ));
+                }
+                audit_log(
+                    "UNLOCK_MOUNT",
+                    &format!(
+                        "{} at {} canmount={}",
+                        state.name, state.mountpoint, state.canmount
+                    ),
+                );
+            }
+            Err(err) => {
+                ui.warn(&format!("Unable to mount {} ({}).", state.name, err));
+                outcomes.push((state.name.clone(), format!("mount failed: {}", err)));
+            }
+        }
+    }
+    outcomes
+}
+
+/// Why `--mount` leaves an unmounted filesystem alone, when the reason is
+/// worth showing in the panel.
+fn mount_skip_reason(state: &MountState, named: bool, in_use: &[String]) -> Option<String> {
+    if state.mounted || state.canmount == "off" {
+        return None;
+    }
+    let mountpoint = state.mountpoint.as_str();
+    if matches!(mountpoint, "none" | "legacy" | "/") {
+        return Some(format!("mountpoint={}", mountpoint));
+    }
+    if state.canmount == "noauto" && !named {
+        return Some("canmount=noauto".to_string());
+    }
+    if in_use.iter().any(|used| used == mountpoint) {
+        return Some(format!("{} already in use", mountpoint));
+    }
+    None
+}
+
+/// Every mountpoint in `/proc/mounts`, unescaped.
+fn mountpoints_in_use() -> Vec<String> {
+    fs::read_to_string("/proc/mounts")
+        .map(|mounts| {
+            mounts
+                .lines()
+                .filter_map(|line| line.split_whitespace().nth(1))
+                .map(unescape_mount_field)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Encryption roots behind `policy.datasets`, each once, in config order.
+/// Unencrypted entries are skipped with a warning.
+fn configured_roots(
+    ui: &UX,
+    zfs: &Zfs,
+    cfg: &ConfigFile,
+    pool: Option<&str>,
+) -> Result<Vec<String>> {
+    let mut roots: Vec<String> = Vec::new();
+    for dataset in &cfg.policy.datasets {
+        if pool.is_some_and(|pool| pool_of(dataset) != pool) {
+            continue;
+        }
+        let root = match zfs
+            .encryption_root(dataset)
+            .with_context(|| format!("resolve encryption root for {}", dataset))?
+        {
+            Some(root) => root,
+            None => {
+                ui.warn(&format!("{} is not encrypted; skipping it.", dataset));
+                continue;
+            }
+        };
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+    if roots.is_empty() {
+        return Err(match pool {
+            Some(pool) => anyhow!(
+                "policy.datasets names no encrypted datasets in pool {}",
+                pool
+            ),
+            None => anyhow!("policy.datasets names no encrypted datasets to unlock"),
+        });
+    }
+    Ok(roots)
+}
+
+/// State shared by every root unlocked in one invocation: one zfs client, one
+/// lockout tracker (so failures on any root escalate the cooldown), and the
+/// USB key once it has been read and checksummed.
+struct UnlockSession {
+    zfs: Zfs,
+    lockout: Lockout,
+    usb_key: Option<Zeroizing<Vec<u8>>>,
+    recovery_key: Option<Zeroizing<Vec<u8>>>,
+    stdin_key: Option<Zeroizing<Vec<u8>>>,
+    /// Checksum of `usb_key` accepted under `--accept-new-checksum`, recorded
+    /// in the config once ZFS has taken the key.
+    new_checksum: Option<String>,
+}
+
+impl UnlockSession {
+    fn new(cfg: &ConfigFile) -> Result<Self> {
+        let zfs = Zfs::from_config(cfg)?;
+        Ok(Self {
+            zfs,
+            lockout: Lockout::new(),
+            usb_key: None,
+            recovery_key: None,
+            stdin_key: None,
+            new_checksum: None,
+        })
+    }
+}
+
+/// `--recovery`: the base32 sigil from `init`, asked for once per invocation
+/// and proven against `usb.expected_sha256` when one is recorded.
+fn cached_recovery_key(
+    cache: &mut Option<Zeroizing<Vec<u8>>>,
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+) -> Result<Zeroizing<Vec<u8>>> {
+    if let Some(key) = cache {
+        return Ok(key.clone());
+    }
+    if initramfs::detected() {
+        return Err(anyhow!(
+            "the recovery sigil needs an interactive terminal; boot to a rescue shell first"
+        ));
+    }
+    require_terminal("the recovery sigil")?;
+    timing.pace(Pace::Prompt);
+    let sigil = Zeroizing::new(
+        Password::new()
+            .with_prompt("Enter Armorer recovery sigil")
+            .allow_empty_password(false)
+            .interact()
+            .context("read recovery sigil")?,
+    );
+    let key = decode_recovery_code(&sigil)?;
+    if cfg.usb.expected_sha256.is_some() {
+        verify_fingerprint(cfg, &key)?;
+        ui.success("Sigil matches the recorded fingerprint.");
+    } else {
+        ui.warn("No fingerprint on record; ZFS alone will judge the sigil.");
+    }
+    *cache = Some(key.clone());
+    Ok(key)
+}
+
+/// `--stdin-key`: material piped in by a key-management system, read once per
+/// invocation and proven against `usb.expected_sha256` unless skipped.
+fn cached_stdin_key(
+    cache: &mut Option<Zeroizing<Vec<u8>>>,
+    ui: &UX,
+    cfg: &ConfigFile,
+    skip_checksum: bool,
+) -> Result<Zeroizing<Vec<u8>>> {
+    if let Some(key) = cache {
+        return Ok(key.clone());
+    }
+    if interactive_terminal() {
+        return Err(anyhow!(
+            "--stdin-key expects key material piped on stdin, not a terminal"
+        ));
+    }
+    let material = read_key_material_stdin(cfg.usb.strict_keyfile)?;
+    let algo = cfg.usb.checksum_algo;
+    match &cfg.usb.expected_sha256 {
+        _ if skip_checksum => {
+            ui.warn("--skip-checksum: ZFS alone will judge the piped key.");
+            audit_log("UNLOCK_CHECKSUM_SKIP", "Checksum skipped for stdin key");
+        }
+        Some(expected) => {
+            if !algo.matches(&material.raw, expected) {
+                return Err(anyhow!(
+                    "Stdin key checksum mismatch (expected {}, found {})",
+                    expected,
+                    algo.digest_hex(&material.raw)
+                ));
+            }
+            ui.success(&format!(
+                "Stdin key checksum verified ({} match).",
+                algo.label()
+            ));
+            audit_log("UNLOCK_CHECKSUM", "Stdin key checksum verified");
+        }
+        None => {
+            ui.warn(&format!(
+                "No reference {} recorded in config.usb.expected_sha256 — authenticity check skipped.",
+                algo.label()
+            ));
+            audit_log("UNLOCK_CHECKSUM_SKIP", "Checksum skipped; field not set");
+        }
+    }
+    *cache = Some(material.raw.clone());
+    Ok(material.raw)
+}
+
+/// USB key material, read from the token only the first time it is needed.
+fn cached_usb_key(
+    cache: &mut Option<Zeroizing<Vec<u8>>>,
+    new_checksum: &mut Option<String>,
+    ui: &UX,
+    cfg: &ConfigFile,
+    opts: UnlockOptions,
+    checksum_time: &mut Duration,
+) -> Result<Zeroizing<Vec<u8>>> {
+    if let Some(key) = cache {
+        return Ok(key.clone());
+    }
+    let key = load_usb_key_material(ui, cfg, opts, new_checksum, checksum_time)?;
+    *cache = Some(key.clone());
+    Ok(key)
+}
+
+fn unlock_dataset(
+    session: &mut UnlockSession,
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    dataset: &str,
+    opts: UnlockOptions,
+) -> Result<Vec<String>> {
+    let started = Instant::now();
+    let mut timings = UnlockTimings::default();
+    let UnlockSession {
+        zfs,
+        lockout,
+        usb_key,
+        recovery_key,
+        stdin_key,
+        new_checksum,
+    } = session;
 
+    // ------------------------------------------------------------------------
+    // Step 1: Verify dataset state
+    // ------------------------------------------------------------------------
     if zfs.is_unlocked(dataset)? {
         ui.success("Dataset already stands open; no further strikes required.");
         audit_log("UNLOCK_SKIP", &format!("{} already unlocked", dataset));
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     // ------------------------------------------------------------------------
     // Step 2: Identify encryption root
     // ------------------------------------------------------------------------
     let enc_root = match zfs.encryption_root(dataset) {
-        Ok(root) => {
+        Ok(Some(root)) => {
             if root != dataset {
                 ui.info(&format!(
                     "Dataset {} draws its ward from encryption root {}.",
@@ -70,11 +599,16 @@ pub fn run_unlock(
             }
             root
         }
-        Err(_) => {
-            ui.warn(
-                "Unable to trace the encryption root; proceeding directly against the dataset.",
+        Ok(None) => {
+            audit_log("UNLOCK_FAIL", &format!("{} is not encrypted", dataset));
+            return Err(not_encrypted(dataset));
+        }
+        Err(err) => {
+            audit_log(
+                "UNLOCK_FAIL",
+                &format!("{} encryption root unresolved: {:#}", dataset, err),
             );
-            dataset.to_string()
+            return Err(err.context(format!("resolve encryption root for {}", dataset)));
         }
     };
     audit_log(
@@ -85,102 +619,284 @@ pub fn run_unlock(
     // ------------------------------------------------------------------------
     // Step 3: Attempt unlock (with USB-first path and fallback)
     // ------------------------------------------------------------------------
-    const MAX_ATTEMPTS: usize = 3;
-    let mut lockout = Lockout::new();
     let mut usb_available = true;
+    // Set when USB dropped out because the key file was missing (not
+    // rejected), so a later attempt may pick the token back up.
+    let mut usb_absent = false;
     let mut logged_usb_source = false;
-    let fallback_allowed = cfg.fallback.enabled && !opts.strict_usb;
+    let override_strict = cfg
+        .policy
+        .override_for(&enc_root, |ds| zfs.encryption_root(ds).ok().flatten())
+        .is_some_and(|o| o.strict_usb);
+    if override_strict && !opts.strict_usb {
+        ui.note(&format!(
+            "Policy override holds {} to USB-only; passphrase fallback withheld.",
+            enc_root
+        ));
+        audit_log(
+            "UNLOCK_POLICY_OVERRIDE",
+            &format!("{} strict_usb=true", enc_root),
+        );
+    }
+    let strict_usb = opts.strict_usb || override_strict;
+    let fallback_allowed = cfg.fallback.enabled && !strict_usb;
     let mut fallback_primed = false;
-    let key_path = Path::new(&cfg.usb.key_hex_path);
+    // FIDO2 is an explicit opt-in; strict USB boots only consult it when it is
+    // configured as the primary source.
+    let mut fido2_available = cfg.fido2.enabled && (cfg.fido2.primary || !strict_usb);
+    // The break-glass seal needs a human at the prompt, so it is withheld
+    // from strict USB boots just like the fallback passphrase.
+    let mut break_glass_available = cfg.usb.break_glass_path.is_some() && !strict_usb;
+    // Consulted once, before any token is touched. Strict USB roots must
+    // prove the token is present, so a cached key never stands in for it.
+    let mut keyring_cache_pending =
+        cfg.crypto.keyring_cache && !opts.recovery && !opts.stdin_key && !strict_usb;
+    let key_path = token_source(cfg);
+    let plan = attempt_plan(opts.sources, fallback_allowed || break_glass_available);
 
-    for attempt in 1..=MAX_ATTEMPTS {
+    let mut attempt = 0;
+    let mut unavailable_retries = 0;
+    'attempts: while attempt < MAX_ATTEMPTS {
+        attempt += 1;
+        timings.attempts = attempt;
         ui.info(&format!(
             "Attempt {}/{} to unlock {}...",
             attempt, MAX_ATTEMPTS, enc_root
         ));
         timing.pace(Pace::Info);
 
-        let (key_material, origin) = if usb_available {
-            match load_usb_key_material(ui, cfg) {
-                Ok(bytes) => {
-                    if !logged_usb_source {
-                        audit_log("UNLOCK_SOURCE", "Using USB key material");
-                        logged_usb_source = true;
+        if usb_absent && key_path.exists() {
+            ui.info(&format!(
+                "{} surfaced; the token rejoins the attempt.",
+                key_path.display()
+            ));
+            audit_log("UNLOCK_USB_RETURNED", &key_path.display().to_string());
+            usb_available = true;
+            usb_absent = false;
+        }
+
+        let planned = plan.get(attempt - 1).copied();
+        if let Some(source) = planned {
+            audit_log(
+                "UNLOCK_PLAN",
+                &format!("attempt {} for {}: {}", attempt, enc_root, source.label()),
+            );
+        }
+        match planned {
+            Some(KeySource::Usb) if attempt > 1 => {
+                ui.info("Attempt plan calls for the token again; settling udev and rereading it.");
+                if let Err(err) = settle_udev(ui) {
+                    ui.warn(&format!(
+                        "udev settle failed ({}); rereading the token anyway.",
+                        err
+                    ));
+                    audit_log("UNLOCK_PLAN_SETTLE_FAIL", &err.to_string());
+                }
+                *usb_key = None;
+                usb_available = true;
+                usb_absent = false;
+            }
+            Some(KeySource::Passphrase) => {
+                ui.note("Attempt plan calls for the passphrase; the token sits this one out.");
+                fallback_primed = true;
+            }
+            _ => {}
+        }
+        let passphrase_turn = planned == Some(KeySource::Passphrase);
+
+        let fetch_started = Instant::now();
+        let (key_material, origin) = loop {
+            if keyring_cache_pending {
+                keyring_cache_pending = false;
+                match keyring::cached_key(&enc_root) {
+                    Ok(Some(bytes)) => {
+                        ui.info("Key recalled from the kernel keyring; the token stays untouched.");
+                        audit_log("UNLOCK_SOURCE", "Using key cached in the kernel keyring");
+                        break (bytes, KeyOrigin::Keyring);
                     }
-                    (bytes, KeyOrigin::Usb)
+                    Ok(None) => {}
+                    Err(err) => ui.warn(&format!("Keyring cache unreadable ({}).", err)),
                 }
-                Err(usb_err) => {
-                    audit_log("UNLOCK_USB_UNAVAILABLE", &format!("reason={}", usb_err));
-                    usb_available = false;
-                    if !fallback_allowed {
-                        let err = anyhow!(
-                            "USB key material unavailable ({}). Strict USB mode forbids fallback. Key path: {}",
-                            usb_err,
-                            key_path.display()
-                        );
-                        ui.error(&err.to_string());
+            }
+
+            if opts.stdin_key {
+                match cached_stdin_key(stdin_key, ui, cfg, opts.skip_checksum) {
+                    Ok(bytes) => {
+                        audit_log("UNLOCK_SOURCE", "Using key material piped on stdin");
+                        break (bytes, KeyOrigin::Stdin);
+                    }
+                    Err(err) => {
+                        ui.error(&format!("Stdin key refused ({}).", err));
                         audit_log("UNLOCK_KEY_FETCH_FAIL", &err.to_string());
                         return Err(err);
                     }
-                    ui.warn(&format!(
-                        "USB key unavailable ({}); invoking the fallback passphrase ritual.",
-                        usb_err
-                    ));
-                    timing.pace(Pace::Prompt);
-                    let passphrase = match prompt_fallback_passphrase(ui, timing, cfg, &enc_root) {
-                        Ok(pass) => pass,
-                        Err(fallback_err) => {
+                }
+            }
+
+            if opts.recovery {
+                match cached_recovery_key(recovery_key, ui, timing, cfg) {
+                    Ok(bytes) => {
+                        audit_log(
+                            "UNLOCK_RECOVERY_USED",
+                            &format!("Recovery sigil supplied for {}", enc_root),
+                        );
+                        break (bytes, KeyOrigin::Recovery);
+                    }
+                    Err(err) => {
+                        ui.error(&format!("Recovery sigil refused ({}).", err));
+                        audit_log(
+                            "UNLOCK_RECOVERY_REJECTED",
+                            &format!("attempt {} for {}: {}", attempt, enc_root, err),
+                        );
+                        if attempt < MAX_ATTEMPTS {
+                            lockout.register_failure(ui, timing);
+                            lockout.wait_if_needed(ui, timing);
+                        }
+                        continue 'attempts;
+                    }
+                }
+            }
+
+            if fido2_available && planned.is_none() && (cfg.fido2.primary || !usb_available) {
+                ui.note("Touch the FIDO2 authenticator to derive the key.");
+                timing.pace(Pace::Prompt);
+                match fido2::derive_key(&cfg.fido2) {
+                    Ok(bytes) => {
+                        audit_log("UNLOCK_SOURCE", "Using FIDO2 hmac-secret key material");
+                        break (bytes, KeyOrigin::Fido2);
+                    }
+                    Err(fido_err) => {
+                        audit_log("UNLOCK_FIDO2_UNAVAILABLE", &format!("reason={}", fido_err));
+                        ui.warn(&format!("FIDO2 authenticator unavailable ({}).", fido_err));
+                        fido2_available = false;
+                        continue;
+                    }
+                }
+            }
+
+            if usb_available && !passphrase_turn {
+                match cached_usb_key(usb_key, new_checksum, ui, cfg, opts, &mut timings.checksum) {
+                    Ok(bytes) => {
+                        if !logged_usb_source {
+                            audit_log("UNLOCK_SOURCE", "Using USB key material");
+                            logged_usb_source = true;
+                        }
+                        break (bytes, KeyOrigin::Usb);
+                    }
+                    Err(usb_err)
+                        if classify_failure(&usb_err) == FailureClass::Unavailable
+                            && unavailable_retries < MAX_UNAVAILABLE_RETRIES =>
+                    {
+                        unavailable_retries += 1;
+                        ui.warn(&format!(
+                            "Token read faltered ({:#}); retrying without spending an attempt.",
+                            usb_err
+                        ));
+                        audit_log(
+                            "UNLOCK_KEY_UNAVAILABLE",
+                            &format!("attempt {} for {}: {:#}", attempt, enc_root, usb_err),
+                        );
+                        thread::sleep(UNAVAILABLE_RETRY_DELAY);
+                        continue;
+                    }
+                    Err(usb_err) => {
+                        audit_log("UNLOCK_USB_UNAVAILABLE", &format!("reason={}", usb_err));
+                        usb_available = false;
+                        usb_absent = !key_path.exists();
+                        if planned == Some(KeySource::Usb) {
+                            ui.warn(&format!(
+                                "USB key unavailable ({}); the attempt plan moves on.",
+                                usb_err
+                            ));
+                            continue 'attempts;
+                        }
+                        if fido2_available {
+                            ui.warn(&format!(
+                                "USB key unavailable ({}); consulting the FIDO2 authenticator.",
+                                usb_err
+                            ));
+                            continue;
+                        }
+                        if !fallback_allowed && !break_glass_available {
                             let err = anyhow!(
-                                "USB key unavailable ({}) and fallback failed ({})",
+                                "USB key material unavailable ({}). Strict USB mode forbids fallback. Key path: {}",
                                 usb_err,
-                                fallback_err
+                                key_path.display()
                             );
-                            ui.error(&format!("Unable to obtain key material ({}).", err));
+                            ui.error(&err.to_string());
                             audit_log("UNLOCK_KEY_FETCH_FAIL", &err.to_string());
                             return Err(err);
                         }
-                    };
-                    if passphrase.is_empty() {
-                        let err = anyhow!(
-                            "Fallback passphrase prompt returned empty input in response to USB failure."
-                        );
-                        ui.error(&err.to_string());
-                        audit_log("UNLOCK_KEY_FETCH_FAIL", &err.to_string());
-                        return Err(err);
+                        ui.warn(&format!(
+                            "USB key unavailable ({}); invoking the {} ritual.",
+                            usb_err,
+                            if break_glass_available {
+                                "break-glass"
+                            } else {
+                                "fallback passphrase"
+                            }
+                        ));
+                        fallback_primed = true;
+                        continue;
                     }
-                    let raw_from_pass =
-                        recover_raw_key_from_passphrase(&cfg.fallback, &passphrase)?;
-                    fallback_primed = true;
-                    audit_log("UNLOCK_FALLBACK_USED", "Fallback passphrase requested");
-                    (raw_from_pass, KeyOrigin::Passphrase)
                 }
             }
-        } else if fallback_allowed {
-            if !fallback_primed {
-                ui.warn("USB key rejected; invoking fallback passphrase ritual.");
-                fallback_primed = true;
+
+            if break_glass_available {
+                match break_glass_key(ui, timing, cfg, &enc_root) {
+                    Ok(Some(bytes)) => {
+                        audit_log("UNLOCK_SOURCE", "Using break-glass key material");
+                        break (bytes, KeyOrigin::BreakGlass);
+                    }
+                    Ok(None) => {
+                        break_glass_available = false;
+                        continue;
+                    }
+                    Err(err) => {
+                        ui.error(&format!("Break-glass seal stays shut ({}).", err));
+                        audit_log(
+                            "UNLOCK_BREAK_GLASS_REJECTED",
+                            &format!("attempt {} for {}: {}", attempt, enc_root, err),
+                        );
+                        if attempt < MAX_ATTEMPTS {
+                            lockout.register_failure(ui, timing);
+                            lockout.wait_if_needed(ui, timing);
+                        }
+                        continue 'attempts;
+                    }
+                }
             }
-            timing.pace(Pace::Prompt);
-            let passphrase = match prompt_fallback_passphrase(ui, timing, cfg, &enc_root) {
-                Ok(pass) => pass,
-                Err(fallback_err) => {
-                    let err = anyhow!("Fallback passphrase prompt failed ({})", fallback_err);
-                    ui.error(&format!("Unable to obtain key material ({}).", err));
+
+            if fallback_allowed {
+                if !fallback_primed {
+                    ui.warn("USB key rejected; invoking fallback passphrase ritual.");
+                    fallback_primed = true;
+                }
+                timing.pace(Pace::Prompt);
+                let passphrase =
+                    match prompt_fallback_passphrase(ui, timing, cfg, &enc_root, "fallback") {
+                        Ok(pass) => pass,
+                        Err(fallback_err) => {
+                            let err =
+                                anyhow!("Fallback passphrase prompt failed ({})", fallback_err);
+                            ui.error(&format!("Unable to obtain key material ({}).", err));
+                            audit_log("UNLOCK_KEY_FETCH_FAIL", &err.to_string());
+                            return Err(err);
+                        }
+                    };
+                if passphrase.is_empty() {
+                    let err = anyhow!(
+                        "Fallback passphrase prompt returned empty input; aborting unlock."
+                    );
+                    ui.error(&err.to_string());
                     audit_log("UNLOCK_KEY_FETCH_FAIL", &err.to_string());
                     return Err(err);
                 }
-            };
-            if passphrase.is_empty() {
-                let err =
-                    anyhow!("Fallback passphrase prompt returned empty input; aborting unlock.");
-                ui.error(&err.to_string());
-                audit_log("UNLOCK_KEY_FETCH_FAIL", &err.to_string());
-                return Err(err);
+                audit_log("UNLOCK_FALLBACK_USED", "Fallback passphrase requested");
+                let material = fallback_key_material(zfs, cfg, &enc_root, passphrase)?;
+                break (material, KeyOrigin::Passphrase);
             }
-            audit_log("UNLOCK_FALLBACK_USED", "Fallback passphrase requested");
-            let raw_from_pass = recover_raw_key_from_passphrase(&cfg.fallback, &passphrase)?;
-            (raw_from_pass, KeyOrigin::Passphrase)
-        } else {
+
             let err = anyhow!(
                 "No key material sources remain for {}. USB-only mode is active and fallback is disabled.",
                 enc_root
@@ -190,9 +906,32 @@ pub fn run_unlock(
             return Err(err);
         };
 
-        match zfs.load_key_tree(&enc_root, &key_material[..]) {
-            Ok(unlocked) => {
+        timings.key_fetch += fetch_started.elapsed();
+
+        let load_started = Instant::now();
+        let loaded = zfs.load_key_tree(&enc_root, &key_material[..], &mut timings.tree);
+        timings.loads.push(load_started.elapsed());
+        let loaded = loaded.and_then(|tree| {
+            // The root and the requested dataset must open; other
+            // stragglers only fail the run under require_all_descendants.
+            if cfg.crypto.require_all_descendants
+                || tree.still_sealed.iter().any(|ds| ds == dataset)
+            {
+                tree.strict(&enc_root)
+            } else {
+                Ok(tree)
+            }
+        });
+        match loaded {
+            Ok(tree) => {
+                let sealed_detail = tree.describe_sealed();
+                let KeyTreeOutcome {
+                    fully_unlocked: unlocked,
+                    still_sealed,
+                    ..
+                } = tree;
                 let descendants = unlocked.iter().filter(|ds| *ds != &enc_root).count();
+                timings.descendants = descendants;
                 if descendants > 0 {
                     ui.success(&format!(
                         "Key accepted. Encryption root {} and {} descendant dataset(s) now stand unlocked.",
@@ -206,16 +945,116 @@ pub fn run_unlock(
                 }
                 if matches!(origin, KeyOrigin::Passphrase) {
                     ui.note("Fallback passphrase accepted. Replace or rebuild the beskar key at the earliest opportunity.");
+                } else if matches!(origin, KeyOrigin::BreakGlass) {
+                    ui.note("Break-glass seal used. Rebuild the beskar key and rotate the break-glass passphrase.");
+                    audit_log(
+                        "UNLOCK_BREAK_GLASS_USED",
+                        &format!(
+                            "seal={} root={}",
+                            cfg.usb.break_glass_path.as_deref().unwrap_or("-"),
+                            enc_root
+                        ),
+                    );
+                } else if matches!(origin, KeyOrigin::Recovery) {
+                    ui.warn("Vault opened from the recovery sigil alone. Re-forge a token (`recover` or `init`) before the next boot.");
+                }
+                if !still_sealed.is_empty() {
+                    ui.warn(&format!(
+                        "{} descendant(s) of {} refused the key and stay sealed: {}. Set crypto.require_all_descendants to fail instead.",
+                        still_sealed.len(),
+                        enc_root,
+                        still_sealed.join(", ")
+                    ));
+                    audit_log(
+                        "UNLOCK_PARTIAL",
+                        &format!("{} open; still sealed: [{}]", enc_root, sealed_detail),
+                    );
                 }
                 audit_log(
                     "UNLOCK_OK",
                     &format!(
-                        "Unlocked {} (descendants_unlocked={})",
-                        enc_root, descendants
+                        "Unlocked {} (descendants_unlocked={} datasets=[{}])",
+                        enc_root,
+                        descendants,
+                        unlocked.join(", ")
                     ),
                 );
+                if matches!(origin, KeyOrigin::Usb) {
+                    if let Some(digest) = new_checksum.take() {
+                        record_new_checksum(ui, cfg, &digest);
+                    }
+                }
+                if let Some(spec) = &cfg.crypto.keyring {
+                    deposit_in_keyring(ui, cfg, spec, &enc_root, &key_material[..]);
+                }
+                if cfg.crypto.keyring_cache && !matches!(origin, KeyOrigin::Keyring) {
+                    cache_in_keyring(ui, cfg, &enc_root, &key_material[..]);
+                }
+                let outcome = if still_sealed.is_empty() {
+                    "ok"
+                } else {
+                    "partial"
+                };
+                report_timings(ui, &timings, started.elapsed(), outcome);
                 lockout.reset(ui, timing);
-                return Ok(());
+                return Ok(unlocked);
+            }
+            Err(err) if matches!(origin, KeyOrigin::Keyring) => {
+                // A stale cache (key rotated since) is not an operator
+                // failure: drop it and let the next attempt use the token.
+                if let Err(revoke_err) = keyring::revoke_cached(&enc_root) {
+                    ui.warn(&format!("Unable to revoke stale cache ({}).", revoke_err));
+                }
+                ui.warn(&format!(
+                    "Cached key rejected by {} ({}); consulting the token.",
+                    enc_root, err
+                ));
+                audit_log(
+                    "UNLOCK_KEYRING_CACHE_STALE",
+                    &format!("{} rejected cached key: {}", enc_root, err),
+                );
+                // The operator supplied nothing; the token still gets a full turn.
+                attempt -= 1;
+                timings.attempts = attempt;
+            }
+            Err(err) if classify_failure(&err) == FailureClass::Fatal => {
+                // Retrying, or blaming the key, would only delay the answer.
+                ui.error(&format!("Unlock of {} refused ({:#}).", enc_root, err));
+                audit_log(
+                    "UNLOCK_ABORT",
+                    &format!("attempt {} for {}: {:#}", attempt, enc_root, err),
+                );
+                report_timings(ui, &timings, started.elapsed(), "abort");
+                return Err(err.context(format!("unlock of {} refused by ZFS", enc_root)));
+            }
+            Err(err) if classify_failure(&err) == FailureClass::Unavailable => {
+                // ZFS never judged the key; neither the attempt counter nor
+                // the lockout should move.
+                audit_log(
+                    "UNLOCK_KEY_UNAVAILABLE",
+                    &format!("attempt {} for {}: {:#}", attempt, enc_root, err),
+                );
+                attempt -= 1;
+                timings.attempts = attempt;
+                if unavailable_retries >= MAX_UNAVAILABLE_RETRIES {
+                    ui.error(&format!(
+                        "Key material never reached {} ({:#}).",
+                        enc_root, err
+                    ));
+                    report_timings(ui, &timings, started.elapsed(), "abort");
+                    return Err(anyhow!(
+                        "Unlock of {} failed {} times before ZFS could judge the key: {:#}",
+                        enc_root,
+                        MAX_UNAVAILABLE_RETRIES + 1,
+                        err
+                    ));
+                }
+                unavailable_retries += 1;
+                ui.warn(&format!(
+                    "Unlock attempt on {} faltered before the key was judged ({:#}); retrying.",
+                    enc_root, err
+                ));
+                thread::sleep(UNAVAILABLE_RETRY_DELAY);
             }
             Err(err) => {
                 let err_msg = err.to_string();
@@ -229,21 +1068,57 @@ pub fn run_unlock(
                 );
 
                 let mut trigger_fallback = false;
-                if matches!(origin, KeyOrigin::Usb) && fallback_allowed {
+                if matches!(origin, KeyOrigin::Usb)
+                    && (fallback_allowed || fido2_available || break_glass_available)
+                {
                     audit_log(
                         "UNLOCK_USB_REJECTED",
                         &format!("{} rejected USB key: {}", enc_root, err_msg),
                     );
                     usb_available = false;
                     trigger_fallback = true;
-                } else if err_msg.contains("Key already loaded") {
+                } else if matches!(origin, KeyOrigin::Fido2) {
+                    audit_log(
+                        "UNLOCK_FIDO2_REJECTED",
+                        &format!("{} rejected FIDO2 key: {}", enc_root, err_msg),
+                    );
+                    fido2_available = false;
+                    trigger_fallback = usb_available || fallback_allowed;
+                } else if matches!(origin, KeyOrigin::Recovery) {
+                    audit_log(
+                        "UNLOCK_RECOVERY_REJECTED",
+                        &format!("{} rejected recovery key: {}", enc_root, err_msg),
+                    );
+                    *recovery_key = None;
+                } else if matches!(origin, KeyOrigin::BreakGlass) {
+                    audit_log(
+                        "UNLOCK_BREAK_GLASS_REJECTED",
+                        &format!("{} rejected break-glass key: {}", enc_root, err_msg),
+                    );
+                    ui.warn("The break-glass seal wraps a stale key; rerun set-break-glass after rotation.");
+                    break_glass_available = false;
+                    trigger_fallback = fallback_allowed;
+                } else if is_zfs_error(&err, &ZfsError::KeyAlreadyLoaded) {
                     ui.note("ZFS reports the key was already resident; verification deferred to the self-test.");
                     audit_log(
                         "UNLOCK_OK_ALREADY",
                         &format!("{} reports key already loaded", enc_root),
                     );
+                    report_timings(ui, &timings, started.elapsed(), "already");
                     lockout.reset(ui, timing);
-                    return Ok(());
+                    return Ok(Vec::new());
+                } else if matches!(origin, KeyOrigin::Stdin) {
+                    // The pipe is drained; retrying would only replay the same key.
+                    audit_log(
+                        "UNLOCK_STDIN_REJECTED",
+                        &format!("{} rejected stdin key: {}", enc_root, err_msg),
+                    );
+                    report_timings(ui, &timings, started.elapsed(), "abort");
+                    return Err(anyhow!(
+                        "{} rejected the key piped on stdin ({})",
+                        enc_root,
+                        err_msg
+                    ));
                 }
 
                 if attempt < MAX_ATTEMPTS {
@@ -268,6 +1143,7 @@ pub fn run_unlock(
         ),
     );
     ui.error("Unlock failed after exhausting the maximum retry attempts.");
+    report_timings(ui, &timings, started.elapsed(), "abort");
     Err(anyhow!(
         "Unlock failed after {} attempts for {}",
         MAX_ATTEMPTS,
@@ -275,112 +1151,664 @@ pub fn run_unlock(
     ))
 }
 
+/// `[fallback] order` as one root may follow it: passphrase turns are dropped
+/// when neither the break-glass seal nor the fallback may be offered.
+fn attempt_plan(order: &[KeySource], passphrase_allowed: bool) -> Vec<KeySource> {
+    order
+        .iter()
+        .copied()
+        .filter(|source| passphrase_allowed || *source != KeySource::Passphrase)
+        .collect()
+}
+
+/// Why an attempt ended without opening the root. Only `Rejected` counts
+/// toward `MAX_ATTEMPTS` and escalates the lockout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FailureClass {
+    /// Key material was presented and refused (by ZFS or a local check), or
+    /// the failure is one the fallback path should handle.
+    Rejected,
+    /// No key material was judged because the token read or the `zfs` call
+    /// faltered; a short retry may heal it.
+    Unavailable,
+    /// ZFS refused the request itself (no such dataset, not permitted, busy):
+    /// no key would change the answer, so the unlock stops at once.
+    Fatal,
+}
+
+fn classify_failure(err: &anyhow::Error) -> FailureClass {
+    for cause in err.chain() {
+        if let Some(zfs_err) = cause.downcast_ref::<ZfsError>() {
+            return match zfs_err {
+                ZfsError::IncorrectKey | ZfsError::KeyAlreadyLoaded => FailureClass::Rejected,
+                ZfsError::DatasetNotFound | ZfsError::PermissionDenied | ZfsError::Busy => {
+                    FailureClass::Fatal
+                }
+                ZfsError::Other { .. } => FailureClass::Unavailable,
+            };
+        }
+        if let Some(io_err) = cause.downcast_ref::<std::io::Error>() {
+            // A missing file or a read-only mount stays that way; leave it
+            // to the usual fallback path.
+            return match io_err.kind() {
+                ErrorKind::NotFound
+                | ErrorKind::PermissionDenied
+                | ErrorKind::ReadOnlyFilesystem
+                | ErrorKind::InvalidData => FailureClass::Rejected,
+                _ => FailureClass::Unavailable,
+            };
+        }
+    }
+    FailureClass::Rejected
+}
+
 enum KeyOrigin {
     Usb,
+    Fido2,
+    BreakGlass,
     Passphrase,
+    Recovery,
+    Keyring,
+    Stdin,
 }
 
-fn load_usb_key_material(ui: &UX, cfg: &ConfigFile) -> Result<Zeroizing<Vec<u8>>> {
-    let key_path = Path::new(&cfg.usb.key_hex_path);
-    if !key_path.exists() {
-        return Err(anyhow!("Key file not found: {}", key_path.display()));
+/// `[crypto] keyring` handoff. The pool is already open, so a refusal here
+/// is reported but never fails the unlock.
+fn deposit_in_keyring(ui: &UX, cfg: &ConfigFile, spec: &str, enc_root: &str, key: &[u8]) {
+    let description = keyring::description_for(enc_root);
+    let secs = cfg.crypto.keyring_secs;
+    match keyring::parse_target(spec)
+        .and_then(|t| keyring::deposit_key(&t, &description, key, secs))
+    {
+        Ok(serial) => {
+            ui.info(&format!(
+                "Key filed in keyring {} as {} for {}s.",
+                spec, description, secs
+            ));
+            audit_log(
+                "UNLOCK_KEYRING",
+                &format!(
+                    "{} deposited in {} (serial {}, timeout {}s)",
+                    description, spec, serial, secs
+                ),
+            );
+        }
+        Err(err) => {
+            ui.warn(&format!("Keyring handoff to {} refused ({}).", spec, err));
+            audit_log("UNLOCK_KEYRING_FAIL", &format!("{}: {}", spec, err));
+        }
     }
+}
 
-    let material = ensure_raw_key_file(key_path)
-        .with_context(|| format!("normalize key file {}", key_path.display()))?;
-    if material.encoding == KeyEncoding::Hex {
+/// `[crypto] keyring_cache`: keep the key for later runs this boot. A refusal
+/// is reported but never fails the unlock.
+fn cache_in_keyring(ui: &UX, cfg: &ConfigFile, enc_root: &str, key: &[u8]) {
+    match keyring::cache_key(enc_root, key, cfg.crypto.keyring_cache_secs) {
+        Ok(serial) => audit_log(
+            "UNLOCK_KEYRING_CACHE",
+            &format!(
+                "{} cached (serial {}, {}s)",
+                enc_root, serial, cfg.crypto.keyring_cache_secs
+            ),
+        ),
+        Err(err) => {
+            ui.warn(&format!("Keyring cache refused ({}).", err));
+            audit_log(
+                "UNLOCK_KEYRING_CACHE_FAIL",
+                &format!("{}: {}", enc_root, err),
+            );
+        }
+    }
+}
+
+/// Audit the phase split, trace it, and under `--json` or `--verbose` show it.
+fn report_timings(ui: &UX, timings: &UnlockTimings, total: Duration, outcome: &str) {
+    let ms = |d: Duration| d.as_millis();
+    let loads: Vec<String> = timings.loads.iter().map(|d| ms(*d).to_string()).collect();
+    let detail = format!(
+        "outcome={} attempts={} key_fetch_ms={} checksum_ms={} root_load_ms={} descendant_load_ms={} loads_ms={} descendants={} total_ms={}",
+        outcome,
+        timings.attempts,
+        ms(timings.key_fetch),
+        ms(timings.checksum),
+        ms(timings.tree.root),
+        ms(timings.tree.descendants),
+        if loads.is_empty() { "-".to_string() } else { loads.join(",") },
+        timings.descendants,
+        ms(total)
+    );
+    ui.trace(&format!("unlock timings: {}", detail));
+    audit_log("UNLOCK_TIMINGS", &detail);
+    if ui.json_mode() {
+        println!(
+            "{{\"event\":\"unlock_timings\",\"outcome\":\"{}\",\"attempts\":{},\"key_fetch_ms\":{},\"checksum_ms\":{},\"root_load_ms\":{},\"descendant_load_ms\":{},\"loads_ms\":[{}],\"descendants\":{},\"total_ms\":{}}}",
+            outcome,
+            timings.attempts,
+            ms(timings.key_fetch),
+            ms(timings.checksum),
+            ms(timings.tree.root),
+            ms(timings.tree.descendants),
+            loads.join(","),
+            timings.descendants,
+            ms(total)
+        );
+    } else if ui.verbose {
+        let fmt = |d: Duration| format!("{} ms", d.as_millis());
+        ui.data_panel(
+            "Unlock timings",
+            &[
+                ("Attempts", timings.attempts.to_string()),
+                ("Key fetch", fmt(timings.key_fetch)),
+                ("Checksum", fmt(timings.checksum)),
+                ("Root load-key", fmt(timings.tree.root)),
+                (
+                    "Descendants",
+                    format!(
+                        "{} ({} dataset(s))",
+                        fmt(timings.tree.descendants),
+                        timings.descendants
+                    ),
+                ),
+                (
+                    "Load-key calls",
+                    timings
+                        .loads
+                        .iter()
+                        .map(|d| fmt(*d))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+                ("Total", fmt(total)),
+            ],
+        );
+    }
+}
+
+fn load_usb_key_material(
+    ui: &UX,
+    cfg: &ConfigFile,
+    opts: UnlockOptions,
+    new_checksum: &mut Option<String>,
+    checksum_time: &mut Duration,
+) -> Result<Zeroizing<Vec<u8>>> {
+    let key_path = token_source(cfg);
+    let mut self_mounted = None;
+    if !key_path.exists() && !await_key_file(ui, cfg, key_path, &mut self_mounted) {
+        return Err(match cfg.usb.raw_device {
+            Some(_) => anyhow!("Raw token not found: {}", key_path.display()),
+            None => anyhow!("Key file not found: {}", key_path.display()),
+        });
+    }
+    if cfg.usb.require_serial.is_some() || cfg.usb.require_vidpid.is_some() {
+        verify_token_device(ui, cfg, key_path)?;
+    }
+
+    let raw = if cfg.usb.raw_device.is_some() {
+        let raw = rawtoken::read_key(key_path, cfg.usb.raw_offset)?;
         ui.info(&format!(
-            "Legacy hex key at {} converted to raw bytes for initramfs parity.",
-            key_path.display()
+            "Key record read from raw token {} at offset {}.",
+            key_path.display(),
+            cfg.usb.raw_offset
         ));
+        raw
+    } else {
+        let material = if cfg.usb.strict_keyfile {
+            ensure_raw_key_file_strict(key_path)
+        } else {
+            ensure_raw_key_file(key_path)
+        }
+        .with_context(|| format!("normalize key file {}", key_path.display()))?;
+        if material.encoding == KeyEncoding::Hex {
+            ui.info(&format!(
+                "Legacy hex key at {} converted to raw bytes for initramfs parity.",
+                key_path.display()
+            ));
+        }
+        material.raw
+    };
+    if let Some(token) = self_mounted.take() {
+        ui.info(&format!("Key read; releasing {}.", token.device()));
     }
 
+    let algo = cfg.usb.checksum_algo;
     if let Some(expected) = &cfg.usb.expected_sha256 {
-        let mut hasher = Sha256::new();
-        hasher.update(&*material.raw);
-        let actual = hex::encode(hasher.finalize());
-        if !actual.eq_ignore_ascii_case(expected) {
-            return Err(anyhow!(
+        let checksum_started = Instant::now();
+        let matched = algo.matches(&raw, expected);
+        *checksum_time += checksum_started.elapsed();
+        if matched {
+            ui.success(&format!(
+                "USB key checksum verified ({} match).",
+                algo.label()
+            ));
+            audit_log("UNLOCK_CHECKSUM", "Checksum verified successfully");
+        } else {
+            let found = algo.digest_hex(&raw);
+            let mismatch = anyhow!(
                 "USB key checksum mismatch (expected {}, found {})",
                 expected,
-                actual
-            ));
+                found
+            );
+            if !opts.accept_new_checksum {
+                return Err(mismatch);
+            }
+            ui.warn(&format!("{}.", mismatch));
+            ui.note("--accept-new-checksum: ZFS will judge this key; the new checksum is recorded only if it opens the vault.");
+            if !opts.yes {
+                require_terminal("the --accept-new-checksum confirmation; pass --yes")?;
+                let trusted = Confirm::new()
+                    .with_prompt("Trust this token and let ZFS judge its key?")
+                    .default(false)
+                    .interact()
+                    .context("checksum confirmation failed")?;
+                if !trusted {
+                    return Err(mismatch.context("new checksum not accepted"));
+                }
+            }
+            audit_log(
+                "UNLOCK_CHECKSUM_MISMATCH_ACCEPTED",
+                &format!("expected={} found={}", expected, found),
+            );
+            *new_checksum = Some(found);
         }
-        ui.success("USB key checksum verified (SHA-256 match).");
-        audit_log("UNLOCK_CHECKSUM", "Checksum verified successfully");
     } else {
-        ui.warn(
-            "No reference SHA-256 recorded in config.usb.expected_sha256 — authenticity check skipped.",
-        );
+        ui.warn(&format!(
+            "No reference {} recorded in config.usb.expected_sha256 — authenticity check skipped.",
+            algo.label()
+        ));
         audit_log("UNLOCK_CHECKSUM_SKIP", "Checksum skipped; field not set");
     }
 
-    Ok(material.raw)
+    Ok(raw)
+}
+
+/// Where the token key lives: the raw partition when `[usb] raw_device` is
+/// set, otherwise the key file on the mounted token.
+fn token_source(cfg: &ConfigFile) -> &Path {
+    Path::new(
+        cfg.usb
+            .raw_device
+            .as_deref()
+            .unwrap_or(&cfg.usb.key_hex_path),
+    )
+}
+
+/// Trust-on-first-use after a token restore: the key just opened the vault,
+/// so its checksum replaces `usb.expected_sha256`. The unlock already
+/// succeeded, so a failed write only warns.
+fn record_new_checksum(ui: &UX, cfg: &ConfigFile, digest: &str) {
+    let previous = cfg.usb.expected_sha256.clone().unwrap_or_default();
+    let mut updated = cfg.clone();
+    updated.usb.expected_sha256 = Some(digest.to_string());
+    match persist_config(&updated) {
+        Ok(()) => {
+            ui.security(&format!(
+                "CHECKSUM ROTATED: {} now trusts {} (was {}).",
+                cfg.path.display(),
+                digest,
+                previous
+            ));
+            audit_log(
+                "CHECKSUM_ROTATED",
+                &format!(
+                    "algo={} old={} new={}",
+                    cfg.usb.checksum_algo.label(),
+                    previous,
+                    digest
+                ),
+            );
+        }
+        Err(err) => ui.warn(&format!(
+            "Key accepted, but the new checksum could not be recorded ({}); set usb.expected_sha256 = \"{}\" by hand.",
+            err, digest
+        )),
+    }
+}
+
+/// `[usb] require_serial` / `require_vidpid`: the device mounted under the key
+/// path (or the raw token itself) must carry the pinned USB identity before
+/// its key is read. Anything unverifiable (no backing device, no udevadm) is
+/// refused too.
+fn verify_token_device(ui: &UX, cfg: &ConfigFile, key_path: &Path) -> Result<()> {
+    let backing = match cfg.usb.raw_device {
+        Some(_) => Some(key_path.display().to_string()),
+        None => usbid::mount_source(key_path),
+    };
+    let identity = backing
+        .ok_or_else(|| anyhow!("no mounted block device backs {}", key_path.display()))
+        .and_then(|device| usbid::query(&device).map(|identity| (device, identity)));
+    let problem = match &identity {
+        Ok((device, identity)) => identity
+            .mismatch(
+                cfg.usb.require_serial.as_deref(),
+                cfg.usb.require_vidpid.as_deref(),
+            )
+            .map(|why| format!("token device {}: {}", device, why)),
+        Err(err) => Some(format!("token device unverifiable: {}", err)),
+    };
+    if let Some(problem) = problem {
+        ui.security(&format!(
+            "Token identity rejected ({}). The key on it will not be used.",
+            problem
+        ));
+        audit_log("UNLOCK_DEVICE_MISMATCH", &problem);
+        return Err(anyhow!("USB token identity mismatch: {}", problem));
+    }
+    if let Ok((device, _)) = identity {
+        ui.info(&format!(
+            "Token device {} matches the pinned USB identity.",
+            device
+        ));
+    }
+    Ok(())
 }
 
+/// Give a late token until `[usb] wait_secs`: nudge the mount unit, mount the
+/// token by label, settle udev, then poll. The wait is audited either way.
+fn await_key_file(
+    ui: &UX,
+    cfg: &ConfigFile,
+    key_path: &Path,
+    self_mounted: &mut Option<MountedToken>,
+) -> bool {
+    let started = Instant::now();
+    let deadline = Duration::from_secs(cfg.usb.wait_secs);
+    // A raw token has nothing to mount; only udev can bring it in.
+    let mut found = cfg.usb.raw_device.is_none()
+        && (await_runtime_mount(ui, key_path) || {
+            *self_mounted = mount_token_if_needed(ui, cfg, key_path);
+            self_mounted.is_some()
+        });
+    if !found && started.elapsed() < deadline {
+        let _ = settle_udev(ui);
+        while started.elapsed() < deadline {
+            if key_path.exists() {
+                found = true;
+                break;
+            }
+            thread::sleep(KEY_POLL_INTERVAL);
+        }
+    }
+    audit_log(
+        "UNLOCK_TOKEN_WAIT",
+        &format!(
+            "{} {} after {} ms (budget {}s)",
+            key_path.display(),
+            if found { "appeared" } else { "still absent" },
+            started.elapsed().as_millis(),
+            cfg.usb.wait_secs
+        ),
+    );
+    found
+}
+
+/// Nudge `run-beskar.mount` and re-check the key path a bounded number of
+/// times, so a boot-time mount race doesn't drop straight to the passphrase.
+fn await_runtime_mount(ui: &UX, key_path: &Path) -> bool {
+    for attempt in 1..=MOUNT_RETRY_ATTEMPTS {
+        ui.warn(&format!(
+            "{} absent; triggering {} (attempt {}/{}).",
+            key_path.display(),
+            USB_MOUNT_UNIT,
+            attempt,
+            MOUNT_RETRY_ATTEMPTS
+        ));
+        let started = systemctl(Duration::from_secs(5))
+            .and_then(|cmd| cmd.run(&["start", USB_MOUNT_UNIT], None));
+        let detail = match &started {
+            Ok(out) if out.status == 0 => "mount unit started".to_string(),
+            Ok(out) => format!("systemctl status {}: {}", out.status, out.stderr.trim()),
+            Err(err) => format!("systemctl unavailable: {}", err),
+        };
+        audit_log(
+            "UNLOCK_MOUNT_RETRY",
+            &format!(
+                "attempt {}/{} for {}: {}",
+                attempt,
+                MOUNT_RETRY_ATTEMPTS,
+                key_path.display(),
+                detail
+            ),
+        );
+        thread::sleep(MOUNT_RETRY_DELAY);
+        if key_path.exists() {
+            ui.success(&format!(
+                "{} surfaced after {} retr{}.",
+                key_path.display(),
+                attempt,
+                if attempt == 1 { "y" } else { "ies" }
+            ));
+            return true;
+        }
+    }
+    false
+}
+
+/// Ask for a passphrase through the configured backend. `label` names it in
+/// every prompt ("fallback", "break-glass").
 fn prompt_fallback_passphrase(
     ui: &UX,
     timing: &Timing,
     cfg: &ConfigFile,
     enc_root: &str,
+    label: &str,
 ) -> Result<Zeroizing<Vec<u8>>> {
     ui.note(&format!(
-        "Fallback activation: provide the passphrase for {}.",
-        enc_root
+        "Passphrase activation: provide the {} passphrase for {}.",
+        label, enc_root
     ));
     timing.pace(Pace::Prompt);
 
-    if cfg.fallback.askpass {
-        if let Some(path) = cfg.fallback.askpass_path.as_deref() {
-            if Path::new(path).exists() {
-                if let Ok(cmd) = Cmd::new_allowlisted(path, Duration::from_secs(90)) {
-                    let prompt = format!("Beskar fallback passphrase for {}", enc_root);
-                    match cmd.run(&["--timeout=90", &prompt], None) {
-                        Ok(out) if out.status == 0 => {
-                            let cleaned = out.stdout.trim_end_matches(['\n', '\r']).to_string();
-                            if !cleaned.is_empty() {
-                                ui.info("Passphrase captured via systemd-ask-password.");
-                                return Ok(Zeroizing::new(cleaned.into_bytes()));
-                            }
-                            ui.warn("Fallback prompt returned empty response.");
-                        }
-                        Ok(out) => {
-                            ui.warn(&format!(
-                                "systemd-ask-password exited with status {}: {}",
-                                out.status, out.stderr
-                            ));
-                        }
-                        Err(err) => {
-                            ui.warn(&format!(
-                                "Unable to invoke systemd-ask-password at {}: {}",
-                                path, err
-                            ));
-                        }
-                    }
-                } else {
-                    ui.warn(&format!(
-                        "systemd-ask-password at {} is not allowlisted.",
-                        path
-                    ));
-                }
-            } else {
-                ui.warn(&format!("Configured ask-password path {} not found.", path));
-            }
+    let backend = cfg.fallback.prompt_backend;
+    if backend == PromptBackend::Plymouth || (backend == PromptBackend::Auto && plymouth_running())
+    {
+        if let Some(passphrase) = ask_via_plymouth(ui, enc_root, label) {
+            return Ok(passphrase);
+        }
+        if backend == PromptBackend::Plymouth {
+            return Err(anyhow!(
+                "plymouth ask-for-password yielded no passphrase (fallback.prompt_backend = plymouth)."
+            ));
         }
     }
 
+    if backend == PromptBackend::Systemd || (backend == PromptBackend::Auto && cfg.fallback.askpass)
+    {
+        if let Some(passphrase) = ask_via_systemd(ui, cfg, enc_root, label) {
+            return Ok(passphrase);
+        }
+        if backend == PromptBackend::Systemd {
+            return Err(anyhow!(
+                "systemd-ask-password yielded no passphrase (fallback.prompt_backend = systemd)."
+            ));
+        }
+    }
+
+    if initramfs::detected() {
+        return Err(anyhow!(
+            "No interactive passphrase prompt inside the initramfs; configure fallback.askpass or fallback.prompt_backend."
+        ));
+    }
+    if !interactive_terminal() {
+        return Err(anyhow!(
+            "no interactive terminal available for the {} passphrase; configure fallback.askpass or fallback.prompt_backend",
+            label
+        ));
+    }
+
     // Interactive fallback via dialoguer
-    let prompt = format!("Enter fallback passphrase for {}", enc_root);
+    let prompt = format!("Enter {} passphrase for {}", label, enc_root);
     let passphrase = Password::new()
         .with_prompt(prompt)
         .allow_empty_password(false)
         .interact()
         .context("interactive fallback passphrase prompt failed")?;
 
-    Ok(Zeroizing::new(passphrase.into_bytes()))
+    Ok(secret_line(passphrase))
+}
+
+/// True when a plymouth daemon owns the console (its splash would hide any
+/// other prompt).
+fn plymouth_running() -> bool {
+    PLYMOUTH_PID_FILES.iter().any(|p| Path::new(p).exists()) && plymouth_binary().is_some()
 }
 
-fn recover_raw_key_from_passphrase(
+fn plymouth_binary() -> Option<&'static str> {
+    PLYMOUTH_BINARIES
+        .iter()
+        .copied()
+        .find(|p| Path::new(p).exists())
+}
+
+/// `plymouth ask-for-password`; `None` (with a warning) when it is missing,
+/// fails, or returns nothing.
+fn ask_via_plymouth(ui: &UX, enc_root: &str, label: &str) -> Option<Zeroizing<Vec<u8>>> {
+    let Some(path) = plymouth_binary() else {
+        ui.warn("plymouth not found; skipping the splash prompt.");
+        return None;
+    };
+    let cmd = match Cmd::new_allowlisted(path, ASKPASS_TIMEOUT) {
+        Ok(cmd) => cmd,
+        Err(err) => {
+            ui.warn(&format!(
+                "plymouth at {} is not allowlisted ({}).",
+                path, err
+            ));
+            return None;
+        }
+    };
+    let prompt = format!("--prompt=Beskar {} passphrase for {}", label, enc_root);
+    match cmd.run_secret(&["ask-for-password", &prompt], None) {
+        Ok(out) if out.status == 0 => {
+            let passphrase = secret_bytes_line(out.stdout);
+            if !passphrase.is_empty() {
+                ui.info("Passphrase captured via plymouth.");
+                return Some(passphrase);
+            }
+            ui.warn("plymouth prompt returned empty response.");
+        }
+        Ok(out) => ui.warn(&format!(
+            "plymouth ask-for-password exited with status {}: {}",
+            out.status,
+            out.stderr.trim()
+        )),
+        Err(err) => ui.warn(&format!("Unable to invoke plymouth at {}: {}", path, err)),
+    }
+    None
+}
+
+/// `systemd-ask-password` at `fallback.askpass_path`; `None` (with a warning)
+/// when it is missing, fails, or returns nothing.
+fn ask_via_systemd(
+    ui: &UX,
+    cfg: &ConfigFile,
+    enc_root: &str,
+    label: &str,
+) -> Option<Zeroizing<Vec<u8>>> {
+    let Some(path) = cfg.fallback.askpass_path.as_deref() else {
+        ui.warn("fallback.askpass_path is unset; skipping systemd-ask-password.");
+        return None;
+    };
+    if !Path::new(path).exists() {
+        ui.warn(&format!("Configured ask-password path {} not found.", path));
+        return None;
+    }
+    let Ok(cmd) = Cmd::new_allowlisted(path, ASKPASS_TIMEOUT).map(Cmd::in_foreground) else {
+        ui.warn(&format!(
+            "systemd-ask-password at {} is not allowlisted.",
+            path
+        ));
+        return None;
+    };
+    let prompt = format!("Beskar {} passphrase for {}", label, enc_root);
+    let timeout = format!("--timeout={}", ASKPASS_TIMEOUT.as_secs());
+    match cmd.run_secret(&[&timeout, &prompt], None) {
+        Ok(out) if out.status == 0 => {
+            let passphrase = secret_bytes_line(out.stdout);
+            if !passphrase.is_empty() {
+                ui.info("Passphrase captured via systemd-ask-password.");
+                return Some(passphrase);
+            }
+            ui.warn("Fallback prompt returned empty response.");
+        }
+        Ok(out) => {
+            ui.warn(&format!(
+                "systemd-ask-password exited with status {}: {}",
+                out.status, out.stderr
+            ));
+        }
+        Err(err) => {
+            ui.warn(&format!(
+                "Unable to invoke systemd-ask-password at {}: {}",
+                path, err
+            ));
+        }
+    }
+    None
+}
+
+/// Unwrap `[usb] break_glass_path`. `Ok(None)` when the seal is unreadable
+/// (the token is gone too) or no passphrase was given; an error when the
+/// passphrase does not open it.
+fn break_glass_key(
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    enc_root: &str,
+) -> Result<Option<Zeroizing<Vec<u8>>>> {
+    let Some(path) = cfg.usb.break_glass_path.as_deref() else {
+        return Ok(None);
+    };
+    let blob = match fs::read_to_string(path) {
+        Ok(blob) => blob,
+        Err(err) => {
+            audit_log(
+                "UNLOCK_BREAK_GLASS_UNAVAILABLE",
+                &format!("{}: {}", path, err),
+            );
+            ui.note(&format!("Break-glass seal {} unavailable ({}).", path, err));
+            return Ok(None);
+        }
+    };
+    ui.warn("Primary key unavailable; the break-glass seal answers instead.");
+    timing.pace(Pace::Prompt);
+    let passphrase = match prompt_fallback_passphrase(ui, timing, cfg, enc_root, "break-glass") {
+        Ok(passphrase) if !passphrase.is_empty() => passphrase,
+        Ok(_) => return Ok(None),
+        Err(err) => {
+            ui.warn(&format!("Break-glass prompt failed ({}).", err));
+            return Ok(None);
+        }
+    };
+    audit_log(
+        "UNLOCK_BREAK_GLASS_ATTEMPT",
+        &format!("seal={} root={}", path, enc_root),
+    );
+    breakglass::open(&blob, &passphrase).map(Some)
+}
+
+/// What `zfs load-key` expects for the fallback passphrase: the passphrase
+/// itself for `keyformat=passphrase` roots, otherwise (Beskar forges `raw`)
+/// the 32-byte key recovered through the enrolled PBKDF2 salt.
+fn fallback_key_material(
+    zfs: &Zfs,
+    cfg: &ConfigFile,
+    enc_root: &str,
+    passphrase: Zeroizing<Vec<u8>>,
+) -> Result<Zeroizing<Vec<u8>>> {
+    match zfs.get_property(enc_root, "keyformat") {
+        Ok(format) if format == "passphrase" => Ok(passphrase),
+        Ok(_) => recover_raw_key_from_passphrase(&cfg.fallback, &passphrase),
+        Err(err) => {
+            audit_log(
+                "UNLOCK_KEYFORMAT_UNKNOWN",
+                &format!("{}: {}; assuming raw", enc_root, err),
+            );
+            recover_raw_key_from_passphrase(&cfg.fallback, &passphrase)
+        }
+    }
+}
+
+pub(crate) fn recover_raw_key_from_passphrase(
     fallback: &Fallback,
     passphrase: &[u8],
 ) -> Result<Zeroizing<Vec<u8>>> {
@@ -402,7 +1830,7 @@ fn recover_raw_key_from_passphrase(
     let salt = hex::decode(salt_hex).context("decode fallback salt hex")?;
     let cipher = hex::decode(xor_hex).context("decode fallback key data")?;
 
-    if cipher.len() != 32 {
+    if cipher.len() != KEY_LEN {
         return Err(anyhow!(
             "Fallback key data length mismatch ({} bytes).",
             cipher.len()
@@ -420,3 +1848,73 @@ fn recover_raw_key_from_passphrase(
         .collect();
     Ok(Zeroizing::new(raw))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{attempt_plan, classify_failure, mount_skip_reason, FailureClass};
+    use crate::config::KeySource::{Passphrase, Usb};
+    use crate::zfs::{MountState, ZfsError};
+    use anyhow::{anyhow, Context};
+    use std::io::{Error, ErrorKind};
+
+    #[test]
+    fn mount_leaves_boot_environments_root_and_busy_mountpoints_alone() {
+        let state = |name: &str, canmount: &str, mountpoint: &str| MountState {
+            name: name.to_string(),
+            canmount: canmount.to_string(),
+            mounted: false,
+            mountpoint: mountpoint.to_string(),
+            keystatus: "available".to_string(),
+        };
+        let in_use = vec!["/".to_string(), "/srv".to_string()];
+        let named = state("tank/data", "noauto", "/data");
+        assert_eq!(mount_skip_reason(&named, true, &in_use), None);
+        assert_eq!(
+            mount_skip_reason(&named, false, &in_use).as_deref(),
+            Some("canmount=noauto")
+        );
+        let be = state("rpool/ROOT/ubuntu_x", "noauto", "/");
+        assert_eq!(
+            mount_skip_reason(&be, true, &in_use).as_deref(),
+            Some("mountpoint=/")
+        );
+        let busy = state("tank/srv", "on", "/srv");
+        assert_eq!(
+            mount_skip_reason(&busy, false, &in_use).as_deref(),
+            Some("/srv already in use")
+        );
+    }
+
+    #[test]
+    fn only_judged_keys_count_as_rejections() {
+        let wrong = anyhow::Error::new(ZfsError::IncorrectKey).context("zfs load-key rpool");
+        assert_eq!(classify_failure(&wrong), FailureClass::Rejected);
+        let died = anyhow::Error::new(ZfsError::Other {
+            stderr: "timed out".into(),
+        });
+        assert_eq!(classify_failure(&died), FailureClass::Unavailable);
+        for refused in [ZfsError::DatasetNotFound, ZfsError::PermissionDenied] {
+            let refused = anyhow::Error::new(refused).context("zfs load-key rpool");
+            assert_eq!(classify_failure(&refused), FailureClass::Fatal);
+        }
+
+        let eio = Err::<(), _>(Error::other("Input/output error"))
+            .context("read key file /run/beskar/key")
+            .unwrap_err();
+        assert_eq!(classify_failure(&eio), FailureClass::Unavailable);
+        let missing = anyhow::Error::new(Error::from(ErrorKind::NotFound));
+        assert_eq!(classify_failure(&missing), FailureClass::Rejected);
+        assert_eq!(
+            classify_failure(&anyhow!("USB key checksum mismatch")),
+            FailureClass::Rejected
+        );
+    }
+
+    #[test]
+    fn strict_roots_drop_passphrase_turns_from_the_plan() {
+        let order = [Usb, Usb, Passphrase];
+        assert_eq!(attempt_plan(&order, true), order);
+        assert_eq!(attempt_plan(&order, false), [Usb, Usb]);
+        assert!(attempt_plan(&[], true).is_empty());
+    }
+}
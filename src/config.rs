@@ -2,11 +2,25 @@
 // src/config.rs – strict config loader (aligned with CLI UX system)
 // ============================================================================
 
-use anyhow::{Context, Result};
+use crate::util::atomic::atomic_write_bytes;
+use crate::util::audit::audit_log;
+use crate::util::checksum::ChecksumAlgo;
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::env;
 use std::fs;
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/zfs-beskar.toml";
+const DEFAULT_YAML_CONFIG_PATH: &str = "/etc/zfs-beskar.yaml";
+const CONFIG_ENV: &str = "BESKAR_CONFIG";
+
+/// Schema version written by this build. Configs without a `version` key are
+/// version 0 and are brought forward by `ConfigFile::migrate` on load.
+pub const CONFIG_VERSION: u32 = 1;
+
 // ----------------------------------------------------------------------------
 // Policy Section
 // ----------------------------------------------------------------------------
@@ -27,6 +41,81 @@ pub struct Policy {
     /// Allow root context execution (advanced users)
     #[serde(default)]
     pub allow_root: bool,
+
+    /// Absolute paths admitted to the command allowlist for unusual layouts
+    /// (e.g. /usr/local/sbin/mkfs.ext4); must be root-owned, not group/world-writable.
+    #[serde(default)]
+    pub extra_allowed_binaries: Vec<String>,
+
+    /// Abort `auto-unlock` when a target pool is not ONLINE instead of
+    /// warning and carrying on.
+    #[serde(default)]
+    pub require_healthy_pool: bool,
+
+    /// Per-dataset postures (`[[policy.dataset_overrides]]`), matched by
+    /// encryption root.
+    #[serde(default)]
+    pub dataset_overrides: Vec<DatasetOverride>,
+
+    /// How `init` hands over the recovery sigil (stdout, file, qr)
+    #[serde(default)]
+    pub recovery_display: RecoveryDisplay,
+
+    /// Root-only file the sigil is written to under `recovery_display = "file"`
+    #[serde(default)]
+    pub recovery_file: Option<String>,
+}
+
+/// Where `init` shows the recovery sigil (`[policy] recovery_display`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecoveryDisplay {
+    /// Printed inline, where it stays in scrollback.
+    #[default]
+    Stdout,
+    /// Written 0600 to `recovery_file`; only the path is printed.
+    File,
+    /// A terminal QR code, cleared once the operator confirms the capture.
+    Qr,
+}
+
+impl RecoveryDisplay {
+    pub fn label(self) -> &'static str {
+        match self {
+            RecoveryDisplay::Stdout => "stdout",
+            RecoveryDisplay::File => "file",
+            RecoveryDisplay::Qr => "qr",
+        }
+    }
+}
+
+/// Unlock posture for one dataset, overriding the global settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetOverride {
+    /// Dataset or encryption root this entry governs.
+    pub name: String,
+
+    /// Forbid the passphrase fallback for this encryption root.
+    #[serde(default)]
+    pub strict_usb: bool,
+}
+
+impl Policy {
+    /// The override governing `enc_root`: an entry naming it directly, else
+    /// one whose dataset `resolve_root` maps onto it.
+    pub fn override_for<F>(&self, enc_root: &str, resolve_root: F) -> Option<&DatasetOverride>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        self.dataset_overrides
+            .iter()
+            .find(|o| o.name == enc_root)
+            .or_else(|| {
+                self.dataset_overrides
+                    .iter()
+                    .find(|o| resolve_root(&o.name).as_deref() == Some(enc_root))
+            })
+    }
 }
 
 // ----------------------------------------------------------------------------
@@ -38,16 +127,54 @@ pub struct CryptoCfg {
     /// Timeout (seconds) for zfs operations
     #[serde(default = "default_timeout_secs")]
     pub timeout_secs: u64,
+
+    /// Optional kernel keyring (`@u`, `@s`, `@us`, or a name) that receives
+    /// the raw key after a successful unlock. Off by default: it widens where
+    /// the secret lives.
+    #[serde(default)]
+    pub keyring: Option<String>,
+
+    /// Seconds the `keyring` handoff lives before the kernel expires it;
+    /// `lock` revokes it sooner.
+    #[serde(default = "default_keyring_secs")]
+    pub keyring_secs: u64,
+
+    /// Cache the key in root's user keyring after unlock so later runs in the
+    /// same boot skip the token. Revoked by `lock`. Off by default.
+    #[serde(default)]
+    pub keyring_cache: bool,
+
+    /// Seconds a cached key lives before the kernel expires it
+    #[serde(default = "default_keyring_cache_secs")]
+    pub keyring_cache_secs: u64,
+
+    /// Fail the unlock when any inheriting descendant stays sealed, instead of
+    /// warning once the root and the requested dataset are open.
+    #[serde(default)]
+    pub require_all_descendants: bool,
 }
 
 fn default_timeout_secs() -> u64 {
     10
 }
 
+fn default_keyring_secs() -> u64 {
+    600
+}
+
+fn default_keyring_cache_secs() -> u64 {
+    600
+}
+
 impl Default for CryptoCfg {
     fn default() -> Self {
         Self {
             timeout_secs: default_timeout_secs(),
+            keyring: None,
+            keyring_secs: default_keyring_secs(),
+            keyring_cache: false,
+            keyring_cache_secs: default_keyring_cache_secs(),
+            require_all_descendants: false,
         }
     }
 }
@@ -65,17 +192,135 @@ pub struct Usb {
     /// Optional SHA-256 checksum for integrity verification
     #[serde(default)]
     pub expected_sha256: Option<String>,
+
+    /// Digest algorithm behind `expected_sha256` (sha256, sha512, blake3).
+    /// Configs written before this field existed keep SHA-256.
+    #[serde(default)]
+    pub checksum_algo: ChecksumAlgo,
+
+    /// Optional token key file name template; `{name}` expands to the sanitized
+    /// dataset and `{hash}` to a short digest of the full dataset name.
+    #[serde(default)]
+    pub key_name_template: Option<String>,
+
+    /// Filesystem label stamped on the token and searched for at boot.
+    #[serde(default = "default_token_label")]
+    pub label: String,
+
+    /// Labels tried in priority order when locating the token (e.g. a
+    /// travel spare); empty means `label` alone.
+    #[serde(default)]
+    pub labels: Vec<String>,
+
+    /// Refuse key files that are not exactly the raw key or its hex form with
+    /// at most one trailing newline, instead of filtering stray characters.
+    #[serde(default)]
+    pub strict_keyfile: bool,
+
+    /// Seconds unlock waits for an absent key file to appear before giving
+    /// up on the token for this attempt.
+    #[serde(default = "default_usb_wait_secs")]
+    pub wait_secs: u64,
+
+    /// Let unlock mount a labelled token itself when nothing mounted it at
+    /// the key path; disable where only the mount unit may touch the token.
+    #[serde(default = "default_auto_mount")]
+    pub auto_mount: bool,
+
+    /// Only trust a token whose USB serial (udev `ID_SERIAL_SHORT`) matches.
+    #[serde(default)]
+    pub require_serial: Option<String>,
+
+    /// Only trust a token with this `vendor:product` id (e.g. `0781:5583`).
+    #[serde(default)]
+    pub require_vidpid: Option<String>,
+
+    /// Passphrase-wrapped copy of the key on the token (`set-break-glass`)
+    #[serde(default)]
+    pub break_glass_path: Option<String>,
+
+    /// Unformatted token partition carrying the key record (`init
+    /// --raw-token`); when set, unlock reads it instead of `key_hex_path`.
+    #[serde(default)]
+    pub raw_device: Option<String>,
+
+    /// Byte offset of the key record on `raw_device`.
+    #[serde(default = "default_raw_offset")]
+    pub raw_offset: u64,
+}
+
+/// `[usb] wait_secs` when unset: how long unlock waits for a late token.
+pub const DEFAULT_USB_WAIT_SECS: u64 = 10;
+
+fn default_usb_wait_secs() -> u64 {
+    DEFAULT_USB_WAIT_SECS
+}
+
+fn default_auto_mount() -> bool {
+    true
+}
+
+fn default_raw_offset() -> u64 {
+    crate::util::rawtoken::DEFAULT_OFFSET
 }
 
 fn default_usb_key_path() -> String {
     "/run/beskar/key.hex".to_string()
 }
 
+fn default_token_label() -> String {
+    crate::dracut::BESKAR_TOKEN_LABEL.to_string()
+}
+
+/// Token labels land in mkfs, blkid searches, and boot shell scripts, so keep
+/// them to ext4's 16 bytes of plain `[A-Za-z0-9_-]`.
+pub fn validate_token_label(label: &str) -> Result<()> {
+    if label.is_empty() || label.len() > 16 {
+        return Err(anyhow!(
+            "usb.label '{}' must be 1-16 characters long",
+            label
+        ));
+    }
+    if !label
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(anyhow!(
+            "usb.label '{}' may only contain letters, digits, '-' or '_'",
+            label
+        ));
+    }
+    Ok(())
+}
+
+impl Usb {
+    /// Labels to search for, highest priority first.
+    pub fn token_labels(&self) -> Vec<String> {
+        if self.labels.is_empty() {
+            vec![self.label.clone()]
+        } else {
+            self.labels.clone()
+        }
+    }
+}
+
 impl Default for Usb {
     fn default() -> Self {
         Self {
             key_hex_path: default_usb_key_path(),
             expected_sha256: None,
+            checksum_algo: ChecksumAlgo::default(),
+            key_name_template: None,
+            label: default_token_label(),
+            labels: Vec::new(),
+            strict_keyfile: false,
+            wait_secs: default_usb_wait_secs(),
+            auto_mount: default_auto_mount(),
+            require_serial: None,
+            require_vidpid: None,
+            break_glass_path: None,
+            raw_device: None,
+            raw_offset: default_raw_offset(),
         }
     }
 }
@@ -109,6 +354,50 @@ pub struct Fallback {
     /// PBKDF2 iteration count
     #[serde(default = "default_passphrase_iters")]
     pub passphrase_iters: u32,
+
+    /// Which agent asks for the passphrase (auto, systemd, plymouth, interactive)
+    #[serde(default)]
+    pub prompt_backend: PromptBackend,
+
+    /// Source for each unlock attempt in turn (e.g. usb, usb, passphrase);
+    /// empty keeps the implicit USB-then-fallback order.
+    #[serde(default)]
+    pub order: Vec<KeySource>,
+}
+
+/// Key source one unlock attempt draws on (`[fallback] order`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeySource {
+    /// The token; a repeat entry re-settles udev and reads it afresh.
+    Usb,
+    /// Break-glass seal when configured, then the fallback passphrase.
+    Passphrase,
+}
+
+impl KeySource {
+    pub fn label(self) -> &'static str {
+        match self {
+            KeySource::Usb => "usb",
+            KeySource::Passphrase => "passphrase",
+        }
+    }
+}
+
+/// Passphrase prompt agent for the fallback (`[fallback] prompt_backend`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptBackend {
+    /// Plymouth when its daemon is running, then systemd-ask-password (if
+    /// `askpass`), then the terminal.
+    #[default]
+    Auto,
+    /// Only `systemd-ask-password` at `askpass_path`.
+    Systemd,
+    /// Only `plymouth ask-for-password`.
+    Plymouth,
+    /// Only the terminal prompt.
+    Interactive,
 }
 
 fn default_passphrase_iters() -> u32 {
@@ -124,16 +413,289 @@ impl Default for Fallback {
             passphrase_salt: None,
             passphrase_xor: None,
             passphrase_iters: default_passphrase_iters(),
+            prompt_backend: PromptBackend::default(),
+            order: Vec::new(),
         }
     }
 }
 
+// ----------------------------------------------------------------------------
+// FIDO2 Section
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fido2 {
+    /// Derive the key from a FIDO2 authenticator's hmac-secret (opt-in)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Try the authenticator before the USB token instead of after it
+    #[serde(default)]
+    pub primary: bool,
+
+    /// Relying party id the credential was enrolled under
+    #[serde(default = "default_fido2_rp")]
+    pub relying_party: String,
+
+    /// Base64 credential id returned at enrollment
+    #[serde(default)]
+    pub credential_id: Option<String>,
+
+    /// Base64 salt fed to hmac-secret
+    #[serde(default)]
+    pub hmac_salt: Option<String>,
+
+    /// Hex-encoded XOR of the hmac-secret output with the raw key
+    #[serde(default)]
+    pub key_xor: Option<String>,
+
+    /// Optional explicit authenticator device (e.g. /dev/hidraw0)
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+fn default_fido2_rp() -> String {
+    "beskar.local".to_string()
+}
+
+impl Default for Fido2 {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            primary: false,
+            relying_party: default_fido2_rp(),
+            credential_id: None,
+            hmac_salt: None,
+            key_xor: None,
+            device: None,
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Audit Section
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Audit {
+    /// Record every external command (path, redacted args, status, duration)
+    #[serde(default)]
+    pub log_commands: bool,
+
+    /// Stamp records in ISO-8601 UTC (`2026-03-02T07:00:00Z`) for fleet-wide
+    /// correlation. New configs write `true`; files without it keep local time.
+    #[serde(default)]
+    pub utc: bool,
+}
+
+// ----------------------------------------------------------------------------
+// UI Section
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiCfg {
+    /// Typing effect and banner flicker; disable for SSH or logged sessions
+    #[serde(default = "default_animations")]
+    pub animations: bool,
+}
+
+fn default_animations() -> bool {
+    true
+}
+
+impl Default for UiCfg {
+    fn default() -> Self {
+        Self {
+            animations: default_animations(),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Commented Template
+// ----------------------------------------------------------------------------
+
+/// Fully commented TOML covering every section (`generate-config`). Optional
+/// fields are shown commented out; uncommented values match what `init`
+/// writes for new deployments (e.g. `[audit] utc = true`).
+pub const CONFIG_TEMPLATE: &str = r#"# ============================================================================
+# /etc/zfs-beskar.toml – zfs_beskar_key configuration
+# ============================================================================
+# `init` fills in the key fingerprint and fallback data; everything else can be
+# edited by hand. Keep this file root-owned with mode 0600.
+
+# Schema version; older files are read as current and rewritten (with a
+# backup) by `init` or a full `doctor` run.
+version = 1
+
+[policy]
+# Datasets under Beskar's care. The first entry is the default target for
+# unlock, lock, status and doctor; each is resolved to its encryption root.
+datasets = ["rpool/ROOT"]
+
+# Explicit zfs binary (discovered on PATH when unset).
+# zfs_path = "/sbin/zfs"
+
+# Installed zfs_beskar_key binary referenced by the systemd and dracut units.
+# binary_path = "/usr/local/bin/zfs_beskar_key"
+
+# Permit running with root context for advanced setups.
+allow_root = false
+
+# Extra absolute paths admitted to the command allowlist (e.g. a mkfs.ext4 in
+# /usr/local/sbin). Each must be root-owned without group/world write access;
+# entries that fail the check are skipped with a warning.
+extra_allowed_binaries = []
+
+# Hold auto-unlock when a target pool is not ONLINE (DEGRADED, FAULTED, ...).
+# Off: boot warns, audits the health, and unlocks anyway.
+require_healthy_pool = false
+
+# How init hands over the recovery sigil: "stdout" (inline, stays in
+# scrollback), "file" (written 0600 to recovery_file; only the path is
+# printed), or "qr" (a terminal QR code cleared once captured).
+recovery_display = "stdout"
+# recovery_file = "/root/beskar-recovery.txt"
+
+# Per-encryption-root postures. `name` may be the root or any dataset under it.
+# [[policy.dataset_overrides]]
+# name = "tank/secrets"
+# strict_usb = true        # USB token only; no passphrase fallback
+
+[crypto]
+# Seconds allowed for each zfs operation.
+timeout_secs = 10
+
+# Kernel keyring (@u, @s, @us, or a plain name) that receives the raw key after
+# a successful unlock. Off by default: it widens where the secret lives. The
+# key expires after keyring_secs, and `lock` revokes it.
+# keyring = "@u"
+keyring_secs = 600
+
+# Cache the key in root's user keyring after unlock so later runs in the same
+# boot (e.g. the post-boot unlock service) skip the token. `lock` revokes it.
+keyring_cache = false
+keyring_cache_secs = 600
+
+# A descendant that refuses the key only warns once the root and the requested
+# dataset are open. Set true to fail the whole unlock instead.
+require_all_descendants = false
+
+[usb]
+# Where the raw 32-byte key is read from once the token is mounted.
+key_hex_path = "/run/beskar/key.hex"
+
+# Fingerprint of the token key, written by init and checked before every use.
+# expected_sha256 = "<hex digest>"
+
+# Digest behind expected_sha256: "sha256", "sha512" or "blake3".
+checksum_algo = "sha256"
+
+# Token key file name; {name} is the sanitized dataset, {hash} a short digest
+# of the full dataset name.
+# key_name_template = "{name}-{hash}.keyhex"
+
+# Filesystem label stamped on the token and searched for at boot (1-16 chars
+# of [A-Za-z0-9_-]).
+label = "BESKARKEY"
+
+# Labels tried in priority order when locating the token at unlock and boot,
+# e.g. a travel spare holding the same key. Empty means `label` alone.
+# labels = ["BESKARKEY", "BESKARKEY2"]
+
+# Reject key files that are not exactly the raw key or its hex form (with at
+# most one trailing newline) instead of filtering stray characters.
+strict_keyfile = false
+
+# Seconds unlock waits for a missing key file to appear before moving on.
+wait_secs = 10
+
+# When the key file is missing, let unlock find the token by label, mount it
+# read-only at the key path, and unmount it once the key is read. Turn off
+# where only run-beskar.mount may touch the token.
+auto_mount = true
+
+# Pin the physical token so a look-alike stick with the same label is refused
+# (see `udevadm info --query=property --name=/dev/sdX`).
+# require_serial = "4C530001230101112233"   # ID_SERIAL_SHORT
+# require_vidpid = "0781:5583"              # ID_VENDOR_ID:ID_MODEL_ID
+
+# Passphrase-wrapped copy of the key kept on the token, written by
+# `set-break-glass` and tried before the fallback passphrase.
+# break_glass_path = "/run/beskar/beskar.breakglass"
+
+# Raw token (`init --raw-token`): the key sits in a headed record at
+# raw_offset on an unformatted partition instead of in a file. The boot
+# loader copies it to key_hex_path; unlock reads the partition directly.
+# raw_device = "/dev/disk/by-partuuid/0a1b2c3d-..."
+raw_offset = 4096
+
+[fallback]
+# Offer the passphrase fallback when the USB token fails.
+enabled = true
+
+# Use systemd-ask-password when no terminal is available (boot).
+askpass = true
+askpass_path = "/usr/bin/systemd-ask-password"
+
+# PBKDF2 salt and XOR-wrapped key, written by init when a passphrase is set.
+# passphrase_salt = "<hex>"
+# passphrase_xor = "<hex>"
+
+# PBKDF2 iteration count used to derive the wrapping key.
+passphrase_iters = 250000
+
+# Passphrase prompt: "auto" tries plymouth (when its splash is up), then
+# systemd-ask-password (if askpass), then the terminal. "systemd", "plymouth"
+# or "interactive" use only that agent.
+prompt_backend = "auto"
+
+# What each unlock attempt tries, in turn ("usb" or "passphrase"); attempts
+# past the end of the list fall back to the implicit USB-then-passphrase
+# order. A repeated "usb" settles udev and rereads the token. Strict USB
+# policies drop "passphrase" entries.
+# order = ["usb", "usb", "passphrase"]
+
+[fido2]
+# Derive the key from a FIDO2 authenticator (see `enroll-fido2`).
+enabled = false
+
+# Ask for the authenticator before the USB token instead of after it.
+primary = false
+
+# Relying party id the credential is enrolled under.
+relying_party = "beskar.local"
+
+# Credential id, hmac-secret salt and XOR-wrapped key, written by enroll-fido2.
+# credential_id = "<base64>"
+# hmac_salt = "<base64>"
+# key_xor = "<hex>"
+
+# Explicit authenticator device (first `fido2-token -L` entry when unset).
+# device = "/dev/hidraw0"
+
+[audit]
+# Record every external command (path, redacted args, status, duration).
+log_commands = false
+
+# Stamp records in ISO-8601 UTC (trailing Z) instead of local time, so events
+# line up across hosts. The UI clock stays local.
+utc = true
+
+[ui]
+# Typing effect and banner flicker; disable for SSH or logged sessions.
+animations = true
+"#;
+
 // ----------------------------------------------------------------------------
 // Main Config Object
 // ----------------------------------------------------------------------------
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfigFile {
+    /// Schema version (`CONFIG_VERSION`); absent in pre-versioned configs.
+    #[serde(default)]
+    pub version: u32,
     pub policy: Policy,
     #[serde(default)]
     pub crypto: CryptoCfg,
@@ -141,10 +703,21 @@ pub struct ConfigFile {
     pub usb: Usb,
     #[serde(default)]
     pub fallback: Fallback,
+    #[serde(default)]
+    pub fido2: Fido2,
+    #[serde(default)]
+    pub audit: Audit,
+    #[serde(default)]
+    pub ui: UiCfg,
 
     /// Internal path reference for better error messages (not serialized)
     #[serde(skip)]
     pub path: PathBuf,
+
+    /// Schema version this config was read at when `load` migrated it in
+    /// memory; the file itself is only rewritten by `persist_migration`.
+    #[serde(skip)]
+    pub migrated_from: Option<u32>,
 }
 
 impl ConfigFile {
@@ -154,18 +727,299 @@ impl ConfigFile {
         let s = fs::read_to_string(path_ref)
             .with_context(|| format!("read config: {}", path_ref.display()))?;
 
-        let mut cfg: Self = if path_ref
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(|e| e.eq_ignore_ascii_case("toml"))
-            .unwrap_or(false)
-        {
+        let mut cfg: Self = if is_toml(path_ref) {
             toml::from_str(&s).context("toml parse")?
         } else {
             serde_yaml::from_str(&s).context("yaml parse")?
         };
 
         cfg.path = path_ref.to_path_buf();
+        cfg.migrate();
+
+        for label in std::iter::once(&cfg.usb.label).chain(&cfg.usb.labels) {
+            validate_token_label(label)
+                .with_context(|| format!("invalid config: {}", path_ref.display()))?;
+        }
+        if let Some(spec) = &cfg.usb.require_serial {
+            crate::util::usbid::validate_serial(spec)
+                .with_context(|| format!("invalid config: {}", path_ref.display()))?;
+        }
+        if let Some(spec) = &cfg.usb.require_vidpid {
+            crate::util::usbid::validate_vidpid(spec)
+                .with_context(|| format!("invalid config: {}", path_ref.display()))?;
+        }
+        if let Some(device) = &cfg.usb.raw_device {
+            if !Path::new(device).is_absolute() {
+                return Err(anyhow!(
+                    "invalid config {}: usb.raw_device must be an absolute device path",
+                    path_ref.display()
+                ));
+            }
+        }
+        if let Some(spec) = &cfg.crypto.keyring {
+            crate::util::keyring::parse_target(spec)
+                .with_context(|| format!("invalid config: {}", path_ref.display()))?;
+        }
+        if cfg.crypto.keyring_secs == 0 {
+            return Err(anyhow!(
+                "invalid config {}: crypto.keyring_secs must be at least 1",
+                path_ref.display()
+            ));
+        }
+        if cfg.crypto.keyring_cache && cfg.crypto.keyring_cache_secs == 0 {
+            return Err(anyhow!(
+                "invalid config {}: crypto.keyring_cache_secs must be at least 1",
+                path_ref.display()
+            ));
+        }
         Ok(cfg)
     }
+
+    /// Upgrade an older schema to `CONFIG_VERSION` in memory, one version step
+    /// at a time, recording the starting version in `migrated_from`. Returns
+    /// true when anything changed.
+    pub fn migrate(&mut self) -> bool {
+        let from = self.version;
+        if from >= CONFIG_VERSION {
+            return false;
+        }
+        if self.version == 0 {
+            // v0 -> v1: fill in what pre-versioned builds left blank and
+            // init used to patch up only when it ran again.
+            if self.crypto.timeout_secs == 0 {
+                self.crypto.timeout_secs = default_timeout_secs();
+            }
+            if self
+                .policy
+                .zfs_path
+                .as_ref()
+                .is_some_and(|p| p.trim().is_empty())
+            {
+                self.policy.zfs_path = None;
+            }
+            if self.fallback.askpass && self.fallback.askpass_path.is_none() {
+                self.fallback.askpass_path = Fallback::default().askpass_path;
+            }
+            self.version = 1;
+        }
+        self.migrated_from = Some(from);
+        true
+    }
+
+    /// The config in the format its path implies (TOML, else YAML).
+    pub fn serialize(&self) -> Result<String> {
+        if is_toml(&self.path) {
+            toml::to_string_pretty(self).context("toml serialize")
+        } else {
+            serde_yaml::to_string(self).context("yaml serialize")
+        }
+    }
+
+    /// Write a migrated config back in its own format after backing up the
+    /// original, returning the backup path. Only explicit write paths (`init`,
+    /// a full `doctor` sweep) call this; plain loads never touch the file.
+    pub fn persist_migration(&self) -> Result<PathBuf> {
+        let backup = backup_config(&self.path)?;
+        persist_config(self)?;
+        audit_log(
+            "CONFIG_MIGRATE_WRITE",
+            &format!("{} (backup {})", self.path.display(), backup.display()),
+        );
+        Ok(backup)
+    }
+
+    /// Candidate config locations in priority order: each entry of
+    /// `$BESKAR_CONFIG` (colon-separated), then the stock TOML and YAML paths.
+    pub fn default_candidates() -> Vec<PathBuf> {
+        let mut candidates: Vec<PathBuf> = env::var(CONFIG_ENV)
+            .map(|list| {
+                list.split(':')
+                    .map(str::trim)
+                    .filter(|entry| !entry.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        for stock in [DEFAULT_CONFIG_PATH, DEFAULT_YAML_CONFIG_PATH] {
+            let stock = PathBuf::from(stock);
+            if !candidates.contains(&stock) {
+                candidates.push(stock);
+            }
+        }
+        candidates
+    }
+
+    /// Load the first candidate that exists, returning its path alongside the
+    /// parsed config. Yields `None` when no candidate is present on disk.
+    pub fn discover(candidates: &[&Path]) -> Result<Option<(PathBuf, Self)>> {
+        for candidate in candidates {
+            if candidate.is_file() {
+                let cfg = Self::load(candidate)?;
+                return Ok(Some((candidate.to_path_buf(), cfg)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Atomically rewrite `cfg` at its own path, in the format that path implies,
+/// and keep it owner-only.
+pub fn persist_config(cfg: &ConfigFile) -> Result<()> {
+    let path = cfg.path.as_path();
+    atomic_write_bytes(path, cfg.serialize()?.as_bytes(), 0o600, true)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("set permissions on {}", path.display()))?;
+    Ok(())
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("toml"))
+        .unwrap_or(false)
+}
+
+/// Copy `path` to `<name>.bak-<UTC stamp>` (mode 0600) beside it.
+pub fn backup_config(path: &Path) -> Result<PathBuf> {
+    if !path.exists() {
+        return Err(anyhow!(
+            "No existing config to backup at {}",
+            path.display()
+        ));
+    }
+
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow!("Config path missing filename"))?;
+    let stamp = Utc::now().format("%Y%m%d%H%M%S");
+    let backup_name = format!("{}.bak-{}", file_name, stamp);
+    let backup_path = path.with_file_name(backup_name);
+
+    fs::copy(path, &backup_path).with_context(|| {
+        format!(
+            "Failed to backup existing config to {}",
+            backup_path.display()
+        )
+    })?;
+    fs::set_permissions(&backup_path, fs::Permissions::from_mode(0o600)).with_context(|| {
+        format!(
+            "Failed to set permissions on backup {}",
+            backup_path.display()
+        )
+    })?;
+    Ok(backup_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        validate_token_label, ConfigFile, DatasetOverride, Policy, PromptBackend, RecoveryDisplay,
+        Usb, CONFIG_TEMPLATE, CONFIG_VERSION,
+    };
+
+    #[test]
+    fn token_labels_stay_shell_and_ext4_safe() {
+        assert!(validate_token_label("BESKARKEY").is_ok());
+        assert!(validate_token_label("lab-node_07").is_ok());
+        assert!(validate_token_label("").is_err());
+        assert!(validate_token_label("SEVENTEEN_CHARS_X").is_err());
+        assert!(validate_token_label("KEY\"$(id)").is_err());
+    }
+
+    #[test]
+    fn token_labels_keep_priority_order_and_default_to_label() {
+        let mut usb = Usb::default();
+        assert_eq!(usb.token_labels(), vec!["BESKARKEY"]);
+        usb.labels = vec!["BESKARKEY2".into(), "BESKARKEY".into()];
+        assert_eq!(usb.token_labels(), vec!["BESKARKEY2", "BESKARKEY"]);
+    }
+
+    #[test]
+    fn dataset_overrides_match_through_the_encryption_root() {
+        let policy = Policy {
+            datasets: Vec::new(),
+            zfs_path: None,
+            binary_path: None,
+            allow_root: false,
+            extra_allowed_binaries: Vec::new(),
+            require_healthy_pool: false,
+            dataset_overrides: vec![
+                DatasetOverride {
+                    name: "tank/secrets/db".to_string(),
+                    strict_usb: true,
+                },
+                DatasetOverride {
+                    name: "rpool/ROOT".to_string(),
+                    strict_usb: false,
+                },
+            ],
+            recovery_display: RecoveryDisplay::Stdout,
+            recovery_file: None,
+        };
+        let resolve = |ds: &str| match ds {
+            "tank/secrets/db" => Some("tank/secrets".to_string()),
+            other => Some(other.to_string()),
+        };
+        assert!(
+            policy
+                .override_for("tank/secrets", resolve)
+                .unwrap()
+                .strict_usb
+        );
+        assert!(
+            !policy
+                .override_for("rpool/ROOT", resolve)
+                .unwrap()
+                .strict_usb
+        );
+        assert!(policy.override_for("rpool/home", resolve).is_none());
+    }
+
+    #[test]
+    fn commented_template_parses_to_what_init_writes() {
+        let cfg: ConfigFile = toml::from_str(CONFIG_TEMPLATE).unwrap();
+        assert_eq!(cfg.version, CONFIG_VERSION);
+        assert_eq!(cfg.policy.datasets, vec!["rpool/ROOT"]);
+        assert!(cfg.policy.dataset_overrides.is_empty());
+        assert!(!cfg.policy.require_healthy_pool);
+        assert_eq!(cfg.crypto.timeout_secs, 10);
+        assert_eq!(cfg.usb.label, "BESKARKEY");
+        assert_eq!(cfg.usb.wait_secs, 10);
+        assert!(cfg.fallback.enabled);
+        assert_eq!(cfg.fallback.passphrase_iters, 250_000);
+        assert_eq!(cfg.fallback.prompt_backend, PromptBackend::Auto);
+        assert_eq!(cfg.fido2.relying_party, "beskar.local");
+        assert!(cfg.ui.animations);
+        assert!(cfg.audit.utc);
+    }
+
+    #[test]
+    fn unversioned_configs_migrate_once() {
+        let mut cfg: ConfigFile = toml::from_str(
+            "[crypto]\ntimeout_secs = 0\n[policy]\ndatasets = [\"rpool/ROOT\"]\nzfs_path = \" \"\n[fallback]\naskpass = true\n",
+        )
+        .unwrap();
+        assert_eq!(cfg.version, 0);
+        assert!(cfg.migrate());
+        assert_eq!(cfg.version, CONFIG_VERSION);
+        assert_eq!(cfg.migrated_from, Some(0));
+        assert_eq!(cfg.crypto.timeout_secs, 10);
+        assert_eq!(cfg.policy.zfs_path, None);
+        assert!(cfg.fallback.askpass_path.is_some());
+        assert!(!cfg.migrate());
+    }
+
+    #[test]
+    fn loading_an_old_config_leaves_the_file_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zfs-beskar.toml");
+        let original = "# operator note\n[policy]\ndatasets = [\"rpool/ROOT\"]\n";
+        std::fs::write(&path, original).unwrap();
+        let cfg = ConfigFile::load(&path).unwrap();
+        assert_eq!(cfg.version, CONFIG_VERSION);
+        assert_eq!(cfg.migrated_from, Some(0));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
 }
@@ -1,4 +1,5 @@
-use anyhow::{Context, Result};
+use crate::util::checksum::ChecksumAlgo;
+use anyhow::{anyhow, Context, Result};
 use std::fs::{self, File, Permissions};
 use std::io::Write;
 use std::os::unix::fs::PermissionsExt;
@@ -19,11 +20,23 @@ pub(crate) const DEFAULT_MOUNTPOINT: &str = "/run/beskar";
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Searched after `$PATH` for the loader's checksum tool, as dracut would.
+const TOOL_DIRS: &[&str] = &["/usr/bin", "/bin", "/usr/sbin", "/sbin", "/usr/local/bin"];
+
 #[derive(Debug, Clone)]
 pub(crate) struct ModuleContext<'a> {
     pub mountpoint: &'a str,
     pub key_path: &'a str,
     pub key_sha256: Option<&'a str>,
+    pub checksum_algo: ChecksumAlgo,
+    /// Token labels, highest priority first.
+    pub token_labels: &'a [String],
+    pub require_serial: Option<&'a str>,
+    pub require_vidpid: Option<&'a str>,
+    /// Unformatted token partition to copy the key record from, instead of
+    /// mounting a labelled filesystem.
+    pub raw_device: Option<&'a str>,
+    pub raw_offset: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -97,6 +110,27 @@ pub(crate) fn preferred_module_dir() -> PathBuf {
         .unwrap_or_else(|| PathBuf::from(MODULE_DIR_PRIMARY))
 }
 
+/// The module installs `algo`'s shell tool (`b3sum` for BLAKE3) into the
+/// initramfs, and `inst_multiple` fails the dracut build when the host lacks
+/// it. Refuse before anything is rendered instead.
+pub(crate) fn ensure_checksum_tool(algo: ChecksumAlgo) -> Result<PathBuf> {
+    let tool = algo.shell_tool();
+    let path_dirs = std::env::var("PATH").unwrap_or_default();
+    path_dirs
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .chain(TOOL_DIRS.iter().copied())
+        .map(|dir| Path::new(dir).join(tool))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| {
+            anyhow!(
+                "{} not found on this host; the dracut loader needs it to check the {} fingerprint. Install it or set [usb] checksum_algo = \"sha256\".",
+                tool,
+                algo.label()
+            )
+        })
+}
+
 pub(crate) fn expected_module(ctx: &ModuleContext<'_>) -> ExpectedModule {
     let replacements = replacements(ctx);
     ExpectedModule {
@@ -175,7 +209,7 @@ pub(crate) fn install_module(module_paths: &ModulePaths, ctx: &ModuleContext<'_>
 fn replacements(ctx: &ModuleContext<'_>) -> Vec<(&'static str, String)> {
     vec![
         ("VERSION", VERSION.to_string()),
-        ("TOKEN_LABEL", BESKAR_TOKEN_LABEL.to_string()),
+        ("TOKEN_LABELS", ctx.token_labels.join(" ")),
         ("MOUNTPOINT", ctx.mountpoint.to_string()),
         ("SCRIPT_NAME", SCRIPT_NAME.to_string()),
         ("SERVICE_NAME", SERVICE_NAME.to_string()),
@@ -187,6 +221,29 @@ fn replacements(ctx: &ModuleContext<'_>) -> Vec<(&'static str, String)> {
             "KEY_SHA256",
             ctx.key_sha256.map(|s| s.to_string()).unwrap_or_default(),
         ),
+        ("CHECKSUM_TOOL", ctx.checksum_algo.shell_tool().to_string()),
+        ("CHECKSUM_LABEL", ctx.checksum_algo.label().to_string()),
+        (
+            "REQUIRE_SERIAL",
+            ctx.require_serial
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "REQUIRE_VIDPID",
+            ctx.require_vidpid
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "RAW_DEVICE",
+            ctx.raw_device.map(|s| s.to_string()).unwrap_or_default(),
+        ),
+        ("RAW_OFFSET", ctx.raw_offset.to_string()),
+        (
+            "RAW_MAGIC",
+            String::from_utf8_lossy(crate::util::rawtoken::MAGIC).into_owned(),
+        ),
     ]
 }
 
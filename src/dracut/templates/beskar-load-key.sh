@@ -3,14 +3,23 @@
 set -euo pipefail
 set -E
 
-LABEL="{{TOKEN_LABEL}}"
+# Anything spawned from here (zfs_beskar_key included) knows it is pre-root.
+export BESKAR_INITRAMFS=1
+
+LABELS="{{TOKEN_LABELS}}"
 MOUNTPOINT="{{MOUNTPOINT}}"
 KEY_PATH="{{KEY_PATH}}"
 KEY_SHA256="{{KEY_SHA256}}"
+REQUIRE_SERIAL="{{REQUIRE_SERIAL}}"
+REQUIRE_VIDPID="{{REQUIRE_VIDPID}}"
+RAW_DEVICE="{{RAW_DEVICE}}"
+RAW_OFFSET="{{RAW_OFFSET}}"
+RAW_MAGIC="{{RAW_MAGIC}}"
 MAX_WAIT_SECONDS=30
 SLEEP_INTERVAL=1
 MOUNT_RETRIES=3
 MOUNT_OPTS="ro,nosuid,nodev,noexec"
+RAW_KEY_STAGED=0
 
 log_line() {
     echo "[BESKAR] $*" >&2
@@ -33,12 +42,47 @@ fail() {
     exit 1
 }
 
-trap 'rc=$?; if (( rc != 0 )); then error "Beskar loader aborted (rc=${rc})."; fi' EXIT
+# Raw mode copies the key onto tmpfs, and /run survives switch_root: zero
+# and remove the copy once load-key has used it, or on any early exit.
+scrub_raw_key() {
+    if (( RAW_KEY_STAGED )) && [[ -f "$KEY_PATH" ]]; then
+        dd if=/dev/zero of="$KEY_PATH" bs=32 count=1 conv=notrunc,fsync 2>/dev/null || true
+        rm -f "$KEY_PATH" || true
+        info "Staged key $KEY_PATH scrubbed."
+    fi
+    RAW_KEY_STAGED=0
+}
+
+trap 'rc=$?; scrub_raw_key; if (( rc != 0 )); then error "Beskar loader aborted (rc=${rc})."; fi' EXIT
+
+# First label in LABELS (priority order) that blkid can resolve.
+present_label() {
+    local label
+    for label in $LABELS; do
+        if blkid -L "$label" >/dev/null 2>&1; then
+            echo "$label"
+            return 0
+        fi
+    done
+    return 1
+}
 
 wait_for_device() {
     local elapsed=0
     while (( elapsed < MAX_WAIT_SECONDS )); do
-        if blkid -L "$LABEL" >/dev/null 2>&1; then
+        if present_label >/dev/null; then
+            return 0
+        fi
+        sleep "$SLEEP_INTERVAL"
+        ((elapsed += SLEEP_INTERVAL))
+    done
+    return 1
+}
+
+wait_for_raw_device() {
+    local elapsed=0
+    while (( elapsed < MAX_WAIT_SECONDS )); do
+        if [[ -b "$RAW_DEVICE" ]]; then
             return 0
         fi
         sleep "$SLEEP_INTERVAL"
@@ -59,6 +103,15 @@ wait_for_key() {
     return 1
 }
 
+# Replay block add events so a token inserted before udev started is seen.
+trigger_udev() {
+    if command -v udevadm >/dev/null 2>&1; then
+        if ! udevadm trigger --subsystem-match=block --action=add >/dev/null 2>&1; then
+            warn "udevadm trigger reported an error; continuing regardless."
+        fi
+    fi
+}
+
 settle_udev() {
     if command -v udevadm >/dev/null 2>&1; then
         if ! udevadm settle >/dev/null 2>&1; then
@@ -67,6 +120,31 @@ settle_udev() {
     fi
 }
 
+# Refuse a token whose USB serial or vendor:product differs from the pin.
+verify_device() {
+    local device="$1"
+    if [[ -z "$REQUIRE_SERIAL" && -z "$REQUIRE_VIDPID" ]]; then
+        return 0
+    fi
+
+    local key value serial="" vendor="" model=""
+    while IFS='=' read -r key value; do
+        case "$key" in
+            ID_SERIAL_SHORT) serial="$value" ;;
+            ID_VENDOR_ID) vendor="$value" ;;
+            ID_MODEL_ID) model="$value" ;;
+        esac
+    done < <(udevadm info --query=property --name="$device" 2>/dev/null || true)
+
+    if [[ -n "$REQUIRE_SERIAL" && "$serial" != "$REQUIRE_SERIAL" ]]; then
+        fail "Token $device serial '${serial:-none}' does not match the pinned serial; refusing its key."
+    fi
+    if [[ -n "$REQUIRE_VIDPID" && "${vendor,,}:${model,,}" != "${REQUIRE_VIDPID,,}" ]]; then
+        fail "Token $device id '${vendor:-?}:${model:-?}' does not match pinned $REQUIRE_VIDPID; refusing its key."
+    fi
+    info "Token $device matches the pinned USB identity."
+}
+
 mount_token() {
     local device="$1"
     mkdir -p "$MOUNTPOINT"
@@ -88,6 +166,60 @@ mount_token() {
     return 1
 }
 
+# Raw token: no filesystem to mount. Copy the key out of the headed record at
+# RAW_OFFSET into KEY_PATH on tmpfs, where keylocation points.
+extract_raw_key() {
+    local magic
+    magic="$(dd if="$RAW_DEVICE" bs=1 skip="$RAW_OFFSET" count="${#RAW_MAGIC}" 2>/dev/null || true)"
+    if [[ "$magic" != "$RAW_MAGIC" ]]; then
+        fail "No Beskar key record at offset $RAW_OFFSET on $RAW_DEVICE."
+    fi
+    mkdir -p "$MOUNTPOINT"
+    RAW_KEY_STAGED=1
+    if ! (umask 077 && dd if="$RAW_DEVICE" of="$KEY_PATH" bs=1 \
+        skip="$((RAW_OFFSET + ${#RAW_MAGIC}))" count=32 2>/dev/null); then
+        fail "Unable to copy the key record from $RAW_DEVICE to $KEY_PATH."
+    fi
+    info "Key record copied from $RAW_DEVICE to $KEY_PATH."
+}
+
+fetch_from_raw_device() {
+    info "Awaiting raw token $RAW_DEVICE (timeout ${MAX_WAIT_SECONDS}s)…"
+    if ! wait_for_raw_device; then
+        fail "Raw token $RAW_DEVICE not detected within ${MAX_WAIT_SECONDS}s."
+    fi
+    settle_udev
+    verify_device "$RAW_DEVICE"
+    extract_raw_key
+}
+
+fetch_from_label() {
+    info "Awaiting token label(s) $LABELS (timeout ${MAX_WAIT_SECONDS}s)…"
+    if ! wait_for_device; then
+        fail "No token labelled $LABELS detected within ${MAX_WAIT_SECONDS}s."
+    fi
+
+    settle_udev
+
+    local label device
+    label="$(present_label || true)"
+    device="$(blkid -L "${label:-}" 2>/dev/null || true)"
+    if [[ -z "$label" || -z "$device" ]]; then
+        fail "Token label(s) $LABELS resolved earlier but vanished before mount."
+    fi
+    info "Token label $label satisfied the search; detected at $device."
+    verify_device "$device"
+
+    if ! mount_token "$device"; then
+        fail "Unable to mount $device at $MOUNTPOINT after ${MOUNT_RETRIES} attempts."
+    fi
+
+    info "Waiting for key file $KEY_PATH (timeout ${MAX_WAIT_SECONDS}s)…"
+    if ! wait_for_key; then
+        fail "Key file $KEY_PATH not detected within ${MAX_WAIT_SECONDS}s."
+    fi
+}
+
 verify_checksum() {
     if [[ -z "${KEY_SHA256}" ]]; then
         warn "No checksum recorded; skipping key verification."
@@ -102,10 +234,10 @@ verify_checksum() {
     fi
 
     local actual=""
-    if actual="$(sha256sum "$KEY_PATH" | awk '{print $1}')"; then
+    if actual="$({{CHECKSUM_TOOL}} "$KEY_PATH" | awk '{print $1}')"; then
         actual="${actual,,}"
     else
-        fail "Unable to compute SHA-256 for $KEY_PATH."
+        fail "Unable to compute {{CHECKSUM_LABEL}} for $KEY_PATH."
     fi
 
     if [[ "$actual" != "$expected" ]]; then
@@ -115,27 +247,13 @@ verify_checksum() {
 }
 
 main() {
-    info "Awaiting token label $LABEL (timeout ${MAX_WAIT_SECONDS}s)…"
-    if ! wait_for_device; then
-        fail "Token $LABEL not detected within ${MAX_WAIT_SECONDS}s."
-    fi
-
+    trigger_udev
     settle_udev
 
-    local device
-    device="$(blkid -L "$LABEL" 2>/dev/null || true)"
-    if [[ -z "$device" ]]; then
-        fail "Token label $LABEL resolved earlier but vanished before mount."
-    fi
-    info "Token $LABEL detected at $device."
-
-    if ! mount_token "$device"; then
-        fail "Unable to mount $device at $MOUNTPOINT after ${MOUNT_RETRIES} attempts."
-    fi
-
-    info "Waiting for key file $KEY_PATH (timeout ${MAX_WAIT_SECONDS}s)…"
-    if ! wait_for_key; then
-        fail "Key file $KEY_PATH not detected within ${MAX_WAIT_SECONDS}s."
+    if [[ -n "$RAW_DEVICE" ]]; then
+        fetch_from_raw_device
+    else
+        fetch_from_label
     fi
     if command -v stat >/dev/null 2>&1; then
         info "Key file located: $(stat -c '%s bytes, mode %a' "$KEY_PATH")."
@@ -148,10 +266,12 @@ main() {
     verify_checksum
 
     info "Invoking zfs load-key -a using keylocation $KEY_PATH."
-    if zfs load-key -a; then
+    local rc=0
+    zfs load-key -a || rc=$?
+    scrub_raw_key
+    if (( rc == 0 )); then
         info "zfs load-key -a completed successfully."
     else
-        local rc=$?
         fail "zfs load-key -a returned non-zero status (${rc}); native prompts will take over."
     fi
 }
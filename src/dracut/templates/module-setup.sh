@@ -7,7 +7,7 @@ depends() {
 }
 
 install() {
-    inst_multiple blkid mount umount mkdir mountpoint zfs sha256sum udevadm
+    inst_multiple blkid mount umount mkdir mountpoint dd zfs {{CHECKSUM_TOOL}} udevadm
     instmods ext4 vfat nls_utf8
     inst_simple "$moddir/{{SCRIPT_NAME}}" "/sbin/{{SCRIPT_NAME}}"
     inst_simple "$moddir/{{SERVICE_NAME}}" "$systemdsystemunitdir/{{SERVICE_NAME}}"
@@ -9,22 +9,27 @@ mod menu;
 mod ui;
 mod util;
 mod zfs;
+mod zpool;
 
+use crate::cmd::changepin::PinSource;
+use crate::cmd::fingerprint::FingerprintFormat;
 use crate::cmd::unlock::UnlockOptions;
 use crate::config::ConfigFile;
+use crate::util::atomic::atomic_write_bytes;
+use crate::util::audit::audit_log;
 use crate::util::binary::determine_binary_path;
+use crate::util::holders::{describe_holders, mount_holders};
+use crate::util::keyring;
 use anyhow::{anyhow, Context, Result};
 use clap::{Parser, Subcommand};
+use dialoguer::{theme::ColorfulTheme, Select};
 use rand::rngs::OsRng;
 use rand::RngCore;
-#[cfg(test)]
-use sha2::{Digest, Sha256};
 use std::fs;
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, IsTerminal, Write};
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
 use ui::{Pace, Timing, UX};
 use zeroize::Zeroizing;
 
@@ -38,18 +43,41 @@ use zeroize::Zeroizing;
     about = "Manage ZFS encrypted dataset keys with USB-first auto-unlock."
 )]
 struct Cli {
-    /// Path to config file (TOML or YAML)
-    #[arg(short, long, global = true, default_value = "/etc/zfs-beskar.toml")]
-    config: String,
+    /// Path to config file (TOML or YAML). Defaults to the first existing entry of
+    /// $BESKAR_CONFIG, /etc/zfs-beskar.toml, /etc/zfs-beskar.yaml.
+    #[arg(short, long, global = true)]
+    config: Option<String>,
 
     /// Dataset target when relevant (e.g., rpool/ROOT or rpool/ROOT/ubuntu)
     #[arg(short = 'd', long)]
     dataset: Option<String>,
 
+    /// Pool target for `unlock`, `lock` and `status`: every encryption root in
+    /// it (with `--all`, every configured root in it)
+    #[arg(long, conflicts_with = "dataset")]
+    pool: Option<String>,
+
+    /// Treat --dataset as a subtree: every encryption root at or below it.
+    /// A --dataset containing `*` or `?` is expanded as a glob either way.
+    #[arg(short = 'r', long, requires = "dataset")]
+    recursive: bool,
+
     /// Force JSON logs (legacy env compatibility)
     #[arg(long, global = true)]
     json: bool,
 
+    /// Show trace lines and per-phase reports (e.g. unlock timings)
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Print instantly: no typing effect or banner flicker (overrides `[ui] animations`)
+    #[arg(long, global = true, visible_aliases = ["fast", "instant"])]
+    plain: bool,
+
+    /// Never prompt: anything that would ask the operator fails instead (automation)
+    #[arg(long, global = true)]
+    non_interactive: bool,
+
     /// Launch interactive menu when no subcommand provided
     #[arg(long)]
     menu: bool,
@@ -61,7 +89,8 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Commands {
     Init {
-        /// Explicit USB block device (e.g., /dev/sdb). Prompts if omitted.
+        /// Explicit USB block device (e.g., /dev/sdb). Prompts if omitted, so
+        /// --non-interactive runs must pass it.
         #[arg(long)]
         usb_device: Option<String>,
 
@@ -70,26 +99,209 @@ enum Commands {
         key_path: Option<PathBuf>,
 
         /// Safe mode: prompt before each forge phase and skip forced wipe.
-        #[arg(long)]
+        #[arg(long, conflicts_with = "non_interactive")]
         safe: bool,
+
+        /// Forge key and config only; leave dracut/initramfs-tools untouched.
+        #[arg(long)]
+        no_initramfs: bool,
+
+        /// Filesystem label for the token (defaults to config `usb.label`, BESKARKEY).
+        #[arg(long)]
+        label: Option<String>,
+
+        /// Write the key as a raw record on an unformatted partition (no filesystem).
+        #[arg(long, conflicts_with = "safe")]
+        raw_token: bool,
+
+        /// Also save the recovery code, fingerprint and token UUID as a 0600
+        /// file in this directory (ideally on an unlocked encrypted volume).
+        #[arg(long, visible_alias = "output-dir")]
+        artifacts_dir: Option<PathBuf>,
     },
     ForgeKey,
-    Unlock,
-    Lock,
+    Unlock {
+        /// Unlock every encryption root behind `policy.datasets` in one run
+        /// (only those in `--pool` when given).
+        #[arg(long)]
+        all: bool,
+
+        /// Use the recovery sigil from `init` instead of the token (dead USB).
+        #[arg(long)]
+        recovery: bool,
+
+        /// Mount the unlocked datasets, including the named dataset if it is `canmount=noauto`.
+        #[arg(long)]
+        mount: bool,
+
+        /// Trust a token whose checksum no longer matches the config if its key
+        /// opens the dataset, and record the new checksum.
+        #[arg(long)]
+        accept_new_checksum: bool,
+
+        /// Skip the --accept-new-checksum confirmation.
+        #[arg(long, requires = "accept_new_checksum")]
+        yes: bool,
+
+        /// Read the raw or hex key from stdin instead of the token (automation).
+        #[arg(long, conflicts_with = "recovery")]
+        stdin_key: bool,
+
+        /// Do not check the --stdin-key material against usb.expected_sha256.
+        #[arg(long, requires = "stdin_key")]
+        skip_checksum: bool,
+    },
+    Lock {
+        /// Unmount mounted datasets under the encryption root before sealing.
+        #[arg(long)]
+        unmount: bool,
+    },
     AutoUnlock {
         /// USB-only mode for initramfs: disable passphrase fallback.
         #[arg(long)]
         strict_usb: bool,
+
+        /// Unlock every encryption root behind `policy.datasets` in one run
+        /// (only those in `--pool` when given).
+        #[arg(long)]
+        all: bool,
+    },
+    Doctor {
+        /// Comma-separated checks allowed to repair (units, dracut, keylocation,
+        /// checksum, binary); everything else is inspected read-only.
+        #[arg(long)]
+        fix_only: Option<String>,
+        /// Follow the report with remediation steps for each warning or failure.
+        #[arg(long)]
+        explain: bool,
+    },
+    Status,
+    Recover {
+        /// Rewrite the key on the attached BESKARKEY token instead of wiping a USB.
+        #[arg(long)]
+        in_place: bool,
+
+        /// Label for the etched token (defaults to `usb.label`), e.g. a travel spare.
+        #[arg(long)]
+        label: Option<String>,
+
+        /// USB block device to etch (e.g., /dev/sdb). Prompts if omitted.
+        #[arg(long)]
+        usb_device: Option<String>,
+    },
+    /// Encrypt the token key to a security-team age recipient for escrow.
+    Escrow {
+        /// X25519 age recipient (age1…).
+        #[arg(long)]
+        recipient_pubkey: String,
+
+        /// Path for the armored escrow blob.
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Decrypt an escrow blob and etch the key onto a fresh USB token.
+    Unescrow {
+        /// File holding the security team's AGE-SECRET-KEY identity.
+        #[arg(long)]
+        identity: PathBuf,
+
+        /// Armored escrow blob produced by `escrow`.
+        #[arg(long)]
+        input: PathBuf,
+    },
+    /// Bind a FIDO2 authenticator (hmac-secret) as an extra key source.
+    EnrollFido2 {
+        /// Authenticator device (defaults to the first `fido2-token -L` entry).
+        #[arg(long)]
+        device: Option<String>,
+
+        /// Relying party id for the credential (defaults to config, beskar.local).
+        #[arg(long)]
+        relying_party: Option<String>,
+
+        /// Challenge the authenticator before the USB token during unlock.
+        #[arg(long)]
+        primary: bool,
+    },
+    /// Wrap the token key under a shared break-glass passphrase, stored on the token.
+    SetBreakGlass {
+        /// Where to write the seal (defaults to `usb.break_glass_path`, else next to the key).
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+    /// Rewrap the fallback or break-glass key copy under a new passphrase.
+    ChangePin {
+        /// Which seal to rewrap: fallback or break-glass.
+        #[arg(long, value_enum, default_value_t = PinSource::Fallback)]
+        source: PinSource,
+    },
+    /// Retarget the encryption root's keylocation without a full forge.
+    #[command(alias = "change-keylocation")]
+    SetKeylocation {
+        /// `prompt` or a file:// URI (e.g. file:///run/beskar/rpool.keyhex).
+        location: String,
+    },
+    InstallUnits {
+        /// Diff the installed units against the rendered ones; write nothing
+        /// and exit nonzero on drift.
+        #[arg(long)]
+        check_only: bool,
+    },
+    InstallDracut {
+        /// Rewrite the module and rebuild the initramfs even if already current.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Run the rendered dracut loader against a throwaway pool (no reboot).
+    TestInitramfs,
+    /// Time key read, checksum, and load-key across repeated unlocks of a sealed root.
+    Benchmark {
+        /// Number of load/unload cycles to time.
+        #[arg(long, default_value_t = 5)]
+        iterations: usize,
+
+        /// Benchmark a throwaway holoforge pool instead of the configured dataset.
+        #[arg(long)]
+        simulate: bool,
+    },
+    /// Inspect every attached token carrying the configured label.
+    ListTokens,
+    /// Print the SHA-256 fingerprint and recovery-code form of the token key.
+    #[command(name = "show-fingerprint", alias = "fingerprint")]
+    Fingerprint {
+        /// Fingerprint encoding: hex, raw (digest bytes only), or base32.
+        #[arg(long, value_enum, default_value_t = FingerprintFormat::Hex)]
+        format: FingerprintFormat,
+    },
+    /// Review audit records from /var/log/beskar.log.
+    AuditLog {
+        /// Only records at or after this time (YYYY-MM-DD[ HH:MM[:SS]] or RFC 3339).
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only events starting with this prefix (e.g. UNLOCK_, LOCKOUT_).
+        #[arg(long)]
+        event: Option<String>,
+
+        /// Show only the last N matching records.
+        #[arg(long)]
+        tail: Option<usize>,
     },
-    Doctor,
-    Recover,
-    InstallUnits,
-    InstallDracut,
     SelfTest {
         /// Simulate missing USB to test fallback passphrase.
         #[arg(long)]
         fallback: bool,
     },
+    /// Print a fully commented config template covering every section.
+    GenerateConfig {
+        /// Write the template to this path instead of stdout.
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Overwrite an existing file at --output.
+        #[arg(long, requires = "output")]
+        force: bool,
+    },
 }
 
 // ----------------------------------------------------------------------------
@@ -103,48 +315,58 @@ fn main() -> Result<()> {
     }
 
     // New UI layer (no from_env in UX)
-    let ui = UX::new(false, false);
+    let ui = UX::new(cli.verbose, false);
+    ui.set_animations(!cli.plain);
+    ui::set_non_interactive(cli.non_interactive);
     let timing = Timing::new(false, false);
 
+    // Templates need no config; resolving one would inscribe the starter creed.
+    if let Some(Commands::GenerateConfig { output, force }) = &cli.command {
+        return generate_config(output.as_deref(), *force, &ui);
+    }
+
     // ------------------------------------------------------------------------
-    // Ensure config file exists
+    // Resolve config (explicit path, else discovery; starter creed if none)
     // ------------------------------------------------------------------------
-    let cfg_path = Path::new(&cli.config);
-    if !cfg_path.exists() {
-        ui.warn(&format!(
-            "Forge ledger missing at {} — I will inscribe a starter creed.",
-            cfg_path.display()
-        ));
-        let default_cfg = r#"[crypto]
-timeout_secs = 10
-
-[usb]
-key_hex_path = "/run/beskar/key.hex"
-
-[policy]
-zfs_path = "/sbin/zfs"
-binary_path = "/usr/local/bin/zfs_beskar_key"
-datasets = ["rpool/ROOT"]
-
-[fallback]
-enabled = true
-askpass = true
-askpass_path = "/usr/bin/systemd-ask-password"
-"#;
+    let cfg: ConfigFile = match cli.config.as_deref() {
+        Some(explicit) => {
+            let cfg_path = Path::new(explicit);
+            if !cfg_path.exists() {
+                write_starter_config(cfg_path, &ui)?;
+            }
+            ConfigFile::load(cfg_path)?
+        }
+        None => {
+            let candidates = ConfigFile::default_candidates();
+            let refs: Vec<&Path> = candidates.iter().map(PathBuf::as_path).collect();
+            match ConfigFile::discover(&refs)? {
+                Some((_, cfg)) => cfg,
+                None => {
+                    let cfg_path = Path::new(config::DEFAULT_CONFIG_PATH);
+                    write_starter_config(cfg_path, &ui)?;
+                    ConfigFile::load(cfg_path)?
+                }
+            }
+        }
+    };
 
-        let mut f = File::create(cfg_path)
-            .with_context(|| format!("create default config at {}", cfg_path.display()))?;
-        f.write_all(default_cfg.as_bytes())?;
-        fs::set_permissions(cfg_path, fs::Permissions::from_mode(0o600))
-            .context("set config permissions")?;
-        ui.info(&format!(
-            "Template etched at {}. Inspect every value before the next muster.",
-            cfg_path.display()
-        ));
+    for err in cmd::base::extend_allowlist(&cfg.policy.extra_allowed_binaries) {
+        ui.warn(&format!("{:#}; allowlist entry skipped.", err));
     }
-
-    // Load config
-    let cfg: ConfigFile = ConfigFile::load(&cli.config)?;
+    cmd::base::set_command_audit(cfg.audit.log_commands);
+    util::audit::set_utc(cfg.audit.utc);
+    if let Some(from) = cfg.migrated_from {
+        audit_log(
+            "CONFIG_MIGRATE",
+            &format!(
+                "{}: version {} -> {} in memory",
+                cfg.path.display(),
+                from,
+                cfg.version
+            ),
+        );
+    }
+    ui.set_animations(cfg.ui.animations && !cli.plain);
 
     // ------------------------------------------------------------------------
     // Command dispatch or menu
@@ -180,6 +402,10 @@ fn dispatch_command(
             usb_device,
             key_path,
             safe,
+            no_initramfs,
+            label,
+            raw_token,
+            artifacts_dir,
         } => {
             let opts = cmd::init::InitOptions {
                 pool: cli.dataset.clone(),
@@ -188,76 +414,278 @@ fn dispatch_command(
                 force: !safe,
                 auto_unlock: true,
                 confirm_each_phase: *safe,
+                key_name_template: cfg.usb.key_name_template.clone(),
+                checksum_algo: cfg.usb.checksum_algo,
+                skip_initramfs: *no_initramfs,
+                label: label.clone().unwrap_or_else(|| cfg.usb.label.clone()),
+                raw_token: *raw_token,
+                recovery_display: cfg.policy.recovery_display,
+                recovery_file: cfg.policy.recovery_file.as_ref().map(PathBuf::from),
+                artifacts_dir: artifacts_dir.clone(),
             };
             cmd::init::run_init(ui, timing, opts)?;
         }
         Commands::ForgeKey => {
-            let mut key = Zeroizing::new([0u8; 32]);
+            let mut key = Zeroizing::new([0u8; zfs::KEY_LEN]);
             OsRng.fill_bytes(&mut *key);
-            println!("{}", hex::encode(&key[..]));
+            let key_hex = Zeroizing::new(hex::encode(&key[..]));
+            println!("{}", *key_hex);
             ui.success("Raw beskar drawn into key form. This is the Way.");
             timing.pace(Pace::Prompt);
         }
 
-        Commands::Unlock => {
-            let dataset = resolve_dataset(&cli.dataset, cfg)?;
-            cmd::unlock::run_unlock(ui, timing, cfg, &dataset, UnlockOptions::default())?;
+        Commands::Unlock {
+            all: true,
+            recovery,
+            mount,
+            accept_new_checksum,
+            yes,
+            stdin_key,
+            skip_checksum,
+        } => {
+            reject_dataset_with_all(cli)?;
+            let opts = UnlockOptions {
+                recovery: *recovery,
+                mount: *mount,
+                accept_new_checksum: *accept_new_checksum,
+                yes: *yes,
+                stdin_key: *stdin_key,
+                skip_checksum: *skip_checksum,
+                sources: &cfg.fallback.order,
+                ..UnlockOptions::default()
+            };
+            cmd::unlock::run_unlock_all(ui, timing, cfg, cli.pool.as_deref(), opts)?;
         }
 
-        Commands::Lock => {
-            let dataset = resolve_dataset(&cli.dataset, cfg)?;
-            let timeout = Duration::from_secs(cfg.crypto.timeout_secs.max(1));
-            let zfs = if let Some(path) = &cfg.policy.zfs_path {
-                zfs::Zfs::with_path(path, timeout)?
-            } else {
-                zfs::Zfs::discover(timeout)?
+        Commands::Unlock {
+            all: false,
+            recovery,
+            mount,
+            accept_new_checksum,
+            yes,
+            stdin_key,
+            skip_checksum,
+        } => {
+            let opts = UnlockOptions {
+                recovery: *recovery,
+                mount: *mount,
+                named: names_one_dataset(cli),
+                accept_new_checksum: *accept_new_checksum,
+                yes: *yes,
+                stdin_key: *stdin_key,
+                skip_checksum: *skip_checksum,
+                sources: &cfg.fallback.order,
+                ..UnlockOptions::default()
             };
-            let enc_root = determine_encryption_root(&zfs, &dataset, ui);
-            zfs.unload_key(&enc_root)?;
-            ui.success(&format!("Vault sealed tight around {}.", enc_root));
-            timing.pace(Pace::Critical);
+            let targets = resolve_targets(ui, cli, cfg)?;
+            if *stdin_key && targets.len() > 1 {
+                return Err(anyhow!(
+                    "--stdin-key reads the key once; use `unlock --all` to open several roots with it"
+                ));
+            }
+            let several = targets.len() > 1;
+            let mut failed = Vec::new();
+            for dataset in targets {
+                if let Err(err) = cmd::unlock::run_unlock(ui, timing, cfg, &dataset, opts) {
+                    if !several {
+                        return Err(err);
+                    }
+                    ui.error(&format!("{} stays sealed ({}).", dataset, err));
+                    failed.push(dataset);
+                }
+            }
+            if !failed.is_empty() {
+                return Err(anyhow!(
+                    "{} encryption root(s) remain sealed: {}",
+                    failed.len(),
+                    failed.join(", ")
+                ));
+            }
+        }
+
+        Commands::Lock { unmount } => {
+            let targets = resolve_targets(ui, cli, cfg)?;
+            let several = targets.len() > 1;
+            let mut failed = Vec::new();
+            for dataset in targets {
+                if let Err(err) = lock_dataset(ui, timing, cfg, &dataset, *unmount) {
+                    if !several {
+                        return Err(err);
+                    }
+                    ui.error(&format!("{} stays unlocked ({}).", dataset, err));
+                    failed.push(dataset);
+                }
+            }
+            if !failed.is_empty() {
+                return Err(anyhow!(
+                    "{} encryption root(s) could not be locked: {}",
+                    failed.len(),
+                    failed.join(", ")
+                ));
+            }
         }
 
-        Commands::AutoUnlock { strict_usb } => {
-            let dataset = resolve_dataset(&cli.dataset, cfg)?;
+        Commands::AutoUnlock { strict_usb, all } => {
             let opts = UnlockOptions {
                 strict_usb: *strict_usb,
+                sources: &cfg.fallback.order,
+                ..UnlockOptions::default()
             };
-            cmd::unlock::run_unlock(ui, timing, cfg, &dataset, opts)?;
+            if *all {
+                reject_dataset_with_all(cli)?;
+                match &cli.pool {
+                    Some(pool) => {
+                        cmd::unlock::gate_pool_health(ui, cfg, std::slice::from_ref(pool))?
+                    }
+                    None => cmd::unlock::gate_pool_health(ui, cfg, &cfg.policy.datasets)?,
+                }
+                cmd::unlock::run_unlock_all(ui, timing, cfg, cli.pool.as_deref(), opts)?;
+            } else {
+                let dataset = resolve_dataset(ui, cli, cfg)?;
+                cmd::unlock::gate_pool_health(ui, cfg, std::slice::from_ref(&dataset))?;
+                cmd::unlock::run_unlock(ui, timing, cfg, &dataset, opts)?;
+            }
         }
 
-        Commands::Recover => {
-            let dataset = resolve_dataset(&cli.dataset, cfg)?;
-            cmd::recover::run_recover(ui, timing, &dataset)?;
+        Commands::Recover {
+            in_place,
+            label,
+            usb_device,
+        } => {
+            let dataset = resolve_dataset(ui, cli, cfg)?;
+            let label = label.as_deref().unwrap_or(&cfg.usb.label);
+            cmd::recover::run_recover(
+                ui,
+                timing,
+                cfg,
+                &dataset,
+                *in_place,
+                label,
+                usb_device.as_deref(),
+            )?;
             timing.pace(Pace::Prompt);
         }
 
-        Commands::Doctor => {
-            cmd::doctor::run_doctor(ui, timing)?;
+        Commands::Escrow {
+            recipient_pubkey,
+            output,
+        } => {
+            let dataset = resolve_dataset(ui, cli, cfg)?;
+            cmd::escrow::run_escrow(ui, timing, cfg, &dataset, recipient_pubkey, output)?;
+        }
+
+        Commands::Unescrow { identity, input } => {
+            let dataset = resolve_dataset(ui, cli, cfg)?;
+            cmd::escrow::run_unescrow(ui, timing, cfg, &dataset, identity, input)?;
+        }
+
+        Commands::EnrollFido2 {
+            device,
+            relying_party,
+            primary,
+        } => {
+            let opts = cmd::fido2::Fido2EnrollOptions {
+                device: device.clone(),
+                relying_party: relying_party.clone(),
+                primary: *primary,
+            };
+            cmd::fido2::run_enroll_fido2(ui, timing, cfg, &opts)?;
+        }
+
+        Commands::TestInitramfs => {
+            cmd::simulate::run_initramfs_drill(ui, timing, cfg)?;
+        }
+
+        Commands::Benchmark {
+            iterations,
+            simulate,
+        } => {
+            if *simulate {
+                cmd::simulate::run_benchmark_drill(ui, timing, cfg, *iterations)?;
+            } else {
+                let dataset = resolve_dataset(ui, cli, cfg)?;
+                cmd::benchmark::run_benchmark(ui, timing, cfg, &dataset, *iterations)?;
+            }
+        }
+
+        Commands::SetBreakGlass { path } => {
+            cmd::breakglass::run_set_break_glass(ui, timing, cfg, path.as_deref())?;
+        }
+
+        Commands::ChangePin { source } => {
+            cmd::changepin::run_change_pin(ui, timing, cfg, *source)?;
+        }
+
+        Commands::ListTokens => {
+            cmd::tokens::run_list_tokens(ui, timing, cfg)?;
+        }
+
+        Commands::Fingerprint { format } => {
+            cmd::fingerprint::run_show_fingerprint(ui, timing, cfg, *format)?;
         }
 
-        Commands::InstallUnits => {
+        Commands::AuditLog { since, event, tail } => {
+            let opts = cmd::auditlog::AuditLogOptions {
+                since: since.clone(),
+                event: event.clone(),
+                tail: *tail,
+                utc: cfg.audit.utc,
+            };
+            cmd::auditlog::run_audit_log(ui, timing, &opts)?;
+        }
+
+        // `main` answers generate-config before any config is loaded.
+        Commands::GenerateConfig { .. } => {
+            unreachable!("generate-config is handled before dispatch")
+        }
+
+        Commands::SetKeylocation { location } => {
+            let dataset = resolve_dataset(ui, cli, cfg)?;
+            cmd::keylocation::run_set_keylocation(ui, timing, cfg, &dataset, location)?;
+        }
+
+        Commands::Doctor { fix_only, explain } => {
+            let opts = cmd::doctor::DoctorOptions {
+                fix_only: fix_only
+                    .as_deref()
+                    .map(cmd::doctor::RepairCategory::parse_list)
+                    .transpose()?,
+                explain: *explain,
+            };
+            cmd::doctor::run_doctor(ui, timing, &cfg.path, &opts)?;
+        }
+
+        Commands::Status => {
+            for dataset in resolve_targets(ui, cli, cfg)? {
+                cmd::status::run_status(ui, timing, cfg, &dataset)?;
+            }
+        }
+
+        Commands::InstallUnits { check_only } => {
             let binary_path = determine_binary_path(Some(cfg))?;
-            cmd::repair::install_units(ui, cfg, &binary_path)?;
-            ui.success("Systemd sentries posted. This is the Way.");
+            if *check_only {
+                cmd::repair::check_units(ui, cfg, &binary_path)?;
+                ui.success("Systemd sentries match their rendered form.");
+            } else {
+                cmd::repair::install_units(ui, cfg, &binary_path)?;
+                ui.success("Systemd sentries posted. This is the Way.");
+            }
             timing.pace(Pace::Prompt);
         }
-        Commands::InstallDracut => {
-            cmd::dracut_install::run(ui, cfg, cli.dataset.as_deref(), None)?;
+        Commands::InstallDracut { force } => {
+            cmd::dracut_install::run(ui, cfg, cli.dataset.as_deref(), None, *force)?;
             timing.pace(Pace::Prompt);
         }
 
         Commands::SelfTest { fallback } => {
             let fallback = *fallback;
             ui.info("Initiating beskar self-test sequence…");
-            let dataset = resolve_dataset(&cli.dataset, cfg)?;
-            let timeout = Duration::from_secs(cfg.crypto.timeout_secs.max(1));
-            let zfs = if let Some(path) = &cfg.policy.zfs_path {
-                zfs::Zfs::with_path(path, timeout)?
-            } else {
-                zfs::Zfs::discover(timeout)?
-            };
-            let enc_root = zfs.encryption_root(&dataset).unwrap_or(dataset.clone());
+            let dataset = resolve_dataset(ui, cli, cfg)?;
+            let zfs = zfs::Zfs::from_config(cfg)?;
+            let enc_root = zfs
+                .encryption_root(&dataset)
+                .with_context(|| format!("resolve encryption root for {}", dataset))?
+                .ok_or_else(|| zfs::not_encrypted(&dataset))?;
             ui.info(&format!("Encryption root confirmed as {}.", enc_root));
             let _ = zfs.unload_key(&enc_root);
             if !zfs.is_unlocked(&enc_root)? {
@@ -275,13 +703,11 @@ fn dispatch_command(
                 None
             };
 
-            let unlock_opts = if fallback {
-                UnlockOptions { strict_usb: false }
-            } else {
-                UnlockOptions::default()
+            let opts = UnlockOptions {
+                sources: &cfg.fallback.order,
+                ..UnlockOptions::default()
             };
-
-            let result = cmd::unlock::run_unlock(ui, timing, cfg, &enc_root, unlock_opts);
+            let result = cmd::unlock::run_unlock(ui, timing, cfg, &enc_root, opts);
             if let Some(guard) = hide_guard.as_ref() {
                 guard.restore()?;
             }
@@ -320,6 +746,14 @@ fn dispatch_menu_choice(
                 force: true,
                 auto_unlock: true,
                 confirm_each_phase: false,
+                key_name_template: cfg.usb.key_name_template.clone(),
+                checksum_algo: cfg.usb.checksum_algo,
+                skip_initramfs: false,
+                label: cfg.usb.label.clone(),
+                raw_token: false,
+                recovery_display: cfg.policy.recovery_display,
+                recovery_file: cfg.policy.recovery_file.as_ref().map(PathBuf::from),
+                artifacts_dir: None,
             };
             cmd::init::run_init(ui, timing, opts)?;
         }
@@ -331,6 +765,14 @@ fn dispatch_menu_choice(
                 force: false,
                 auto_unlock: true,
                 confirm_each_phase: true,
+                key_name_template: cfg.usb.key_name_template.clone(),
+                checksum_algo: cfg.usb.checksum_algo,
+                skip_initramfs: false,
+                label: cfg.usb.label.clone(),
+                raw_token: false,
+                recovery_display: cfg.policy.recovery_display,
+                recovery_file: cfg.policy.recovery_file.as_ref().map(PathBuf::from),
+                artifacts_dir: None,
             };
             cmd::init::run_init(ui, timing, opts)?;
         }
@@ -338,11 +780,12 @@ fn dispatch_menu_choice(
             cmd::simulate::run_vault_drill(ui, timing, cfg)?;
         }
         menu::MenuChoice::Recover => {
-            let dataset = resolve_dataset(&cli.dataset, cfg)?;
-            cmd::recover::run_recover(ui, timing, &dataset)?;
+            let dataset = resolve_dataset(ui, cli, cfg)?;
+            cmd::recover::run_recover(ui, timing, cfg, &dataset, false, &cfg.usb.label, None)?;
         }
         menu::MenuChoice::Doctor => {
-            cmd::doctor::run_doctor(ui, timing)?;
+            let opts = cmd::doctor::DoctorOptions::default();
+            cmd::doctor::run_doctor(ui, timing, &cfg.path, &opts)?;
         }
         menu::MenuChoice::Quit => {
             ui.info("Forge console banked. Return with new orders.");
@@ -355,11 +798,84 @@ fn dispatch_menu_choice(
 // ----------------------------------------------------------------------------
 // Helpers
 // ----------------------------------------------------------------------------
-fn resolve_dataset(dataset_opt: &Option<String>, cfg: &ConfigFile) -> Result<String> {
-    if let Some(d) = dataset_opt {
+/// Minimal config written when none exists. It audits in UTC, as the config
+/// `init` writes does.
+const STARTER_CONFIG: &str = r#"version = 1
+
+[crypto]
+timeout_secs = 10
+
+[usb]
+key_hex_path = "/run/beskar/key.hex"
+
+[policy]
+zfs_path = "/sbin/zfs"
+binary_path = "/usr/local/bin/zfs_beskar_key"
+datasets = ["rpool/ROOT"]
+
+[fallback]
+enabled = true
+askpass = true
+askpass_path = "/usr/bin/systemd-ask-password"
+
+[audit]
+utc = true
+"#;
+
+fn write_starter_config(cfg_path: &Path, ui: &UX) -> Result<()> {
+    ui.warn(&format!(
+        "Forge ledger missing at {} — I will inscribe a starter creed.",
+        cfg_path.display()
+    ));
+
+    let mut f = File::create(cfg_path)
+        .with_context(|| format!("create default config at {}", cfg_path.display()))?;
+    f.write_all(STARTER_CONFIG.as_bytes())?;
+    fs::set_permissions(cfg_path, fs::Permissions::from_mode(0o600))
+        .context("set config permissions")?;
+    ui.info(&format!(
+        "Template etched at {}. Inspect every value before the next muster.",
+        cfg_path.display()
+    ));
+    Ok(())
+}
+
+/// `generate-config`: the commented template on stdout, or written to
+/// `output` (mode 0600) when that path is free or `force` is set.
+fn generate_config(output: Option<&Path>, force: bool, ui: &UX) -> Result<()> {
+    let Some(path) = output else {
+        print!("{}", config::CONFIG_TEMPLATE);
+        return Ok(());
+    };
+    atomic_write_bytes(path, config::CONFIG_TEMPLATE.as_bytes(), 0o600, force)
+        .with_context(|| format!("write config template to {}", path.display()))?;
+    ui.info(&format!(
+        "Annotated creed etched at {}. Tune it, then point --config at it.",
+        path.display()
+    ));
+    Ok(())
+}
+
+/// The one dataset a single-target command acts on. Globs and `--recursive`
+/// only make sense for `unlock`/`lock`, so they are refused here.
+fn resolve_dataset(ui: &UX, cli: &Cli, cfg: &ConfigFile) -> Result<String> {
+    if cli.recursive {
+        return Err(anyhow!(
+            "--recursive only applies to unlock and lock; this command acts on one dataset"
+        ));
+    }
+    if let Some(d) = &cli.dataset {
+        if zfs::DatasetSelector::parse(d, false).is_some() {
+            return Err(anyhow!(
+                "--dataset {} matches several datasets; this command acts on exactly one",
+                d
+            ));
+        }
         Ok(d.clone())
     } else if let Some(d) = cfg.policy.datasets.first() {
         Ok(d.clone())
+    } else if ui::interactive_terminal() && io::stdout().is_terminal() && !ui.json_mode() {
+        pick_encrypted_dataset(ui, cfg)
     } else {
         Err(anyhow!(
             "dataset not specified; use --dataset or config.policy.datasets[0]"
@@ -367,6 +883,188 @@ fn resolve_dataset(dataset_opt: &Option<String>, cfg: &ConfigFile) -> Result<Str
     }
 }
 
+/// First-run helper: nothing configured and a human at the terminal, so offer
+/// every encrypted dataset on the system.
+fn pick_encrypted_dataset(ui: &UX, cfg: &ConfigFile) -> Result<String> {
+    let zfs = zfs::Zfs::from_config(cfg)?;
+    let mut candidates = zfs.encrypted_datasets()?;
+    if candidates.is_empty() {
+        return Err(anyhow!(
+            "dataset not specified and no encrypted datasets found; use --dataset"
+        ));
+    }
+    ui.note("No dataset configured; choose one (set policy.datasets to skip this).");
+    let choice = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Encrypted dataset")
+        .items(&candidates)
+        .default(0)
+        .interact()
+        .context("select dataset")?;
+    Ok(candidates.swap_remove(choice))
+}
+
+/// `--all` takes its targets from config, narrowed to one pool by `--pool`; a
+/// `--dataset` alongside it is a mistake.
+fn reject_dataset_with_all(cli: &Cli) -> Result<()> {
+    if cli.dataset.is_some() {
+        return Err(anyhow!(
+            "--all unlocks every configured dataset; drop --dataset or narrow it with --pool"
+        ));
+    }
+    Ok(())
+}
+
+/// True when `--dataset` names exactly one dataset: no glob, `--recursive` or
+/// `--pool`. Only then may `unlock --mount` force a `canmount=noauto` root.
+fn names_one_dataset(cli: &Cli) -> bool {
+    cli.pool.is_none()
+        && cli
+            .dataset
+            .as_deref()
+            .is_some_and(|dataset| zfs::DatasetSelector::parse(dataset, cli.recursive).is_none())
+}
+
+/// `--pool` expands to every encryption root in that pool, and a glob or
+/// `--recursive` dataset to every root it covers; otherwise the single
+/// dataset from `resolve_dataset`.
+fn resolve_targets(ui: &UX, cli: &Cli, cfg: &ConfigFile) -> Result<Vec<String>> {
+    let selector = cli
+        .dataset
+        .as_deref()
+        .and_then(|dataset| zfs::DatasetSelector::parse(dataset, cli.recursive));
+    if cli.pool.is_none() && selector.is_none() {
+        if cli.recursive {
+            return Err(anyhow!("--recursive needs --dataset to name the subtree"));
+        }
+        return Ok(vec![resolve_dataset(ui, cli, cfg)?]);
+    }
+    let zfs = zfs::Zfs::from_config(cfg)?;
+    let (roots, what) = match (&selector, &cli.pool) {
+        (Some(selector), _) => (
+            zfs.encryption_roots_matching(selector)?,
+            format!("--dataset {}", cli.dataset.as_deref().unwrap_or_default()),
+        ),
+        (None, pool) => {
+            let pool = pool.as_deref().unwrap_or_default();
+            (
+                zfs.encryption_roots_in_pool(pool)?,
+                format!("pool {}", pool),
+            )
+        }
+    };
+    if roots.is_empty() {
+        return Err(anyhow!("{} matches no encrypted datasets", what));
+    }
+    Ok(roots)
+}
+
+/// Seal `dataset`'s encryption root and every descendant root under it.
+fn lock_dataset(
+    ui: &UX,
+    timing: &Timing,
+    cfg: &ConfigFile,
+    dataset: &str,
+    unmount: bool,
+) -> Result<()> {
+    let zfs = zfs::Zfs::from_config(cfg)?;
+    let enc_root = determine_encryption_root(&zfs, dataset, ui)?;
+    let mounted = zfs.mounted_descendants(&enc_root)?;
+    if !mounted.is_empty() {
+        if !unmount {
+            for (name, mountpoint) in &mounted {
+                ui.warn(&format!("{} is mounted at {}.", name, mountpoint));
+            }
+            timing.pace(Pace::Error);
+            return Err(anyhow!(
+                "{} mounted dataset(s) under {} block the lock; unmount them or rerun with --unmount",
+                mounted.len(),
+                enc_root
+            ));
+        }
+        for (name, mountpoint) in &mounted {
+            if let Err(err) = zfs.unmount(name) {
+                let holders = mount_holders(mountpoint);
+                if !holders.is_empty() {
+                    ui.warn(&format!(
+                        "{} still held by PID(s) {}.",
+                        mountpoint,
+                        describe_holders(&holders)
+                    ));
+                }
+                timing.pace(Pace::Error);
+                return Err(err);
+            }
+            ui.info(&format!("Unmounted {} from {}.", name, mountpoint));
+        }
+    }
+    let report = zfs.unload_key_tree(&enc_root)?;
+    for root in std::iter::once(&enc_root).chain(&report.sealed) {
+        match keyring::revoke_cached(root) {
+            Ok(true) => {
+                ui.info(&format!(
+                    "Cached key for {} revoked from the keyring.",
+                    root
+                ));
+                audit_log("LOCK_KEYRING_REVOKE", root);
+            }
+            Ok(false) => {}
+            Err(err) => ui.warn(&format!(
+                "Unable to revoke cached key for {} ({}).",
+                root, err
+            )),
+        }
+        if let Some(spec) = &cfg.crypto.keyring {
+            let description = keyring::description_for(root);
+            match keyring::parse_target(spec).and_then(|t| keyring::revoke_deposited(&t, root)) {
+                Ok(true) => {
+                    ui.info(&format!(
+                        "Handed-off key {} revoked from keyring {}.",
+                        description, spec
+                    ));
+                    audit_log(
+                        "LOCK_KEYRING_REVOKE",
+                        &format!("{} in {}", description, spec),
+                    );
+                }
+                Ok(false) => {}
+                Err(err) => ui.warn(&format!(
+                    "Unable to revoke {} from keyring {} ({}).",
+                    description, spec, err
+                )),
+            }
+        }
+    }
+    if report.sealed.is_empty() && report.is_clean() {
+        ui.note(&format!(
+            "{} and its descendants already stand sealed.",
+            enc_root
+        ));
+    }
+    for sealed in &report.sealed {
+        ui.info(&format!("Key withdrawn from {}.", sealed));
+    }
+    for (busy, reason) in &report.busy {
+        ui.warn(&format!(
+            "{} refused to seal while mounted ({}). Unmount it and rerun lock.",
+            busy, reason
+        ));
+    }
+    for (failed, reason) in &report.failed {
+        ui.error(&format!("Unable to seal {} ({}).", failed, reason));
+    }
+    if !report.is_clean() {
+        timing.pace(Pace::Error);
+        return Err(anyhow!(
+            "{} dataset(s) under {} remain unlocked",
+            report.busy.len() + report.failed.len(),
+            enc_root
+        ));
+    }
+    ui.success(&format!("Vault sealed tight around {}.", enc_root));
+    timing.pace(Pace::Critical);
+    Ok(())
+}
+
 struct HiddenKeyFile {
     original: PathBuf,
     backup: PathBuf,
@@ -401,9 +1099,9 @@ impl Drop for HiddenKeyFile {
     }
 }
 
-fn determine_encryption_root(zfs: &impl ZfsCryptoOps, dataset: &str, ui: &UX) -> String {
+fn determine_encryption_root(zfs: &impl ZfsCryptoOps, dataset: &str, ui: &UX) -> Result<String> {
     match zfs.encryption_root(dataset) {
-        Ok(root) => {
+        Ok(Some(root)) => {
             if root != dataset {
                 ui.info(&format!(
                     "Dataset {} draws its ward from encryption root {}.",
@@ -412,24 +1110,19 @@ fn determine_encryption_root(zfs: &impl ZfsCryptoOps, dataset: &str, ui: &UX) ->
             } else {
                 ui.info(&format!("Encryption root stands as {}.", root));
             }
-            root
-        }
-        Err(e) => {
-            ui.warn(&format!(
-                "Unable to read the encryption lineage for {} ({}). Proceeding against the dataset directly.",
-                dataset, e
-            ));
-            dataset.to_string()
+            Ok(root)
         }
+        Ok(None) => Err(zfs::not_encrypted(dataset)),
+        Err(e) => Err(e.context(format!("resolve encryption root for {}", dataset))),
     }
 }
 
 trait ZfsCryptoOps {
-    fn encryption_root(&self, dataset: &str) -> Result<String>;
+    fn encryption_root(&self, dataset: &str) -> Result<Option<String>>;
 }
 
 impl ZfsCryptoOps for zfs::Zfs {
-    fn encryption_root(&self, dataset: &str) -> Result<String> {
+    fn encryption_root(&self, dataset: &str) -> Result<Option<String>> {
         zfs::Zfs::encryption_root(self, dataset)
     }
 }
@@ -448,7 +1141,7 @@ fn auto_unlock_with(
     cfg: &ConfigFile,
     dataset: &str,
 ) -> Result<()> {
-    let enc_root = determine_encryption_root(zfs, dataset, ui);
+    let enc_root = determine_encryption_root(zfs, dataset, ui)?;
 
     let unlocked = match zfs.is_unlocked(&enc_root) {
         Ok(state) => state,
@@ -472,12 +1165,11 @@ fn auto_unlock_with(
         .with_context(|| format!("normalize USB key file {}", usb_path.display()))?
         .raw;
 
-    let mut hasher = Sha256::new();
-    hasher.update(&raw_key_bytes);
-    let actual_hash = hex::encode(hasher.finalize());
+    let algo = cfg.usb.checksum_algo;
+    let actual_hash = algo.digest_hex(&raw_key_bytes);
 
     if let Some(expected_hash) = cfg.usb.expected_sha256.as_ref() {
-        if !actual_hash.eq_ignore_ascii_case(expected_hash) {
+        if !algo.matches(&raw_key_bytes, expected_hash) {
             ui.error("USB key checksum mismatch detected.");
             ui.warn(&format!(
                 "Expected: {}\nFound:    {}",
@@ -485,9 +1177,15 @@ fn auto_unlock_with(
             ));
             return Err(anyhow!("USB key checksum mismatch"));
         }
-        ui.info("USB key checksum verified (SHA-256 match).");
+        ui.info(&format!(
+            "USB key checksum verified ({} match).",
+            algo.label()
+        ));
     } else {
-        ui.warn("No stored SHA-256; authenticity skipped.");
+        ui.warn(&format!(
+            "No stored {}; authenticity skipped.",
+            algo.label()
+        ));
     }
 
     if !unlocked {
@@ -508,7 +1206,10 @@ fn auto_unlock_with(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{ConfigFile, CryptoCfg, Fallback, Policy, Usb};
+    use crate::config::{
+        Audit, ConfigFile, CryptoCfg, Fallback, Fido2, Policy, RecoveryDisplay, UiCfg, Usb,
+    };
+    use crate::util::checksum::ChecksumAlgo;
     use anyhow::Result;
     use std::io::Write;
     use std::path::PathBuf;
@@ -537,12 +1238,13 @@ mod tests {
     }
 
     impl ZfsCryptoOps for MockZfs {
-        fn encryption_root(&self, dataset: &str) -> Result<String> {
+        fn encryption_root(&self, dataset: &str) -> Result<Option<String>> {
             self.encryption_queries
                 .lock()
                 .unwrap()
                 .push(dataset.to_string());
-            Ok(self.root.clone())
+            // An empty root models an unencrypted dataset (encryptionroot `-`).
+            Ok((!self.root.is_empty()).then(|| self.root.clone()))
         }
     }
 
@@ -568,19 +1270,48 @@ mod tests {
         writeln!(key_file, "{hex_key}")?;
 
         let cfg = ConfigFile {
+            version: config::CONFIG_VERSION,
             policy: Policy {
                 datasets: vec!["rpool/ROOT/ubuntu".into()],
                 zfs_path: None,
                 binary_path: None,
                 allow_root: false,
+                extra_allowed_binaries: Vec::new(),
+                require_healthy_pool: false,
+                dataset_overrides: Vec::new(),
+                recovery_display: RecoveryDisplay::Stdout,
+                recovery_file: None,
+            },
+            crypto: CryptoCfg {
+                timeout_secs: 5,
+                keyring: None,
+                keyring_secs: 600,
+                keyring_cache: false,
+                keyring_cache_secs: 600,
+                require_all_descendants: false,
             },
-            crypto: CryptoCfg { timeout_secs: 5 },
             usb: Usb {
                 key_hex_path: key_file.path().to_string_lossy().into_owned(),
                 expected_sha256: None,
+                checksum_algo: ChecksumAlgo::default(),
+                key_name_template: None,
+                label: crate::dracut::BESKAR_TOKEN_LABEL.to_string(),
+                labels: Vec::new(),
+                strict_keyfile: false,
+                wait_secs: config::DEFAULT_USB_WAIT_SECS,
+                auto_mount: true,
+                require_serial: None,
+                require_vidpid: None,
+                break_glass_path: None,
+                raw_device: None,
+                raw_offset: crate::util::rawtoken::DEFAULT_OFFSET,
             },
             fallback: Fallback::default(),
+            fido2: Fido2::default(),
+            audit: Audit::default(),
+            ui: UiCfg::default(),
             path: PathBuf::from("/tmp/test-config"),
+            migrated_from: None,
         };
 
         let ui = UX::new(false, false);
@@ -599,4 +1330,63 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn unencrypted_dataset_stops_before_any_key_operation() {
+        let ui = UX::new(false, false);
+        let mock = MockZfs::new("", false);
+
+        let err = determine_encryption_root(&mock, "rpool/scratch", &ui).unwrap_err();
+        assert!(err.to_string().contains("rpool/scratch is not encrypted"));
+        assert!(mock.load_calls.lock().unwrap().is_empty());
+        assert!(mock.is_unlocked_calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_a_literal_dataset_counts_as_named() {
+        let named = |args: &[&str]| {
+            let cli = Cli::try_parse_from(
+                ["zfs_beskar_key"]
+                    .iter()
+                    .chain(args)
+                    .chain(&["unlock", "--mount"]),
+            )
+            .unwrap();
+            names_one_dataset(&cli)
+        };
+        assert!(named(&["--dataset", "rpool/ROOT/ubuntu"]));
+        assert!(!named(&[]));
+        assert!(!named(&["--pool", "rpool"]));
+        assert!(!named(&["--dataset", "rpool/ROOT/*"]));
+        assert!(!named(&["--dataset", "rpool/ROOT", "--recursive"]));
+    }
+
+    #[test]
+    fn single_target_commands_refuse_dataset_selectors() {
+        let ui = UX::new(false, true);
+        let cfg: ConfigFile = toml::from_str(STARTER_CONFIG).unwrap();
+        let resolve = |args: &[&str]| {
+            let cli = Cli::try_parse_from(
+                ["zfs_beskar_key"]
+                    .iter()
+                    .chain(args)
+                    .chain(&["set-keylocation", "prompt"]),
+            )
+            .unwrap();
+            resolve_dataset(&ui, &cli, &cfg)
+        };
+        assert_eq!(
+            resolve(&["--dataset", "rpool/ROOT/ubuntu"]).unwrap(),
+            "rpool/ROOT/ubuntu"
+        );
+        assert!(resolve(&["--dataset", "rpool/ROOT/*"]).is_err());
+        assert!(resolve(&["--dataset", "rpool/ROOT", "--recursive"]).is_err());
+    }
+
+    #[test]
+    fn starter_config_logs_in_utc_like_init() {
+        let cfg: ConfigFile = toml::from_str(STARTER_CONFIG).unwrap();
+        assert_eq!(cfg.version, config::CONFIG_VERSION);
+        assert!(cfg.audit.utc);
+    }
 }
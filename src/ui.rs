@@ -2,22 +2,70 @@
 // src/ui.rs – Mandalorian-inspired CLI experience with security-forward tone
 // ============================================================================
 
-use anyhow::Result;
+use crate::util::initramfs;
+use anyhow::{anyhow, Result};
 use chrono::Local;
 use console::Style;
 use std::{
     env,
-    io::{self, Write},
-    sync::atomic::{AtomicBool, Ordering},
+    io::{self, IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
+use unicode_width::UnicodeWidthStr;
 
 pub const BANNER_BODY_WIDTH: usize = 100;
 const LOG_BODY_WIDTH: usize = BANNER_BODY_WIDTH;
 const DEFAULT_CURSOR_DELAY_MS: u64 = 6;
 const CYBER_FLICKER_PALETTE: [u8; 6] = [208, 214, 220, 178, 142, 202];
 const CYBER_FLICKER_DELAY_MS: u64 = 14;
+const SPINNER_FRAMES: [&str; 4] = ["◐", "◓", "◑", "◒"];
+const SPINNER_TICK_MS: u64 = 120;
+const SPINNER_TRACE_WIDTH: usize = 60;
+
+/// `--non-interactive`: refuse every prompt even when a terminal is attached.
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_non_interactive(on: bool) {
+    NON_INTERACTIVE.store(on, Ordering::Relaxed);
+}
+
+/// True under `--non-interactive`; callers with a safe default (a prompt that
+/// may be left blank) take it instead of failing.
+pub fn non_interactive() -> bool {
+    NON_INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Whether a human can answer a prompt: stdin must be a terminal. Boot units
+/// and pipes fail this, so prompts bail out instead of letting dialoguer error
+/// cryptically or block on a stdin that never answers.
+pub fn interactive_terminal() -> bool {
+    !non_interactive() && io::stdin().is_terminal()
+}
+
+/// Typing effects only make sense on a terminal: piped output, CI logs and
+/// the initramfs always take the instant `println!` path.
+fn animations_possible() -> bool {
+    !initramfs::detected() && io::stdout().is_terminal()
+}
+
+/// `interactive_terminal` as an error naming what needed the operator.
+pub fn require_terminal(purpose: &str) -> Result<()> {
+    if non_interactive() {
+        Err(anyhow!(
+            "--non-interactive forbids the prompt for {}",
+            purpose
+        ))
+    } else if interactive_terminal() {
+        Ok(())
+    } else {
+        Err(anyhow!("no interactive terminal available for {}", purpose))
+    }
+}
 
 #[derive(Clone)]
 struct Theme {
@@ -115,6 +163,7 @@ pub struct Timing {
 
 impl Timing {
     pub fn new(verbose: bool, quiet: bool) -> Self {
+        let quiet = quiet || initramfs::detected();
         let base = env::var("BESKAR_UI_DELAY_SECS")
             .ok()
             .and_then(|v| v.parse::<u64>().ok())
@@ -151,6 +200,54 @@ impl Timing {
     }
 }
 
+// --------------------------- Spinner ----------------------------------------
+
+/// Background status-line animation for genuinely long operations.
+/// The worker stops and clears its line when the handle is dropped.
+pub struct SpinnerHandle {
+    stop: Arc<AtomicBool>,
+    trace: Arc<Mutex<String>>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl SpinnerHandle {
+    fn inert() -> Self {
+        Self {
+            stop: Arc::new(AtomicBool::new(true)),
+            trace: Arc::new(Mutex::new(String::new())),
+            worker: None,
+        }
+    }
+
+    /// Show `line` (e.g. the latest tool output) after the spinner label.
+    /// No-op when the spinner is inert.
+    pub fn trace(&self, line: &str) {
+        if self.worker.is_none() || line.trim().is_empty() {
+            return;
+        }
+        if let Ok(mut current) = self.trace.lock() {
+            *current = UX::trim_to_width(line.trim(), SPINNER_TRACE_WIDTH);
+        }
+    }
+
+    /// True while the animation is drawing; an inert spinner shows nothing.
+    pub fn is_live(&self) -> bool {
+        self.worker.is_some()
+    }
+
+    /// Stop the animation explicitly (equivalent to dropping the handle).
+    pub fn finish(self) {}
+}
+
+impl Drop for SpinnerHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
 // --------------------------- UX Facade --------------------------------------
 
 /// Centralized UX facade for Mandalorian-flavored messaging.
@@ -163,10 +260,12 @@ pub struct UX {
     app_version: &'static str,
     operator: String,
     cursor_delay: Duration,
+    animations: AtomicBool,
 }
 
 impl UX {
     pub fn new(verbose: bool, quiet: bool) -> Self {
+        let quiet = quiet || initramfs::detected();
         let operator = env::var("USER")
             .or_else(|_| env::var("LOGNAME"))
             .unwrap_or_else(|_| "unknown-operator".to_string());
@@ -185,18 +284,79 @@ impl UX {
             app_version: env!("CARGO_PKG_VERSION"),
             operator,
             cursor_delay,
+            animations: AtomicBool::new(animations_possible()),
         }
     }
 
+    /// Toggle typing and banner-flicker delays (`[ui] animations`, `--plain`).
+    /// Output content is unchanged either way.
+    pub fn set_animations(&self, enabled: bool) {
+        self.animations
+            .store(enabled && animations_possible(), Ordering::SeqCst);
+    }
+
+    /// `--json` / `BESKAR_UI=json`: callers with machine-readable reports
+    /// emit them as single JSON lines.
+    pub fn json_mode(&self) -> bool {
+        env::var("BESKAR_UI")
+            .map(|v| v.eq_ignore_ascii_case("json"))
+            .unwrap_or(false)
+    }
+
+    fn animated(&self) -> bool {
+        self.animations.load(Ordering::SeqCst)
+    }
+
+    /// Longest prefix of `text` that fits in `width` terminal columns; wide
+    /// characters (CJK, emoji) take two. Prefixes are measured as strings,
+    /// the same way the padding is, because sequences like `❤\u{FE0F}` are
+    /// wider than the sum of their chars.
     fn trim_to_width(text: &str, width: usize) -> String {
-        let mut buffer = String::with_capacity(width);
-        for (count, ch) in text.chars().enumerate() {
-            if count >= width {
+        let mut end = 0;
+        for (idx, ch) in text.char_indices() {
+            let next = idx + ch.len_utf8();
+            if text[..next].width() > width {
                 break;
             }
-            buffer.push(ch);
+            end = next;
+        }
+        text[..end].to_string()
+    }
+
+    /// `text` trimmed to `width` columns and left-aligned in exactly `width`.
+    /// `format!` padding counts chars, so it cannot be used for box interiors.
+    fn pad_to_width(text: &str, width: usize) -> String {
+        let trimmed = Self::trim_to_width(text, width);
+        let fill = width.saturating_sub(trimmed.width());
+        format!("{}{}", trimmed, " ".repeat(fill))
+    }
+
+    /// Like `pad_to_width`, centred (extra column on the right).
+    fn center_to_width(text: &str, width: usize) -> String {
+        let trimmed = Self::trim_to_width(text, width);
+        let fill = width.saturating_sub(trimmed.width());
+        format!(
+            "{}{}{}",
+            " ".repeat(fill / 2),
+            trimmed,
+            " ".repeat(fill - fill / 2)
+        )
+    }
+
+    /// Split a word wider than `width` into pieces that each fit.
+    fn split_to_width(word: &str, width: usize) -> Vec<String> {
+        let mut pieces = Vec::new();
+        let mut rest = word;
+        while !rest.is_empty() {
+            let mut piece = Self::trim_to_width(rest, width);
+            if piece.is_empty() {
+                // A single character wider than the box still has to go somewhere.
+                piece = rest.chars().next().map(String::from).unwrap_or_default();
+            }
+            rest = &rest[piece.len()..];
+            pieces.push(piece);
         }
-        buffer
+        pieces
     }
 
     fn cyber_tint(&self, text: &str, shift: usize) -> String {
@@ -218,7 +378,7 @@ impl UX {
             return;
         }
 
-        if slow {
+        if slow && self.animated() {
             let mut out = io::stdout();
             for ch in text.chars() {
                 let _ = write!(out, "{}", ch);
@@ -233,8 +393,7 @@ impl UX {
     }
 
     fn box_line(&self, content: &str, style: &Style) -> String {
-        let trimmed = Self::trim_to_width(content, LOG_BODY_WIDTH);
-        let padded = format!("║ {:<width$} ║", trimmed, width = LOG_BODY_WIDTH);
+        let padded = format!("║ {} ║", Self::pad_to_width(content, LOG_BODY_WIDTH));
         style.apply_to(padded).to_string()
     }
 
@@ -244,36 +403,25 @@ impl UX {
 
         for word in text.split_whitespace() {
             if current.is_empty() {
-                if word.chars().count() <= width {
+                if word.width() <= width {
                     current.push_str(word);
                 } else {
-                    for chunk in word.chars().collect::<Vec<_>>().chunks(width) {
-                        lines.push(chunk.iter().collect());
-                    }
+                    lines.extend(Self::split_to_width(word, width));
                 }
             } else {
-                let needed = current.chars().count() + 1 + word.chars().count();
+                let needed = current.width() + 1 + word.width();
                 if needed <= width {
                     current.push(' ');
                     current.push_str(word);
                 } else {
                     lines.push(current);
                     current = String::new();
-                    if word.chars().count() <= width {
+                    if word.width() <= width {
                         current.push_str(word);
                     } else {
-                        let chars = word.chars().collect::<Vec<_>>();
-                        let mut idx = 0;
-                        while idx < chars.len() {
-                            let chunk: String =
-                                chars[idx..(idx + width).min(chars.len())].iter().collect();
-                            if chunk.chars().count() == width && idx + width < chars.len() {
-                                lines.push(chunk);
-                            } else {
-                                current = chunk;
-                            }
-                            idx += width;
-                        }
+                        let mut pieces = Self::split_to_width(word, width);
+                        current = pieces.pop().unwrap_or_default();
+                        lines.extend(pieces);
                     }
                 }
             }
@@ -315,9 +463,8 @@ impl UX {
             .theme
             .log_border
             .apply_to(format!(
-                "║ {:<width$} ║",
-                Self::trim_to_width(&title, LOG_BODY_WIDTH),
-                width = LOG_BODY_WIDTH
+                "║ {} ║",
+                Self::pad_to_width(&title, LOG_BODY_WIDTH)
             ))
             .to_string();
         self.emit_line(&title_line, false);
@@ -390,6 +537,11 @@ impl UX {
         self.log_line(LogLevel::Note, msg);
     }
 
+    /// Muted tool output; shown only with `--verbose`.
+    pub fn trace(&self, msg: &str) {
+        self.log_line(LogLevel::Trace, msg);
+    }
+
     pub fn phase(&self, title: &str) {
         let normalized = title.trim().to_uppercase();
         self.divider();
@@ -412,12 +564,12 @@ impl UX {
             return;
         }
         self.ensure_log_header();
-        let label_width = rows.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+        let label_width = rows.iter().map(|(k, _)| k.width()).max().unwrap_or(0);
         let header_line = format!("{} manifest", title.to_uppercase());
         let header = self.box_line(&header_line, &self.theme.accent);
         self.emit_line(&header, true);
         for (idx, (label, value)) in rows.iter().enumerate() {
-            let label_formatted = format!("{:>width$}", label, width = label_width);
+            let label_formatted = format!("{}{}", " ".repeat(label_width - label.width()), label);
             let payload = format!("{} ⇢ {}", label_formatted, value);
             let style = if idx % 2 == 0 {
                 &self.theme.info
@@ -430,6 +582,13 @@ impl UX {
         self.divider();
     }
 
+    /// Pre-rendered art (a QR code) printed as-is, outside the log box.
+    pub fn verbatim(&self, text: &str) {
+        for line in text.lines() {
+            self.emit_line(line, false);
+        }
+    }
+
     fn render_banner_frame(&self) {
         if self.quiet {
             return;
@@ -470,8 +629,7 @@ impl UX {
         let inner_width = BANNER_BODY_WIDTH;
 
         for (idx, line) in CREST.iter().enumerate() {
-            let trimmed = Self::trim_to_width(line, inner_width);
-            let padded = format!("{:^width$}", trimmed, width = inner_width);
+            let padded = Self::center_to_width(line, inner_width);
             let tinted_body = self.cyber_tint(&padded, idx * 3);
 
             let color_idx = idx % border_palette.len();
@@ -502,7 +660,9 @@ impl UX {
             let decorated = format!("{}{}{}", motif_left, tinted_body, motif_right);
             let entry = format!("{}{}{}", left_edge, decorated, right_edge);
             self.emit_line(&entry, false);
-            thread::sleep(Duration::from_millis(CYBER_FLICKER_DELAY_MS));
+            if self.animated() {
+                thread::sleep(Duration::from_millis(CYBER_FLICKER_DELAY_MS));
+            }
         }
 
         let bottom = self
@@ -524,6 +684,53 @@ impl UX {
         self.ensure_log_header();
     }
 
+    /// Animate `label` with elapsed time until the returned handle drops.
+    /// No-op in quiet mode, JSON mode, or when stdout is not a terminal.
+    pub fn spinner(&self, label: &str) -> SpinnerHandle {
+        if self.quiet || self.json_mode() || !io::stdout().is_terminal() {
+            return SpinnerHandle::inert();
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let trace = Arc::new(Mutex::new(String::new()));
+        let latest = Arc::clone(&trace);
+        let style = self.theme.accent.clone();
+        let muted = self.theme.muted.clone();
+        let label = label.to_string();
+        let worker = thread::spawn(move || {
+            let started = Instant::now();
+            let mut out = io::stdout();
+            let mut frame = 0usize;
+            while !flag.load(Ordering::SeqCst) {
+                let line = format!(
+                    "{} {} [{:.1}s]",
+                    SPINNER_FRAMES[frame % SPINNER_FRAMES.len()],
+                    label,
+                    started.elapsed().as_secs_f32()
+                );
+                let detail = latest.lock().map(|t| t.clone()).unwrap_or_default();
+                let _ = write!(
+                    out,
+                    "\r\x1b[2K{} {}",
+                    style.apply_to(line),
+                    muted.apply_to(detail)
+                );
+                let _ = out.flush();
+                frame += 1;
+                thread::sleep(Duration::from_millis(SPINNER_TICK_MS));
+            }
+            let _ = write!(out, "\r\x1b[2K");
+            let _ = out.flush();
+        });
+
+        SpinnerHandle {
+            stop,
+            trace,
+            worker: Some(worker),
+        }
+    }
+
     pub fn banner_flicker(&self, timing: &Timing) -> Result<()> {
         if self.quiet {
             return Ok(());
@@ -544,13 +751,45 @@ impl UX {
                 .to_string();
             write!(out, "\r{}", pulse)?;
             out.flush()?;
-            thread::sleep(Duration::from_millis(140));
+            if self.animated() {
+                thread::sleep(Duration::from_millis(140));
+            }
             write!(out, "\r{}", " ".repeat(86))?;
             out.flush()?;
-            thread::sleep(Duration::from_millis(90));
+            if self.animated() {
+                thread::sleep(Duration::from_millis(90));
+            }
         }
         println!();
         timing.pace(Pace::Prompt);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{LOG_BODY_WIDTH, UX};
+    use console::Style;
+    use unicode_width::UnicodeWidthStr;
+
+    #[test]
+    fn framed_lines_keep_their_width_with_wide_characters() {
+        let ui = UX::new(false, true);
+        let wide = "盾".repeat(60);
+        let edge = format!("{}❤\u{FE0F}", "a".repeat(LOG_BODY_WIDTH - 1));
+        for content in [
+            "plain ascii",
+            "鍛冶場 🔒 armorer",
+            "❤\u{FE0F}",
+            wide.as_str(),
+            edge.as_str(),
+        ] {
+            let line = ui.box_line(content, &Style::new());
+            assert_eq!(line.width(), LOG_BODY_WIDTH + 4, "{}", content);
+        }
+
+        let wrapped = ui.wrap_text(&"盾".repeat(30), 25);
+        assert_eq!(wrapped.len(), 3);
+        assert!(wrapped.iter().all(|line| line.width() <= 25));
+    }
+}
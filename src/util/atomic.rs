@@ -106,6 +106,12 @@ pub fn atomic_write_bytes(path: &Path, bytes: &[u8], mode: u32, force: bool) ->
     Ok(())
 }
 
+/// Atomic write of raw key material with 0400 permissions; a crash leaves
+/// either the old key or the new one, never a truncated file.
+pub fn atomic_write_key(path: &Path, key: &[u8], force: bool) -> Result<()> {
+    atomic_write_bytes(path, key, 0o400, force)
+}
+
 /// Atomic write of TOML-serializable config with 0600 permissions.
 pub fn atomic_write_toml<T: serde::Serialize>(path: &Path, value: &T, force: bool) -> Result<()> {
     let s = toml::to_string_pretty(value).context("Serialize TOML failed")?;
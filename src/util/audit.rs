@@ -2,22 +2,90 @@
 // src/util/audit.rs – Minimal append-only audit trail
 // ============================================================================
 
-use chrono::Local;
+use crate::util::initramfs;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::os::unix::fs::OpenOptionsExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Append-only audit trail on the host (initramfs runs go to kmsg).
+pub const AUDIT_LOG_PATH: &str = "/var/log/beskar.log";
+
+/// Local-time layout inside the leading `[...]` of each record.
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// ISO-8601 UTC layout used under `[audit] utc`.
+const UTC_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Mirrors `audit.utc`; records stay in local time until config says otherwise.
+static AUDIT_UTC: AtomicBool = AtomicBool::new(false);
+
+/// Stamp subsequent records in UTC (`…Z`) instead of local time.
+pub fn set_utc(enabled: bool) {
+    AUDIT_UTC.store(enabled, Ordering::Relaxed);
+}
+
+/// One `[timestamp] EVENT: detail` record read back from the log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Normalized to UTC whichever layout the record was written in.
+    pub timestamp: DateTime<Utc>,
+    pub event: String,
+    pub detail: String,
+}
+
+/// Parse a line written by `audit_log` (UTC or local stamps, so logs that
+/// span the switch still read in order); anything else yields `None`.
+pub fn parse_line(line: &str) -> Option<AuditEntry> {
+    let rest = line.strip_prefix('[')?;
+    let (ts, rest) = rest.split_once("] ")?;
+    let timestamp = match NaiveDateTime::parse_from_str(ts, UTC_TIMESTAMP_FORMAT) {
+        Ok(naive) => Utc.from_utc_datetime(&naive),
+        Err(_) => local_to_utc(NaiveDateTime::parse_from_str(ts, TIMESTAMP_FORMAT).ok()?),
+    };
+    let (event, detail) = rest
+        .split_once(": ")
+        .unwrap_or((rest.trim_end_matches(':'), ""));
+    Some(AuditEntry {
+        timestamp,
+        event: event.to_string(),
+        detail: detail.to_string(),
+    })
+}
+
+/// Interpret a wall-clock reading in the local zone (the earlier instant
+/// across a DST fold; as UTC inside a DST gap, where no local reading exists).
+pub fn local_to_utc(naive: NaiveDateTime) -> DateTime<Utc> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|ts| ts.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&naive))
+}
 
 /// Append a timestamped event to /var/log/beskar.log (0600 permissions).
+/// Inside an initramfs the event goes to the kernel log instead.
 /// Silent failure if log is unwritable – avoids blocking main logic.
 pub fn audit_log(event: &str, detail: &str) {
-    let path = "/var/log/beskar.log";
+    if initramfs::detected() {
+        if let Ok(mut kmsg) = OpenOptions::new().write(true).open("/dev/kmsg") {
+            // <5> = KERN_NOTICE; one write per record.
+            let _ = kmsg.write_all(format!("<5>beskar: {event}: {detail}\n").as_bytes());
+        }
+        return;
+    }
     if let Ok(mut file) = OpenOptions::new()
         .create(true)
         .append(true)
         .mode(0o600)
-        .open(path)
+        .open(AUDIT_LOG_PATH)
     {
-        let ts = Local::now().format("%Y-%m-%d %H:%M:%S");
+        let ts = if AUDIT_UTC.load(Ordering::Relaxed) {
+            Utc::now().format(UTC_TIMESTAMP_FORMAT)
+        } else {
+            Local::now().format(TIMESTAMP_FORMAT)
+        };
         let _ = writeln!(file, "[{ts}] {event}: {detail}");
     }
 }
@@ -5,6 +5,7 @@
 use crate::config::ConfigFile;
 use anyhow::{anyhow, Context, Result};
 use std::fs;
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 
 /// Resolve the path to the zfs_beskar_key binary, preferring the configured
@@ -37,6 +38,21 @@ pub fn determine_binary_path(cfg: Option<&ConfigFile>) -> Result<PathBuf> {
     ))
 }
 
+/// `which`-style lookup: the first executable `name` on `PATH`, canonicalized.
+/// Callers must still vet the result against the command allowlist.
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var)
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join(name))
+        .find(|candidate| {
+            fs::metadata(candidate)
+                .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+                .unwrap_or(false)
+        })
+        .and_then(|found| fs::canonicalize(found).ok())
+}
+
 fn sanitize_path(path: &Path) -> Option<PathBuf> {
     if !path.is_absolute() {
         return None;
@@ -0,0 +1,118 @@
+// ============================================================================
+// src/util/breakglass.rs – passphrase-wrapped copy of the raw key
+// ============================================================================
+
+use crate::util::kdf::{hmac_sha256, pbkdf2_sha256};
+use crate::zfs::KEY_LEN;
+use anyhow::{anyhow, Context, Result};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+const HEADER: &str = "beskar-breakglass v2";
+const WRAP_INFO: &[u8] = b"beskar-breakglass wrap";
+const CHECK_INFO: &[u8] = b"beskar-breakglass check";
+
+/// Wrap `raw` under `passphrase`. PBKDF2 yields one 32-byte secret; HKDF
+/// expands it into the XOR pad for the key and the HMAC key for a check
+/// value, so a wrong passphrase is caught before ZFS (or the lockout) ever
+/// sees the result and testing a guess costs the full PBKDF2 run.
+pub fn seal(passphrase: &[u8], raw: &[u8], iterations: u32) -> Result<String> {
+    if raw.len() != KEY_LEN {
+        return Err(anyhow!(
+            "break-glass wrap expects a {}-byte key (got {})",
+            KEY_LEN,
+            raw.len()
+        ));
+    }
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let (pad, mac_key) = derive(passphrase, &salt, iterations);
+    let wrapped: Vec<u8> = raw.iter().zip(pad.iter()).map(|(k, d)| k ^ d).collect();
+    Ok(format!(
+        "{}\niterations={}\nsalt={}\nwrapped={}\ncheck={}\n",
+        HEADER,
+        iterations,
+        hex::encode(salt),
+        hex::encode(&wrapped),
+        hex::encode(hmac_sha256(&mac_key[..], &wrapped))
+    ))
+}
+
+/// Recover the raw key from a `seal` blob; a wrong passphrase is an error.
+pub fn open(blob: &str, passphrase: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let mut lines = blob.lines().map(str::trim).filter(|l| !l.is_empty());
+    if lines.next() != Some(HEADER) {
+        return Err(anyhow!("not a {} file", HEADER));
+    }
+    let (mut iterations, mut salt, mut wrapped, mut expected) = (None, None, None, None);
+    for line in lines {
+        let (field, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("malformed break-glass line: {}", line))?;
+        match field {
+            "iterations" => {
+                iterations = Some(value.parse::<u32>().context("break-glass iterations")?)
+            }
+            "salt" => salt = Some(hex::decode(value).context("break-glass salt hex")?),
+            "wrapped" => wrapped = Some(hex::decode(value).context("break-glass key hex")?),
+            "check" => expected = Some(hex::decode(value).context("break-glass check hex")?),
+            _ => {}
+        }
+    }
+    let missing = |field| anyhow!("break-glass file lacks {}", field);
+    let iterations = iterations.ok_or_else(|| missing("iterations"))?.max(1);
+    let salt = salt.ok_or_else(|| missing("salt"))?;
+    let wrapped = wrapped.ok_or_else(|| missing("wrapped"))?;
+    let expected = expected.ok_or_else(|| missing("check"))?;
+    if wrapped.len() != KEY_LEN {
+        return Err(anyhow!(
+            "break-glass key length mismatch ({} bytes)",
+            wrapped.len()
+        ));
+    }
+
+    let (pad, mac_key) = derive(passphrase, &salt, iterations);
+    let check = hmac_sha256(&mac_key[..], &wrapped);
+    if !bool::from(check[..].ct_eq(&expected[..])) {
+        return Err(anyhow!("break-glass passphrase incorrect"));
+    }
+    Ok(Zeroizing::new(
+        wrapped.iter().zip(pad.iter()).map(|(w, d)| w ^ d).collect(),
+    ))
+}
+
+/// One PBKDF2 output (the PRK), split with HKDF-Expand into the wrapping pad
+/// and the check key. Each needs a single 32-byte block: T(1) = HMAC(PRK, info || 0x01).
+fn derive(
+    passphrase: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> (Zeroizing<[u8; KEY_LEN]>, Zeroizing<[u8; 32]>) {
+    let mut prk = Zeroizing::new([0u8; 32]);
+    pbkdf2_sha256(passphrase, salt, iterations, &mut prk[..]);
+    let expand = |info: &[u8]| {
+        let mut input = Vec::with_capacity(info.len() + 1);
+        input.extend_from_slice(info);
+        input.push(1);
+        Zeroizing::new(hmac_sha256(&prk[..], &input))
+    };
+    (expand(WRAP_INFO), expand(CHECK_INFO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open, seal};
+
+    #[test]
+    fn break_glass_round_trips_and_rejects_wrong_passphrase() {
+        let raw = [7u8; 32];
+        let blob = seal(b"on-call", &raw, 10).unwrap();
+        assert_eq!(&open(&blob, b"on-call").unwrap()[..], &raw[..]);
+        assert!(open(&blob, b"on-cal1").is_err());
+        assert!(open("garbage", b"on-call").is_err());
+        let padded = blob.replace("check=", "check=00");
+        assert!(open(&padded, b"on-call").is_err());
+    }
+}
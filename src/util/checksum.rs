@@ -0,0 +1,72 @@
+// ============================================================================
+// src/util/checksum.rs – token digest algorithms (SHA-256 / SHA-512 / BLAKE3)
+// ============================================================================
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+
+/// Digest used to fingerprint the token key (`[usb] checksum_algo`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgo {
+    #[default]
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl ChecksumAlgo {
+    /// Operator-facing name, e.g. `SHA-256`.
+    pub fn label(self) -> &'static str {
+        match self {
+            ChecksumAlgo::Sha256 => "SHA-256",
+            ChecksumAlgo::Sha512 => "SHA-512",
+            ChecksumAlgo::Blake3 => "BLAKE3",
+        }
+    }
+
+    /// Coreutils-style tool the initramfs loader uses to recompute the digest.
+    pub fn shell_tool(self) -> &'static str {
+        match self {
+            ChecksumAlgo::Sha256 => "sha256sum",
+            ChecksumAlgo::Sha512 => "sha512sum",
+            ChecksumAlgo::Blake3 => "b3sum",
+        }
+    }
+
+    /// Lowercase hex digest of `data`.
+    pub fn digest_hex(self, data: &[u8]) -> String {
+        match self {
+            ChecksumAlgo::Sha256 => hex::encode(Sha256::digest(data)),
+            ChecksumAlgo::Sha512 => hex::encode(Sha512::digest(data)),
+            ChecksumAlgo::Blake3 => blake3::hash(data).to_hex().to_string(),
+        }
+    }
+
+    /// True when `expected` (case-insensitive hex) is the digest of `data`.
+    pub fn matches(self, data: &[u8], expected: &str) -> bool {
+        self.digest_hex(data).eq_ignore_ascii_case(expected.trim())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChecksumAlgo;
+
+    #[test]
+    fn digests_follow_selected_algorithm() {
+        let sha256 = ChecksumAlgo::Sha256.digest_hex(b"abc");
+        assert_eq!(
+            sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(ChecksumAlgo::Sha512.digest_hex(b"abc").len(), 128);
+        assert_eq!(
+            ChecksumAlgo::Blake3.digest_hex(b"abc"),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        assert!(ChecksumAlgo::Sha256.matches(b"abc", &sha256.to_uppercase()));
+        assert!(!ChecksumAlgo::Sha512.matches(b"abc", &sha256));
+        assert_eq!(ChecksumAlgo::default(), ChecksumAlgo::Sha256);
+    }
+}
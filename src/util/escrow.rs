@@ -0,0 +1,61 @@
+// ============================================================================
+// src/util/escrow.rs – age (X25519) escrow sealing for compliance copies
+// ============================================================================
+
+use crate::zfs::KEY_LEN;
+use anyhow::{anyhow, Context, Result};
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+/// Encrypt `raw` to an `age1…` X25519 recipient and return an ASCII-armored blob.
+/// The plaintext never leaves this function; only ciphertext is returned.
+pub fn seal_for_recipient(recipient: &str, raw: &[u8]) -> Result<String> {
+    let recipient = age::x25519::Recipient::from_str(recipient.trim())
+        .map_err(|e| anyhow!("Escrow recipient invalid: {}", e))?;
+    age::encrypt_and_armor(&recipient, raw).context("age encrypt escrow blob")
+}
+
+/// Decrypt an armored escrow blob with the first `AGE-SECRET-KEY-` line found
+/// in `identity_text` (comments and blank lines are ignored).
+pub fn open_with_identity(identity_text: &str, blob: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let line = identity_text
+        .lines()
+        .map(str::trim)
+        .find(|l| l.starts_with("AGE-SECRET-KEY-"))
+        .ok_or_else(|| anyhow!("No AGE-SECRET-KEY line found in escrow identity."))?;
+    let identity = age::x25519::Identity::from_str(line)
+        .map_err(|e| anyhow!("Escrow identity invalid: {}", e))?;
+    let raw = Zeroizing::new(age::decrypt(&identity, blob).context("age decrypt escrow blob")?);
+    if raw.len() != KEY_LEN {
+        return Err(anyhow!(
+            "Escrow blob decrypted to {} bytes (expected {}).",
+            raw.len(),
+            KEY_LEN
+        ));
+    }
+    Ok(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open_with_identity, seal_for_recipient};
+    use age::secrecy::ExposeSecret;
+
+    #[test]
+    fn escrow_round_trips_without_plaintext_in_blob() {
+        let identity = age::x25519::Identity::generate();
+        let recipient = identity.to_public().to_string();
+        let key = [0x5Au8; 32];
+
+        let blob = seal_for_recipient(&recipient, &key).expect("seal");
+        assert!(blob.starts_with("-----BEGIN AGE ENCRYPTED FILE-----"));
+        assert!(!blob.contains(&hex::encode(key)));
+
+        let identity_file = format!(
+            "# created: test\n{}\n",
+            identity.to_string().expose_secret()
+        );
+        let opened = open_with_identity(&identity_file, blob.as_bytes()).expect("open");
+        assert_eq!(&opened[..], &key[..]);
+    }
+}
@@ -0,0 +1,234 @@
+// ============================================================================
+// src/util/fido2.rs – FIDO2 hmac-secret key wrapping via libfido2 tools
+// ============================================================================
+
+use crate::cmd::{Cmd, OutputData};
+use crate::config::Fido2;
+use crate::zfs::KEY_LEN;
+use anyhow::{anyhow, Context, Result};
+use data_encoding::BASE64;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::path::Path;
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+const FIDO2_TOKEN_BINARIES: &[&str] = &["/usr/bin/fido2-token"];
+const FIDO2_CRED_BINARIES: &[&str] = &["/usr/bin/fido2-cred"];
+const FIDO2_ASSERT_BINARIES: &[&str] = &["/usr/bin/fido2-assert"];
+/// Generous enough for the operator to find and touch the authenticator.
+const TOUCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Credential material produced by `enroll`, ready to store in `[fido2]`.
+pub struct Enrollment {
+    pub credential_id: String,
+    pub hmac_salt: String,
+    pub key_xor: String,
+}
+
+/// Resolve the authenticator: the configured device, else the first one
+/// reported by `fido2-token -L`.
+pub fn find_device(configured: Option<&str>) -> Result<String> {
+    if let Some(device) = configured {
+        return Ok(device.to_string());
+    }
+    let out = run_tool(FIDO2_TOKEN_BINARIES, &["-L"], None, Duration::from_secs(5))?;
+    parse_token_list(&out.stdout)
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("no FIDO2 authenticator detected"))
+}
+
+/// Create a non-resident hmac-secret credential and wrap `raw_key` with the
+/// secret it yields. Requires two touches (make-credential, then assertion).
+pub fn enroll(device: &str, relying_party: &str, raw_key: &[u8]) -> Result<Enrollment> {
+    let mut user_id = [0u8; 32];
+    OsRng.fill_bytes(&mut user_id);
+    let input = format!(
+        "{}\n{}\nbeskar\n{}\n",
+        random_client_hash(),
+        relying_party,
+        BASE64.encode(&user_id)
+    );
+    let out = run_tool(
+        FIDO2_CRED_BINARIES,
+        &["-M", "-h", device],
+        Some(input.as_bytes()),
+        TOUCH_TIMEOUT,
+    )?;
+    let credential_id = parse_credential_id(&out.stdout)?;
+
+    let mut salt = [0u8; 32];
+    OsRng.fill_bytes(&mut salt);
+    let hmac_salt = BASE64.encode(&salt);
+    let secret = hmac_secret(device, relying_party, &credential_id, &hmac_salt)?;
+
+    Ok(Enrollment {
+        credential_id,
+        hmac_salt,
+        key_xor: hex::encode(xor_bytes(raw_key, &secret)?),
+    })
+}
+
+/// Challenge the enrolled authenticator and unwrap the raw ZFS key.
+pub fn derive_key(cfg: &Fido2) -> Result<Zeroizing<Vec<u8>>> {
+    let credential_id = cfg
+        .credential_id
+        .as_deref()
+        .ok_or_else(|| anyhow!("fido2.credential_id missing; enroll the authenticator first"))?;
+    let salt = cfg
+        .hmac_salt
+        .as_deref()
+        .ok_or_else(|| anyhow!("fido2.hmac_salt missing from config"))?;
+    let wrapped = hex::decode(
+        cfg.key_xor
+            .as_deref()
+            .ok_or_else(|| anyhow!("fido2.key_xor missing from config"))?,
+    )
+    .context("decode fido2.key_xor hex")?;
+
+    let device = find_device(cfg.device.as_deref())?;
+    let secret = hmac_secret(&device, &cfg.relying_party, credential_id, salt)?;
+    xor_bytes(&wrapped, &secret)
+}
+
+fn hmac_secret(
+    device: &str,
+    relying_party: &str,
+    credential_id: &str,
+    salt: &str,
+) -> Result<Zeroizing<Vec<u8>>> {
+    let input = format!(
+        "{}\n{}\n{}\n{}\n",
+        random_client_hash(),
+        relying_party,
+        credential_id,
+        salt
+    );
+    // The secret unwraps the key, so it never passes through `OutputData`.
+    let path = find_tool(FIDO2_ASSERT_BINARIES)?;
+    let out = Cmd::new_allowlisted(path, TOUCH_TIMEOUT)?
+        .in_foreground()
+        .run_secret(&["-G", "-h", device], Some(input.as_bytes()))?;
+    if out.status != 0 {
+        return Err(tool_failed(path, out.status, &out.stderr));
+    }
+    parse_hmac_secret(&out.stdout)
+}
+
+fn run_tool(
+    candidates: &[&str],
+    args: &[&str],
+    input: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<OutputData> {
+    let path = find_tool(candidates)?;
+    let out = Cmd::new_allowlisted(path, timeout)?
+        .in_foreground()
+        .run(args, input)?;
+    if out.status != 0 {
+        return Err(tool_failed(path, out.status, &out.stderr));
+    }
+    Ok(out)
+}
+
+fn find_tool<'a>(candidates: &[&'a str]) -> Result<&'a str> {
+    candidates
+        .iter()
+        .copied()
+        .find(|p| Path::new(p).exists())
+        .ok_or_else(|| anyhow!("libfido2 tool not found in {:?}", candidates))
+}
+
+fn tool_failed(path: &str, status: i32, stderr: &str) -> anyhow::Error {
+    anyhow!("{} exited with status {}: {}", path, status, stderr.trim())
+}
+
+fn random_client_hash() -> String {
+    let mut hash = [0u8; 32];
+    OsRng.fill_bytes(&mut hash);
+    BASE64.encode(&hash)
+}
+
+/// `fido2-token -L` prints `<device>: vendor=…` per authenticator.
+fn parse_token_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(':').map(|(dev, _)| dev.trim().to_string()))
+        .filter(|dev| !dev.is_empty())
+        .collect()
+}
+
+/// `fido2-cred -M` prints client hash, rp id, format, authdata, then the
+/// credential id.
+fn parse_credential_id(output: &str) -> Result<String> {
+    output
+        .lines()
+        .nth(4)
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("fido2-cred output missing credential id"))
+}
+
+/// With `-h`, the hmac-secret is the last line `fido2-assert -G` prints.
+fn parse_hmac_secret(output: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let line = output
+        .split(|&b| b == b'\n')
+        .map(<[u8]>::trim_ascii)
+        .rfind(|l| !l.is_empty())
+        .ok_or_else(|| anyhow!("fido2-assert returned no hmac-secret"))?;
+    let secret = Zeroizing::new(BASE64.decode(line).context("decode hmac-secret base64")?);
+    if secret.len() != KEY_LEN {
+        return Err(anyhow!(
+            "hmac-secret is {} bytes (expected {}); was the credential made with -h?",
+            secret.len(),
+            KEY_LEN
+        ));
+    }
+    Ok(secret)
+}
+
+fn xor_bytes(a: &[u8], b: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    if a.len() != b.len() {
+        return Err(anyhow!(
+            "FIDO2 key length mismatch ({} vs {} bytes)",
+            a.len(),
+            b.len()
+        ));
+    }
+    Ok(Zeroizing::new(
+        a.iter().zip(b.iter()).map(|(x, y)| x ^ y).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_credential_id, parse_hmac_secret, parse_token_list, xor_bytes};
+    use data_encoding::BASE64;
+
+    #[test]
+    fn fido2_tool_output_parses_and_unwraps() {
+        let devices = parse_token_list(
+            "/dev/hidraw3: vendor=0x1050, product=0x0407 (Yubico YubiKey)\n\
+             /dev/hidraw5: vendor=0x20a0, product=0x42b1 (Nitrokey)\n",
+        );
+        assert_eq!(devices, vec!["/dev/hidraw3", "/dev/hidraw5"]);
+
+        let cred = "hash\nbeskar.local\npacked\nauthdata\nQ1JFRA==\nsig\n";
+        assert_eq!(parse_credential_id(cred).unwrap(), "Q1JFRA==");
+
+        let secret = [7u8; 32];
+        let assert_out = format!(
+            "hash\nbeskar.local\nauthdata\nsig\n{}\n",
+            BASE64.encode(&secret)
+        );
+        let parsed = parse_hmac_secret(assert_out.as_bytes()).unwrap();
+        assert_eq!(&parsed[..], &secret[..]);
+        assert!(parse_hmac_secret(b"hash\nrp\nauth\nc2ln\n").is_err());
+
+        let raw = [0x5au8; 32];
+        let wrapped = xor_bytes(&raw, &secret).unwrap();
+        assert_eq!(&xor_bytes(&wrapped, &parsed).unwrap()[..], &raw[..]);
+    }
+}
@@ -0,0 +1,132 @@
+// ============================================================================
+// src/util/holders.rs – fuser-style scan for processes pinning a mount
+// ============================================================================
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A process keeping a mount busy.
+#[derive(Debug, Clone)]
+pub struct MountHolder {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// Return processes whose cwd, root, or open file descriptors live beneath the
+/// mount backing `target` (either a mountpoint or a block device node).
+/// Best effort: unreadable `/proc` entries are skipped silently.
+pub fn mount_holders(target: &str) -> Vec<MountHolder> {
+    let mountpoints = resolve_mountpoints(target);
+    if mountpoints.is_empty() {
+        return Vec::new();
+    }
+
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let own_pid = std::process::id();
+    let mut holders = Vec::new();
+    for entry in entries.flatten() {
+        let pid = match entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        {
+            Some(pid) if pid != own_pid => pid,
+            _ => continue,
+        };
+
+        let base = entry.path();
+        let mut links = vec![base.join("cwd"), base.join("root")];
+        if let Ok(fds) = fs::read_dir(base.join("fd")) {
+            links.extend(fds.flatten().map(|fd| fd.path()));
+        }
+
+        let pinned = links
+            .iter()
+            .filter_map(|link| fs::read_link(link).ok())
+            .any(|resolved| mountpoints.iter().any(|mp| resolved.starts_with(mp)));
+        if pinned {
+            let name = fs::read_to_string(base.join("comm"))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_else(|_| "?".to_string());
+            holders.push(MountHolder { pid, name });
+        }
+    }
+
+    holders.sort_by_key(|h| h.pid);
+    holders
+}
+
+/// Render holders as `PID (name)` pairs for operator-facing errors.
+pub fn describe_holders(holders: &[MountHolder]) -> String {
+    holders
+        .iter()
+        .map(|h| format!("{} ({})", h.pid, h.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn resolve_mountpoints(target: &str) -> Vec<PathBuf> {
+    let mut points: Vec<PathBuf> = Vec::new();
+    if let Ok(mounts) = fs::read_to_string("/proc/mounts") {
+        for line in mounts.lines() {
+            let mut parts = line.split_whitespace();
+            if let (Some(source), Some(mountpoint)) = (parts.next(), parts.next()) {
+                let mountpoint = unescape_mount_field(mountpoint);
+                if (source == target || mountpoint == target) && mountpoint != "/" {
+                    let candidate = PathBuf::from(mountpoint);
+                    if !points.contains(&candidate) {
+                        points.push(candidate);
+                    }
+                }
+            }
+        }
+    }
+
+    let path = Path::new(target);
+    if points.is_empty() && path.is_dir() && path != Path::new("/") {
+        points.push(path.to_path_buf());
+    }
+    points
+}
+
+/// Decode the octal escapes (`\040` etc.) the kernel uses in `/proc/mounts`.
+pub(crate) fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'\\' && idx + 4 <= bytes.len() {
+            let octal = &bytes[idx + 1..idx + 4];
+            if let Some(value) = std::str::from_utf8(octal)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 8).ok())
+            {
+                out.push(value);
+                idx += 4;
+                continue;
+            }
+        }
+        out.push(bytes[idx]);
+        idx += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unescape_mount_field;
+
+    #[test]
+    fn mount_fields_decode_octal_escapes() {
+        assert_eq!(unescape_mount_field("/media/My\\040Key"), "/media/My Key");
+        assert_eq!(unescape_mount_field("/run/beskar"), "/run/beskar");
+        assert_eq!(
+            unescape_mount_field("/tmp/trailing\\04"),
+            "/tmp/trailing\\04"
+        );
+    }
+}
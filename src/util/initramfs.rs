@@ -0,0 +1,24 @@
+// ============================================================================
+// src/util/initramfs.rs – Detect early-boot (initramfs) execution
+// ============================================================================
+
+use std::env;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Exported as `1` by the dracut loader and the initramfs-tools local-top
+/// script, so anything they spawn knows it runs before the real root.
+pub const INITRAMFS_ENV: &str = "BESKAR_INITRAMFS";
+/// systemd-based initramfs images (dracut, mkinitcpio-systemd) ship this file.
+const INITRD_RELEASE: &str = "/etc/initrd-release";
+
+/// True when running inside an initramfs. There is no terminal to draw on or
+/// prompt from, and `/var/log` is not the real root's, so callers go quiet,
+/// skip interactive prompts, and audit to the kernel log instead.
+pub fn detected() -> bool {
+    static DETECTED: OnceLock<bool> = OnceLock::new();
+    *DETECTED.get_or_init(|| {
+        env::var(INITRAMFS_ENV).map(|v| v == "1").unwrap_or(false)
+            || Path::new(INITRD_RELEASE).exists()
+    })
+}
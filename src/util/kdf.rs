@@ -36,7 +36,7 @@ fn pbkdf2_block(password: &[u8], salt: &[u8], iterations: u32, counter: u32) ->
     result
 }
 
-fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
     let mut key_block = [0u8; 64];
     if key.len() > 64 {
         let digest = Sha256::digest(key);
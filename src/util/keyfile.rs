@@ -2,13 +2,24 @@
 // src/util/keyfile.rs – helpers for reading/writing USB key material
 // ============================================================================
 
+use crate::util::secret::read_stdin_secret;
+use crate::zfs::KEY_LEN;
 use anyhow::{anyhow, Context, Result};
+use std::ffi::CString;
 use std::fs::{self, File, Permissions};
-use std::io::Write;
-use std::os::unix::fs::PermissionsExt;
-use std::path::Path;
+use std::io::{Seek, SeekFrom, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
 use zeroize::Zeroizing;
 
+/// Memory-backed directories tried (after a private `$XDG_RUNTIME_DIR`) for
+/// transient key files, so raw key bytes never reach a block device.
+const TMPFS_DIRS: [&str; 2] = ["/dev/shm", "/run"];
+/// Upper bound on piped key material; anything longer is not a key.
+const MAX_STDIN_KEY: usize = 4096;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyEncoding {
     Raw,
@@ -23,36 +34,113 @@ pub struct KeyMaterialDisk {
 
 /// Read key material from disk, auto-detecting whether it is raw bytes or legacy hex.
 pub fn read_key_material(path: &Path) -> Result<KeyMaterialDisk> {
-    let data = fs::read(path).with_context(|| format!("read key file {}", path.display()))?;
-    if data.len() == 32 {
+    let data = Zeroizing::new(
+        fs::read(path).with_context(|| format!("read key file {}", path.display()))?,
+    );
+    detect_encoding(data, &format!("Key file {}", path.display()))
+}
+
+/// Key material piped on stdin (`unlock --stdin-key`), detected like
+/// `read_key_material`, or held to `read_key_material_strict` rules.
+pub fn read_key_material_stdin(strict: bool) -> Result<KeyMaterialDisk> {
+    let data = read_stdin_secret(MAX_STDIN_KEY).context("read key material from stdin")?;
+    if strict {
+        parse_strict(&data).context("strict check of key material on stdin")
+    } else {
+        detect_encoding(data, "Key material on stdin")
+    }
+}
+
+fn detect_encoding(data: Zeroizing<Vec<u8>>, source: &str) -> Result<KeyMaterialDisk> {
+    if data.len() == KEY_LEN {
         return Ok(KeyMaterialDisk {
-            raw: Zeroizing::new(data),
+            raw: data,
             encoding: KeyEncoding::Raw,
         });
     }
 
-    let text = String::from_utf8_lossy(&data);
-    let cleaned: String = text.chars().filter(|c| c.is_ascii_hexdigit()).collect();
-    if cleaned.len() == 64 {
-        let decoded = Zeroizing::new(
-            hex::decode(&cleaned)
-                .with_context(|| format!("decode hex key material at {}", path.display()))?,
-        );
+    let digits = hex_digits(&data);
+    if digits.len() == KEY_LEN * 2 {
         return Ok(KeyMaterialDisk {
-            raw: decoded,
+            raw: decode_hex_key(&digits)
+                .with_context(|| format!("decode hex key material ({})", source))?,
             encoding: KeyEncoding::Hex,
         });
     }
 
     Err(anyhow!(
-        "Key file {} malformed (expected 32 raw bytes or 64 hex chars).",
-        path.display()
+        "{} malformed (expected {} raw bytes or {} hex chars).",
+        source,
+        KEY_LEN,
+        KEY_LEN * 2
     ))
 }
 
+/// Like `read_key_material`, but the file must be exactly `KEY_LEN` raw bytes
+/// or exactly `KEY_LEN * 2` hex chars with at most one trailing `\n`. Leading
+/// whitespace, extra lines, or any other byte is rejected rather than filtered.
+pub fn read_key_material_strict(path: &Path) -> Result<KeyMaterialDisk> {
+    let data = Zeroizing::new(
+        fs::read(path).with_context(|| format!("read key file {}", path.display()))?,
+    );
+    parse_strict(&data).with_context(|| format!("strict key file check for {}", path.display()))
+}
+
+fn parse_strict(data: &[u8]) -> Result<KeyMaterialDisk> {
+    if data.len() == KEY_LEN {
+        return Ok(KeyMaterialDisk {
+            raw: Zeroizing::new(data.to_vec()),
+            encoding: KeyEncoding::Raw,
+        });
+    }
+    let body = data.strip_suffix(b"\n").unwrap_or(data);
+    if body.len() != KEY_LEN * 2 || !body.iter().all(u8::is_ascii_hexdigit) {
+        return Err(anyhow!(
+            "expected exactly {} raw bytes or {} hex chars plus an optional newline ({} bytes found)",
+            KEY_LEN,
+            KEY_LEN * 2,
+            data.len()
+        ));
+    }
+    Ok(KeyMaterialDisk {
+        raw: decode_hex_key(body).context("decode hex key material")?,
+        encoding: KeyEncoding::Hex,
+    })
+}
+
+/// The hex digits of `data`, everything else (whitespace, separators) dropped.
+/// The buffer is sized up front so filtering never reallocates and strands
+/// an unscrubbed copy.
+pub fn hex_digits(data: &[u8]) -> Zeroizing<Vec<u8>> {
+    let mut digits = Zeroizing::new(Vec::with_capacity(data.len()));
+    digits.extend(data.iter().copied().filter(u8::is_ascii_hexdigit));
+    digits
+}
+
+/// Decode `KEY_LEN * 2` hex digits straight into a zeroizing key buffer.
+pub fn decode_hex_key(digits: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let mut raw = Zeroizing::new(vec![0u8; KEY_LEN]);
+    hex::decode_to_slice(digits, &mut raw[..]).map_err(|err| {
+        anyhow!(
+            "expected {} hex chars of key material ({})",
+            KEY_LEN * 2,
+            err
+        )
+    })?;
+    Ok(raw)
+}
+
 /// Ensure the on-disk key file contains raw bytes; legacy hex files are rewritten in-place.
 pub fn ensure_raw_key_file(path: &Path) -> Result<KeyMaterialDisk> {
-    let mut key = read_key_material(path)?;
+    normalize_to_raw(path, read_key_material(path)?)
+}
+
+/// `ensure_raw_key_file` gated by `read_key_material_strict` (`[usb] strict_keyfile`).
+pub fn ensure_raw_key_file_strict(path: &Path) -> Result<KeyMaterialDisk> {
+    normalize_to_raw(path, read_key_material_strict(path)?)
+}
+
+fn normalize_to_raw(path: &Path, mut key: KeyMaterialDisk) -> Result<KeyMaterialDisk> {
     if key.encoding == KeyEncoding::Hex {
         rewrite_key_file(path, &key.raw)?;
         key.encoding = KeyEncoding::Raw;
@@ -70,3 +158,150 @@ pub fn rewrite_key_file(path: &Path, raw: &[u8]) -> Result<()> {
         .with_context(|| format!("set permissions on {}", path.display()))?;
     Ok(())
 }
+
+/// Raw key bytes staged on tmpfs for tools that only take a key file (e.g.
+/// `zfs change-key` via `keylocation=file://`). The file is 0600, zeroed and
+/// synced on drop, then unlinked — on success and error paths alike.
+pub struct TransientKeyFile {
+    file: NamedTempFile,
+    len: usize,
+}
+
+impl TransientKeyFile {
+    pub fn new(key: &[u8]) -> Result<Self> {
+        let dir = tmpfs_dir().ok_or_else(|| {
+            anyhow!(
+                "no tmpfs directory available for transient key material (tried $XDG_RUNTIME_DIR, {})",
+                TMPFS_DIRS.join(", ")
+            )
+        })?;
+        let file = tempfile::Builder::new()
+            .prefix(".beskar-key-")
+            .tempfile_in(&dir)
+            .with_context(|| format!("create transient key file in {}", dir.display()))?;
+        fs::set_permissions(file.path(), Permissions::from_mode(0o600))
+            .context("set transient key permissions")?;
+        // Sized up front: a partial write still leaves bytes for Drop to scrub.
+        let mut guard = Self {
+            file,
+            len: key.len(),
+        };
+        guard
+            .file
+            .write_all(key)
+            .context("write key material to transient file")?;
+        guard.file.as_file().sync_all().ok();
+        Ok(guard)
+    }
+
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+}
+
+impl Drop for TransientKeyFile {
+    fn drop(&mut self) {
+        // NamedTempFile unlinks after this; scrub the pages first.
+        let file = self.file.as_file_mut();
+        let _ = file
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| file.write_all(&vec![0u8; self.len]))
+            .and_then(|_| file.sync_all());
+    }
+}
+
+/// `$XDG_RUNTIME_DIR` counts only when it is ours alone: under `sudo -E` it is
+/// the invoking user's directory, and they could read or swap the key there.
+fn tmpfs_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| is_private_to_euid(dir))
+        .into_iter()
+        .chain(TMPFS_DIRS.iter().map(PathBuf::from))
+        .find(|dir| is_tmpfs(dir))
+}
+
+/// Owned by the effective user with no group/other permission bits.
+fn is_private_to_euid(dir: &Path) -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    let euid = unsafe { libc::geteuid() };
+    fs::metadata(dir)
+        .map(|meta| meta.is_dir() && meta.uid() == euid && meta.mode() & 0o077 == 0)
+        .unwrap_or(false)
+}
+
+fn is_tmpfs(dir: &Path) -> bool {
+    let Ok(c_path) = CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is NUL-terminated and stat is a valid out-pointer.
+    let rc = unsafe { libc::statfs(c_path.as_ptr(), &mut stat) };
+    rc == 0 && stat.f_type == libc::TMPFS_MAGIC
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_hex_key, hex_digits, is_private_to_euid, parse_strict, tmpfs_dir, KeyEncoding,
+        TransientKeyFile,
+    };
+    use crate::zfs::KEY_LEN;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn strict_parsing_rejects_padding_and_smuggled_bytes() {
+        let hex = "ab".repeat(KEY_LEN);
+        assert_eq!(
+            parse_strict(&[7u8; KEY_LEN]).unwrap().encoding,
+            KeyEncoding::Raw
+        );
+        assert_eq!(
+            parse_strict(hex.as_bytes()).unwrap().encoding,
+            KeyEncoding::Hex
+        );
+        assert!(parse_strict(format!("{}\n", hex).as_bytes()).is_ok());
+        assert!(parse_strict(format!("{}\n\n", hex).as_bytes()).is_err());
+        assert!(parse_strict(format!(" {}", hex).as_bytes()).is_err());
+        assert!(parse_strict(format!("{}\r\n", hex).as_bytes()).is_err());
+        assert!(parse_strict(format!("{}\nzz", hex).as_bytes()).is_err());
+        assert!(parse_strict(format!("{}:{}", &hex[..32], &hex[32..]).as_bytes()).is_err());
+    }
+
+    #[test]
+    fn transient_key_file_lives_on_tmpfs_and_is_removed() {
+        if tmpfs_dir().is_none() {
+            eprintln!("skipping transient key file test: no tmpfs directory on this host");
+            return;
+        }
+        let guard = TransientKeyFile::new(&[9u8; KEY_LEN]).unwrap();
+        let path = guard.path().to_path_buf();
+        assert_eq!(fs::read(&path).unwrap(), vec![9u8; KEY_LEN]);
+        drop(guard);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn runtime_dirs_open_to_others_are_not_private() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o700)).unwrap();
+        assert!(is_private_to_euid(dir.path()));
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o750)).unwrap();
+        assert!(!is_private_to_euid(dir.path()));
+        assert!(!is_private_to_euid(&dir.path().join("missing")));
+    }
+
+    #[test]
+    fn hex_filtering_stays_in_one_presized_buffer() {
+        let text = format!("{}\n", "ab:".repeat(KEY_LEN));
+        let digits = hex_digits(text.as_bytes());
+        assert_eq!(digits.len(), KEY_LEN * 2);
+        assert_eq!(digits.capacity(), text.len());
+        assert_eq!(
+            &decode_hex_key(&digits).unwrap()[..],
+            &[0xABu8; KEY_LEN][..]
+        );
+        assert!(decode_hex_key(&digits[2..]).is_err());
+    }
+}
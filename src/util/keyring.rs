@@ -0,0 +1,259 @@
+// ============================================================================
+// src/util/keyring.rs – Kernel keyring handoff for `[crypto] keyring` and
+// the same-boot key cache (`[crypto] keyring_cache`)
+// ============================================================================
+
+use anyhow::{anyhow, Result};
+use std::ffi::CString;
+use std::io;
+use zeroize::Zeroizing;
+
+/// Key type used for the deposited secret (`keyctl print` can read it back).
+const KEY_TYPE: &str = "user";
+
+/// Where the key lands: a special keyring or a named one hung off `@u`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyringTarget {
+    Special(i32),
+    Named(String),
+}
+
+/// Accept `@u`, `@s`, `@us`, or a plain keyring name (`[A-Za-z0-9_.:-]`).
+pub fn parse_target(spec: &str) -> Result<KeyringTarget> {
+    match spec {
+        "@u" => return Ok(KeyringTarget::Special(libc::KEY_SPEC_USER_KEYRING)),
+        "@s" => return Ok(KeyringTarget::Special(libc::KEY_SPEC_SESSION_KEYRING)),
+        "@us" => return Ok(KeyringTarget::Special(libc::KEY_SPEC_USER_SESSION_KEYRING)),
+        _ => {}
+    }
+    if spec.is_empty()
+        || spec.starts_with('@')
+        || !spec
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-'))
+    {
+        return Err(anyhow!(
+            "crypto.keyring '{}' must be @u, @s, @us, or a plain keyring name",
+            spec
+        ));
+    }
+    Ok(KeyringTarget::Named(spec.to_string()))
+}
+
+/// Description the key is filed under for `encryption_root`.
+pub fn description_for(encryption_root: &str) -> String {
+    format!("zfs:{}", encryption_root)
+}
+
+/// Add (or update) `key` as a `user` key in the target keyring, expiring
+/// after `timeout_secs`; returns the kernel key serial.
+pub fn deposit_key(
+    target: &KeyringTarget,
+    description: &str,
+    key: &[u8],
+    timeout_secs: u64,
+) -> Result<i32> {
+    let keyring = match target {
+        KeyringTarget::Special(id) => *id,
+        KeyringTarget::Named(name) => add_key("keyring", name, &[], libc::KEY_SPEC_USER_KEYRING)?,
+    };
+    let serial = add_key(KEY_TYPE, description, key, keyring)?;
+    expire_or_revoke(serial, timeout_secs)?;
+    Ok(serial)
+}
+
+/// Revoke the key `deposit_key` filed for `encryption_root` in `target`;
+/// false when none is there.
+pub fn revoke_deposited(target: &KeyringTarget, encryption_root: &str) -> Result<bool> {
+    let keyring = match target {
+        KeyringTarget::Special(id) => *id,
+        KeyringTarget::Named(name) => match search(libc::KEY_SPEC_USER_KEYRING, "keyring", name)? {
+            Some(serial) => serial,
+            None => return Ok(false),
+        },
+    };
+    let Some(serial) = search(keyring, KEY_TYPE, &description_for(encryption_root))? else {
+        return Ok(false);
+    };
+    keyctl(libc::KEYCTL_REVOKE, serial as libc::c_ulong, 0, 0)
+        .map_err(|err| anyhow!("revoke key {}: {}", serial, err))?;
+    Ok(true)
+}
+
+/// Description the same-boot cache files `encryption_root` under; distinct
+/// from `description_for` so interop tooling never sees cache entries.
+pub fn cache_description(encryption_root: &str) -> String {
+    format!("beskar:{}", encryption_root)
+}
+
+/// Cache `key` for `encryption_root` in the user keyring, expiring after
+/// `timeout_secs`.
+pub fn cache_key(encryption_root: &str, key: &[u8], timeout_secs: u64) -> Result<i32> {
+    let serial = add_key(
+        KEY_TYPE,
+        &cache_description(encryption_root),
+        key,
+        libc::KEY_SPEC_USER_KEYRING,
+    )?;
+    expire_or_revoke(serial, timeout_secs)?;
+    Ok(serial)
+}
+
+/// Arm the expiry on a freshly added key; if that fails, revoke the key
+/// rather than leave the secret in the keyring with no expiry.
+fn expire_or_revoke(serial: i32, timeout_secs: u64) -> Result<()> {
+    set_timeout(serial, timeout_secs).inspect_err(|_| {
+        let _ = keyctl(libc::KEYCTL_REVOKE, serial as libc::c_ulong, 0, 0);
+    })
+}
+
+/// A timeout of 0 means "never expire" to the kernel, so it is refused here.
+fn set_timeout(serial: i32, timeout_secs: u64) -> Result<()> {
+    if timeout_secs == 0 {
+        return Err(anyhow!("refusing a zero timeout on key {}", serial));
+    }
+    let timeout = u32::try_from(timeout_secs).unwrap_or(u32::MAX);
+    keyctl(
+        libc::KEYCTL_SET_TIMEOUT,
+        serial as libc::c_ulong,
+        timeout as libc::c_ulong,
+        0,
+    )
+    .map_err(|err| anyhow!("set timeout on key {}: {}", serial, err))?;
+    Ok(())
+}
+
+/// The cached key for `encryption_root`, or `None` when nothing (or nothing
+/// unexpired) is cached.
+pub fn cached_key(encryption_root: &str) -> Result<Option<Zeroizing<Vec<u8>>>> {
+    let Some(serial) = find_cached(encryption_root)? else {
+        return Ok(None);
+    };
+    let mut buf = Zeroizing::new(vec![0u8; 64]);
+    let len = keyctl(
+        libc::KEYCTL_READ,
+        serial as libc::c_ulong,
+        buf.as_mut_ptr() as libc::c_ulong,
+        buf.len() as libc::c_ulong,
+    )
+    .map_err(|err| anyhow!("read cached key {}: {}", serial, err))?;
+    if len as usize > buf.len() {
+        return Err(anyhow!(
+            "cached key {} is {} bytes; refusing it",
+            serial,
+            len
+        ));
+    }
+    buf.truncate(len as usize);
+    Ok(Some(buf))
+}
+
+/// Revoke the cached key for `encryption_root`; false when none was cached.
+pub fn revoke_cached(encryption_root: &str) -> Result<bool> {
+    let Some(serial) = find_cached(encryption_root)? else {
+        return Ok(false);
+    };
+    keyctl(libc::KEYCTL_REVOKE, serial as libc::c_ulong, 0, 0)
+        .map_err(|err| anyhow!("revoke cached key {}: {}", serial, err))?;
+    Ok(true)
+}
+
+fn find_cached(encryption_root: &str) -> Result<Option<i32>> {
+    search(
+        libc::KEY_SPEC_USER_KEYRING,
+        KEY_TYPE,
+        &cache_description(encryption_root),
+    )
+}
+
+/// Search `keyring` (and keyrings nested in it) for a live key; `None` when
+/// it is absent, expired or revoked.
+fn search(keyring: i32, key_type: &str, description: &str) -> Result<Option<i32>> {
+    let key_type = CString::new(key_type)?;
+    let description = CString::new(description)?;
+    match keyctl(
+        libc::KEYCTL_SEARCH,
+        keyring as libc::c_ulong,
+        key_type.as_ptr() as libc::c_ulong,
+        description.as_ptr() as libc::c_ulong,
+    ) {
+        Ok(serial) => Ok(Some(serial as i32)),
+        Err(err)
+            if matches!(
+                err.raw_os_error(),
+                Some(libc::ENOKEY) | Some(libc::EKEYEXPIRED) | Some(libc::EKEYREVOKED)
+            ) =>
+        {
+            Ok(None)
+        }
+        Err(err) => Err(anyhow!("search keyring {}: {}", keyring, err)),
+    }
+}
+
+fn keyctl(
+    op: u32,
+    arg2: libc::c_ulong,
+    arg3: libc::c_ulong,
+    arg4: libc::c_ulong,
+) -> io::Result<libc::c_long> {
+    // SAFETY: callers pass serials, lengths, or pointers to buffers that stay
+    // live (and correctly sized) for the duration of the call.
+    let rc = unsafe { libc::syscall(libc::SYS_keyctl, op as libc::c_ulong, arg2, arg3, arg4, 0) };
+    if rc < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(rc)
+    }
+}
+
+fn add_key(key_type: &str, description: &str, payload: &[u8], keyring: i32) -> Result<i32> {
+    let key_type = CString::new(key_type)?;
+    let description = CString::new(description)?;
+    let payload_ptr = if payload.is_empty() {
+        std::ptr::null()
+    } else {
+        payload.as_ptr() as *const libc::c_void
+    };
+    // SAFETY: all pointers reference live, NUL-terminated (or length-bounded)
+    // buffers for the duration of the call.
+    let serial = unsafe {
+        libc::syscall(
+            libc::SYS_add_key,
+            key_type.as_ptr(),
+            description.as_ptr(),
+            payload_ptr,
+            payload.len(),
+            keyring,
+        )
+    };
+    if serial < 0 {
+        return Err(anyhow!(
+            "add_key {} '{}' failed: {}",
+            key_type.to_string_lossy(),
+            description.to_string_lossy(),
+            io::Error::last_os_error()
+        ));
+    }
+    Ok(serial as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{description_for, parse_target, KeyringTarget};
+
+    #[test]
+    fn keyring_targets_parse_specials_and_plain_names() {
+        assert_eq!(
+            parse_target("@u").unwrap(),
+            KeyringTarget::Special(libc::KEY_SPEC_USER_KEYRING)
+        );
+        assert_eq!(
+            parse_target("beskar").unwrap(),
+            KeyringTarget::Named("beskar".to_string())
+        );
+        assert!(parse_target("@x").is_err());
+        assert!(parse_target("bad name").is_err());
+        assert!(parse_target("").is_err());
+        assert_eq!(description_for("rpool/ROOT"), "zfs:rpool/ROOT");
+    }
+}
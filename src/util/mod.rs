@@ -5,7 +5,19 @@
 pub mod atomic;
 pub mod audit;
 pub mod binary;
+pub mod breakglass;
+pub mod checksum;
+pub mod escrow;
+pub mod fido2;
+pub mod holders;
+pub mod initramfs;
 pub mod kdf;
 pub mod keyfile;
+pub mod keyring;
 pub mod lockout;
+pub mod rawtoken;
 pub mod recovery;
+pub mod secret;
+pub mod token;
+pub mod usbid;
+pub mod wear;
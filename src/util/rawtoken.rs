@@ -0,0 +1,102 @@
+// ============================================================================
+// src/util/rawtoken.rs – key record on an unformatted token partition
+// ============================================================================
+
+use crate::zfs::KEY_LEN;
+use anyhow::{anyhow, Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Leads the record so a blank or foreign partition is refused, not misread.
+/// The boot loaders compare against the same string.
+pub const MAGIC: &[u8; 8] = b"BESKRAW1";
+/// Record offset `init --raw-token` uses unless `[usb] raw_offset` says otherwise.
+pub const DEFAULT_OFFSET: u64 = 4096;
+/// Leading bytes zeroed before a record is written, so no stale filesystem
+/// signature (ext4 keeps its superblock at 1 KiB) survives for a finder to mount.
+const SCRUB_LEN: u64 = 1 << 20;
+
+/// Read the key from the record at `offset` on `device`, opened read-only.
+pub fn read_key(device: &Path, offset: u64) -> Result<Zeroizing<Vec<u8>>> {
+    let mut file =
+        File::open(device).with_context(|| format!("open raw token {}", device.display()))?;
+    file.seek(SeekFrom::Start(offset))
+        .with_context(|| format!("seek to {} on {}", offset, device.display()))?;
+    let mut record = Zeroizing::new(vec![0u8; MAGIC.len() + KEY_LEN]);
+    file.read_exact(&mut record)
+        .with_context(|| format!("read key record at {} on {}", offset, device.display()))?;
+    parse_record(&record).with_context(|| format!("raw token {}", device.display()))
+}
+
+/// Zero the head of `device`, then write the magic and `key` at `offset`.
+pub fn write_key(device: &Path, offset: u64, key: &[u8]) -> Result<()> {
+    if key.len() != KEY_LEN {
+        return Err(anyhow!(
+            "raw token record expects a {}-byte key (got {})",
+            KEY_LEN,
+            key.len()
+        ));
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(device)
+        .with_context(|| format!("open raw token {} for writing", device.display()))?;
+    let size = file.seek(SeekFrom::End(0))?;
+    if size < offset + (MAGIC.len() + KEY_LEN) as u64 {
+        return Err(anyhow!(
+            "{} ({} bytes) is too small for a key record at offset {}",
+            device.display(),
+            size,
+            offset
+        ));
+    }
+
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&vec![0u8; SCRUB_LEN.min(size) as usize])
+        .with_context(|| format!("scrub {}", device.display()))?;
+
+    let mut record = Zeroizing::new(MAGIC.to_vec());
+    record.extend_from_slice(key);
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(&record)
+        .with_context(|| format!("write key record to {}", device.display()))?;
+    file.sync_all()
+        .with_context(|| format!("flush {}", device.display()))?;
+    Ok(())
+}
+
+fn parse_record(record: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let (magic, key) = record.split_at(MAGIC.len().min(record.len()));
+    if magic != MAGIC || key.len() != KEY_LEN {
+        return Err(anyhow!(
+            "no Beskar key record found (expected the {} header)",
+            String::from_utf8_lossy(MAGIC)
+        ));
+    }
+    Ok(Zeroizing::new(key.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_key, write_key, MAGIC};
+    use std::fs;
+
+    #[test]
+    fn record_round_trips_and_blank_media_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let device = dir.path().join("token.img");
+        fs::write(&device, vec![0xAAu8; 64 * 1024]).unwrap();
+        assert!(read_key(&device, 4096).is_err());
+
+        write_key(&device, 4096, &[9u8; 32]).unwrap();
+        assert_eq!(&read_key(&device, 4096).unwrap()[..], &[9u8; 32]);
+        assert!(read_key(&device, 0).is_err());
+
+        let image = fs::read(&device).unwrap();
+        assert!(image[..4096].iter().all(|b| *b == 0));
+        assert_eq!(&image[4096..4104], MAGIC);
+        assert!(write_key(&device, 64 * 1024, &[9u8; 32]).is_err());
+    }
+}
@@ -2,28 +2,68 @@
 // src/util/recovery.rs – encode/decode helpers for recovery keys
 // ============================================================================
 
+use crate::zfs::KEY_LEN;
 use anyhow::{anyhow, Result};
 use data_encoding::BASE32_NOPAD;
 use zeroize::Zeroizing;
 
-pub fn encode_recovery_code(raw: &[u8]) -> String {
-    BASE32_NOPAD.encode(raw).to_uppercase()
+/// Fewer distinct bytes than this in 32 random bytes is effectively impossible.
+const MIN_DISTINCT_BYTES: usize = 8;
+
+/// The sigil is the key itself, so it only ever lives in a zeroizing buffer.
+/// `BASE32_NOPAD` already emits uppercase, so no re-cased copy is made.
+pub fn encode_recovery_code(raw: &[u8]) -> Zeroizing<String> {
+    Zeroizing::new(BASE32_NOPAD.encode(raw))
 }
 
 pub fn decode_recovery_code(input: &str) -> Result<Zeroizing<Vec<u8>>> {
-    let cleaned: String = input
-        .chars()
-        .filter(|c| !c.is_whitespace() && *c != '-')
-        .map(|c| c.to_ascii_uppercase())
-        .collect();
-    let bytes = BASE32_NOPAD
-        .decode(cleaned.as_bytes())
-        .map_err(|e| anyhow!("Recovery key invalid: {}", e))?;
-    if bytes.len() != 32 {
+    // Sized up front: a growing String would strand unscrubbed copies.
+    let mut cleaned = Zeroizing::new(String::with_capacity(input.len()));
+    cleaned.extend(
+        input
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_uppercase()),
+    );
+    let bytes = Zeroizing::new(
+        BASE32_NOPAD
+            .decode(cleaned.as_bytes())
+            .map_err(|e| anyhow!("Recovery key invalid: {}", e))?,
+    );
+    if bytes.len() != KEY_LEN {
+        return Err(anyhow!(
+            "Recovery key decoded to {} bytes (expected {}).",
+            bytes.len(),
+            KEY_LEN
+        ));
+    }
+    // Forged keys come from OsRng; a sigil with only a handful of distinct
+    // bytes (e.g. "AAAA…") was mistyped or invented, never issued.
+    let mut distinct = bytes.clone();
+    distinct.sort_unstable();
+    distinct.dedup();
+    if distinct.len() < MIN_DISTINCT_BYTES {
         return Err(anyhow!(
-            "Recovery key decoded to {} bytes (expected 32).",
-            bytes.len()
+            "Recovery key lacks entropy ({} distinct bytes); check the sigil.",
+            distinct.len()
         ));
     }
-    Ok(Zeroizing::new(bytes))
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_recovery_code, encode_recovery_code};
+
+    #[test]
+    fn recovery_codes_roundtrip_and_reject_low_entropy() {
+        let raw: Vec<u8> = (0u8..32).map(|b| b.wrapping_mul(37)).collect();
+        let code = encode_recovery_code(&raw);
+        assert_eq!(
+            &decode_recovery_code(&code.to_lowercase()).unwrap()[..],
+            &raw[..]
+        );
+        assert!(decode_recovery_code(&encode_recovery_code(&[0u8; 32])).is_err());
+        assert!(decode_recovery_code(&encode_recovery_code(&raw[..16])).is_err());
+    }
 }
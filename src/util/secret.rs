@@ -0,0 +1,93 @@
+// ============================================================================
+// src/util/secret.rs – Keep passphrase text inside zeroizing buffers
+// ============================================================================
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::File;
+use std::io::{self, Read};
+use std::mem::ManuallyDrop;
+use std::os::unix::io::FromRawFd;
+use zeroize::Zeroizing;
+
+/// Take ownership of secret text (a prompt reply, askpass stdout), drop any
+/// trailing line ending in place, and hand back the same allocation as bytes
+/// that are zeroed on drop. No copy of the secret is left behind.
+pub fn secret_line(text: String) -> Zeroizing<Vec<u8>> {
+    let mut text = Zeroizing::new(text);
+    secret_bytes_line(Zeroizing::new(std::mem::take(&mut *text).into_bytes()))
+}
+
+/// `secret_line` for bytes already held in a zeroizing buffer (a
+/// `Cmd::run_secret` reply): the line ending is trimmed in place.
+pub fn secret_bytes_line(mut bytes: Zeroizing<Vec<u8>>) -> Zeroizing<Vec<u8>> {
+    let len = bytes.len()
+        - bytes
+            .iter()
+            .rev()
+            .take_while(|b| matches!(b, b'\n' | b'\r'))
+            .count();
+    // The line ending is not secret, but wipe it anyway so the whole buffer
+    // is clean once the caller drops the bytes.
+    for b in bytes[len..].iter_mut() {
+        *b = 0;
+    }
+    bytes.truncate(len);
+    bytes
+}
+
+/// Everything piped on stdin, read from fd 0 directly: std's `Stdin` keeps
+/// its own buffer, which would strand a copy of the secret. The bytes land in
+/// one pre-sized zeroizing buffer; more than `max` of them is an error.
+pub fn read_stdin_secret(max: usize) -> Result<Zeroizing<Vec<u8>>> {
+    // SAFETY: fd 0 stays open for the life of the process, and ManuallyDrop
+    // keeps this borrowed handle from closing it.
+    let mut fd0 = ManuallyDrop::new(unsafe { File::from_raw_fd(0) });
+    read_capped(&mut *fd0, max).context("read secret from stdin")
+}
+
+fn read_capped(src: &mut impl Read, max: usize) -> Result<Zeroizing<Vec<u8>>> {
+    let mut buf = Zeroizing::new(vec![0u8; max + 1]);
+    let mut len = 0;
+    loop {
+        if len > max {
+            return Err(anyhow!("more than {} bytes on stdin", max));
+        }
+        match src.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    buf.truncate(len);
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_capped, secret_bytes_line, secret_line};
+    use zeroize::Zeroizing;
+
+    #[test]
+    fn capped_reads_stay_in_one_buffer_and_refuse_overflow() {
+        let read = read_capped(&mut &b"0123abcd\n"[..], 16).unwrap();
+        assert_eq!(&read[..], b"0123abcd\n");
+        assert_eq!(read.capacity(), 17);
+        assert!(read_capped(&mut &[7u8; 17][..], 16).is_err());
+        assert_eq!(
+            &read_capped(&mut &[7u8; 16][..], 16).unwrap()[..],
+            &[7u8; 16]
+        );
+    }
+
+    #[test]
+    fn secret_lines_lose_trailing_newlines_only() {
+        assert_eq!(&secret_line("hunter2\r\n".to_string())[..], b"hunter2");
+        assert_eq!(&secret_line(" pass \n\n".to_string())[..], b" pass ");
+        assert!(secret_line("\n".to_string()).is_empty());
+        assert_eq!(
+            &secret_bytes_line(Zeroizing::new(b"pin\n".to_vec()))[..],
+            b"pin"
+        );
+    }
+}
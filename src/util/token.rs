@@ -0,0 +1,366 @@
+// ============================================================================
+// src/util/token.rs – Find, mount, and release labelled tokens
+// ============================================================================
+
+use crate::cmd::base::extra_binaries_named;
+use crate::cmd::{Cmd, OutputData};
+use crate::config::ConfigFile;
+use crate::ui::UX;
+use crate::util::audit::audit_log;
+use crate::util::holders::unescape_mount_field;
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub(crate) const BLKID_BINARIES: &[&str] = &["/sbin/blkid", "/usr/sbin/blkid", "/usr/bin/blkid"];
+pub(crate) const MOUNT_BINARIES: &[&str] = &["/bin/mount", "/usr/bin/mount"];
+pub(crate) const UMOUNT_BINARIES: &[&str] = &["/bin/umount", "/usr/bin/umount"];
+// Per-operation limits for `external_cmd` handles; callers pick one per run.
+pub(crate) const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+pub(crate) const MOUNT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Anything a provisioning helper writes is owner-only.
+pub(crate) const PROVISION_UMASK: libc::mode_t = 0o077;
+
+/// A token this process mounted under the key path. Dropping it unmounts the
+/// token again, so a manual `unlock` leaves the runtime directory as it was.
+pub struct MountedToken {
+    device: String,
+    mountpoint: PathBuf,
+}
+
+impl MountedToken {
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    /// Unmount the token, auditing the outcome either way.
+    fn release(&self) -> Result<()> {
+        let detail = format!(
+            "device={} mountpoint={}",
+            self.device,
+            self.mountpoint.display()
+        );
+        let result = unmount_partition(&self.mountpoint);
+        match &result {
+            Ok(()) => audit_log("TOKEN_AUTO_UNMOUNT", &detail),
+            Err(err) => audit_log(
+                "TOKEN_AUTO_UNMOUNT_FAIL",
+                &format!("{} reason={}", detail, err),
+            ),
+        }
+        result
+    }
+}
+
+impl Drop for MountedToken {
+    fn drop(&mut self) {
+        let _ = self.release();
+    }
+}
+
+/// The mount unit pins a single token's UUID. When `key_path` is missing and
+/// `[usb] auto_mount` allows it, walk `[usb] labels` in priority order and
+/// mount the first token that carries the key read-only under the key path,
+/// so a spare carrying another label still serves.
+pub fn mount_token_if_needed(ui: &UX, cfg: &ConfigFile, key_path: &Path) -> Option<MountedToken> {
+    if key_path.exists() || !cfg.usb.auto_mount || cfg.usb.raw_device.is_some() {
+        return None;
+    }
+    let mountpoint = key_path.parent()?;
+    for label in cfg.usb.token_labels() {
+        let device = match find_labeled_tokens(&label) {
+            Ok(devices) => match devices.into_iter().next() {
+                Some(device) => device,
+                None => continue,
+            },
+            Err(err) => {
+                ui.trace(&format!("label {} search failed: {}", label, err));
+                continue;
+            }
+        };
+        let mounted = fs::create_dir_all(mountpoint)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| mount_partition_read_only(&device, mountpoint));
+        if let Err(err) = mounted {
+            ui.warn(&format!(
+                "Token {} at {} would not mount ({}).",
+                label, device, err
+            ));
+            continue;
+        }
+        if key_path.exists() {
+            ui.info(&format!(
+                "Token label {} satisfied the search; {} mounted at {} until the key is read.",
+                label,
+                device,
+                mountpoint.display()
+            ));
+            audit_log(
+                "UNLOCK_TOKEN_LABEL",
+                &format!("label={} device={}", label, device),
+            );
+            return Some(MountedToken {
+                device,
+                mountpoint: mountpoint.to_path_buf(),
+            });
+        }
+        ui.warn(&format!(
+            "Token {} at {} carries no {}; releasing it.",
+            label,
+            device,
+            key_path.display()
+        ));
+        let _ = unmount_partition(mountpoint);
+    }
+    None
+}
+
+/// A read-only token mount flipped to read-write for one write. Dropping it
+/// flips the mount back, so the boot-time `ro` posture survives the edit.
+pub struct WritableMount {
+    mountpoint: PathBuf,
+}
+
+impl Drop for WritableMount {
+    fn drop(&mut self) {
+        let detail = format!("mountpoint={}", self.mountpoint.display());
+        match remount(&self.mountpoint, "remount,ro") {
+            Ok(()) => audit_log("TOKEN_REMOUNT_RO", &detail),
+            Err(err) => audit_log(
+                "TOKEN_REMOUNT_RO_FAIL",
+                &format!("{} reason={}", detail, err),
+            ),
+        }
+    }
+}
+
+/// Make the filesystem that will hold `path` writable while the guard lives.
+/// `None` when it already is. `run-beskar.mount` mounts the token `ro`, so
+/// files kept beside the key (the break-glass seal) need this to change.
+pub fn remount_writable(ui: &UX, path: &Path) -> Result<Option<WritableMount>> {
+    // The write creates missing parents, so judge the nearest one that exists.
+    let dir = path
+        .ancestors()
+        .skip(1)
+        .find(|p| p.exists())
+        .unwrap_or(Path::new("."));
+    let dir = fs::canonicalize(dir).map_err(|err| anyhow!("resolve {}: {}", dir.display(), err))?;
+    let mounts =
+        fs::read_to_string("/proc/mounts").map_err(|err| anyhow!("read /proc/mounts: {}", err))?;
+    let Some((mountpoint, read_only)) = enclosing_mount(&mounts, &dir) else {
+        return Ok(None);
+    };
+    if !read_only {
+        return Ok(None);
+    }
+    remount(&mountpoint, "remount,rw").map_err(|err| {
+        anyhow!(
+            "{} is mounted read-only and could not be remounted read-write ({:#})",
+            mountpoint.display(),
+            err
+        )
+    })?;
+    ui.note(&format!(
+        "{} remounted read-write for the write; it returns to read-only afterwards.",
+        mountpoint.display()
+    ));
+    audit_log(
+        "TOKEN_REMOUNT_RW",
+        &format!("mountpoint={}", mountpoint.display()),
+    );
+    Ok(Some(WritableMount { mountpoint }))
+}
+
+/// The innermost `/proc/mounts` entry containing `dir`, and whether it is
+/// read-only. The last of several entries on one mountpoint is the visible one.
+fn enclosing_mount(mounts: &str, dir: &Path) -> Option<(PathBuf, bool)> {
+    let mut best: Option<(PathBuf, bool)> = None;
+    for line in mounts.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(_), Some(mountpoint), Some(_), Some(options)) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let mountpoint = PathBuf::from(unescape_mount_field(mountpoint));
+        if !dir.starts_with(&mountpoint) {
+            continue;
+        }
+        let deeper = best.as_ref().is_none_or(|(current, _)| {
+            mountpoint.components().count() >= current.components().count()
+        });
+        if deeper {
+            let read_only = options.split(',').any(|opt| opt == "ro");
+            best = Some((mountpoint, read_only));
+        }
+    }
+    best
+}
+
+pub(crate) fn remount(mountpoint: &Path, options: &str) -> Result<()> {
+    let mount_str = mountpoint
+        .to_str()
+        .ok_or_else(|| anyhow!("invalid mount path"))?;
+    run_checked(MOUNT_BINARIES, &["-o", options, mount_str])?;
+    Ok(())
+}
+
+/// Block devices carrying the token filesystem label. `blkid -L` only ever
+/// reports the first match, so the token scan uses `-t LABEL=` to see them all.
+pub fn find_labeled_tokens(label: &str) -> Result<Vec<String>> {
+    let token = format!("LABEL={}", label);
+    let out = external_cmd(BLKID_BINARIES)?.run_with_timeout(
+        &["-o", "device", "-t", &token],
+        None,
+        PROBE_TIMEOUT,
+    )?;
+    // blkid exits 2 when nothing matches the search token.
+    if out.status == 2 {
+        return Ok(Vec::new());
+    }
+    if out.status != 0 {
+        return Err(anyhow!("blkid label search failed: {}", out.stderr.trim()));
+    }
+    Ok(parse_device_list(&out.stdout))
+}
+
+/// Mount `partition` read-only for inspection (token inventory, audits).
+pub fn mount_partition_read_only(partition: &str, mountpoint: &Path) -> Result<()> {
+    let mount_str = mountpoint
+        .to_str()
+        .ok_or_else(|| anyhow!("invalid mount path"))?;
+    run_checked(
+        MOUNT_BINARIES,
+        &["-o", "ro,nosuid,nodev,noexec", partition, mount_str],
+    )?;
+    Ok(())
+}
+
+/// Mount a small private tmpfs at `mountpoint`, a stand-in token for drills
+/// that `remount` can later make read-only.
+pub fn mount_scratch_token(mountpoint: &Path) -> Result<()> {
+    let mount_str = mountpoint
+        .to_str()
+        .ok_or_else(|| anyhow!("invalid mount path"))?;
+    run_checked(
+        MOUNT_BINARIES,
+        &[
+            "-t",
+            "tmpfs",
+            "-o",
+            "size=1m,mode=0700,nosuid,nodev,noexec",
+            "beskar-drill",
+            mount_str,
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn unmount_partition(mountpoint: &Path) -> Result<()> {
+    let mount_str = mountpoint
+        .to_str()
+        .ok_or_else(|| anyhow!("invalid mount path"))?;
+    run_checked(UMOUNT_BINARIES, &[mount_str])?;
+    Ok(())
+}
+
+fn parse_device_list(output: &str) -> Vec<String> {
+    let mut devices: Vec<String> = Vec::new();
+    for line in output.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if !devices.iter().any(|d| d == line) {
+            devices.push(line.to_string());
+        }
+    }
+    devices
+}
+
+fn run_checked(candidates: &[&str], args: &[&str]) -> Result<OutputData> {
+    let cmd = external_cmd(candidates)?;
+    let out = cmd.run_with_timeout(args, None, MOUNT_TIMEOUT)?;
+    cmd.ensure_success(args, out)
+}
+
+/// Resolve the first present candidate (or a same-named config extra) to an
+/// allowlisted handle; callers pick the timeout per invocation. Helpers run
+/// from `/` (never pinning the token mount) under `PROVISION_UMASK`.
+pub(crate) fn external_cmd(candidates: &[&str]) -> Result<Cmd> {
+    let confine = |cmd: Cmd| cmd.with_cwd("/").with_umask(PROVISION_UMASK);
+    if let Some(&path) = candidates.iter().find(|p| Path::new(p).exists()) {
+        return Cmd::new_allowlisted(path, MOUNT_TIMEOUT).map(confine);
+    }
+    let name = candidates
+        .first()
+        .and_then(|c| Path::new(c).file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    if let Some(extra) = extra_binaries_named(name).into_iter().next() {
+        return Cmd::new_allowlisted(extra, MOUNT_TIMEOUT).map(confine);
+    }
+    Err(anyhow!(
+        "None of the candidate binaries {:?} were found on this system",
+        candidates
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{enclosing_mount, mount_token_if_needed, parse_device_list, MountedToken};
+    use crate::config::{ConfigFile, CONFIG_TEMPLATE};
+    use crate::ui::UX;
+
+    #[test]
+    fn label_walk_leaves_present_keys_raw_tokens_and_opt_outs_alone() {
+        let ui = UX::new(false, true);
+        let dir = tempfile::tempdir().unwrap();
+        let mut cfg: ConfigFile = toml::from_str(CONFIG_TEMPLATE).unwrap();
+
+        let present = dir.path().join("present.key");
+        std::fs::write(&present, "key").unwrap();
+        assert!(mount_token_if_needed(&ui, &cfg, &present).is_none());
+
+        // Neither case may even create the mountpoint.
+        let missing = dir.path().join("run/beskar/missing.key");
+        cfg.usb.auto_mount = false;
+        assert!(mount_token_if_needed(&ui, &cfg, &missing).is_none());
+        cfg.usb.auto_mount = true;
+        cfg.usb.raw_device = Some("/dev/disk/by-partuuid/0000".to_string());
+        assert!(mount_token_if_needed(&ui, &cfg, &missing).is_none());
+        assert!(!dir.path().join("run").exists());
+
+        assert_eq!(
+            parse_device_list("/dev/sdb1\n\n/dev/sdc1\n/dev/sdb1\n"),
+            ["/dev/sdb1", "/dev/sdc1"]
+        );
+    }
+
+    #[test]
+    fn the_innermost_mount_decides_whether_a_remount_is_needed() {
+        let mounts = "/dev/sda2 / ext4 rw,relatime 0 0\n\
+                      tmpfs /run tmpfs rw,nosuid,nodev 0 0\n\
+                      /dev/sdb1 /run/beskar ext4 ro,nosuid,nodev,noexec 0 0\n\
+                      /dev/sdc1 /run/beskar\\040spare ext4 rw 0 0\n";
+        let seal = std::path::Path::new("/run/beskar");
+        assert_eq!(enclosing_mount(mounts, seal), Some((seal.into(), true)));
+        assert_eq!(
+            enclosing_mount(mounts, std::path::Path::new("/run/beskar spare")),
+            Some(("/run/beskar spare".into(), false))
+        );
+        assert_eq!(
+            enclosing_mount(mounts, std::path::Path::new("/run/other")),
+            Some(("/run".into(), false))
+        );
+    }
+
+    #[test]
+    fn dropping_a_token_that_is_not_mounted_reports_and_keeps_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let token = MountedToken {
+            device: "/dev/null".to_string(),
+            mountpoint: dir.path().to_path_buf(),
+        };
+        assert!(token.release().is_err());
+        drop(token);
+        assert!(dir.path().is_dir());
+    }
+}
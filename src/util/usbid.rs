@@ -0,0 +1,175 @@
+// ============================================================================
+// src/util/usbid.rs – USB identity pinning for `[usb] require_serial/vidpid`
+// ============================================================================
+
+use crate::cmd::Cmd;
+use crate::util::holders::unescape_mount_field;
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const UDEVADM_CANDIDATES: [&str; 3] = ["/sbin/udevadm", "/usr/sbin/udevadm", "/usr/bin/udevadm"];
+
+/// Identity udev reports for a block device (partitions inherit the USB
+/// attributes of their parent disk).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UsbIdentity {
+    pub serial: Option<String>,
+    pub vendor_id: Option<String>,
+    pub product_id: Option<String>,
+}
+
+impl UsbIdentity {
+    /// `vvvv:pppp` in lowercase hex, when udev knows both halves.
+    pub fn vidpid(&self) -> Option<String> {
+        match (&self.vendor_id, &self.product_id) {
+            (Some(v), Some(p)) => Some(format!("{}:{}", v, p).to_ascii_lowercase()),
+            _ => None,
+        }
+    }
+
+    /// Why this device fails the pins, or `None` when it satisfies them.
+    pub fn mismatch(&self, serial: Option<&str>, vidpid: Option<&str>) -> Option<String> {
+        if let Some(want) = serial {
+            if self.serial.as_deref() != Some(want) {
+                return Some(format!(
+                    "serial {} does not match pinned {}",
+                    self.serial.as_deref().unwrap_or("(none)"),
+                    want
+                ));
+            }
+        }
+        if let Some(want) = vidpid {
+            let want = want.to_ascii_lowercase();
+            let found = self.vidpid();
+            if found.as_deref() != Some(want.as_str()) {
+                return Some(format!(
+                    "vendor:product {} does not match pinned {}",
+                    found.as_deref().unwrap_or("(none)"),
+                    want
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Accept `vvvv:pppp` (four hex digits each, any case).
+pub fn validate_vidpid(spec: &str) -> Result<()> {
+    let valid = spec.split_once(':').is_some_and(|(v, p)| {
+        [v, p]
+            .iter()
+            .all(|h| h.len() == 4 && h.chars().all(|c| c.is_ascii_hexdigit()))
+    });
+    if !valid {
+        return Err(anyhow!(
+            "usb.require_vidpid '{}' must look like 0781:5583 (vendor:product hex)",
+            spec
+        ));
+    }
+    Ok(())
+}
+
+/// Serials land in the boot scripts, so keep them to `[A-Za-z0-9_.-]`.
+pub fn validate_serial(spec: &str) -> Result<()> {
+    if spec.is_empty()
+        || !spec
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+    {
+        return Err(anyhow!(
+            "usb.require_serial '{}' may only contain letters, digits, '_', '.' or '-'",
+            spec
+        ));
+    }
+    Ok(())
+}
+
+/// Ask udev for the USB identity of `device` (e.g. /dev/sdb1).
+pub fn query(device: &str) -> Result<UsbIdentity> {
+    let udevadm = UDEVADM_CANDIDATES
+        .iter()
+        .find(|p| Path::new(p).exists())
+        .ok_or_else(|| anyhow!("udevadm not found; cannot verify the token device"))?;
+    let cmd = Cmd::new_allowlisted(*udevadm, Duration::from_secs(5))?;
+    let name = format!("--name={}", device);
+    let out = cmd.run_checked(&["info", "--query=property", &name], None)?;
+    Ok(parse_properties(&out.stdout))
+}
+
+/// Device mounted at the deepest mountpoint containing `path`, from
+/// `/proc/self/mounts`.
+pub fn mount_source(path: &Path) -> Option<String> {
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    mount_source_in(&mounts, path)
+}
+
+fn mount_source_in(mounts: &str, path: &Path) -> Option<String> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let source = fields.next()?;
+            let target = unescape_mount_field(fields.next()?);
+            Path::new(path)
+                .starts_with(&target)
+                .then(|| (target.len(), source.to_string()))
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map(|(_, source)| source)
+        .filter(|source| source.starts_with("/dev/"))
+}
+
+fn parse_properties(text: &str) -> UsbIdentity {
+    let mut identity = UsbIdentity::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        match key {
+            "ID_SERIAL_SHORT" => identity.serial = value,
+            "ID_VENDOR_ID" => identity.vendor_id = value,
+            "ID_MODEL_ID" => identity.product_id = value,
+            _ => {}
+        }
+    }
+    identity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mount_source_in, parse_properties, validate_serial, validate_vidpid};
+    use std::path::Path;
+
+    #[test]
+    fn token_identity_pins_serial_and_vidpid() {
+        let identity = parse_properties(
+            "DEVNAME=/dev/sdb1\nID_VENDOR_ID=0781\nID_MODEL_ID=5583\nID_SERIAL_SHORT=4C530001\n",
+        );
+        assert_eq!(identity.vidpid().as_deref(), Some("0781:5583"));
+        assert_eq!(identity.mismatch(Some("4C530001"), Some("0781:5583")), None);
+        assert!(identity.mismatch(Some("FFFF"), None).is_some());
+        assert!(identity.mismatch(None, Some("0781:5584")).is_some());
+        assert!(parse_properties("")
+            .mismatch(None, Some("0781:5583"))
+            .is_some());
+
+        assert!(validate_vidpid("0781:5583").is_ok());
+        assert!(validate_vidpid("781:5583").is_err());
+        assert!(validate_vidpid("sandisk").is_err());
+        assert!(validate_serial("4C530001").is_ok());
+        assert!(validate_serial("x\"$(id)").is_err());
+    }
+
+    #[test]
+    fn key_path_resolves_to_the_deepest_mount() {
+        let mounts = "/dev/nvme0n1p2 / ext4 rw 0 0\ntmpfs /run tmpfs rw 0 0\n/dev/sdb1 /run/beskar ext4 ro 0 0\n";
+        assert_eq!(
+            mount_source_in(mounts, Path::new("/run/beskar/rpool.keyhex")).as_deref(),
+            Some("/dev/sdb1")
+        );
+        assert_eq!(mount_source_in(mounts, Path::new("/run/other/key")), None);
+    }
+}
@@ -0,0 +1,131 @@
+// ============================================================================
+// src/util/wear.rs – write-endurance ledger kept beside the key on the token
+// ============================================================================
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::fs;
+use std::path::Path;
+
+use crate::util::atomic::atomic_write_bytes;
+
+/// Sidecar file name stored in the token root next to the key file.
+pub const WEAR_FILE_NAME: &str = ".beskar-wear";
+
+/// Rewrites after which doctor suggests keeping a spare token on hand.
+pub const WEAR_WARN_WRITES: u32 = 100;
+
+/// Rewrite and verification history for one token.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WearRecord {
+    pub writes: u32,
+    pub verify_failures: u32,
+    pub last_write: Option<String>,
+}
+
+impl WearRecord {
+    /// Parse `key=value` lines; unknown keys and malformed counters are ignored.
+    pub fn parse(text: &str) -> Self {
+        let mut record = WearRecord::default();
+        for line in text.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                match key.trim() {
+                    "writes" => record.writes = value.parse().unwrap_or(record.writes),
+                    "verify_failures" => {
+                        record.verify_failures = value.parse().unwrap_or(record.verify_failures)
+                    }
+                    "last_write" if !value.is_empty() => {
+                        record.last_write = Some(value.to_string())
+                    }
+                    _ => {}
+                }
+            }
+        }
+        record
+    }
+
+    pub fn render(&self) -> String {
+        format!(
+            "writes={}\nverify_failures={}\nlast_write={}\n",
+            self.writes,
+            self.verify_failures,
+            self.last_write.as_deref().unwrap_or_default()
+        )
+    }
+
+    /// True once the token has been rewritten often enough to warrant a spare.
+    pub fn is_worn(&self) -> bool {
+        self.writes >= WEAR_WARN_WRITES
+    }
+}
+
+/// Read the ledger from a mounted token root, if one has been recorded.
+pub fn load(token_root: &Path) -> Result<Option<WearRecord>> {
+    let path = token_root.join(WEAR_FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(&path)
+        .with_context(|| format!("read wear ledger {}", path.display()))?;
+    Ok(Some(WearRecord::parse(&text)))
+}
+
+/// Increment the rewrite counter on a mounted token root.
+pub fn record_write(token_root: &Path) -> Result<WearRecord> {
+    update(token_root, |record| {
+        record.writes = record.writes.saturating_add(1);
+        record.last_write = Some(Utc::now().to_rfc3339());
+    })
+}
+
+/// Count a key whose read-back did not match what was written.
+pub fn record_verify_failure(token_root: &Path) -> Result<WearRecord> {
+    update(token_root, |record| {
+        record.verify_failures = record.verify_failures.saturating_add(1);
+    })
+}
+
+fn update(token_root: &Path, change: impl FnOnce(&mut WearRecord)) -> Result<WearRecord> {
+    let mut record = load(token_root)?.unwrap_or_default();
+    change(&mut record);
+    let path = token_root.join(WEAR_FILE_NAME);
+    atomic_write_bytes(&path, record.render().as_bytes(), 0o600, true)
+        .with_context(|| format!("write wear ledger {}", path.display()))?;
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WearRecord, WEAR_WARN_WRITES};
+
+    #[test]
+    fn wear_ledger_round_trips_and_tolerates_noise() {
+        let record = WearRecord {
+            writes: 7,
+            verify_failures: 1,
+            last_write: Some("2025-10-24T10:00:00+00:00".to_string()),
+        };
+        assert_eq!(WearRecord::parse(&record.render()), record);
+
+        let noisy = WearRecord::parse("writes=abc\nunknown=1\nverify_failures=2\n");
+        assert_eq!(noisy.writes, 0);
+        assert_eq!(noisy.verify_failures, 2);
+        assert!(noisy.last_write.is_none());
+
+        let worn = WearRecord {
+            writes: WEAR_WARN_WRITES,
+            ..WearRecord::default()
+        };
+        assert!(worn.is_worn());
+    }
+
+    #[test]
+    fn read_back_failures_accumulate_beside_the_rewrites() {
+        let token = tempfile::tempdir().unwrap();
+        super::record_write(token.path()).unwrap();
+        super::record_verify_failure(token.path()).unwrap();
+        let record = super::load(token.path()).unwrap().unwrap();
+        assert_eq!((record.writes, record.verify_failures), (1, 1));
+    }
+}
@@ -2,16 +2,193 @@
 // src/zfs.rs – safe wrappers for ZFS key operations
 // ============================================================================
 
-use crate::cmd::{Cmd, OutputData};
+use crate::cmd::base::{extra_binaries_named, is_allowlisted};
+use crate::cmd::{Cmd, OutputData, Termination};
+use crate::config::ConfigFile;
+use crate::util::binary::find_in_path;
+use crate::util::holders::unescape_mount_field;
+use crate::zpool::pool_of;
 use anyhow::{anyhow, Context, Result};
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::path::Path;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Length of a ZFS `keyformat=raw` wrapping key (AES-256). Every Beskar key
+/// is generated and validated against this.
+pub const KEY_LEN: usize = 32;
 
 /// Safe ZFS command wrapper. All calls go through the allow-listed `cmd` layer.
 pub struct Zfs {
     path: String,
     timeout: Duration,
+    /// Path came from `policy.zfs_path` and may sit outside the static allowlist.
+    configured: bool,
+}
+
+/// Wall-clock split of `Zfs::load_key_tree`, accumulated across attempts so
+/// slow boots can be traced to the root load or the descendant sweep.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KeyTreeTimings {
+    pub root: Duration,
+    pub descendants: Duration,
+}
+
+/// Outcome of `Zfs::load_key_tree`: the root is always open; descendants
+/// inheriting it that refused the key are listed rather than failing the load.
+#[derive(Debug, Default)]
+pub struct KeyTreeOutcome {
+    /// Datasets confirmed unlocked, root first.
+    pub fully_unlocked: Vec<String>,
+    /// Descendants inheriting the root that are still sealed after retries.
+    pub still_sealed: Vec<String>,
+    /// The `load-key` error each of those descendants gave, as
+    /// `(dataset, error)`.
+    pub refusals: Vec<(String, String)>,
+}
+
+impl KeyTreeOutcome {
+    /// The pre-partial behaviour: any sealed descendant fails the whole tree.
+    pub fn strict(self, root: &str) -> Result<Self> {
+        if self.still_sealed.is_empty() {
+            Ok(self)
+        } else {
+            Err(anyhow!(
+                "Datasets inheriting {} remain sealed after retries: {}",
+                root,
+                self.describe_sealed()
+            ))
+        }
+    }
+
+    /// `still_sealed` joined for logs, each with the error ZFS gave for it.
+    pub fn describe_sealed(&self) -> String {
+        self.still_sealed
+            .iter()
+            .map(
+                |ds| match self.refusals.iter().find(|(name, _)| name == ds) {
+                    Some((_, err)) => format!("{} ({})", ds, err),
+                    None => ds.clone(),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Per-dataset outcome of `Zfs::unload_key_tree`.
+#[derive(Debug, Default)]
+pub struct UnloadReport {
+    /// Encryption roots whose keys were unloaded (deepest first).
+    pub sealed: Vec<String>,
+    /// Datasets that refused to unload because they are mounted or busy.
+    pub busy: Vec<(String, String)>,
+    /// Datasets that failed to unload for any other reason.
+    pub failed: Vec<(String, String)>,
+}
+
+impl UnloadReport {
+    pub fn is_clean(&self) -> bool {
+        self.busy.is_empty() && self.failed.is_empty()
+    }
+}
+
+/// Classified failure from a `zfs` key operation. Built in one place from the
+/// exit status and stderr so callers match variants instead of message text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ZfsError {
+    KeyAlreadyLoaded,
+    IncorrectKey,
+    DatasetNotFound,
+    PermissionDenied,
+    Busy,
+    Other { stderr: String },
+}
+
+impl ZfsError {
+    /// Classify a finished `zfs` invocation; `None` when it succeeded.
+    pub fn classify(status: i32, stderr: &str) -> Option<Self> {
+        if status == 0 {
+            return None;
+        }
+        let lowered = stderr.to_lowercase();
+        let has = |needle: &str| lowered.contains(needle);
+        Some(if has("key already loaded") {
+            ZfsError::KeyAlreadyLoaded
+        } else if has("incorrect key") || has("invalid key") {
+            ZfsError::IncorrectKey
+        } else if has("dataset does not exist") || has("no such pool or dataset") {
+            ZfsError::DatasetNotFound
+        } else if has("permission denied")
+            || has("operation not permitted")
+            || has("must be superuser")
+        {
+            ZfsError::PermissionDenied
+        } else if has("busy") || has("is mounted") || has("currently mounted") {
+            ZfsError::Busy
+        } else {
+            ZfsError::Other {
+                stderr: stderr.trim().to_string(),
+            }
+        })
+    }
+}
+
+impl std::fmt::Display for ZfsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ZfsError::KeyAlreadyLoaded => write!(f, "key already loaded"),
+            ZfsError::IncorrectKey => write!(f, "incorrect key"),
+            ZfsError::DatasetNotFound => write!(f, "dataset not found"),
+            ZfsError::PermissionDenied => write!(f, "permission denied"),
+            ZfsError::Busy => write!(f, "dataset busy or mounted"),
+            ZfsError::Other { stderr } => write!(f, "{}", stderr),
+        }
+    }
+}
+
+impl std::error::Error for ZfsError {}
+
+/// True when `err` (or anything it wraps) is the given `ZfsError` variant.
+pub fn is_zfs_error(err: &anyhow::Error, kind: &ZfsError) -> bool {
+    err.downcast_ref::<ZfsError>() == Some(kind)
+}
+
+/// Encryption lineage and key state for one dataset, from a recursive scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyState {
+    pub name: String,
+    pub encryption_root: String,
+    pub keystatus: String,
+}
+
+/// Mount-relevant properties of one filesystem (`Zfs::mount_states`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountState {
+    pub name: String,
+    pub canmount: String,
+    pub mounted: bool,
+    pub mountpoint: String,
+    pub keystatus: String,
+}
+
+impl MountState {
+    /// Has a zfs-managed mountpoint other than `/`, a readable key, and is not
+    /// yet mounted. `canmount=noauto` qualifies only when `named` (the operator
+    /// asked for this dataset): zsys boot environments rely on it to stay out
+    /// of every mount but the one the bootloader chose.
+    pub fn wants_mount(&self, named: bool) -> bool {
+        !self.mounted
+            && (self.canmount == "on" || (named && self.canmount == "noauto"))
+            && !matches!(self.mountpoint.as_str(), "none" | "legacy" | "-" | "/")
+            && self.keystatus != "unavailable"
+    }
+}
+
+impl KeyState {
+    fn is_sealed(&self) -> bool {
+        let status = self.keystatus.trim();
+        status != "available" && !status.is_empty() && status != "-" && status != "none"
+    }
 }
 
 impl Zfs {
@@ -29,10 +206,45 @@ impl Zfs {
                 return Ok(Self {
                     path: c.to_string(),
                     timeout,
+                    configured: false,
                 });
             }
         }
-        Err(anyhow!("zfs binary not found in {:?}", candidates))
+
+        if let Some(extra) = extra_binaries_named("zfs").into_iter().next() {
+            return Ok(Self {
+                path: extra,
+                timeout,
+                configured: false,
+            });
+        }
+
+        if let Some(found) = find_in_path("zfs") {
+            let found = found.to_string_lossy().into_owned();
+            if is_allowlisted(&found) {
+                return Ok(Self {
+                    path: found,
+                    timeout,
+                    configured: false,
+                });
+            }
+            return Err(anyhow!(
+                "zfs found on PATH at {} but outside trusted locations; set policy.zfs_path = \"{}\" to trust it",
+                found,
+                found
+            ));
+        }
+        Err(anyhow!("zfs binary not found in {:?} or PATH", candidates))
+    }
+
+    /// The client a command should use under `cfg`: `policy.zfs_path` when
+    /// set, else `discover`, with the `crypto.timeout_secs` limit.
+    pub fn from_config(cfg: &ConfigFile) -> Result<Self> {
+        let timeout = Duration::from_secs(cfg.crypto.timeout_secs.max(1));
+        match &cfg.policy.zfs_path {
+            Some(path) => Self::with_path(path, timeout),
+            None => Self::discover(timeout),
+        }
     }
 
     /// Use an explicit binary path (for policy-controlled environments).
@@ -43,138 +255,201 @@ impl Zfs {
         Ok(Self {
             path: path.to_string(),
             timeout,
+            configured: true,
         })
     }
 
+    fn cmd(&self) -> Result<Cmd> {
+        if self.configured {
+            Cmd::new_configured(&self.path, self.timeout)
+        } else {
+            Cmd::new_allowlisted(&self.path, self.timeout)
+        }
+    }
+
+    /// Runner for sub-commands whose only failure handling is reporting it.
+    fn run_checked(&self, args: &[&str], input: Option<&[u8]>) -> Result<OutputData> {
+        self.cmd()?.run_checked(args, input)
+    }
+
     /// Internal runner for all ZFS sub-commands.
     fn run(&self, args: &[&str], input: Option<&[u8]>) -> Result<OutputData> {
-        let cmd = Cmd::new_allowlisted(&self.path, self.timeout)?;
-        cmd.run(args, input)
+        let out = self.cmd()?.run(args, input)?;
+        if let Termination::Signaled(_) = out.termination {
+            return Err(anyhow!(
+                "zfs {} {}: {}",
+                args.first().copied().unwrap_or_default(),
+                out.termination,
+                out.stderr.trim()
+            ));
+        }
+        Ok(out)
     }
 
     /// Returns true if dataset encryption is enabled.
     pub fn is_encrypted(&self, dataset: &str) -> Result<bool> {
-        let out = self.run(&["get", "-H", "-o", "value", "encryption", dataset], None)?;
-        if out.status != 0 {
-            return Err(anyhow!("zfs get encryption failed: {}", out.stderr));
-        }
+        let out = self.run_checked(&["get", "-H", "-o", "value", "encryption", dataset], None)?;
         let v = out.stdout.trim();
         Ok(v != "off" && !v.is_empty())
     }
 
     /// Returns true if dataset key is loaded.
     pub fn is_unlocked(&self, dataset: &str) -> Result<bool> {
-        let out = self.run(&["get", "-H", "-o", "value", "keystatus", dataset], None)?;
-        if out.status != 0 {
-            return Err(anyhow!("zfs get keystatus failed: {}", out.stderr));
-        }
+        let out = self.run_checked(&["get", "-H", "-o", "value", "keystatus", dataset], None)?;
         Ok(out.stdout.trim() == "available")
     }
 
+    /// Run a key-management sub-command, classifying failures as `ZfsError`.
+    fn run_key_op(&self, args: &[&str], input: Option<&[u8]>) -> std::result::Result<(), ZfsError> {
+        let out = self.run(args, input).map_err(|err| ZfsError::Other {
+            stderr: format!("{:#}", err),
+        })?;
+        match ZfsError::classify(out.status, &out.stderr) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
     /// Loads a key into ZFS using stdin (never shell-escaped).
-    pub fn load_key(&self, dataset: &str, key: &[u8]) -> Result<()> {
-        let out = self
-            .run(&["load-key", "-L", "prompt", dataset], Some(key))
-            .context("zfs load-key")?;
-        if out.status != 0 {
-            let stderr = out.stderr.trim();
-            if stderr.contains("Key already loaded") {
-                return Ok(());
-            }
-            return Err(anyhow!("zfs load-key failed: {}", stderr));
+    pub fn load_key(&self, dataset: &str, key: &[u8]) -> std::result::Result<(), ZfsError> {
+        self.run_key_op(&["load-key", "-L", "prompt", dataset], Some(key))
+    }
+
+    /// Loads a key, treating an already-resident key as success.
+    fn ensure_key_loaded(&self, dataset: &str, key: &[u8]) -> Result<()> {
+        match self.load_key(dataset, key) {
+            Ok(()) | Err(ZfsError::KeyAlreadyLoaded) => Ok(()),
+            Err(err) => Err(err).with_context(|| format!("zfs load-key {}", dataset)),
         }
-        Ok(())
     }
 
     /// Unloads a key from ZFS, sealing the dataset.
-    pub fn unload_key(&self, dataset: &str) -> Result<()> {
-        let out = self.run(&["unload-key", dataset], None)?;
-        if out.status != 0 {
-            return Err(anyhow!("zfs unload-key failed: {}", out.stderr));
+    pub fn unload_key(&self, dataset: &str) -> std::result::Result<(), ZfsError> {
+        self.run_key_op(&["unload-key", dataset], None)
+    }
+
+    /// Unload keys for `root` and every descendant encryption root still holding
+    /// an available key. Children are sealed before their parents; mounted
+    /// datasets are reported as busy instead of aborting the sweep.
+    pub fn unload_key_tree(&self, root: &str) -> Result<UnloadReport> {
+        let mut targets: Vec<String> = self
+            .scan_key_states(root)?
+            .into_iter()
+            .filter(|state| state.name == state.encryption_root && state.keystatus == "available")
+            .map(|state| state.name)
+            .collect();
+        targets.sort_by_key(|name| std::cmp::Reverse(name.matches('/').count()));
+
+        let mut report = UnloadReport::default();
+        for dataset in targets {
+            let out = self.run(&["unload-key", &dataset], None)?;
+            let stderr = out.stderr.trim().to_string();
+            match ZfsError::classify(out.status, &stderr) {
+                None => report.sealed.push(dataset),
+                Some(ZfsError::Busy) => report.busy.push((dataset, stderr)),
+                Some(_) => report.failed.push((dataset, stderr)),
+            }
         }
-        Ok(())
+        Ok(report)
     }
 
-    /// Returns the encryption root for a dataset.
-    pub fn encryption_root(&self, dataset: &str) -> Result<String> {
-        let out = self.run(
+    /// Returns the encryption root for a dataset, or `None` when the dataset
+    /// is not encrypted (ZFS reports `-`).
+    pub fn encryption_root(&self, dataset: &str) -> Result<Option<String>> {
+        let out = self.run_checked(
             &["get", "-H", "-o", "value", "encryptionroot", dataset],
             None,
         )?;
-        if out.status != 0 {
-            return Err(anyhow!("zfs get encryptionroot failed: {}", out.stderr));
-        }
-        Ok(out.stdout.trim().to_string())
+        Ok(parse_encryption_root(&out.stdout))
     }
 
     /// Change the dataset key by pointing ZFS at a temporary key file.
-    pub fn change_key_from_file(&self, dataset: &str, key_path: &Path) -> Result<()> {
+    /// `load_with` adds `-l`, feeding the current key on stdin so a sealed
+    /// dataset (keylocation=prompt) is loaded first. ZFS leaves the new key
+    /// loaded either way.
+    pub fn change_key_from_file(
+        &self,
+        dataset: &str,
+        key_path: &Path,
+        load_with: Option<&[u8]>,
+    ) -> std::result::Result<(), ZfsError> {
         let keylocation = format!("keylocation=file://{}", key_path.display());
-        let out = self.run(
-            &[
-                "change-key",
-                "-o",
-                "keyformat=raw",
-                "-o",
-                &keylocation,
-                dataset,
-            ],
-            None,
-        )?;
-        if out.status != 0 {
-            return Err(anyhow!("zfs change-key failed: {}", out.stderr));
+        let mut args = vec!["change-key"];
+        if load_with.is_some() {
+            args.push("-l");
         }
-        Ok(())
+        args.extend(["-o", "keyformat=raw", "-o", &keylocation, dataset]);
+        self.run_key_op(&args, load_with)
     }
 
     /// Set an arbitrary property on a dataset (used for keylocation/keyformat resets).
     pub fn set_property(&self, dataset: &str, property: &str, value: &str) -> Result<()> {
         let assignment = format!("{}={}", property, value);
-        let out = self.run(&["set", &assignment, dataset], None)?;
-        if out.status != 0 {
-            return Err(anyhow!("zfs set {} failed: {}", assignment, out.stderr));
-        }
+        self.run_checked(&["set", &assignment, dataset], None)?;
         Ok(())
     }
 
     /// Query a single dataset property and return the trimmed value.
     pub fn get_property(&self, dataset: &str, property: &str) -> Result<String> {
-        let out = self.run(&["get", "-H", "-o", "value", property, dataset], None)?;
-        if out.status != 0 {
-            return Err(anyhow!(
-                "zfs get {} failed: {}",
-                property,
-                out.stderr.trim()
-            ));
-        }
+        let out = self.run_checked(&["get", "-H", "-o", "value", property, dataset], None)?;
         Ok(out.stdout.trim().to_string())
     }
 
     /// Attempt to load keys for the encryption root and any descendants sharing it.
-    /// Returns the list of datasets confirmed unlocked (root is always first).
-    pub fn load_key_tree(&self, root: &str, key: &[u8]) -> Result<Vec<String>> {
-        self.load_key(root, key)?;
+    /// Only a root that stays sealed is an error; descendants that refuse the
+    /// key come back in `still_sealed`. Time spent in each half is added to
+    /// `timings`.
+    pub fn load_key_tree(
+        &self,
+        root: &str,
+        key: &[u8],
+        timings: &mut KeyTreeTimings,
+    ) -> Result<KeyTreeOutcome> {
+        let started = Instant::now();
+        let loaded = self.ensure_key_loaded(root, key);
+        timings.root += started.elapsed();
+        loaded?;
+
+        let started = Instant::now();
+        let unlocked = self.load_descendant_keys(root, key);
+        timings.descendants += started.elapsed();
+        unlocked
+    }
 
+    /// Like `load_key_tree`, for a root whose key is already resident (e.g.
+    /// right after change-key): only sealed descendants are loaded.
+    pub fn load_descendant_keys(&self, root: &str, key: &[u8]) -> Result<KeyTreeOutcome> {
         let mut unlocked = vec![root.to_string()];
 
-        let pending_scan = self.locked_descendants(root)?;
+        let pending_scan = locked_under(&self.scan_sharing_states(root)?, root);
         if pending_scan.iter().any(|ds| ds == root) {
             return Err(anyhow!(
-                "Encryption root {} still reports a sealed keystatus after load-key",
+                "Encryption root {} still reports a sealed keystatus",
                 root
             ));
         }
 
         let pending: Vec<String> = pending_scan.into_iter().filter(|ds| ds != root).collect();
+        if pending.is_empty() {
+            return Ok(KeyTreeOutcome {
+                fully_unlocked: unlocked,
+                still_sealed: Vec::new(),
+                refusals: Vec::new(),
+            });
+        }
 
-        if !pending.is_empty() {
-            for ds in pending.clone() {
-                self.load_key(&ds, key)?;
-                unlocked.push(ds);
+        // A refusal here is settled by the rescan below rather than aborting
+        // the sweep for the remaining siblings; its error is kept for the
+        // report.
+        let mut refusals = Vec::new();
+        for ds in pending {
+            match self.ensure_key_loaded(&ds, key) {
+                Ok(()) => unlocked.push(ds),
+                Err(err) => refusals.push((ds, format!("{:#}", err))),
             }
         }
 
-        let stubborn_scan = self.locked_descendants(root)?;
+        let stubborn_scan = locked_under(&self.scan_sharing_states(root)?, root);
         if stubborn_scan.iter().any(|ds| ds == root) {
             return Err(anyhow!(
                 "Encryption root {} unexpectedly sealed after descendant retries",
@@ -182,89 +457,654 @@ impl Zfs {
             ));
         }
 
-        let stubborn: Vec<String> = stubborn_scan.into_iter().filter(|ds| ds != root).collect();
-        if !stubborn.is_empty() {
-            return Err(anyhow!(
-                "Datasets inheriting {} remain sealed after retries: {}",
+        let still_sealed: Vec<String> = stubborn_scan.into_iter().filter(|ds| ds != root).collect();
+        unlocked.retain(|ds| !still_sealed.contains(ds));
+        refusals.retain(|(ds, _)| still_sealed.contains(ds));
+        Ok(KeyTreeOutcome {
+            fully_unlocked: unlocked,
+            still_sealed,
+            refusals,
+        })
+    }
+
+    /// Read `encryptionroot` and `keystatus` for `root` and every descendant
+    /// filesystem or volume in a single recursive `zfs get`.
+    pub fn scan_key_states(&self, root: &str) -> Result<Vec<KeyState>> {
+        let out = self.run_checked(
+            &[
+                "get",
+                "-H",
+                "-r",
+                "-t",
+                "filesystem,volume",
+                "-o",
+                "name,property,value",
+                "encryptionroot,keystatus",
                 root,
-                stubborn.join(", ")
-            ));
-        }
+            ],
+            None,
+        )?;
+        Ok(parse_key_states(&out.stdout))
+    }
 
-        Ok(unlocked)
+    /// Key states for every dataset that can share `root`'s key. Clones and
+    /// received datasets keep their origin's encryption root wherever they
+    /// live in the pool, so the whole pool is scanned, not just the subtree.
+    fn scan_sharing_states(&self, root: &str) -> Result<Vec<KeyState>> {
+        self.scan_key_states(pool_of(root))
     }
 
-    /// Return datasets under `root` that still report a sealed keystatus.
-    pub fn locked_descendants(&self, root: &str) -> Result<Vec<String>> {
-        let list = self.run(
-            &["list", "-H", "-r", "-o", "name,encryptionroot", root],
+    /// Every filesystem or volume on the system with encryption enabled.
+    pub fn encrypted_datasets(&self) -> Result<Vec<String>> {
+        let out = self.run_checked(
+            &[
+                "list",
+                "-H",
+                "-o",
+                "name,encryption",
+                "-t",
+                "filesystem,volume",
+            ],
             None,
         )?;
-        if list.status != 0 {
-            return Err(anyhow!(
-                "zfs list encryption roots failed for {}: {}",
+        Ok(parse_encrypted_datasets(&out.stdout))
+    }
+
+    /// Encrypted descendants of `root` that are their own encryption root (or
+    /// inherit from one below `root`), as `(dataset, encryption root)`.
+    /// `change-key` on `root` leaves every one of them on its current key.
+    pub fn independently_keyed(&self, root: &str) -> Result<Vec<(String, String)>> {
+        Ok(independent_descendants(&self.scan_key_states(root)?, root))
+    }
+
+    /// Distinct encryption roots anywhere in `pool`, parents before children.
+    pub fn encryption_roots_in_pool(&self, pool: &str) -> Result<Vec<String>> {
+        Ok(encryption_roots(&self.scan_key_states(pool)?))
+    }
+
+    /// Distinct encryption roots of the encrypted datasets matching `selector`
+    /// (see `DatasetSelector`), parents before children.
+    pub fn encryption_roots_matching(&self, selector: &DatasetSelector) -> Result<Vec<String>> {
+        Ok(roots_of_matching(
+            &self.scan_key_states(selector.scan_base()?)?,
+            selector,
+        ))
+    }
+
+    /// Return `(dataset, mountpoint)` for `root` and every mounted descendant,
+    /// deepest first so they can be unmounted in order.
+    pub fn mounted_descendants(&self, root: &str) -> Result<Vec<(String, String)>> {
+        let out = self.run_checked(
+            &[
+                "list",
+                "-H",
+                "-r",
+                "-o",
+                "name,mounted,mountpoint",
+                "-t",
+                "filesystem",
                 root,
-                list.stderr.trim()
-            ));
-        }
-        let mut same_root = HashSet::new();
-        for line in list.stdout.lines() {
-            let mut parts = line.split('\t');
-            if let (Some(name), Some(encryption_root)) = (parts.next(), parts.next()) {
-                if encryption_root == root {
-                    same_root.insert(name.to_string());
-                }
-            }
-        }
-        let status = self.run(
-            &["get", "-H", "-r", "-o", "name,value", "keystatus", root],
+            ],
             None,
         )?;
-        if status.status != 0 {
-            return Err(anyhow!(
-                "zfs get keystatus failed for {}: {}",
-                root,
-                status.stderr.trim()
-            ));
-        }
-        let mut locked = Vec::new();
-        for line in status.stdout.lines() {
-            let mut parts = line.split('\t');
-            if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
-                let trimmed = value.trim();
-                if same_root.contains(name)
-                    && trimmed != "available"
-                    && !trimmed.is_empty()
-                    && trimmed != "-"
-                    && trimmed != "none"
-                {
-                    locked.push(name.to_string());
+        let mut mounted: Vec<(String, String)> = out
+            .stdout
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split('\t');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(name), Some("yes"), Some(mp)) => {
+                        Some((name.to_string(), mp.trim().to_string()))
+                    }
+                    _ => None,
                 }
-            }
-        }
-        Ok(locked)
+            })
+            .collect();
+        mounted.sort_by_key(|(name, _)| std::cmp::Reverse(name.matches('/').count()));
+        Ok(mounted)
+    }
+
+    /// `canmount`, mount state, mountpoint, and keystatus for `root` and every
+    /// descendant filesystem, parents first.
+    pub fn mount_states(&self, root: &str) -> Result<Vec<MountState>> {
+        let out = self.run_checked(
+            &[
+                "list",
+                "-H",
+                "-r",
+                "-o",
+                "name,canmount,mounted,mountpoint,keystatus",
+                "-t",
+                "filesystem",
+                root,
+            ],
+            None,
+        )?;
+        Ok(parse_mount_states(&out.stdout))
+    }
+
+    /// Mount a single dataset via `zfs mount` (works for `canmount=noauto`).
+    pub fn mount(&self, dataset: &str) -> Result<()> {
+        self.run_checked(&["mount", dataset], None)?;
+        Ok(())
+    }
+
+    /// Unmount a single dataset via `zfs unmount`.
+    pub fn unmount(&self, dataset: &str) -> Result<()> {
+        self.run_checked(&["unmount", dataset], None)?;
+        Ok(())
     }
 
-    /// Discover dataset by mountpoint (e.g., "/"). Returns the first match.
-    pub fn dataset_with_mountpoint(&self, mountpoint: &str) -> Result<Option<String>> {
-        let out = self.run(
+    /// Discover datasets serving `mountpoint` (e.g., "/"). Datasets actually
+    /// mounted there per `/proc/self/mounts` come first (this also catches
+    /// `mountpoint=legacy` fstab mounts), followed by any other filesystem whose
+    /// `mountpoint` property matches, such as inactive boot environments.
+    pub fn dataset_with_mountpoint(&self, mountpoint: &str) -> Result<Vec<String>> {
+        let out = self.run_checked(
             &["list", "-H", "-o", "name,mountpoint", "-t", "filesystem"],
             None,
         )?;
-        if out.status != 0 {
-            return Err(anyhow!(
-                "zfs list mountpoints failed: {}",
-                out.stderr.trim()
-            ));
+        let mounts = std::fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+        Ok(mountpoint_candidates(&out.stdout, &mounts, mountpoint))
+    }
+}
+
+/// Merge live zfs mounts at `mountpoint` with datasets whose property matches.
+/// Mount-table sources are only accepted when `zfs list` knows the dataset.
+fn mountpoint_candidates(zfs_list: &str, mounts: &str, mountpoint: &str) -> Vec<String> {
+    let mut known: Vec<(&str, &str)> = Vec::new();
+    for line in zfs_list.lines() {
+        let mut parts = line.split('\t');
+        if let (Some(name), Some(mp)) = (parts.next(), parts.next()) {
+            known.push((name, mp.trim()));
         }
-        for line in out.stdout.lines() {
-            let mut parts = line.split('\t');
-            if let (Some(name), Some(mp)) = (parts.next(), parts.next()) {
-                if mp == mountpoint {
-                    return Ok(Some(name.to_string()));
+    }
+
+    let mut candidates: Vec<String> = Vec::new();
+    for line in mounts.lines() {
+        let mut fields = line.split_whitespace();
+        if let (Some(source), Some(target), Some("zfs")) =
+            (fields.next(), fields.next(), fields.next())
+        {
+            let source = unescape_mount_field(source);
+            if unescape_mount_field(target) == mountpoint
+                && known.iter().any(|(name, _)| *name == source)
+                && !candidates.contains(&source)
+            {
+                candidates.push(source);
+            }
+        }
+    }
+
+    for (name, mp) in known {
+        if mp == mountpoint && !candidates.iter().any(|c| c == name) {
+            candidates.push(name.to_string());
+        }
+    }
+    candidates
+}
+
+/// Error for a dataset with no encryption root, so callers word it the same.
+pub fn not_encrypted(dataset: &str) -> anyhow::Error {
+    anyhow!(
+        "Dataset {} is not encrypted (encryptionroot is '-'); there is no key to manage.",
+        dataset
+    )
+}
+
+/// `zfs get encryptionroot` value, with `-` (unencrypted) and empty as `None`.
+fn parse_encryption_root(value: &str) -> Option<String> {
+    match value.trim() {
+        "" | "-" => None,
+        root => Some(root.to_string()),
+    }
+}
+
+/// Datasets that are their own encryption root, in scan order.
+fn encryption_roots(states: &[KeyState]) -> Vec<String> {
+    states
+        .iter()
+        .filter(|state| state.name == state.encryption_root)
+        .map(|state| state.name.clone())
+        .collect()
+}
+
+/// Encrypted datasets strictly below `root` whose encryption root is not
+/// `root`, as `(dataset, encryption root)`; `change-key` on `root` would
+/// leave each of them on its old key.
+fn independent_descendants(states: &[KeyState], root: &str) -> Vec<(String, String)> {
+    states
+        .iter()
+        .filter(|state| {
+            state
+                .name
+                .strip_prefix(root)
+                .is_some_and(|rest| rest.starts_with('/'))
+                && state.encryption_root != root
+                && !matches!(state.encryption_root.as_str(), "" | "-")
+        })
+        .map(|state| (state.name.clone(), state.encryption_root.clone()))
+        .collect()
+}
+
+/// Datasets in `states` whose key is not loaded and whose encryption root is
+/// `root`, wherever in the pool they live.
+fn locked_under(states: &[KeyState], root: &str) -> Vec<String> {
+    states
+        .iter()
+        .filter(|state| state.is_sealed() && state.encryption_root == root)
+        .map(|state| state.name.clone())
+        .collect()
+}
+
+/// `--dataset` expanded to a subtree: a glob (`tank/*`, `tank/vm-??`) or,
+/// under `--recursive`, a dataset and everything below it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatasetSelector {
+    /// `*` matches any run of characters, `/` included; `?` matches one.
+    Glob(String),
+    Subtree(String),
+}
+
+impl DatasetSelector {
+    /// `Some` when `dataset` names more than one dataset.
+    pub fn parse(dataset: &str, recursive: bool) -> Option<Self> {
+        if dataset.contains(['*', '?']) {
+            Some(DatasetSelector::Glob(dataset.to_string()))
+        } else if recursive {
+            Some(DatasetSelector::Subtree(
+                dataset.trim_end_matches('/').to_string(),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Deepest dataset every match lives under: the glob up to its last `/`
+    /// before the first wildcard.
+    fn scan_base(&self) -> Result<&str> {
+        match self {
+            DatasetSelector::Subtree(base) => Ok(base),
+            DatasetSelector::Glob(pattern) => {
+                let literal = &pattern[..pattern.find(['*', '?']).unwrap_or(pattern.len())];
+                match literal.rfind('/') {
+                    Some(idx) if idx > 0 => Ok(&literal[..idx]),
+                    _ => Err(anyhow!(
+                        "dataset glob {} must name its pool before any wildcard (e.g. tank/*)",
+                        pattern
+                    )),
                 }
             }
         }
-        Ok(None)
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            DatasetSelector::Subtree(base) => name
+                .strip_prefix(base.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/')),
+            DatasetSelector::Glob(pattern) => glob_match(pattern.as_bytes(), name.as_bytes()),
+        }
+    }
+}
+
+/// Shell-style match where `*` spans any run (slashes included) and `?` one
+/// byte. Iterative with one remembered star, so the work stays O(p·n) even
+/// for patterns like `a*a*a*b`.
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Position just past the last `*`, and the name offset it now covers up to.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&ch) if ch == b'?' || ch == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after_star, covered)) => {
+                    p = after_star;
+                    n = covered + 1;
+                    star = Some((after_star, n));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&ch| ch == b'*')
+}
+
+/// Encryption roots of the encrypted datasets `selector` matches, deduped so
+/// a root and its inheriting children yield the root once.
+fn roots_of_matching(states: &[KeyState], selector: &DatasetSelector) -> Vec<String> {
+    let mut roots: Vec<String> = Vec::new();
+    for state in states {
+        if !selector.matches(&state.name)
+            || matches!(state.encryption_root.as_str(), "" | "-")
+            || roots.contains(&state.encryption_root)
+        {
+            continue;
+        }
+        roots.push(state.encryption_root.clone());
+    }
+    roots
+}
+
+/// Join `name<TAB>property<TAB>value` rows into one `KeyState` per dataset,
+/// preserving the order in which datasets first appear.
+fn parse_key_states(output: &str) -> Vec<KeyState> {
+    let mut states: Vec<KeyState> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for line in output.lines() {
+        let mut parts = line.split('\t');
+        let (Some(name), Some(property), Some(value)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let slot = *index.entry(name.to_string()).or_insert_with(|| {
+            states.push(KeyState {
+                name: name.to_string(),
+                encryption_root: String::new(),
+                keystatus: String::new(),
+            });
+            states.len() - 1
+        });
+        match property {
+            "encryptionroot" => states[slot].encryption_root = value.trim().to_string(),
+            "keystatus" => states[slot].keystatus = value.trim().to_string(),
+            _ => {}
+        }
+    }
+    states
+}
+
+fn parse_encrypted_datasets(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, encryption) = line.split_once('\t')?;
+            match encryption.trim() {
+                "off" | "-" | "" => None,
+                _ => Some(name.trim().to_string()),
+            }
+        })
+        .collect()
+}
+
+fn parse_mount_states(output: &str) -> Vec<MountState> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+            let [name, canmount, mounted, mountpoint, keystatus] = fields[..] else {
+                return None;
+            };
+            Some(MountState {
+                name: name.to_string(),
+                canmount: canmount.to_string(),
+                mounted: mounted == "yes",
+                mountpoint: mountpoint.to_string(),
+                keystatus: keystatus.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        encryption_roots, glob_match, independent_descendants, locked_under, mountpoint_candidates,
+        parse_encrypted_datasets, parse_encryption_root, parse_key_states, parse_mount_states,
+        roots_of_matching, DatasetSelector, KeyTreeOutcome, ZfsError,
+    };
+
+    #[test]
+    fn encrypted_dataset_listing_skips_plaintext() {
+        let listing = "rpool\toff\nrpool/ROOT\taes-256-gcm\ntank/vol\taes-128-ccm\ntank/snap\t-\n";
+        assert_eq!(
+            parse_encrypted_datasets(listing),
+            ["rpool/ROOT", "tank/vol"]
+        );
+    }
+
+    #[test]
+    fn noauto_datasets_still_want_an_explicit_mount() {
+        let states = parse_mount_states(
+            "tank/data\tnoauto\tno\t/data\tavailable\n\
+             tank/data/home\ton\tyes\t/data/home\tavailable\n\
+             tank/data/off\toff\tno\t/data/off\tavailable\n\
+             tank/data/legacy\ton\tno\tlegacy\tavailable\n\
+             tank/data/sealed\ton\tno\t/data/sealed\tunavailable\n\
+             tank/data/be\tnoauto\tno\t/data/be\tavailable\n\
+             tank/data/root\ton\tno\t/\tavailable\n",
+        );
+        let wanted: Vec<&str> = states
+            .iter()
+            .filter(|s| s.wants_mount(s.name == "tank/data"))
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(wanted, ["tank/data"]);
+        assert_eq!(states[0].canmount, "noauto");
+        assert!(!states[0].wants_mount(false));
+    }
+
+    #[test]
+    fn unencrypted_datasets_have_no_encryption_root() {
+        assert_eq!(parse_encryption_root("-\n"), None);
+        assert_eq!(parse_encryption_root(""), None);
+        assert_eq!(
+            parse_encryption_root("rpool/ROOT\n"),
+            Some("rpool/ROOT".to_string())
+        );
+    }
+
+    #[test]
+    fn zfs_errors_classify_from_status_and_stderr() {
+        assert_eq!(ZfsError::classify(0, "Key already loaded"), None);
+        assert_eq!(
+            ZfsError::classify(
+                255,
+                "Key load error: Key already loaded for 'rpool/ROOT'.\n"
+            ),
+            Some(ZfsError::KeyAlreadyLoaded)
+        );
+        assert_eq!(
+            ZfsError::classify(255, "Key load error: Incorrect key provided for 'rpool'."),
+            Some(ZfsError::IncorrectKey)
+        );
+        assert_eq!(
+            ZfsError::classify(1, "cannot open 'tank/nope': dataset does not exist"),
+            Some(ZfsError::DatasetNotFound)
+        );
+        assert_eq!(
+            ZfsError::classify(
+                1,
+                "Permission denied the ZFS utilities must be run as root."
+            ),
+            Some(ZfsError::PermissionDenied)
+        );
+        assert_eq!(
+            ZfsError::classify(255, "Key unload error: 'rpool/home' is busy."),
+            Some(ZfsError::Busy)
+        );
+        assert_eq!(
+            ZfsError::classify(2, "  something new  \n"),
+            Some(ZfsError::Other {
+                stderr: "something new".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn mountpoint_candidates_cover_legacy_and_boot_environments() {
+        let zfs_list = "rpool\t/
+rpool/ROOT\tnone
+rpool/ROOT/debian\tlegacy
+rpool/ROOT/debian-old\t/
+rpool/home\t/home
+";
+        let mounts = "proc /proc proc rw,nosuid 0 0
+rpool/ROOT/debian / zfs rw,relatime,xattr,posixacl 0 0
+rpool/home /home zfs rw,relatime 0 0
+bogus/ds / zfs rw 0 0
+";
+        assert_eq!(
+            mountpoint_candidates(zfs_list, mounts, "/"),
+            vec!["rpool/ROOT/debian", "rpool", "rpool/ROOT/debian-old"]
+        );
+        assert_eq!(
+            mountpoint_candidates(zfs_list, "", "/home"),
+            vec!["rpool/home"]
+        );
+        assert!(mountpoint_candidates(zfs_list, mounts, "/srv").is_empty());
+    }
+
+    #[test]
+    fn sealed_descendants_carry_their_load_key_errors() {
+        let tree = KeyTreeOutcome {
+            fully_unlocked: vec!["rpool/ROOT".to_string()],
+            still_sealed: vec!["rpool/ROOT/a".to_string(), "rpool/ROOT/b".to_string()],
+            refusals: vec![(
+                "rpool/ROOT/a".to_string(),
+                "Incorrect key provided".to_string(),
+            )],
+        };
+        assert_eq!(
+            tree.describe_sealed(),
+            "rpool/ROOT/a (Incorrect key provided), rpool/ROOT/b"
+        );
+        let err = tree.strict("rpool/ROOT").unwrap_err().to_string();
+        assert!(err.contains("rpool/ROOT/a (Incorrect key provided)"));
+    }
+
+    #[test]
+    fn key_states_join_interleaved_properties() {
+        let output = "rpool/ROOT\tencryptionroot\trpool/ROOT
+rpool/ROOT/ubuntu\tkeystatus\tunavailable
+rpool/ROOT\tkeystatus\tavailable
+rpool/ROOT/ubuntu\tencryptionroot\trpool/ROOT
+rpool/ROOT/clone\tencryptionroot\trpool/ROOT/clone
+rpool/ROOT/clone\tkeystatus\tunavailable
+rpool/ROOT/plain\tencryptionroot\t-
+rpool/ROOT/plain\tkeystatus\t-
+";
+        let states = parse_key_states(output);
+        let names: Vec<&str> = states.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "rpool/ROOT",
+                "rpool/ROOT/ubuntu",
+                "rpool/ROOT/clone",
+                "rpool/ROOT/plain"
+            ]
+        );
+        assert_eq!(states[1].encryption_root, "rpool/ROOT");
+        assert_eq!(states[1].keystatus, "unavailable");
+
+        assert_eq!(
+            locked_under(&states, "rpool/ROOT"),
+            vec!["rpool/ROOT/ubuntu"]
+        );
+        assert_eq!(
+            locked_under(&states, "rpool/ROOT/clone"),
+            vec!["rpool/ROOT/clone"]
+        );
+        assert_eq!(
+            encryption_roots(&states),
+            vec!["rpool/ROOT", "rpool/ROOT/clone"]
+        );
+        assert_eq!(
+            independent_descendants(&states, "rpool/ROOT"),
+            vec![(
+                "rpool/ROOT/clone".to_string(),
+                "rpool/ROOT/clone".to_string()
+            )]
+        );
+        assert!(independent_descendants(&states, "rpool/ROOT/clone").is_empty());
+    }
+
+    #[test]
+    fn clones_outside_the_root_subtree_still_count_as_locked() {
+        // `zfs get -H -r -t filesystem,volume -o name,property,value
+        // encryptionroot,keystatus rpool` after
+        // `zfs clone rpool/ROOT/ubuntu@s rpool/data/clone`.
+        let output = "rpool\tencryptionroot\t-
+rpool\tkeystatus\t-
+rpool/ROOT\tencryptionroot\trpool/ROOT
+rpool/ROOT\tkeystatus\tunavailable
+rpool/ROOT/ubuntu\tencryptionroot\trpool/ROOT
+rpool/ROOT/ubuntu\tkeystatus\tunavailable
+rpool/data\tencryptionroot\t-
+rpool/data\tkeystatus\t-
+rpool/data/clone\tencryptionroot\trpool/ROOT
+rpool/data/clone\tkeystatus\tunavailable
+rpool/data/vault\tencryptionroot\trpool/data/vault
+rpool/data/vault\tkeystatus\tunavailable
+";
+        let states = parse_key_states(output);
+        assert_eq!(
+            locked_under(&states, "rpool/ROOT"),
+            vec!["rpool/ROOT", "rpool/ROOT/ubuntu", "rpool/data/clone"]
+        );
+        assert!(independent_descendants(&states, "rpool/ROOT").is_empty());
+        assert_eq!(
+            locked_under(&states, "rpool/data/vault"),
+            vec!["rpool/data/vault"]
+        );
+    }
+
+    #[test]
+    fn dataset_globs_and_subtrees_collapse_to_their_encryption_roots() {
+        let output = "tank\tencryptionroot\t-
+tank/vm\tencryptionroot\ttank/vm
+tank/vm/disk0\tencryptionroot\ttank/vm
+tank/vm/disk1\tencryptionroot\ttank/vm/disk1
+tank/vmware\tencryptionroot\ttank/vmware
+tank/home\tencryptionroot\ttank/home
+";
+        let states = parse_key_states(output);
+        let select = |dataset: &str, recursive: bool| {
+            roots_of_matching(
+                &states,
+                &DatasetSelector::parse(dataset, recursive).unwrap(),
+            )
+        };
+        assert_eq!(
+            select("tank/*", false),
+            ["tank/vm", "tank/vm/disk1", "tank/vmware", "tank/home"]
+        );
+        assert_eq!(select("tank/vm/disk?", false), ["tank/vm", "tank/vm/disk1"]);
+        assert_eq!(select("tank/vm", true), ["tank/vm", "tank/vm/disk1"]);
+        assert_eq!(DatasetSelector::parse("tank/vm", false), None);
+        assert!(DatasetSelector::Glob("*/vm".into()).scan_base().is_err());
+        assert_eq!(
+            DatasetSelector::Glob("tank/vm*".into())
+                .scan_base()
+                .unwrap(),
+            "tank"
+        );
+    }
+
+    #[test]
+    fn glob_matching_stays_linear_on_pathological_patterns() {
+        assert!(glob_match(b"tank/*/disk?", b"tank/vm/disk1"));
+        assert!(glob_match(b"tank/**", b"tank/"));
+        assert!(!glob_match(b"tank/vm?", b"tank/vm"));
+        assert!(!glob_match(b"tank/*x", b"tank/vm"));
+
+        let name = format!("tank/{}", "a".repeat(4096));
+        let pattern = format!("tank/{}b", "a*".repeat(16));
+        let started = std::time::Instant::now();
+        assert!(!glob_match(pattern.as_bytes(), name.as_bytes()));
+        assert!(glob_match(
+            pattern.trim_end_matches('b').as_bytes(),
+            name.as_bytes()
+        ));
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
     }
 }
@@ -0,0 +1,334 @@
+// ============================================================================
+// src/zpool.rs – pool health queries via the allow-listed zpool binary
+// ============================================================================
+
+use crate::cmd::base::{extra_binaries_named, is_allowlisted};
+use crate::cmd::{Cmd, OutputData};
+use crate::util::binary::find_in_path;
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use std::time::Duration;
+
+/// zpool binary locations (must stay in sync with the Cmd allowlist).
+const ZPOOL_CANDIDATES: [&str; 3] = ["/sbin/zpool", "/usr/sbin/zpool", "/usr/local/sbin/zpool"];
+
+/// Health states reported by `zpool get health`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolState {
+    Online,
+    Degraded,
+    Faulted,
+    Offline,
+    Unavail,
+    Removed,
+    Suspended,
+    Unknown(String),
+}
+
+impl PoolState {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "ONLINE" => PoolState::Online,
+            "DEGRADED" => PoolState::Degraded,
+            "FAULTED" => PoolState::Faulted,
+            "OFFLINE" => PoolState::Offline,
+            "UNAVAIL" => PoolState::Unavail,
+            "REMOVED" => PoolState::Removed,
+            "SUSPENDED" => PoolState::Suspended,
+            other => PoolState::Unknown(other.to_string()),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            PoolState::Online => "ONLINE",
+            PoolState::Degraded => "DEGRADED",
+            PoolState::Faulted => "FAULTED",
+            PoolState::Offline => "OFFLINE",
+            PoolState::Unavail => "UNAVAIL",
+            PoolState::Removed => "REMOVED",
+            PoolState::Suspended => "SUSPENDED",
+            PoolState::Unknown(raw) => raw.as_str(),
+        }
+    }
+
+    /// True when the pool cannot serve I/O and key loading will likely fail.
+    pub fn is_unusable(&self) -> bool {
+        matches!(
+            self,
+            PoolState::Faulted
+                | PoolState::Offline
+                | PoolState::Unavail
+                | PoolState::Removed
+                | PoolState::Suspended
+        )
+    }
+}
+
+/// Health summary for a single pool.
+#[derive(Debug, Clone)]
+pub struct PoolHealth {
+    pub pool: String,
+    pub state: PoolState,
+    /// The `status:` paragraph from `zpool status`, when the pool is not ONLINE.
+    pub detail: Option<String>,
+    /// The `scan:` line when a resilver is running or the last scrub or
+    /// resilver found errors; reported even for ONLINE pools.
+    pub scan: Option<String>,
+}
+
+impl PoolHealth {
+    /// ONLINE with no resilver underway and no scan errors outstanding.
+    pub fn is_healthy(&self) -> bool {
+        self.state == PoolState::Online && self.scan.is_none()
+    }
+
+    pub fn summary(&self) -> String {
+        let notes: Vec<&str> = [&self.detail, &self.scan]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        if notes.is_empty() {
+            format!("{} {}", self.pool, self.state.label())
+        } else {
+            format!(
+                "{} {} ({})",
+                self.pool,
+                self.state.label(),
+                notes.join("; ")
+            )
+        }
+    }
+}
+
+/// Safe zpool command wrapper. All calls go through the allow-listed `cmd` layer.
+pub struct Zpool {
+    path: String,
+    timeout: Duration,
+}
+
+impl Zpool {
+    /// Auto-discover `zpool` binary from common system locations.
+    pub fn discover(timeout: Duration) -> Result<Self> {
+        for c in &ZPOOL_CANDIDATES {
+            if Path::new(c).exists() {
+                return Ok(Self {
+                    path: c.to_string(),
+                    timeout,
+                });
+            }
+        }
+
+        if let Some(extra) = extra_binaries_named("zpool").into_iter().next() {
+            return Ok(Self {
+                path: extra,
+                timeout,
+            });
+        }
+
+        if let Some(found) = find_in_path("zpool") {
+            let found = found.to_string_lossy().into_owned();
+            if is_allowlisted(&found) {
+                return Ok(Self {
+                    path: found,
+                    timeout,
+                });
+            }
+            return Err(anyhow!(
+                "zpool found on PATH at {} but outside the command allowlist",
+                found
+            ));
+        }
+        Err(anyhow!(
+            "zpool binary not found in {:?} or PATH",
+            ZPOOL_CANDIDATES
+        ))
+    }
+
+    fn run(&self, args: &[&str]) -> Result<OutputData> {
+        let cmd = Cmd::new_allowlisted(&self.path, self.timeout)?;
+        cmd.run(args, None)
+    }
+
+    /// Query pool health via `zpool get health`, enriched from `zpool status`
+    /// with the explanation when the pool is not ONLINE and with the scan line
+    /// whenever a resilver is running or a scan found errors.
+    pub fn pool_health(&self, pool: &str) -> Result<PoolHealth> {
+        let out = self.run(&["get", "-H", "-o", "value", "health", pool])?;
+        if out.status != 0 {
+            return Err(anyhow!(
+                "zpool get health failed for {}: {}",
+                pool,
+                out.stderr.trim()
+            ));
+        }
+        let state = PoolState::parse(&out.stdout);
+
+        let status = self
+            .run(&["status", pool])
+            .ok()
+            .filter(|status| status.status == 0)
+            .map(|status| status.stdout)
+            .unwrap_or_default();
+        let detail = if state == PoolState::Online {
+            None
+        } else {
+            parse_status_detail(&status)
+        };
+        let scan = parse_scan_warning(&status);
+
+        Ok(PoolHealth {
+            pool: pool.to_string(),
+            state,
+            detail,
+            scan,
+        })
+    }
+}
+
+/// Pool name owning a dataset (`rpool/ROOT/ubuntu` → `rpool`).
+pub fn pool_of(dataset: &str) -> &str {
+    dataset.split('/').next().unwrap_or(dataset)
+}
+
+/// Extract the `status:` paragraph from `zpool status -x` output, joined onto
+/// one line. Healthy pools (`pool 'x' is healthy`) yield `None`.
+fn parse_status_detail(output: &str) -> Option<String> {
+    let mut collected: Vec<&str> = Vec::new();
+    let mut in_status = false;
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("status:") {
+            in_status = true;
+            collected.push(rest.trim());
+            continue;
+        }
+        if in_status {
+            let starts_section = trimmed
+                .split_once(':')
+                .map(|(head, _)| !head.is_empty() && !head.contains(' '))
+                .unwrap_or(false);
+            if trimmed.is_empty() || starts_section {
+                break;
+            }
+            collected.push(trimmed);
+        }
+    }
+    let joined = collected.join(" ");
+    if joined.is_empty() {
+        None
+    } else {
+        Some(joined)
+    }
+}
+
+/// The `scan:` line from `zpool status`, when it reports a resilver in
+/// progress or a finished scrub/resilver `with N errors` (N > 0). Idle pools,
+/// running scrubs and clean scans yield `None`.
+fn parse_scan_warning(output: &str) -> Option<String> {
+    let scan = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("scan:"))?
+        .trim();
+    if scan.starts_with("resilver in progress") {
+        return Some(scan.to_string());
+    }
+    let errors = scan
+        .split_once(" with ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .and_then(|count| count.parse::<u64>().ok())
+        .unwrap_or(0);
+    if errors > 0 {
+        Some(scan.to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_scan_warning, parse_status_detail, pool_of, PoolHealth, PoolState};
+
+    #[test]
+    fn health_values_map_to_states() {
+        assert_eq!(PoolState::parse("ONLINE\n"), PoolState::Online);
+        assert_eq!(PoolState::parse("DEGRADED"), PoolState::Degraded);
+        assert!(PoolState::parse("FAULTED").is_unusable());
+        assert!(!PoolState::parse("DEGRADED").is_unusable());
+        assert_eq!(PoolState::parse("-"), PoolState::Unknown("-".to_string()));
+    }
+
+    #[test]
+    fn status_detail_extracts_status_paragraph() {
+        let degraded = "  pool: rpool
+ state: DEGRADED
+status: One or more devices could not be used because the label is missing or
+\tinvalid.  Sufficient replicas exist for the pool to continue
+\tfunctioning in a degraded state.
+action: Replace the device using 'zpool replace'.
+   see: https://openzfs.github.io/openzfs-docs/msg/ZFS-8000-4J
+config:
+";
+        let detail = parse_status_detail(degraded).expect("status paragraph");
+        assert!(detail.starts_with("One or more devices could not be used"));
+        assert!(detail.ends_with("functioning in a degraded state."));
+        assert!(!detail.contains("zpool replace"));
+
+        assert_eq!(parse_status_detail("pool 'rpool' is healthy\n"), None);
+    }
+
+    #[test]
+    fn online_pool_mid_resilver_is_flagged() {
+        let resilvering = "  pool: rpool
+ state: ONLINE
+status: One or more devices is currently being resilvered.  The pool will
+\tcontinue to function, possibly in a degraded state.
+action: Wait for the resilver to complete.
+  scan: resilver in progress since Sat Oct 17 03:12:44 2026
+\t12.3G scanned at 410M/s, 4.10G issued at 137M/s, 30.0G total
+\t4.05G resilvered, 13.67% done, 00:03:14 to go
+config:
+";
+        let scan = parse_scan_warning(resilvering);
+        assert_eq!(
+            scan.as_deref(),
+            Some("resilver in progress since Sat Oct 17 03:12:44 2026")
+        );
+        let health = PoolHealth {
+            pool: "rpool".to_string(),
+            state: PoolState::parse("ONLINE"),
+            detail: None,
+            scan,
+        };
+        assert!(!health.is_healthy());
+        assert_eq!(
+            health.summary(),
+            "rpool ONLINE (resilver in progress since Sat Oct 17 03:12:44 2026)"
+        );
+    }
+
+    #[test]
+    fn scan_line_flags_errors_but_not_clean_or_idle_scans() {
+        let scrubbed = |line: &str| {
+            parse_scan_warning(&format!(" state: ONLINE\n  scan: {}\nconfig:\n", line))
+        };
+        assert!(scrubbed(
+            "scrub repaired 0B in 00:01:02 with 0 errors on Sat Oct 17 03:12:44 2026"
+        )
+        .is_none());
+        assert!(scrubbed(
+            "scrub repaired 12K in 00:01:02 with 3 errors on Sat Oct 17 03:12:44 2026"
+        )
+        .is_some());
+        assert!(scrubbed("scrub in progress since Sat Oct 17 03:12:44 2026").is_none());
+        assert!(scrubbed("none requested").is_none());
+        assert!(parse_scan_warning("pool 'rpool' is healthy\n").is_none());
+    }
+
+    #[test]
+    fn pool_is_first_dataset_component() {
+        assert_eq!(pool_of("rpool/ROOT/ubuntu"), "rpool");
+        assert_eq!(pool_of("tank"), "tank");
+    }
+}